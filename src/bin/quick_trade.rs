@@ -5,6 +5,7 @@ use log::trace;
 use regex::Error::Syntax;
 use reqwest::Client;
 use binance_api::binance_client::account::account_info::AccountInfoClient;
+use binance_api::binance_client::account::portfolio::Portfolio;
 use binance_api::binance_client::binance_client::BinanceClient;
 use binance_api::binance_client::load_env::EnvVars;
 use binance_api::binance_client::logger_conf::init_logger;
@@ -23,7 +24,6 @@ async fn main() {
     let quote = "USDC";
     let symbol = format!("{}{}", base, quote); 
 
-    let account_size = 1000.0;
     let risk_percentage = 0.00025;
 
 
@@ -31,6 +31,10 @@ async fn main() {
     let binance_client = BinanceClient::new(vars.api_key, vars.api_secret, false).await;
     let spot_client = SpotClient::new(&binance_client);
 
+    let portfolio = Portfolio::fetch(&binance_client, quote).await.unwrap();
+    let account_size = portfolio.total_equity;
+    trace!("account equity: {:?} {}", account_size, quote);
+
     // let url = binance_client.api_url.clone() + "/v3/exchangeInfo?symbol=" + symbol.as_str();
     // let client = Client::new();
     // let response = client.get(url).send().await.unwrap().text().await.unwrap();
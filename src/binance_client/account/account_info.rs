@@ -45,9 +45,21 @@ pub struct AccountInfoClient {
 
 impl AccountInfoClient {
     pub async fn new(api: &BinanceClient) -> Result<Self, Box<dyn Error>> {
+        Self::fetch(api, false).await
+    }
+
+    // Same as `new`, but Binance leaves out assets whose free and locked balances are both zero
+    pub async fn new_without_zero_balances(api: &BinanceClient) -> Result<Self, Box<dyn Error>> {
+        Self::fetch(api, true).await
+    }
+
+    async fn fetch(api: &BinanceClient, omit_zero_balances: bool) -> Result<Self, Box<dyn Error>> {
         let timestamp = BinanceClient::generate_timestamp()?;
         let recv_window = 5000;
-        let params = format!("recvWindow={}&timestamp={}", recv_window, timestamp);
+        let params = format!(
+            "omitZeroBalances={}&recvWindow={}&timestamp={}",
+            omit_zero_balances, recv_window, timestamp
+        );
         let signature = api.sign(&params);
         let url = format!("{}{}?{}&signature={}", api.api_url, "/v3/account", params, signature);
 
@@ -1,11 +1,11 @@
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
 use std::error::Error;
+use crate::binance_client::account::account_info::AccountInfoClient;
 use crate::binance_client::binance_client::BinanceClient;
 use crate::binance_client::deserialization::deserialize_string_to_f64;
 
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct AssetBalance {
     pub asset: String,
@@ -34,62 +34,17 @@ impl AssetBalance {
 
     // Use BinanceAPI to retrieve the balance of a specified asset
     pub async fn retrieve_balance(api: &BinanceClient, asset: &str) -> Result<Self, Box<dyn Error>> {
-        let timestamp = BinanceClient::generate_timestamp()?;
-        let recv_window = 5000;
-        let params = format!("recvWindow={}&timestamp={}", recv_window, timestamp);
-        let signature = api.sign(&params);
-        let url = format!("{}{}?{}&signature={}", api.api_url, "/v3/account", params, signature);
-
-        let response: Value = api
-            .get_client()
-            .get(&url)
-            .header("X-MBX-APIKEY", api.get_api_key())
-            .send()
+        AccountInfoClient::new(api)
             .await?
-            .json()
-            .await?;
-
-        if let Some(balances) = response["balances"].as_array() {
-            for balance in balances {
-                if balance["asset"] == asset {
-                    let free = balance["free"].as_str().unwrap_or("0").parse::<f64>().unwrap_or(0.0);
-                    let locked = balance["locked"].as_str().unwrap_or("0").parse::<f64>().unwrap_or(0.0);
-                    return Ok(AssetBalance::new(asset, free, locked));
-                }
-            }
-        }
-
-        Err("Asset not found".into())
+            .balances
+            .into_iter()
+            .find(|balance| balance.asset == asset)
+            .ok_or_else(|| "Asset not found".into())
     }
 
     // Adjusted to retrieve balances for all assets
     pub async fn retrieve_all_balances(api: &BinanceClient) -> Result<Vec<Self>, Box<dyn Error>> {
-        let timestamp = BinanceClient::generate_timestamp()?;
-        let recv_window = 5000;
-        let params = format!("recvWindow={}&timestamp={}", recv_window, timestamp);
-        let signature = api.sign(&params);
-        let url = format!("{}{}?{}&signature={}", api.api_url, "/v3/account", params, signature);
-
-        let response: Value = api
-            .get_client()
-            .get(&url)
-            .header("X-MBX-APIKEY", api.get_api_key())
-            .send()
-            .await?
-            .json()
-            .await?;
-
-        let mut balances = Vec::new();
-
-        if let Some(balances_data) = response["balances"].as_array() {
-            for balance in balances_data {
-                let asset = balance["asset"].as_str().unwrap_or_default();
-                let free = balance["free"].as_str().unwrap_or("0").parse::<f64>().unwrap_or(0.0);
-                let locked = balance["locked"].as_str().unwrap_or("0").parse::<f64>().unwrap_or(0.0);
-
-                balances.push(AssetBalance::new(asset, free, locked));
-            }
-        }
+        let balances = AccountInfoClient::new(api).await?.balances;
 
         if balances.is_empty() {
             Err("No assets found".into())
@@ -98,6 +53,9 @@ impl AssetBalance {
         }
     }
 
+    pub fn total(&self) -> f64 {
+        self.free + self.locked
+    }
 }
 
 
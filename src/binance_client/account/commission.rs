@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use crate::binance_client::account::commission_rates::CommissionRates;
+use crate::binance_client::deserialization::deserialize_string_to_f64;
+
+/// Current commission rates for a symbol, as returned by `/v3/account/commission`.
+///
+/// The effective rate of a trade is the sum of the standard, tax and special rates.
+/// When the BNB discount is enabled for both the account and the symbol, the standard
+/// commission is reduced by `discount.discount` and paid in `discount.discount_asset`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountCommission {
+    pub symbol: String,
+    pub standard_commission: CommissionRates,
+    pub tax_commission: CommissionRates,
+    #[serde(default)]
+    pub special_commission: CommissionRates, // Not sent for every account
+    pub discount: CommissionDiscount,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommissionDiscount {
+    pub enabled_for_account: bool,
+    pub enabled_for_symbol: bool,
+    pub discount_asset: String,
+    #[serde(deserialize_with = "deserialize_string_to_f64")]
+    pub discount: f64, // Fraction of the standard commission waived, e.g. 0.25
+}
+
+impl CommissionDiscount {
+    pub fn is_active(&self) -> bool {
+        self.enabled_for_account && self.enabled_for_symbol
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_account_commission_deserialization() {
+        let json_data = r#"{
+            "symbol": "BTCUSDT",
+            "standardCommission": {"maker": "0.00000010", "taker": "0.00000020", "buyer": "0.00000030", "seller": "0.00000040"},
+            "specialCommission": {"maker": "0.01000000", "taker": "0.02000000", "buyer": "0.03000000", "seller": "0.04000000"},
+            "taxCommission": {"maker": "0.00000112", "taker": "0.00000114", "buyer": "0.00000118", "seller": "0.00000116"},
+            "discount": {"enabledForAccount": true, "enabledForSymbol": true, "discountAsset": "BNB", "discount": "0.75000000"}
+        }"#;
+        let commission: AccountCommission = serde_json::from_str(json_data).unwrap();
+        assert_eq!(commission.standard_commission.taker, 0.0000002);
+        assert_eq!(commission.special_commission.seller, 0.04);
+        assert_eq!(commission.tax_commission.buyer, 0.00000118);
+        assert!(commission.discount.is_active());
+        assert_eq!(commission.discount.discount, 0.75);
+
+        let without_special = json_data.replace("specialCommission", "unknownCommission");
+        let commission: AccountCommission = serde_json::from_str(&without_special).unwrap();
+        assert_eq!(commission.special_commission, CommissionRates::default());
+    }
+}
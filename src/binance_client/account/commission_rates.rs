@@ -1,10 +1,19 @@
 use serde::{Deserialize, Serialize};
+use crate::binance_client::deserialization::deserialize_string_to_f64;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Commission rates as fractions of the traded amount, e.g. `0.001` for 0.1%.
+///
+/// `maker`/`taker` depend on whether the order added or removed liquidity, while
+/// `buyer`/`seller` are charged on top depending on the side of the order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CommissionRates {
-    buyer: String,
-    maker: String,
-    seller: String,
-    taker: String,
-}
\ No newline at end of file
+    #[serde(deserialize_with = "deserialize_string_to_f64")]
+    pub buyer: f64,
+    #[serde(deserialize_with = "deserialize_string_to_f64")]
+    pub maker: f64,
+    #[serde(deserialize_with = "deserialize_string_to_f64")]
+    pub seller: f64,
+    #[serde(deserialize_with = "deserialize_string_to_f64")]
+    pub taker: f64,
+}
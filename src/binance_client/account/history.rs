@@ -0,0 +1,357 @@
+use std::future::Future;
+use std::io::{Error as IOError, ErrorKind};
+use std::time::Duration;
+use futures::stream::{self, Stream, TryStreamExt};
+use serde::Serialize;
+use crate::binance_client::account::order::Order;
+use crate::binance_client::account::trades::Trade;
+use crate::binance_client::order_response::OrderListResponse;
+
+/// Largest page Binance returns from the history endpoints.
+pub const MAX_HISTORY_LIMIT: u32 = 1000;
+
+/// Longest `startTime`..`endTime` window the history endpoints accept.
+pub const MAX_HISTORY_WINDOW_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// Pause between two page requests.
+///
+/// `allOrders`, `myTrades` and `allOrderList` each weigh 20, so this keeps a full
+/// history walk at about 5 000 of the 6 000 request weight allowed per minute.
+pub const DEFAULT_PAGE_DELAY: Duration = Duration::from_millis(250);
+
+/// Optional filters of `/v3/allOrders`, `/v3/myTrades` and `/v3/allOrderList`.
+///
+/// Not every endpoint accepts every combination: `fromId` cannot be combined with a
+/// time range, and a time range may not span more than 24 hours.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_time: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_time: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+}
+
+impl HistoryQuery {
+    pub fn order_id(mut self, order_id: i64) -> Self {
+        self.order_id = Some(order_id);
+        self
+    }
+
+    pub fn from_id(mut self, from_id: i64) -> Self {
+        self.from_id = Some(from_id);
+        self
+    }
+
+    pub fn time_range(mut self, start_time: u64, end_time: u64) -> Self {
+        self.start_time = Some(start_time);
+        self.end_time = Some(end_time);
+        self
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Rejects combinations Binance would refuse with a 400.
+    pub fn validate(&self) -> Result<(), IOError> {
+        if self.limit.is_some_and(|limit| limit == 0 || limit > MAX_HISTORY_LIMIT) {
+            return Err(invalid_input(format!("limit must be between 1 and {}", MAX_HISTORY_LIMIT)));
+        }
+        if self.from_id.is_some() && (self.start_time.is_some() || self.end_time.is_some()) {
+            return Err(invalid_input("fromId cannot be combined with startTime or endTime".to_string()));
+        }
+        if let (Some(start_time), Some(end_time)) = (self.start_time, self.end_time) {
+            if end_time < start_time || end_time - start_time > MAX_HISTORY_WINDOW_MS {
+                return Err(invalid_input("startTime to endTime must be a window of at most 24 hours".to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    /// The query as URL parameters, each followed by `&`, ready to be prefixed to `timestamp`.
+    pub fn to_params(&self) -> String {
+        let params = serde_qs::to_string(self).unwrap_or_default();
+        if params.is_empty() {
+            params
+        } else {
+            format!("{}&", params)
+        }
+    }
+}
+
+/// Where a history walk starts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HistoryStart {
+    /// From the first record of the account.
+    Beginning,
+    /// From the record with this id, inclusive.
+    FromId(i64),
+    /// From the first record at or after this time in milliseconds.
+    Since(u64),
+}
+
+/// A record of one of the history endpoints.
+pub trait HistoryRecord {
+    fn record_id(&self) -> i64;
+    fn record_time(&self) -> u64;
+}
+
+impl HistoryRecord for Order {
+    fn record_id(&self) -> i64 {
+        self.order_id
+    }
+
+    fn record_time(&self) -> u64 {
+        self.time
+    }
+}
+
+impl HistoryRecord for Trade {
+    fn record_id(&self) -> i64 {
+        self.id
+    }
+
+    fn record_time(&self) -> u64 {
+        self.time
+    }
+}
+
+impl HistoryRecord for OrderListResponse {
+    fn record_id(&self) -> i64 {
+        self.order_list_id
+    }
+
+    fn record_time(&self) -> u64 {
+        self.transaction_time
+    }
+}
+
+/// The parameter an endpoint uses to page by id: `orderId` for `allOrders`, `fromId` otherwise.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum IdParam {
+    OrderId,
+    FromId,
+}
+
+#[derive(Clone, Copy)]
+enum Cursor {
+    // Walking 24 hour windows until the first record is found
+    Seek { window_start: u64 },
+    // Paging forward by id once the start of the history is known
+    FromId(i64),
+    Done,
+}
+
+struct Pager<F> {
+    fetch: F,
+    id_param: IdParam,
+    cursor: Cursor,
+    end_time: u64,
+    page_delay: Duration,
+    requested: bool,
+}
+
+/// Walks a history endpoint page by page and yields every record between `start` and `end_time`.
+///
+/// Pages are requested by id, which is not bound by the 24 hour window rule. A
+/// [`HistoryStart::Since`] start first walks 24 hour windows until it finds a record
+/// and then switches to paging by id. Requests are spaced by `page_delay`.
+pub(crate) fn paginate<T, F, Fut>(
+    fetch: F,
+    id_param: IdParam,
+    start: HistoryStart,
+    end_time: u64,
+    page_delay: Duration,
+) -> impl Stream<Item = Result<T, IOError>>
+where
+    T: HistoryRecord,
+    F: FnMut(HistoryQuery) -> Fut,
+    Fut: Future<Output = Result<Vec<T>, IOError>>,
+{
+    let cursor = match start {
+        HistoryStart::Beginning => Cursor::FromId(0),
+        HistoryStart::FromId(id) => Cursor::FromId(id),
+        HistoryStart::Since(start_time) => Cursor::Seek { window_start: start_time },
+    };
+    let pager = Pager { fetch, id_param, cursor, end_time, page_delay, requested: false };
+
+    stream::try_unfold(pager, next_page)
+        .map_ok(|page| stream::iter(page.into_iter().map(Ok)))
+        .try_flatten()
+}
+
+async fn next_page<T, F, Fut>(mut pager: Pager<F>) -> Result<Option<(Vec<T>, Pager<F>)>, IOError>
+where
+    T: HistoryRecord,
+    F: FnMut(HistoryQuery) -> Fut,
+    Fut: Future<Output = Result<Vec<T>, IOError>>,
+{
+    loop {
+        let query = match pager.cursor {
+            Cursor::Done => return Ok(None),
+            Cursor::Seek { window_start } if window_start > pager.end_time => return Ok(None),
+            Cursor::Seek { window_start } => {
+                let window_end = (window_start + MAX_HISTORY_WINDOW_MS - 1).min(pager.end_time);
+                HistoryQuery::default().time_range(window_start, window_end)
+            }
+            Cursor::FromId(id) => match pager.id_param {
+                IdParam::OrderId => HistoryQuery::default().order_id(id),
+                IdParam::FromId => HistoryQuery::default().from_id(id),
+            },
+        }
+        .limit(MAX_HISTORY_LIMIT);
+
+        if pager.requested {
+            tokio::time::sleep(pager.page_delay).await;
+        }
+        pager.requested = true;
+
+        let mut page = (pager.fetch)(query.clone()).await?;
+        page.sort_by_key(|record| record.record_id());
+        let full_page = page.len() == MAX_HISTORY_LIMIT as usize;
+        let past_end = page.iter().any(|record| record.record_time() > pager.end_time);
+
+        pager.cursor = match (page.last(), pager.cursor) {
+            (None, Cursor::Seek { .. }) => Cursor::Seek { window_start: query.end_time.unwrap_or_default() + 1 },
+            // Ids grow with time, so once a record is found the rest can be paged by id
+            (Some(last), Cursor::Seek { .. }) if !past_end => Cursor::FromId(last.record_id() + 1),
+            (Some(last), _) if full_page && !past_end => Cursor::FromId(last.record_id() + 1),
+            _ => Cursor::Done,
+        };
+
+        let end_time = pager.end_time;
+        page.retain(|record| record.record_time() <= end_time);
+        if !page.is_empty() {
+            return Ok(Some((page, pager)));
+        }
+    }
+}
+
+fn invalid_input(msg: String) -> IOError {
+    IOError::new(ErrorKind::InvalidInput, msg)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use std::cell::RefCell;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Record {
+        id: i64,
+        time: u64,
+    }
+
+    impl HistoryRecord for Record {
+        fn record_id(&self) -> i64 {
+            self.id
+        }
+
+        fn record_time(&self) -> u64 {
+            self.time
+        }
+    }
+
+    // Mimics an endpoint paging by fromId or by a time window
+    fn serve(records: &[Record], query: &HistoryQuery) -> Vec<Record> {
+        query.validate().unwrap();
+        let limit = query.limit.unwrap_or(500) as usize;
+        records
+            .iter()
+            .filter(|record| query.from_id.is_none_or(|id| record.id >= id))
+            .filter(|record| query.start_time.is_none_or(|start| record.time >= start))
+            .filter(|record| query.end_time.is_none_or(|end| record.time <= end))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    fn records(count: i64, spacing_ms: u64) -> Vec<Record> {
+        (0..count).map(|i| Record { id: i + 7, time: 1_000 + i as u64 * spacing_ms }).collect()
+    }
+
+    #[test]
+    fn test_history_query_params_and_validation() {
+        let query = HistoryQuery::default().from_id(5).limit(1000);
+        assert_eq!(query.to_params(), "fromId=5&limit=1000&");
+        assert_eq!(HistoryQuery::default().to_params(), "");
+
+        assert!(HistoryQuery::default().limit(1001).validate().is_err());
+        assert!(HistoryQuery::default().from_id(1).time_range(0, 1).validate().is_err());
+        assert!(HistoryQuery::default().time_range(0, MAX_HISTORY_WINDOW_MS + 1).validate().is_err());
+        assert!(HistoryQuery::default().time_range(0, MAX_HISTORY_WINDOW_MS).validate().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_paginate_from_beginning_pages_by_id() {
+        let all = records(2_500, 1);
+        let requests = RefCell::new(Vec::new());
+        let fetched: Vec<Record> = paginate(
+            |query: HistoryQuery| {
+                requests.borrow_mut().push(query.clone());
+                let page = serve(&all, &query);
+                async move { Ok(page) }
+            },
+            IdParam::FromId,
+            HistoryStart::Beginning,
+            u64::MAX,
+            Duration::ZERO,
+        )
+        .map(Result::unwrap)
+        .collect()
+        .await;
+
+        assert_eq!(fetched, all);
+        let from_ids: Vec<_> = requests.borrow().iter().map(|query| query.from_id).collect();
+        assert_eq!(from_ids, vec![Some(0), Some(1007), Some(2007)]);
+    }
+
+    #[tokio::test]
+    async fn test_paginate_since_skips_empty_windows_and_stops_at_end() {
+        // One record every 10 hours, starting three days after the requested start
+        let all: Vec<Record> = records(20, 10 * 60 * 60 * 1000)
+            .into_iter()
+            .map(|record| Record { time: record.time + 3 * MAX_HISTORY_WINDOW_MS, ..record })
+            .collect();
+        let end_time = all[12].time;
+
+        let fetched: Vec<Record> = paginate(
+            |query: HistoryQuery| {
+                let page = serve(&all, &query);
+                async move { Ok(page) }
+            },
+            IdParam::FromId,
+            HistoryStart::Since(0),
+            end_time,
+            Duration::ZERO,
+        )
+        .map(Result::unwrap)
+        .collect()
+        .await;
+
+        assert_eq!(fetched, all[..13].to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_paginate_stops_on_error() {
+        let mut stream = Box::pin(paginate(
+            |_query: HistoryQuery| async { Err::<Vec<Record>, _>(IOError::new(ErrorKind::Other, "boom")) },
+            IdParam::OrderId,
+            HistoryStart::Beginning,
+            u64::MAX,
+            Duration::ZERO,
+        ));
+        assert!(stream.next().await.unwrap().is_err());
+        assert!(stream.next().await.is_none());
+    }
+}
@@ -4,5 +4,8 @@ pub mod order;
 pub mod open_order;
 pub mod postgresql;
 pub mod commission_rates;
+pub mod commission;
 pub mod order_status;
 pub mod trades;
+pub mod portfolio;
+pub mod history;
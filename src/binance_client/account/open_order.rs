@@ -6,26 +6,62 @@ use crate::binance_client::deserialization::deserialize_string_to_f64;
 #[serde(rename_all = "camelCase")]
 pub struct OpenOrder {
     // This struct can be similar to `Order` with possibly fewer fields depending on the API's response
-    symbol: String,
+    pub symbol: String,
     
-    order_id: i64,
+    pub order_id: i64,
     
-    client_order_id: String,
+    pub client_order_id: String,
     #[serde(deserialize_with = "deserialize_string_to_f64")]
-    price: f64,
-    
-    orig_qty: f64,
-    
-    executed_qty: f64,
-    status: String,
-    
-    time_in_force: TimeInForce,
-    
-    r#type: String,
-    side: String,
+    pub price: f64,
+    #[serde(deserialize_with = "deserialize_string_to_f64")]
+    pub orig_qty: f64,
+    #[serde(deserialize_with = "deserialize_string_to_f64")]
+    pub executed_qty: f64,
+    pub status: String,
     
-    stop_price: f64,
+    pub time_in_force: TimeInForce,
     
-    iceberg_qty: f64,
-    time: u64,
+    pub r#type: String,
+    pub side: String,
+    #[serde(deserialize_with = "deserialize_string_to_f64")]
+    pub stop_price: f64,
+    #[serde(deserialize_with = "deserialize_string_to_f64")]
+    pub iceberg_qty: f64,
+    pub time: u64,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_open_order() {
+        let json = r#"{
+            "symbol": "LTCBTC",
+            "orderId": 1,
+            "orderListId": -1,
+            "clientOrderId": "myOrder1",
+            "price": "0.1",
+            "origQty": "1.0",
+            "executedQty": "0.0",
+            "cummulativeQuoteQty": "0.0",
+            "status": "NEW",
+            "timeInForce": "GTC",
+            "type": "LIMIT",
+            "side": "BUY",
+            "stopPrice": "0.0",
+            "icebergQty": "0.0",
+            "time": 1499827319559,
+            "updateTime": 1499827319559,
+            "isWorking": true,
+            "origQuoteOrderQty": "0.000000",
+            "workingTime": 1499827319559,
+            "selfTradePreventionMode": "NONE"
+        }"#;
+        let order: OpenOrder = serde_json::from_str(json).unwrap();
+        assert_eq!(order.order_id, 1);
+        assert_eq!(order.price, 0.1);
+        assert_eq!(order.orig_qty, 1.0);
+    }
 }
@@ -16,35 +16,36 @@ pub struct Order {
     // Unless OCO, the value will be -1
     
     pub client_order_id: String,
-    
+    #[serde(deserialize_with = "deserialize_string_to_f64")]
     pub price: f64,
-    
+    #[serde(deserialize_with = "deserialize_string_to_f64")]
     pub orig_qty: f64,
-    
+    #[serde(deserialize_with = "deserialize_string_to_f64")]
     pub executed_qty: f64,
-    
-    cummulative_quote_qty: f64,
+    #[serde(deserialize_with = "deserialize_string_to_f64")]
+    pub cummulative_quote_qty: f64, // Negative for some historical orders where it is unavailable
     pub status: OrderStatus,
     
     pub time_in_force: TimeInForce,
     
-    order_type: OrderType,
+    #[serde(rename = "type")]
+    pub order_type: OrderType,
     pub side: Side,
-    
+    #[serde(deserialize_with = "deserialize_string_to_f64")]
     pub stop_price: f64,
-    
-    iceberg_qty: f64,
+    #[serde(deserialize_with = "deserialize_string_to_f64")]
+    pub iceberg_qty: f64,
     pub time: u64,
     
     pub update_time: u64,
     
     pub is_working: bool,
-    
+    #[serde(deserialize_with = "deserialize_string_to_f64")]
     pub orig_quote_order_qty: f64,
     // Optional fields, use deserialize_with for optional numeric types if necessary
     
     pub prevented_match_id: Option<i64>,
-    
+    #[serde(default, deserialize_with = "deserialize_optional_string_to_f64")]
     pub prevented_quantity: Option<f64>,
     
     pub strategy_id: Option<i64>,
@@ -59,3 +60,42 @@ pub struct Order {
     
     pub working_floor: Option<String>,
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_order() {
+        let json = r#"{
+            "symbol": "LTCBTC",
+            "orderId": 1,
+            "orderListId": -1,
+            "clientOrderId": "myOrder1",
+            "price": "0.1",
+            "origQty": "1.0",
+            "executedQty": "0.0",
+            "cummulativeQuoteQty": "0.0",
+            "status": "NEW",
+            "timeInForce": "GTC",
+            "type": "LIMIT",
+            "side": "BUY",
+            "stopPrice": "0.0",
+            "icebergQty": "0.0",
+            "time": 1499827319559,
+            "updateTime": 1499827319559,
+            "isWorking": true,
+            "origQuoteOrderQty": "0.000000",
+            "workingTime": 1499827319559,
+            "selfTradePreventionMode": "NONE",
+            "preventedMatchId": 0,
+            "preventedQuantity": "1.200000"
+        }"#;
+        let order: Order = serde_json::from_str(json).unwrap();
+        assert_eq!(order.order_id, 1);
+        assert_eq!(order.price, 0.1);
+        assert_eq!(order.order_type, OrderType::Limit);
+        assert_eq!(order.prevented_quantity, Some(1.2));
+    }
+}
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum OrderStatus {
     // The order has been accepted by the system but not yet processed.
@@ -17,4 +17,6 @@ pub enum OrderStatus {
     Rejected,
     // The order was canceled due to its time condition not being met. This can happen with day orders that do not get filled by the end of the trading day.
     Expired,
+    // The order was expired by self-trade prevention.
+    ExpiredInMatch,
 }
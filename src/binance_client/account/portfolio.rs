@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+use std::io::{Error as IOError, ErrorKind};
+use crate::binance_client::account::account_info::AccountInfoClient;
+use crate::binance_client::account::asset_balance::AssetBalance;
+use crate::binance_client::binance_client::BinanceClient;
+use crate::binance_client::ticker_price::TickerPrice;
+
+/// Assets tried as the middle leg when an asset has no direct pair with the quote asset.
+const BRIDGE_ASSETS: [&str; 6] = ["USDT", "BTC", "ETH", "BNB", "FDUSD", "USDC"];
+
+/// One asset of a [`Portfolio`], valued in the portfolio's quote asset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Holding {
+    pub asset: String,
+    pub free: f64,
+    pub locked: f64,
+    pub total: f64,
+    pub price: f64, // Price of one unit of the asset in the quote asset
+    pub value: f64,
+    pub allocation_pct: f64, // Share of total equity, 0 to 100
+}
+
+/// A valued snapshot of the account's balances.
+///
+/// Built from a single `/v3/account` fetch and the latest ticker prices. Every asset is
+/// converted into `quote_asset`, either through a direct pair (`ETHUSDT`), an inverted
+/// pair (`USDTTRY` when valuing TRY in USDT) or a triangulated path through one of the
+/// common bridge assets. Assets that cannot be priced are listed in `unpriced_assets` and
+/// left out of the equity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Portfolio {
+    pub quote_asset: String,
+    pub holdings: Vec<Holding>, // Sorted by value, largest first
+    pub unpriced_assets: Vec<String>,
+    pub total_equity: f64,
+}
+
+impl Portfolio {
+    /// Fetches balances and prices and values the account in `quote_asset`.
+    pub async fn fetch(api: &BinanceClient, quote_asset: &str) -> Result<Portfolio, IOError> {
+        let account_info = AccountInfoClient::new_without_zero_balances(api)
+            .await
+            .map_err(|err| IOError::new(ErrorKind::Other, format!("Failed to fetch account info: {}", err)))?;
+        let prices = api.get_all_prices().await?;
+        Ok(Self::from_balances(&account_info.balances, &prices, quote_asset))
+    }
+
+    pub fn from_balances(balances: &[AssetBalance], prices: &[TickerPrice], quote_asset: &str) -> Portfolio {
+        let price_book = PriceBook::new(prices);
+        let mut holdings = Vec::new();
+        let mut unpriced_assets = Vec::new();
+
+        for balance in balances.iter().filter(|balance| balance.total() > 0.0) {
+            match price_book.price_in(&balance.asset, quote_asset) {
+                Some(price) => holdings.push(Holding {
+                    asset: balance.asset.clone(),
+                    free: balance.free,
+                    locked: balance.locked,
+                    total: balance.total(),
+                    price,
+                    value: balance.total() * price,
+                    allocation_pct: 0.0,
+                }),
+                None => unpriced_assets.push(balance.asset.clone()),
+            }
+        }
+
+        let total_equity: f64 = holdings.iter().map(|holding| holding.value).sum();
+        if total_equity > 0.0 {
+            for holding in holdings.iter_mut() {
+                holding.allocation_pct = holding.value / total_equity * 100.0;
+            }
+        }
+        holdings.sort_by(|a, b| b.value.total_cmp(&a.value));
+
+        Portfolio {
+            quote_asset: quote_asset.to_string(),
+            holdings,
+            unpriced_assets,
+            total_equity,
+        }
+    }
+
+    pub fn holding(&self, asset: &str) -> Option<&Holding> {
+        self.holdings.iter().find(|holding| holding.asset == asset)
+    }
+
+    /// Value in the quote asset of balances that are free to trade.
+    pub fn free_equity(&self) -> f64 {
+        self.holdings.iter().map(|holding| holding.free * holding.price).sum()
+    }
+}
+
+/// Last prices keyed by symbol, used to convert between assets.
+pub struct PriceBook {
+    prices: HashMap<String, f64>,
+}
+
+impl PriceBook {
+    pub fn new(prices: &[TickerPrice]) -> Self {
+        PriceBook {
+            prices: prices
+                .iter()
+                // Delisted symbols are reported with a price of zero
+                .filter(|ticker| ticker.price > 0.0)
+                .map(|ticker| (ticker.symbol.clone(), ticker.price))
+                .collect(),
+        }
+    }
+
+    /// Price of one unit of `asset` in `quote_asset`, if a conversion path exists.
+    pub fn price_in(&self, asset: &str, quote_asset: &str) -> Option<f64> {
+        self.pair_price(asset, quote_asset).or_else(|| {
+            BRIDGE_ASSETS
+                .iter()
+                .filter(|bridge| **bridge != asset && **bridge != quote_asset)
+                .find_map(|bridge| Some(self.pair_price(asset, bridge)? * self.pair_price(bridge, quote_asset)?))
+        })
+    }
+
+    // Direct or inverted pair between two assets
+    fn pair_price(&self, base: &str, quote: &str) -> Option<f64> {
+        if base == quote {
+            return Some(1.0);
+        }
+        self.prices
+            .get(&format!("{}{}", base, quote))
+            .copied()
+            .or_else(|| self.prices.get(&format!("{}{}", quote, base)).map(|price| 1.0 / price))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ticker(symbol: &str, price: f64) -> TickerPrice {
+        TickerPrice { symbol: symbol.to_string(), price }
+    }
+
+    fn prices() -> Vec<TickerPrice> {
+        vec![
+            ticker("BTCUSDT", 40000.0),
+            ticker("ETHBTC", 0.05),
+            ticker("USDTTRY", 32.0),
+            ticker("DEADUSDT", 0.0),
+        ]
+    }
+
+    #[test]
+    fn test_price_paths() {
+        let book = PriceBook::new(&prices());
+        assert_eq!(book.price_in("USDT", "USDT"), Some(1.0));
+        assert_eq!(book.price_in("BTC", "USDT"), Some(40000.0));
+        assert_eq!(book.price_in("USDT", "BTC"), Some(1.0 / 40000.0));
+        assert_eq!(book.price_in("TRY", "USDT"), Some(1.0 / 32.0));
+        // ETH -> BTC -> USDT
+        assert_eq!(book.price_in("ETH", "USDT"), Some(2000.0));
+        assert_eq!(book.price_in("DEAD", "USDT"), None);
+    }
+
+    #[test]
+    fn test_portfolio_valuation() {
+        let balances = vec![
+            AssetBalance::new("BTC", 0.5, 0.25),
+            AssetBalance::new("ETH", 5.0, 0.0),
+            AssetBalance::new("USDT", 10000.0, 0.0),
+            AssetBalance::new("DEAD", 100.0, 0.0),
+            AssetBalance::new("BNB", 0.0, 0.0),
+        ];
+        let portfolio = Portfolio::from_balances(&balances, &prices(), "USDT");
+
+        assert_eq!(portfolio.total_equity, 30000.0 + 10000.0 + 10000.0);
+        assert_eq!(portfolio.unpriced_assets, vec!["DEAD".to_string()]);
+        assert_eq!(portfolio.holdings[0].asset, "BTC");
+        assert_eq!(portfolio.holdings.len(), 3);
+
+        let btc = portfolio.holding("BTC").unwrap();
+        assert_eq!(btc.total, 0.75);
+        assert_eq!(btc.allocation_pct, 60.0);
+        assert_eq!(portfolio.holding("ETH").unwrap().allocation_pct, 20.0);
+        assert_eq!(portfolio.free_equity(), 20000.0 + 10000.0 + 10000.0);
+    }
+
+    #[test]
+    fn test_portfolio_in_btc() {
+        let balances = vec![AssetBalance::new("USDT", 20000.0, 0.0), AssetBalance::new("BTC", 1.0, 0.0)];
+        let portfolio = Portfolio::from_balances(&balances, &prices(), "BTC");
+        assert_eq!(portfolio.total_equity, 1.5);
+    }
+}
@@ -1,172 +1,591 @@
-use tokio_postgres::{NoTls, Error};
-
-/*
-Certainly! Setting up an OrderJournal involves several steps, including designing the database schema, implementing the logic to insert and update records, and possibly creating a way to analyze the data. Here’s a basic guide to get you started:
-
-1. Database Schema Design
-First, design a schema for your database that can capture all the relevant information about each order. Here’s a simplified example for a PostgreSQL database:
-
-sql
-Copy code
-CREATE TABLE orders (
-    id SERIAL PRIMARY KEY,
-    binance_order_id BIGINT UNIQUE NOT NULL,
-    symbol VARCHAR(20) NOT NULL,
-    order_type VARCHAR(10) NOT NULL,
-    side VARCHAR(10) NOT NULL,
-    order_status VARCHAR(20) NOT NULL,
-    price DECIMAL(20,10) NOT NULL,
-    original_quantity DECIMAL(20,10) NOT NULL,
-    executed_quantity DECIMAL(20,10) NOT NULL,
-    time TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
-    update_time TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
-    strategy VARCHAR(50),
-    notes TEXT
-);
-2. Inserting Orders into the Journal
-Whenever you place an order through the Binance API, insert a record into your database. Here’s an example in Rust, assuming you are using the tokio-postgres crate for asynchronous PostgreSQL communication:
-
-rust
-Copy code
-use tokio_postgres::{NoTls, Error};
-
-async fn insert_order(
-    client: &tokio_postgres::Client,
-    binance_order_id: i64,
-    symbol: &str,
-    order_type: &str,
-    side: &str,
-    order_status: &str,
-    price: f64,
-    original_quantity: f64,
-    executed_quantity: f64,
-    strategy: &str,
-    notes: &str,
-) -> Result<(), Error> {
-    client.execute(
-        "INSERT INTO orders (binance_order_id, symbol, order_type, side, order_status, price, original_quantity, executed_quantity, strategy, notes) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)",
-        &[&binance_order_id, &symbol, &order_type, &side, &order_status, &price, &original_quantity, &executed_quantity, &strategy, &notes],
-    ).await?;
-    Ok(())
+use std::io::Error as IOError;
+use serde_json::Value;
+use tokio_postgres::{Error, GenericClient, Row};
+use crate::binance_client::database_client::{db_error, DatabaseClient};
+use crate::binance_client::order_response::{OrderListResponse, OrderResponse};
+use crate::binance_client::order_types::cancel_order_response::CancelOrderResponse;
+use crate::binance_client::streams::user_data::{ExecutionReport, ExecutionType, ListStatus, UserDataEvent};
+
+/// Strategy name and free-form notes stored with every order placed through a journaled client.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JournalTag {
+    pub strategy: Option<String>,
+    pub notes: Option<String>,
 }
-3. Updating Orders
-Orders might change status (filled, partially filled, etc.), so you need a way to update them. Here’s a function to update an order's status and executed quantity:
-
-rust
-Copy code
-async fn update_order_status(
-    client: &tokio_postgres::Client,
-    binance_order_id: i64,
-    new_status: &str,
-    executed_quantity: f64,
-) -> Result<(), Error> {
-    client.execute(
-        "UPDATE orders SET order_status = $2, executed_quantity = $3, update_time = CURRENT_TIMESTAMP WHERE binance_order_id = $1",
-        &[&binance_order_id, &new_status, &executed_quantity],
-    ).await?;
-    Ok(())
+
+impl JournalTag {
+    pub fn new(strategy: &str) -> Self {
+        JournalTag { strategy: Some(strategy.to_string()), notes: None }
+    }
+
+    pub fn with_notes(mut self, notes: &str) -> Self {
+        self.notes = Some(notes.to_string());
+        self
+    }
 }
-4. Querying the Journal
-To analyze your trading activity, you’ll create queries against your orders table. For example, to get the total executed quantity of a particular symbol:
-
-rust
-Copy code
-async fn total_executed_for_symbol(
-    client: &tokio_postgres::Client,
-    symbol: &str,
-) -> Result<f64, Error> {
-    let row = client.query_one(
-        "SELECT SUM(executed_quantity) FROM orders WHERE symbol = $1",
-        &[&symbol],
-    ).await?;
-
-    Ok(row.get::<_, f64>(0))
+
+/// An order as stored in `journal_orders`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JournalOrder {
+    pub order_id: i64,
+    pub symbol: String,
+    pub client_order_id: String,
+    pub order_list_id: i64,
+    pub side: Option<String>,
+    pub order_type: Option<String>,
+    pub status: String,
+    pub price: Option<f64>,
+    pub stop_price: Option<f64>,
+    pub orig_qty: Option<f64>,
+    pub executed_qty: Option<f64>,
+    pub cummulative_quote_qty: Option<f64>,
+    pub transact_time: i64,
+    pub update_time: i64,
+    pub strategy: Option<String>,
+    pub notes: Option<String>,
 }
-5. Running the Application
-To run this application, you'll need to manage database connections, possibly using a connection pool (e.g., bb8 or deadpool crates for async pooling). Ensure your application handles errors gracefully, especially around database operations.
-
-6. Next Steps
-Implement additional features for querying your journal, like getting orders by date range, by status, or by strategy.
-Consider creating a dashboard or a CLI tool for easy monitoring and analysis of your trading activities.
-As your requirements evolve, you may find it useful to add more fields to your database or to create additional tables for more detailed analysis (e.g., separate tables for strategies or trade outcomes).
-This setup gives you a solid foundation for tracking and analyzing your orders. It can be expanded with more sophisticated querying and analysis capabilities as needed.
-
- */
-async fn create_database_if_not_exists() -> Result<(), Error> {
-    let (client, connection) = tokio_postgres::connect(
-        "host=localhost user=myuser password=passwd dbname=postgres",
-        NoTls,
-    )
-        .await?;
-
-    tokio::spawn(async move {
-        if let Err(e) = connection.await {
-            eprintln!("connection error: {}", e);
+
+impl From<&Row> for JournalOrder {
+    fn from(row: &Row) -> Self {
+        JournalOrder {
+            order_id: row.get("order_id"),
+            symbol: row.get("symbol"),
+            client_order_id: row.get("client_order_id"),
+            order_list_id: row.get("order_list_id"),
+            side: row.get("side"),
+            order_type: row.get("order_type"),
+            status: row.get("status"),
+            price: row.get("price"),
+            stop_price: row.get("stop_price"),
+            orig_qty: row.get("orig_qty"),
+            executed_qty: row.get("executed_qty"),
+            cummulative_quote_qty: row.get("cummulative_quote_qty"),
+            transact_time: row.get("transact_time"),
+            update_time: row.get("update_time"),
+            strategy: row.get("strategy"),
+            notes: row.get("notes"),
         }
-    });
-
-    // Check if the database exists
-    let rows = client
-        .query(
-            "SELECT 1 FROM pg_database WHERE datname = $1",
-            &[&"binance_assets_db"],
-        )
-        .await?;
-
-    // If the database does not exist, create it
-    if rows.is_empty() {
-        client
-            .batch_execute(&format!(
-                "CREATE DATABASE {}",
-                "binance_assets_db"
-            ))
-            .await?;
-        println!("Database created.");
-    } else {
-        println!("Database already exists.");
-    }
-
-    Ok(())
+    }
 }
 
+/// A fill as stored in `journal_fills`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JournalFill {
+    pub symbol: String,
+    pub trade_id: i64,
+    pub order_id: i64,
+    pub price: f64,
+    pub qty: f64,
+    pub commission: f64,
+    pub commission_asset: String,
+    pub time: i64,
+    pub is_maker: Option<bool>, // Not reported in REST order responses
+}
 
-async fn initialize_tables() -> Result<(), Error> {
-    let (client, connection) = tokio_postgres::connect(
-        "host=localhost user=myuser password=your_password dbname=binance_assets_db",
-        NoTls,
-    )
-        .await?;
+impl From<&Row> for JournalFill {
+    fn from(row: &Row) -> Self {
+        JournalFill {
+            symbol: row.get("symbol"),
+            trade_id: row.get("trade_id"),
+            order_id: row.get("order_id"),
+            price: row.get("price"),
+            qty: row.get("qty"),
+            commission: row.get("commission"),
+            commission_asset: row.get("commission_asset"),
+            time: row.get("time"),
+            is_maker: row.get("is_maker"),
+        }
+    }
+}
+
+/// A cancel, reject or expiry as stored in `journal_order_events`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JournalEvent {
+    pub id: i64,
+    pub order_id: Option<i64>, // None for rejected requests and those of unknown outcome
+    pub symbol: String,
+    pub event_type: String,
+    pub reason: Option<String>,
+    pub request: Option<String>, // The rejected or unknown request as JSON
+    pub event_time: i64,
+    pub strategy: Option<String>,
+}
 
-    tokio::spawn(async move {
-        if let Err(e) = connection.await {
-            eprintln!("connection error: {}", e);
+impl From<&Row> for JournalEvent {
+    fn from(row: &Row) -> Self {
+        JournalEvent {
+            id: row.get("id"),
+            order_id: row.get("order_id"),
+            symbol: row.get("symbol"),
+            event_type: row.get("event_type"),
+            reason: row.get("reason"),
+            request: row.get("request"),
+            event_time: row.get("event_time"),
+            strategy: row.get("strategy"),
         }
-    });
-
-    // Create the asset_balances table if it doesn't exist
-    client.batch_execute("
-        CREATE TABLE IF NOT EXISTS asset_balances (
-            id SERIAL PRIMARY KEY,
-            asset VARCHAR(10) NOT NULL,
-            free NUMERIC(18, 8) NOT NULL,
-            locked NUMERIC(18, 8) NOT NULL,
-            last_updated TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
-        )
-    ").await?;
-
-    println!("Tables initialized.");
-
-    Ok(())
+    }
+}
+
+// Order rows only move forward in time: an older update never overwrites a newer one,
+// and a missing strategy or note never erases a known one.
+const UPSERT_ORDER: &str = "
+    INSERT INTO journal_orders (
+        order_id, symbol, client_order_id, order_list_id, side, order_type, time_in_force,
+        status, price, stop_price, orig_qty, executed_qty, cummulative_quote_qty,
+        transact_time, update_time, strategy, notes
+    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)
+    ON CONFLICT (symbol, order_id)
+    DO UPDATE SET
+        order_list_id = GREATEST(journal_orders.order_list_id, EXCLUDED.order_list_id),
+        side = COALESCE(EXCLUDED.side, journal_orders.side),
+        order_type = COALESCE(EXCLUDED.order_type, journal_orders.order_type),
+        time_in_force = COALESCE(EXCLUDED.time_in_force, journal_orders.time_in_force),
+        price = COALESCE(EXCLUDED.price, journal_orders.price),
+        stop_price = COALESCE(EXCLUDED.stop_price, journal_orders.stop_price),
+        orig_qty = COALESCE(EXCLUDED.orig_qty, journal_orders.orig_qty),
+        status = CASE WHEN EXCLUDED.update_time >= journal_orders.update_time
+            THEN EXCLUDED.status ELSE journal_orders.status END,
+        executed_qty = CASE WHEN EXCLUDED.update_time >= journal_orders.update_time
+            THEN COALESCE(EXCLUDED.executed_qty, journal_orders.executed_qty) ELSE journal_orders.executed_qty END,
+        cummulative_quote_qty = CASE WHEN EXCLUDED.update_time >= journal_orders.update_time
+            THEN COALESCE(EXCLUDED.cummulative_quote_qty, journal_orders.cummulative_quote_qty)
+            ELSE journal_orders.cummulative_quote_qty END,
+        update_time = GREATEST(journal_orders.update_time, EXCLUDED.update_time),
+        strategy = COALESCE(EXCLUDED.strategy, journal_orders.strategy),
+        notes = COALESCE(EXCLUDED.notes, journal_orders.notes)
+";
+
+const UPSERT_FILL: &str = "
+    INSERT INTO journal_fills (symbol, trade_id, order_id, price, qty, commission, commission_asset, time, is_maker)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+    ON CONFLICT (symbol, trade_id)
+    DO UPDATE SET is_maker = COALESCE(EXCLUDED.is_maker, journal_fills.is_maker)
+";
+
+const INSERT_EVENT: &str = "
+    INSERT INTO journal_order_events (order_id, symbol, event_type, reason, request, event_time, strategy)
+    VALUES ($1, $2, $3, $4, $5, $6, $7)
+    ON CONFLICT (symbol, order_id, event_type) WHERE order_id IS NOT NULL DO NOTHING
+";
+
+/// Persists orders, order lists, fills, cancels and rejects in Postgres.
+///
+/// Every write is an idempotent upsert keyed on the symbol and `orderId`, `orderListId`
+/// or `tradeId`, which Binance only keeps unique per symbol, so the REST response of an order and the user data events about it
+/// can arrive in any order and any number of times. Each record call runs in its own
+/// transaction on a pooled connection, so clones of the journal can write concurrently.
+#[derive(Debug, Clone)]
+pub struct OrderJournal {
+    db: DatabaseClient,
 }
 
+impl OrderJournal {
+    pub fn new(db: DatabaseClient) -> Self {
+        OrderJournal { db }
+    }
 
+    pub fn database(&self) -> &DatabaseClient {
+        &self.db
+    }
+
+    /// Creates the journal tables. They are managed by [`DatabaseClient::migrate`], so this
+    /// only brings the schema up to date.
+    pub async fn ensure_tables_exist(&self) -> Result<(), IOError> {
+        self.db.migrate().await.map(|_| ())
+    }
+
+    /// Records a placed order and the fills it reported.
+    pub async fn record_order(&self, order: &OrderResponse, tag: &JournalTag) -> Result<(), IOError> {
+        let mut client = self.db.client().await?;
+        let transaction = client.transaction().await.map_err(db_error)?;
+        Self::write_order(&*transaction, order, tag).await.map_err(db_error)?;
+        transaction.commit().await.map_err(db_error)
+    }
+
+    /// Records an order list, its membership and any order reports it carried.
+    pub async fn record_order_list(&self, order_list: &OrderListResponse, tag: &JournalTag) -> Result<(), IOError> {
+        let mut client = self.db.client().await?;
+        let transaction = client.transaction().await.map_err(db_error)?;
+        Self::upsert_order_list(
+            &*transaction,
+            order_list.order_list_id,
+            &order_list.symbol,
+            &order_list.contingency_type,
+            &order_list.list_status_type,
+            &order_list.list_order_status,
+            &order_list.list_client_order_id,
+            order_list.transaction_time,
+            tag,
+        ).await.map_err(db_error)?;
+
+        for order in &order_list.orders {
+            Self::add_list_member(&*transaction, order_list.order_list_id, order.order_id, &order.symbol, &order.client_order_id)
+                .await
+                .map_err(db_error)?;
+        }
+        for report in &order_list.order_reports {
+            Self::write_order(&*transaction, report, tag).await.map_err(db_error)?;
+        }
+        transaction.commit().await.map_err(db_error)
+    }
+
+    /// Records a successful cancel of the order `cancel.order_id` on `cancel.symbol`.
+    pub async fn record_cancel(&self, cancel: &CancelOrderResponse, event_time: u64, tag: &JournalTag) -> Result<(), IOError> {
+        let event_time = event_time as i64;
+        let mut client = self.db.client().await?;
+        let transaction = client.transaction().await.map_err(db_error)?;
+        transaction.execute(
+            "UPDATE journal_orders SET status = 'CANCELED', update_time = GREATEST(update_time, $3)
+             WHERE symbol = $1 AND order_id = $2",
+            &[&cancel.symbol, &cancel.order_id, &event_time],
+        ).await.map_err(db_error)?;
+        Self::insert_event(&*transaction, Some(cancel.order_id), &cancel.symbol, "CANCELED", None, None, event_time, tag)
+            .await
+            .map_err(db_error)?;
+        transaction.commit().await.map_err(db_error)
+    }
+
+    /// Records an order Binance refused. `request` is the order as it was sent.
+    pub async fn record_reject(
+        &self,
+        symbol: &str,
+        request: &Value,
+        reason: &str,
+        event_time: u64,
+        tag: &JournalTag,
+    ) -> Result<(), IOError> {
+        let request = request.to_string();
+        let client = self.db.client().await?;
+        Self::insert_event(&**client, None, symbol, "REJECTED", Some(reason), Some(&request), event_time as i64, tag)
+            .await
+            .map_err(db_error)
+    }
+
+    /// Records an order request whose outcome is unknown, e.g. after a timeout or a 5xx.
+    ///
+    /// Binance may have placed the order, so reconcile these events against `/v3/allOrders`
+    /// or the user data stream. `request` is the order as it was sent.
+    pub async fn record_unknown(
+        &self,
+        symbol: &str,
+        request: &Value,
+        reason: &str,
+        event_time: u64,
+        tag: &JournalTag,
+    ) -> Result<(), IOError> {
+        let request = request.to_string();
+        let client = self.db.client().await?;
+        Self::insert_event(&**client, None, symbol, "UNKNOWN", Some(reason), Some(&request), event_time as i64, tag)
+            .await
+            .map_err(db_error)
+    }
+
+    /// Order requests recorded with [`OrderJournal::record_unknown`] since `start_time`, oldest first.
+    pub async fn unknown_requests(&self, start_time: u64) -> Result<Vec<JournalEvent>, IOError> {
+        let rows = self.db.client().await?.query(
+            "SELECT * FROM journal_order_events WHERE event_type = 'UNKNOWN' AND event_time >= $1 ORDER BY event_time, id",
+            &[&(start_time as i64)],
+        ).await.map_err(db_error)?;
+        Ok(rows.iter().map(JournalEvent::from).collect())
+    }
+
+    /// Records an `executionReport` from the user data stream.
+    pub async fn record_execution_report(&self, report: &ExecutionReport) -> Result<(), IOError> {
+        let mut client = self.db.client().await?;
+        let transaction = client.transaction().await.map_err(db_error)?;
+        Self::write_execution_report(&*transaction, report).await.map_err(db_error)?;
+        transaction.commit().await.map_err(db_error)
+    }
 
-// #[tokio::main]
-// async fn main() -> Result<(), Error> {
-//     create_database_if_not_exists().await?;
-//     initialize_tables().await?;
-//
-//     // Proceed with the rest of your application logic
-//
-//     Ok(())
-// }
+    /// Records a `listStatus` event from the user data stream.
+    pub async fn record_list_status(&self, status: &ListStatus) -> Result<(), IOError> {
+        let mut client = self.db.client().await?;
+        let transaction = client.transaction().await.map_err(db_error)?;
+        Self::upsert_order_list(
+            &*transaction,
+            status.order_list_id,
+            &status.symbol,
+            &status.contingency_type,
+            &status.list_status_type,
+            &status.list_order_status,
+            &status.list_client_order_id,
+            status.transaction_time,
+            &JournalTag::default(),
+        ).await.map_err(db_error)?;
+        for order in &status.orders {
+            Self::add_list_member(&*transaction, status.order_list_id, order.order_id, &order.symbol, &order.client_order_id)
+                .await
+                .map_err(db_error)?;
+        }
+        transaction.commit().await.map_err(db_error)
+    }
+
+    /// Records any user data event; events the journal does not track are ignored.
+    pub async fn record_user_data_event(&self, event: &UserDataEvent) -> Result<(), IOError> {
+        match event {
+            UserDataEvent::ExecutionReport(report) => self.record_execution_report(report).await,
+            UserDataEvent::ListStatus(status) => self.record_list_status(status).await,
+            UserDataEvent::Other(_) => Ok(()),
+        }
+    }
+
+    pub async fn order(&self, symbol: &str, order_id: i64) -> Result<Option<JournalOrder>, IOError> {
+        let row = self.db.client().await?
+            .query_opt("SELECT * FROM journal_orders WHERE symbol = $1 AND order_id = $2", &[&symbol, &order_id])
+            .await
+            .map_err(db_error)?;
+        Ok(row.as_ref().map(JournalOrder::from))
+    }
+
+    /// Orders of a strategy placed between `start_time` and `end_time`, oldest first.
+    pub async fn orders_for_strategy(&self, strategy: &str, start_time: u64, end_time: u64) -> Result<Vec<JournalOrder>, IOError> {
+        let rows = self.db.client().await?.query(
+            "SELECT * FROM journal_orders WHERE strategy = $1 AND transact_time BETWEEN $2 AND $3
+             ORDER BY transact_time, order_id",
+            &[&strategy, &(start_time as i64), &(end_time as i64)],
+        ).await.map_err(db_error)?;
+        Ok(rows.iter().map(JournalOrder::from).collect())
+    }
+
+    pub async fn orders_in_list(&self, symbol: &str, order_list_id: i64) -> Result<Vec<JournalOrder>, IOError> {
+        let rows = self.db.client().await?.query(
+            "SELECT o.* FROM journal_orders o
+             JOIN journal_order_list_members m ON m.symbol = o.symbol AND m.order_id = o.order_id
+             WHERE m.symbol = $1 AND m.order_list_id = $2 ORDER BY o.order_id",
+            &[&symbol, &order_list_id],
+        ).await.map_err(db_error)?;
+        Ok(rows.iter().map(JournalOrder::from).collect())
+    }
+
+    pub async fn fills_for_order(&self, symbol: &str, order_id: i64) -> Result<Vec<JournalFill>, IOError> {
+        let rows = self.db.client().await?
+            .query("SELECT * FROM journal_fills WHERE symbol = $1 AND order_id = $2 ORDER BY trade_id", &[&symbol, &order_id])
+            .await
+            .map_err(db_error)?;
+        Ok(rows.iter().map(JournalFill::from).collect())
+    }
+
+    /// Cancels, rejects and expiries of `symbol`, oldest first.
+    pub async fn events_for_symbol(&self, symbol: &str) -> Result<Vec<JournalEvent>, IOError> {
+        let rows = self.db.client().await?
+            .query("SELECT * FROM journal_order_events WHERE symbol = $1 ORDER BY event_time, id", &[&symbol])
+            .await
+            .map_err(db_error)?;
+        Ok(rows.iter().map(JournalEvent::from).collect())
+    }
+
+    async fn write_order(client: &impl GenericClient, order: &OrderResponse, tag: &JournalTag) -> Result<(), Error> {
+        let transact_time = order.transact_time as i64;
+        client.execute(UPSERT_ORDER, &[
+            &order.order_id,
+            &order.symbol,
+            &order.client_order_id,
+            &order.order_list_id,
+            &order.side,
+            &order.order_type,
+            &order.time_in_force,
+            // An ACK response carries no status yet
+            &order.status.as_deref().unwrap_or("NEW"),
+            &order.price,
+            &order.stop_price,
+            &order.orig_qty,
+            &order.executed_qty,
+            &order.cummulative_quote_qty,
+            &transact_time,
+            &transact_time,
+            &tag.strategy,
+            &tag.notes,
+        ]).await?;
+
+        for fill in order.fills.iter().flatten() {
+            client.execute(UPSERT_FILL, &[
+                &order.symbol,
+                &fill.trade_id,
+                &order.order_id,
+                &fill.price,
+                &fill.qty,
+                &fill.commission,
+                &fill.commission_asset,
+                &transact_time,
+                &None::<bool>,
+            ]).await?;
+        }
+        Ok(())
+    }
+
+    async fn write_execution_report(client: &impl GenericClient, report: &ExecutionReport) -> Result<(), Error> {
+        let transact_time = report.order_creation_time as i64;
+        let update_time = report.transaction_time as i64;
+        let status = serde_json::to_value(report.order_status)
+            .ok()
+            .and_then(|value| value.as_str().map(str::to_string))
+            .unwrap_or_default();
+        let side = report.side.to_string().to_uppercase();
+        let order_type = serde_json::to_value(report.order_type)
+            .ok()
+            .and_then(|value| value.as_str().map(str::to_string));
+
+        client.execute(UPSERT_ORDER, &[
+            &report.order_id,
+            &report.symbol,
+            &report.client_order_id,
+            &report.order_list_id,
+            &Some(side),
+            &order_type,
+            &Some(&report.time_in_force),
+            &status,
+            &Some(report.price),
+            &Some(report.stop_price),
+            &Some(report.quantity),
+            &Some(report.cumulative_filled_qty),
+            &Some(report.cumulative_quote_qty),
+            &transact_time,
+            &update_time,
+            &None::<String>,
+            &None::<String>,
+        ]).await?;
+
+        let no_tag = JournalTag::default();
+        match report.execution_type {
+            ExecutionType::Trade => {
+                client.execute(UPSERT_FILL, &[
+                    &report.symbol,
+                    &report.trade_id,
+                    &report.order_id,
+                    &report.last_executed_price,
+                    &report.last_executed_qty,
+                    &report.commission,
+                    &report.commission_asset.clone().unwrap_or_default(),
+                    &update_time,
+                    &Some(report.is_maker),
+                ]).await?;
+            }
+            ExecutionType::Canceled => {
+                Self::insert_event(client, Some(report.order_id), &report.symbol, "CANCELED", None, None, update_time, &no_tag).await?;
+            }
+            ExecutionType::Rejected => {
+                Self::insert_event(client, Some(report.order_id), &report.symbol, "REJECTED", Some(&report.reject_reason), None, update_time, &no_tag).await?;
+            }
+            ExecutionType::Expired | ExecutionType::TradePrevention => {
+                Self::insert_event(client, Some(report.order_id), &report.symbol, "EXPIRED", None, None, update_time, &no_tag).await?;
+            }
+            ExecutionType::New | ExecutionType::Replaced => {}
+        }
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn upsert_order_list(
+        client: &impl GenericClient,
+        order_list_id: i64,
+        symbol: &str,
+        contingency_type: &str,
+        list_status_type: &str,
+        list_order_status: &str,
+        list_client_order_id: &str,
+        transaction_time: u64,
+        tag: &JournalTag,
+    ) -> Result<(), Error> {
+        client.execute("
+            INSERT INTO journal_order_lists (
+                order_list_id, symbol, contingency_type, list_status_type, list_order_status,
+                list_client_order_id, transaction_time, strategy, notes
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (symbol, order_list_id)
+            DO UPDATE SET
+                list_status_type = CASE WHEN EXCLUDED.transaction_time >= journal_order_lists.transaction_time
+                    THEN EXCLUDED.list_status_type ELSE journal_order_lists.list_status_type END,
+                list_order_status = CASE WHEN EXCLUDED.transaction_time >= journal_order_lists.transaction_time
+                    THEN EXCLUDED.list_order_status ELSE journal_order_lists.list_order_status END,
+                transaction_time = GREATEST(journal_order_lists.transaction_time, EXCLUDED.transaction_time),
+                strategy = COALESCE(EXCLUDED.strategy, journal_order_lists.strategy),
+                notes = COALESCE(EXCLUDED.notes, journal_order_lists.notes)
+        ", &[
+            &order_list_id,
+            &symbol,
+            &contingency_type,
+            &list_status_type,
+            &list_order_status,
+            &list_client_order_id,
+            &(transaction_time as i64),
+            &tag.strategy,
+            &tag.notes,
+        ]).await?;
+        Ok(())
+    }
+
+    async fn add_list_member(client: &impl GenericClient, order_list_id: i64, order_id: i64, symbol: &str, client_order_id: &str) -> Result<(), Error> {
+        client.execute(
+            "INSERT INTO journal_order_list_members (order_list_id, order_id, symbol, client_order_id)
+             VALUES ($1, $2, $3, $4) ON CONFLICT DO NOTHING",
+            &[&order_list_id, &order_id, &symbol, &client_order_id],
+        ).await?;
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn insert_event(
+        client: &impl GenericClient,
+        order_id: Option<i64>,
+        symbol: &str,
+        event_type: &str,
+        reason: Option<&str>,
+        request: Option<&str>,
+        event_time: i64,
+        tag: &JournalTag,
+    ) -> Result<(), Error> {
+        client.execute(INSERT_EVENT, &[
+            &order_id,
+            &symbol,
+            &event_type,
+            &reason,
+            &request,
+            &event_time,
+            &tag.strategy,
+        ]).await?;
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binance_client::load_env::EnvVars;
+
+    #[tokio::test]
+    async fn test_order_journal_round_trip() {
+        let vars = EnvVars::new();
+        let db = DatabaseClient::connect_or_create_if_not_exist(&vars.database_config())
+            .await
+            .expect("Failed to setup database");
+        let journal = OrderJournal::new(db);
+        journal.ensure_tables_exist().await.expect("Failed to create journal tables");
+
+        let response: OrderResponse = serde_json::from_str(r#"{
+            "symbol": "BTCUSDT", "orderId": 28, "orderListId": -1, "clientOrderId": "6gCrw2kRUAF9CvJDGP16IP",
+            "transactTime": 1507725176595, "price": "0.00000000", "origQty": "10.00000000",
+            "executedQty": "10.00000000", "cummulativeQuoteQty": "10.00000000", "status": "FILLED",
+            "timeInForce": "GTC", "type": "MARKET", "side": "SELL",
+            "fills": [{"price": "4000.00000000", "qty": "1.00000000", "commission": "4.00000000", "commissionAsset": "USDT", "tradeId": 56}]
+        }"#).unwrap();
+        let tag = JournalTag::new("breakout").with_notes("first entry");
+
+        // Writing twice must not duplicate anything
+        journal.record_order(&response, &tag).await.expect("Failed to record order");
+        journal.record_order(&response, &JournalTag::default()).await.expect("Failed to record order again");
+
+        let order = journal.order("BTCUSDT", 28).await.unwrap().expect("Order not journaled");
+        assert_eq!(order.status, "FILLED");
+        assert_eq!(order.strategy.as_deref(), Some("breakout"));
+        assert_eq!(journal.fills_for_order("BTCUSDT", 28).await.unwrap().len(), 1);
+        // The same order id on another symbol is another order
+        assert!(journal.order("ETHUSDT", 28).await.unwrap().is_none());
+
+        journal.database().close().await;
+        drop(journal);
+        DatabaseClient::drop_database_if_exists(&vars.database_config())
+            .await
+            .expect("Failed to drop database");
+    }
+}
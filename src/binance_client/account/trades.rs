@@ -10,6 +10,8 @@ use crate::binance_client::deserialization::*;
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Trade {
+    pub symbol: String,
+
     pub id: i64,
     
     pub order_id: i64,
@@ -0,0 +1,528 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Error as IOError, ErrorKind, Read};
+use std::path::{Path, PathBuf};
+use log::{info, warn};
+use sha2::{Digest, Sha256};
+use tokio::sync::mpsc;
+use zip::ZipArchive;
+use crate::binance_client::database_client::DatabaseClient;
+use crate::binance_client::kline_interval::KlineInterval;
+use crate::binance_client::market_data_store::{AggTradePrint, TradePrint};
+use crate::binance_client::streams::kline_data::Kline;
+
+// Spot archives switched from millisecond to microsecond timestamps on 2025-01-01
+const MICROSECOND_TIMESTAMPS_FROM: u64 = 1_000_000_000_000_000;
+
+/// What a data.binance.vision archive holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Klines(KlineInterval),
+    AggTrades,
+    Trades,
+}
+
+/// A zipped CSV file from data.binance.vision, e.g. `BTCUSDT-1m-2024-01.zip`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveFile {
+    pub path: PathBuf,
+    pub symbol: String,
+    pub kind: ArchiveKind,
+    pub period: String, // `2024-01` for monthly, `2024-01-31` for daily archives
+}
+
+impl ArchiveFile {
+    /// Recognises an archive by its file name, `<SYMBOL>-<kind>-<period>.zip`.
+    pub fn from_path(path: &Path) -> Option<ArchiveFile> {
+        let stem = path.file_name()?.to_str()?.strip_suffix(".zip")?;
+        let mut parts = stem.splitn(3, '-');
+        let symbol = parts.next()?;
+        let kind = match parts.next()? {
+            "aggTrades" => ArchiveKind::AggTrades,
+            "trades" => ArchiveKind::Trades,
+            interval => ArchiveKind::Klines(interval.parse().ok()?),
+        };
+        let period = parts.next()?;
+        let is_period = matches!(period.len(), 7 | 10)
+            && period.chars().all(|c| c.is_ascii_digit() || c == '-');
+        if symbol.is_empty() || !is_period {
+            return None;
+        }
+
+        Some(ArchiveFile {
+            path: path.to_path_buf(),
+            symbol: symbol.to_uppercase(),
+            kind,
+            period: period.to_string(),
+        })
+    }
+
+    /// The `.CHECKSUM` file Binance publishes next to the archive.
+    pub fn checksum_path(&self) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".CHECKSUM");
+        PathBuf::from(path)
+    }
+
+    /// Compares the SHA-256 of the archive with the one in its `.CHECKSUM` file.
+    pub fn verify_checksum(&self) -> Result<(), IOError> {
+        let checksum = std::fs::read_to_string(self.checksum_path())?;
+        let expected = checksum
+            .split_whitespace()
+            .next()
+            .ok_or_else(|| IOError::new(ErrorKind::InvalidData, format!("Empty checksum file for {}", self.path.display())))?;
+
+        let mut file = File::open(&self.path)?;
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0; 1 << 16];
+        loop {
+            let read = file.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+        let actual = hex::encode(hasher.finalize());
+
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(IOError::new(ErrorKind::InvalidData, format!(
+                "Checksum mismatch for {}: expected {}, got {}", self.path.display(), expected, actual
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// How archives are imported.
+#[derive(Debug, Clone)]
+pub struct ArchiveImportConfig {
+    // Refuse archives whose `.CHECKSUM` file is missing instead of importing them unverified
+    pub require_checksum: bool,
+    pub batch_size: usize, // Rows per bulk write
+}
+
+impl Default for ArchiveImportConfig {
+    fn default() -> Self {
+        ArchiveImportConfig {
+            require_checksum: true,
+            batch_size: 100_000,
+        }
+    }
+}
+
+impl ArchiveImportConfig {
+    pub fn without_required_checksums(mut self) -> Self {
+        self.require_checksum = false;
+        self
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+}
+
+/// Outcome of importing one archive.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportReport {
+    pub archive: ArchiveFile,
+    pub checksum_verified: bool,
+    pub rows_read: u64,
+    pub rows_inserted: u64,
+    pub rows_updated: u64, // Only klines are updated, trades already stored are skipped
+}
+
+/// Bulk-loads kline, aggTrades and trades archives downloaded from data.binance.vision.
+///
+/// Works on local files only. Klines go to `kline_data`, aggregated trades to
+/// `market_agg_trades` and trades to `market_trades`. Imports are idempotent, so a
+/// directory can be imported again after new archives were added to it.
+#[derive(Debug)]
+pub struct ArchiveImporter {
+    db: DatabaseClient,
+    config: ArchiveImportConfig,
+}
+
+// Rows parsed on the blocking reader thread, handed to the writer a batch at a time
+enum ArchiveBatch {
+    Klines(Vec<Kline>),
+    AggTrades(Vec<AggTradePrint>),
+    Trades(Vec<TradePrint>),
+}
+
+impl ArchiveImporter {
+    pub fn new(db: DatabaseClient, config: ArchiveImportConfig) -> Self {
+        ArchiveImporter { db, config }
+    }
+
+    /// Imports every archive under `dir`, searching subdirectories too, oldest period first.
+    ///
+    /// Files that are not archives, such as the `.CHECKSUM` files, are skipped. Stops
+    /// at the first archive that fails to verify or import.
+    pub async fn import_dir(&self, dir: &Path) -> Result<Vec<ImportReport>, IOError> {
+        let mut archives = find_archives(dir)?;
+        archives.sort_by(|a, b| (&a.symbol, &a.period, &a.path).cmp(&(&b.symbol, &b.period, &b.path)));
+
+        let mut reports = Vec::with_capacity(archives.len());
+        for archive in archives {
+            reports.push(self.import_archive(archive).await?);
+        }
+        Ok(reports)
+    }
+
+    /// Imports a single archive file.
+    pub async fn import_file(&self, path: &Path) -> Result<ImportReport, IOError> {
+        let archive = ArchiveFile::from_path(path).ok_or_else(|| IOError::new(
+            ErrorKind::InvalidInput,
+            format!("Not a Binance archive name: {}", path.display()),
+        ))?;
+        self.import_archive(archive).await
+    }
+
+    async fn import_archive(&self, archive: ArchiveFile) -> Result<ImportReport, IOError> {
+        let checksum_verified = if archive.checksum_path().exists() {
+            let to_verify = archive.clone();
+            tokio::task::spawn_blocking(move || to_verify.verify_checksum())
+                .await
+                .map_err(|e| IOError::new(ErrorKind::Other, e))??;
+            true
+        } else if self.config.require_checksum {
+            return Err(IOError::new(ErrorKind::NotFound, format!("Missing checksum file for {}", archive.path.display())));
+        } else {
+            warn!("Importing {} without a checksum file", archive.path.display());
+            false
+        };
+
+        // Parse on a blocking thread while the previous batch is being written
+        let (sender, mut receiver) = mpsc::channel(2);
+        let to_read = archive.clone();
+        let batch_size = self.config.batch_size;
+        let reader = tokio::task::spawn_blocking(move || read_archive(&to_read, batch_size, &sender));
+
+        let mut report = ImportReport {
+            archive,
+            checksum_verified,
+            rows_read: 0,
+            rows_inserted: 0,
+            rows_updated: 0,
+        };
+        while let Some(batch) = receiver.recv().await {
+            match batch {
+                ArchiveBatch::Klines(klines) => {
+                    report.rows_read += klines.len() as u64;
+                    let stats = self.db.bulk_upsert_klines(&klines).await?;
+                    report.rows_inserted += stats.inserted;
+                    report.rows_updated += stats.updated;
+                }
+                ArchiveBatch::AggTrades(trades) => {
+                    report.rows_read += trades.len() as u64;
+                    report.rows_inserted += self.db.insert_agg_trade_prints(&trades).await?;
+                }
+                ArchiveBatch::Trades(trades) => {
+                    report.rows_read += trades.len() as u64;
+                    report.rows_inserted += self.db.insert_trade_prints(&trades).await?;
+                }
+            }
+        }
+        reader.await.map_err(|e| IOError::new(ErrorKind::Other, e))??;
+
+        info!(
+            "Imported {}: {} rows read, {} inserted, {} updated",
+            report.archive.path.display(), report.rows_read, report.rows_inserted, report.rows_updated,
+        );
+        Ok(report)
+    }
+}
+
+fn find_archives(dir: &Path) -> Result<Vec<ArchiveFile>, IOError> {
+    let mut archives = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            archives.extend(find_archives(&path)?);
+        } else if let Some(archive) = ArchiveFile::from_path(&path) {
+            archives.push(archive);
+        }
+    }
+    Ok(archives)
+}
+
+// Parses the CSV inside the archive and sends it on in batches, until the receiver hangs up
+fn read_archive(archive: &ArchiveFile, batch_size: usize, sender: &mpsc::Sender<ArchiveBatch>) -> Result<(), IOError> {
+    let mut zip = ZipArchive::new(File::open(&archive.path)?).map_err(|e| IOError::new(ErrorKind::InvalidData, e))?;
+    for index in 0..zip.len() {
+        let entry = zip.by_index(index).map_err(|e| IOError::new(ErrorKind::InvalidData, e))?;
+        if !entry.name().ends_with(".csv") {
+            continue;
+        }
+        let csv_name = entry.name().to_string();
+        let lines = BufReader::new(entry).lines();
+        let sent = match archive.kind {
+            ArchiveKind::Klines(interval) => send_batches(lines, &csv_name, batch_size, sender,
+                |fields| parse_kline_row(&archive.symbol, interval, fields), ArchiveBatch::Klines),
+            ArchiveKind::AggTrades => send_batches(lines, &csv_name, batch_size, sender,
+                |fields| parse_agg_trade_row(&archive.symbol, fields), ArchiveBatch::AggTrades),
+            ArchiveKind::Trades => send_batches(lines, &csv_name, batch_size, sender,
+                |fields| parse_trade_row(&archive.symbol, fields), ArchiveBatch::Trades),
+        }?;
+        if !sent {
+            break;
+        }
+    }
+    Ok(())
+}
+
+// Returns false once the receiver is gone and reading should stop
+fn send_batches<T>(
+    lines: impl Iterator<Item = std::io::Result<String>>,
+    csv_name: &str,
+    batch_size: usize,
+    sender: &mpsc::Sender<ArchiveBatch>,
+    parse_row: impl Fn(&[&str]) -> Result<T, String>,
+    to_batch: impl Fn(Vec<T>) -> ArchiveBatch,
+) -> Result<bool, IOError> {
+    let mut batch = Vec::with_capacity(batch_size);
+    for (index, line) in lines.enumerate() {
+        let line = line?;
+        let line = line.trim();
+        let fields: Vec<&str> = line.split(',').collect();
+        // Newer archives start with a header row
+        if line.is_empty() || (index == 0 && fields[0].parse::<u64>().is_err()) {
+            continue;
+        }
+        let row = parse_row(&fields).map_err(|e| IOError::new(
+            ErrorKind::InvalidData,
+            format!("{} line {}: {}", csv_name, index + 1, e),
+        ))?;
+        batch.push(row);
+        if batch.len() >= batch_size
+            && sender.blocking_send(to_batch(std::mem::replace(&mut batch, Vec::with_capacity(batch_size)))).is_err() {
+            return Ok(false);
+        }
+    }
+    if !batch.is_empty() && sender.blocking_send(to_batch(batch)).is_err() {
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// Reads klines from an unzipped kline archive CSV, with or without its header row.
+pub fn read_kline_csv(reader: impl BufRead, symbol: &str, interval: KlineInterval) -> Result<Vec<Kline>, IOError> {
+    let mut klines = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        let fields: Vec<&str> = line.split(',').collect();
+        if line.is_empty() || (index == 0 && fields[0].parse::<u64>().is_err()) {
+            continue;
+        }
+        let kline = parse_kline_row(symbol, interval, &fields)
+            .map_err(|e| IOError::new(ErrorKind::InvalidData, format!("Line {}: {}", index + 1, e)))?;
+        klines.push(kline);
+    }
+    Ok(klines)
+}
+
+/// Archive timestamps in milliseconds, whichever unit the archive uses.
+pub fn archive_time_ms(time: u64) -> u64 {
+    if time >= MICROSECOND_TIMESTAMPS_FROM {
+        time / 1_000
+    } else {
+        time
+    }
+}
+
+fn field<'f>(fields: &[&'f str], index: usize, name: &str) -> Result<&'f str, String> {
+    fields.get(index).copied().ok_or_else(|| format!("missing {}", name))
+}
+
+fn parse_field<T: std::str::FromStr>(fields: &[&str], index: usize, name: &str) -> Result<T, String> {
+    let value = field(fields, index, name)?;
+    value.parse().map_err(|_| format!("invalid {}: {}", name, value))
+}
+
+fn parse_bool(fields: &[&str], index: usize, name: &str) -> Result<bool, String> {
+    let value = field(fields, index, name)?;
+    match value.to_ascii_lowercase().as_str() {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(format!("invalid {}: {}", name, value)),
+    }
+}
+
+/// Parses a kline archive row: open time, open, high, low, close, volume, close time,
+/// quote volume, trade count, taker buy base volume, taker buy quote volume, ignore.
+///
+/// Archives hold closed klines only and no trade ids, which are left at zero.
+pub fn parse_kline_row(symbol: &str, interval: KlineInterval, fields: &[&str]) -> Result<Kline, String> {
+    Ok(Kline {
+        start_time: archive_time_ms(parse_field(fields, 0, "open time")?),
+        end_time: archive_time_ms(parse_field(fields, 6, "close time")?),
+        symbol: symbol.to_string(),
+        interval: interval.to_string(),
+        first_trade_id: 0,
+        last_trade_id: 0,
+        open_price: parse_field(fields, 1, "open price")?,
+        close_price: parse_field(fields, 4, "close price")?,
+        high_price: parse_field(fields, 2, "high price")?,
+        low_price: parse_field(fields, 3, "low price")?,
+        base_asset_volume: parse_field(fields, 5, "volume")?,
+        number_of_trades: parse_field(fields, 8, "trade count")?,
+        is_kline_closed: true,
+        quote_asset_volume: parse_field(fields, 7, "quote volume")?,
+        taker_buy_base_asset_volume: parse_field(fields, 9, "taker buy base volume")?,
+        taker_buy_quote_asset_volume: parse_field(fields, 10, "taker buy quote volume")?,
+        ignore: fields.get(11).unwrap_or(&"0").to_string(),
+    })
+}
+
+/// Parses an aggTrades archive row: aggregate trade id, price, quantity, first trade id,
+/// last trade id, time, is buyer maker, is best match.
+pub fn parse_agg_trade_row(symbol: &str, fields: &[&str]) -> Result<AggTradePrint, String> {
+    Ok(AggTradePrint {
+        symbol: symbol.to_string(),
+        agg_trade_id: parse_field(fields, 0, "aggregate trade id")?,
+        price: parse_field(fields, 1, "price")?,
+        quantity: parse_field(fields, 2, "quantity")?,
+        first_trade_id: parse_field(fields, 3, "first trade id")?,
+        last_trade_id: parse_field(fields, 4, "last trade id")?,
+        trade_time: archive_time_ms(parse_field(fields, 5, "time")?) as i64,
+        is_buyer_maker: parse_bool(fields, 6, "is buyer maker")?,
+    })
+}
+
+/// Parses a trades archive row: trade id, price, quantity, quote quantity, time,
+/// is buyer maker, is best match.
+///
+/// Archives carry no order ids, so `buyer_order_id` and `seller_order_id` are zero.
+pub fn parse_trade_row(symbol: &str, fields: &[&str]) -> Result<TradePrint, String> {
+    Ok(TradePrint {
+        symbol: symbol.to_string(),
+        trade_id: parse_field(fields, 0, "trade id")?,
+        price: parse_field(fields, 1, "price")?,
+        quantity: parse_field(fields, 2, "quantity")?,
+        buyer_order_id: 0,
+        seller_order_id: 0,
+        trade_time: archive_time_ms(parse_field(fields, 4, "time")?) as i64,
+        is_buyer_maker: parse_bool(fields, 5, "is buyer maker")?,
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+    use crate::binance_client::load_env::EnvVars;
+    use super::*;
+
+    const KLINES_CSV: &str = "\
+1706659200000,42000.00,42100.00,41900.00,42050.00,12.5,1706659259999,525000.0,340,6.25,262500.0,0
+1706659260000,42050.00,42080.00,42010.00,42020.00,8.0,1706659319999,336200.0,210,4.0,168100.0,0
+";
+
+    // Writes `<name>` zipped, with a matching `.CHECKSUM` unless `checksum` says otherwise
+    fn write_archive(dir: &Path, name: &str, csv: &str, checksum: Option<&str>) -> PathBuf {
+        let path = dir.join(name);
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        zip.start_file(name.replace(".zip", ".csv"), SimpleFileOptions::default()).unwrap();
+        zip.write_all(csv.as_bytes()).unwrap();
+        zip.finish().unwrap();
+
+        let actual = hex::encode(Sha256::digest(std::fs::read(&path).unwrap()));
+        let checksum = checksum.map(str::to_string).unwrap_or(actual);
+        std::fs::write(dir.join(format!("{}.CHECKSUM", name)), format!("{}  {}\n", checksum, name)).unwrap();
+        path
+    }
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("binance_archives_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_archive_file_names() {
+        let monthly = ArchiveFile::from_path(Path::new("spot/monthly/BTCUSDT-1m-2024-01.zip")).unwrap();
+        assert_eq!(monthly.symbol, "BTCUSDT");
+        assert_eq!(monthly.kind, ArchiveKind::Klines(KlineInterval::OneMinute));
+        assert_eq!(monthly.period, "2024-01");
+        assert_eq!(monthly.checksum_path(), Path::new("spot/monthly/BTCUSDT-1m-2024-01.zip.CHECKSUM"));
+
+        let daily = ArchiveFile::from_path(Path::new("ETHBTC-aggTrades-2024-01-31.zip")).unwrap();
+        assert_eq!(daily.kind, ArchiveKind::AggTrades);
+        assert_eq!(daily.period, "2024-01-31");
+        assert_eq!(ArchiveFile::from_path(Path::new("BNBUSDT-1M-2024-01.zip")).unwrap().kind, ArchiveKind::Klines(KlineInterval::OneMonth));
+
+        assert!(ArchiveFile::from_path(Path::new("BTCUSDT-1m-2024-01.zip.CHECKSUM")).is_none());
+        assert!(ArchiveFile::from_path(Path::new("BTCUSDT-7m-2024-01.zip")).is_none());
+        assert!(ArchiveFile::from_path(Path::new("BTCUSDT-trades.zip")).is_none());
+    }
+
+    #[test]
+    fn test_parse_rows() {
+        let fields: Vec<&str> = KLINES_CSV.lines().next().unwrap().split(',').collect();
+        let kline = parse_kline_row("BTCUSDT", KlineInterval::OneMinute, &fields).unwrap();
+        assert_eq!((kline.start_time, kline.end_time), (1706659200000, 1706659259999));
+        assert_eq!((kline.open_price, kline.high_price, kline.low_price, kline.close_price), (42000.0, 42100.0, 41900.0, 42050.0));
+        assert_eq!(kline.number_of_trades, 340);
+        assert!(kline.is_kline_closed);
+
+        // Microsecond timestamps of 2025 archives
+        let trade = parse_trade_row("BTCUSDT", &["4431", "94000.01", "0.002", "188.00002", "1735689600123456", "True", "True"]).unwrap();
+        assert_eq!(trade.trade_time, 1735689600123);
+        assert!(trade.is_buyer_maker);
+
+        let agg_trade = parse_agg_trade_row("BTCUSDT", &["26129", "0.01633102", "4.7", "27781", "27781", "1498793709153", "false", "true"]).unwrap();
+        assert_eq!((agg_trade.first_trade_id, agg_trade.trade_time), (27781, 1498793709153));
+
+        assert_eq!(parse_trade_row("BTCUSDT", &["4431", "abc"]).unwrap_err(), "invalid price: abc");
+    }
+
+    #[test]
+    fn test_verify_checksum() {
+        let dir = temp_dir();
+        let valid = ArchiveFile::from_path(&write_archive(&dir, "BTCUSDT-1m-2024-01.zip", KLINES_CSV, None)).unwrap();
+        assert!(valid.verify_checksum().is_ok());
+
+        let corrupt = ArchiveFile::from_path(&write_archive(&dir, "BTCUSDT-1m-2024-02.zip", KLINES_CSV, Some("00ff"))).unwrap();
+        assert_eq!(corrupt.verify_checksum().unwrap_err().kind(), ErrorKind::InvalidData);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_import_dir() {
+        let dir = temp_dir();
+        std::fs::create_dir_all(dir.join("aggTrades")).unwrap();
+        write_archive(&dir, "BTCUSDT-1m-2024-01-31.zip", &format!("open_time,open,high,low,close,volume,close_time,quote_volume,count,taker_buy_volume,taker_buy_quote_volume,ignore\n{}", KLINES_CSV), None);
+        write_archive(&dir.join("aggTrades"), "BTCUSDT-aggTrades-2024-01-31.zip", "\
+1,42000.0,0.5,10,12,1706659200100,true,true
+2,42001.0,0.1,13,13,1706659200200,false,true
+", None);
+
+        let vars = EnvVars::new();
+        let db = DatabaseClient::connect_or_create_if_not_exist(&vars.database_config()).await.expect("Failed to setup database");
+        let importer = ArchiveImporter::new(db.clone(), ArchiveImportConfig::default().with_batch_size(1));
+
+        let reports = importer.import_dir(&dir).await.unwrap();
+        assert_eq!(reports.len(), 2);
+        assert!(reports.iter().all(|report| report.checksum_verified && report.rows_read == 2 && report.rows_inserted == 2));
+        assert_eq!(db.klines("BTCUSDT", KlineInterval::OneMinute, 1706659200000, 1706659320000).await.unwrap().len(), 2);
+        assert_eq!(db.agg_trade_prints("BTCUSDT", 1706659200000, 1706659260000).await.unwrap().len(), 2);
+
+        // Importing again changes nothing
+        let reports = importer.import_dir(&dir).await.unwrap();
+        assert_eq!(reports[1].rows_inserted, 0);
+
+        // Unverifiable archives are refused unless asked otherwise
+        std::fs::remove_file(dir.join("BTCUSDT-1m-2024-01-31.zip.CHECKSUM")).unwrap();
+        assert_eq!(importer.import_dir(&dir).await.unwrap_err().kind(), ErrorKind::NotFound);
+        let lenient = ArchiveImporter::new(db.clone(), ArchiveImportConfig::default().without_required_checksums());
+        assert!(!lenient.import_file(&dir.join("BTCUSDT-1m-2024-01-31.zip")).await.unwrap().checksum_verified);
+
+        std::fs::remove_dir_all(dir).unwrap();
+        db.close().await;
+        drop(db);
+        DatabaseClient::drop_database_if_exists(&vars.database_config()).await.expect("Failed to drop database");
+    }
+}
@@ -0,0 +1,504 @@
+use std::collections::{BTreeMap, HashMap};
+use crate::binance_client::account::order_status::OrderStatus;
+use crate::binance_client::exchange_info::SymbolFilters;
+use crate::binance_client::fee_model::{Fee, FeeModel, LiquidityRole};
+use crate::binance_client::order_types::side::Side;
+use crate::binance_client::strategy::{OrderId, OrderIntent, OrderUpdate, StrategyFill};
+use crate::binance_client::streams::kline_data::Kline;
+
+/// A symbol the simulated exchange trades, with its commission and trading rules.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimMarket {
+    pub symbol: String,
+    pub fee_model: FeeModel, // Also names the base and quote asset
+    pub filters: SymbolFilters,
+}
+
+impl SimMarket {
+    pub fn new(symbol: &str, fee_model: FeeModel) -> Self {
+        SimMarket {
+            symbol: symbol.to_string(),
+            fee_model,
+            filters: SymbolFilters::default(),
+        }
+    }
+
+    pub fn with_filters(mut self, filters: SymbolFilters) -> Self {
+        self.filters = filters;
+        self
+    }
+
+    pub fn base_asset(&self) -> &str {
+        &self.fee_model.base_asset
+    }
+
+    pub fn quote_asset(&self) -> &str {
+        &self.fee_model.quote_asset
+    }
+}
+
+/// A fill of the simulated exchange.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimFill {
+    pub order_id: OrderId,
+    pub symbol: String,
+    pub side: Side,
+    pub price: f64,
+    pub quantity: f64,
+    pub fee: Fee,
+    pub role: LiquidityRole,
+    pub time: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct SimBalance {
+    free: f64,
+    locked: f64,
+}
+
+// How a resting order executes, a triggered stop becomes a plain limit order
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SimOrderKind {
+    Market,
+    Limit { price: f64 },
+    StopLimit { stop_price: f64, price: f64 },
+    Oco { price: f64, stop_price: f64, stop_limit_price: f64 },
+}
+
+#[derive(Debug, Clone)]
+struct SimOrder {
+    symbol: String,
+    side: Side,
+    quantity: f64,
+    kind: SimOrderKind,
+    locked: f64, // Held from the quote asset for buys, the base asset for sells
+    // Not yet matched against a bar; a limit price the open already crossed takes liquidity
+    arriving: bool,
+}
+
+/// A spot exchange simulated on OHLC bars.
+///
+/// Orders fill completely or not at all, volume is not taken into account. Orders
+/// submitted after a bar are matched from the next bar of their symbol on:
+///
+/// - Market orders fill at the open, moved against the order by the slippage.
+/// - Limit orders the open already crosses fill at the open as taker, otherwise at
+///   their limit price as maker once the bar's range reaches it.
+/// - Stop-limit orders trigger when the range reaches the stop price and fill as taker
+///   at the stop price, or at the open if it gapped through, plus slippage but never
+///   beyond the limit price. If the limit price was gapped through as well they rest
+///   as a limit order.
+/// - When both legs of an OCO order could have executed within one bar, the stop leg
+///   is assumed to have executed first.
+///
+/// Like Binance, orders that break the symbol's filters, stop orders that would trigger
+/// immediately and orders the free balance cannot cover are rejected. Commissions come
+/// from the market's [`FeeModel`] and are taken from the balance of the asset they are
+/// charged in.
+#[derive(Debug, Clone)]
+pub struct SimulatedBroker {
+    markets: HashMap<String, SimMarket>,
+    balances: HashMap<String, SimBalance>,
+    orders: BTreeMap<OrderId, SimOrder>,
+    prices: HashMap<String, f64>, // Last close by symbol
+    slippage: f64, // Fraction of the price
+    fills: Vec<SimFill>,
+}
+
+impl SimulatedBroker {
+    pub fn new(markets: Vec<SimMarket>, slippage_bps: f64) -> Self {
+        SimulatedBroker {
+            markets: markets.into_iter().map(|market| (market.symbol.clone(), market)).collect(),
+            balances: HashMap::new(),
+            orders: BTreeMap::new(),
+            prices: HashMap::new(),
+            slippage: slippage_bps / 10_000.0,
+            fills: Vec::new(),
+        }
+    }
+
+    pub fn market(&self, symbol: &str) -> Option<&SimMarket> {
+        self.markets.get(symbol)
+    }
+
+    pub fn markets(&self) -> impl Iterator<Item = &SimMarket> {
+        self.markets.values()
+    }
+
+    pub fn deposit(&mut self, asset: &str, amount: f64) {
+        self.balances.entry(asset.to_string()).or_default().free += amount;
+    }
+
+    /// Balance of `asset` not held by open orders.
+    pub fn free_balance(&self, asset: &str) -> f64 {
+        self.balances.get(asset).map(|balance| balance.free).unwrap_or(0.0)
+    }
+
+    /// Free plus locked balance of `asset`.
+    pub fn total_balance(&self, asset: &str) -> f64 {
+        self.balances.get(asset).map(|balance| balance.free + balance.locked).unwrap_or(0.0)
+    }
+
+    /// Free balances by asset.
+    pub fn free_balances(&self) -> impl Iterator<Item = (&str, f64)> {
+        self.balances.iter().map(|(asset, balance)| (asset.as_str(), balance.free))
+    }
+
+    pub fn last_price(&self, symbol: &str) -> Option<f64> {
+        self.prices.get(symbol).copied()
+    }
+
+    pub fn fills(&self) -> &[SimFill] {
+        &self.fills
+    }
+
+    pub fn open_order_ids(&self) -> impl Iterator<Item = OrderId> + '_ {
+        self.orders.keys().copied()
+    }
+
+    /// Value of all balances in `quote_asset` at the last prices.
+    ///
+    /// Assets without a market quoted in `quote_asset`, or without a price yet, count as zero.
+    pub fn equity(&self, quote_asset: &str) -> f64 {
+        self.balances
+            .iter()
+            .map(|(asset, balance)| {
+                let total = balance.free + balance.locked;
+                if asset == quote_asset {
+                    return total;
+                }
+                self.markets
+                    .values()
+                    .find(|market| market.base_asset() == asset && market.quote_asset() == quote_asset)
+                    .and_then(|market| self.last_price(&market.symbol))
+                    .map(|price| total * price)
+                    .unwrap_or(0.0)
+            })
+            .sum()
+    }
+
+    /// Accepts an order for matching from the next bar on, or rejects it.
+    pub fn submit(&mut self, order_id: OrderId, intent: &OrderIntent, time: u64) -> OrderUpdate {
+        match self.accept(intent) {
+            Ok(order) => {
+                self.lock(&order);
+                self.orders.insert(order_id, order);
+                update(order_id, intent.symbol(), intent.side(), OrderStatus::New, time, None, 0.0, None)
+            }
+            Err(reason) => update(order_id, intent.symbol(), intent.side(), OrderStatus::Rejected, time, None, 0.0, Some(reason)),
+        }
+    }
+
+    /// Cancels an open order, returning `None` if it is not open anymore.
+    pub fn cancel(&mut self, order_id: OrderId, time: u64) -> Option<OrderUpdate> {
+        let order = self.orders.remove(&order_id)?;
+        self.unlock(&order, order.locked);
+        Some(update(order_id, &order.symbol, order.side, OrderStatus::Canceled, time, None, 0.0, None))
+    }
+
+    pub fn process_kline(&mut self, kline: &Kline) -> Vec<OrderUpdate> {
+        self.process_bar(&kline.symbol, kline.end_time, kline.open_price, kline.high_price, kline.low_price, kline.close_price)
+    }
+
+    /// Matches the open orders of `symbol` against one bar and returns their updates.
+    pub fn process_bar(&mut self, symbol: &str, time: u64, open: f64, high: f64, low: f64, close: f64) -> Vec<OrderUpdate> {
+        let order_ids: Vec<OrderId> = self.orders
+            .iter()
+            .filter(|(_, order)| order.symbol == symbol)
+            .map(|(order_id, _)| *order_id)
+            .collect();
+
+        let mut updates = Vec::new();
+        for order_id in order_ids {
+            if let Some(update) = self.match_order(order_id, time, open, high, low) {
+                updates.push(update);
+            }
+        }
+        self.prices.insert(symbol.to_string(), close);
+        updates
+    }
+
+    fn match_order(&mut self, order_id: OrderId, time: u64, open: f64, high: f64, low: f64) -> Option<OrderUpdate> {
+        let slippage = self.slippage;
+        let order = self.orders.get_mut(&order_id)?;
+        let arriving = std::mem::replace(&mut order.arriving, false);
+        let (buy, side) = (order.side == Side::Buy, order.side);
+        let slipped = |price: f64| if buy { price * (1.0 + slippage) } else { price * (1.0 - slippage) };
+
+        let (price, role) = match order.kind {
+            SimOrderKind::Market => (slipped(open), LiquidityRole::Taker),
+            SimOrderKind::Limit { price } => match_limit(buy, price, arriving, open, high, low)?,
+            SimOrderKind::StopLimit { stop_price, price } => {
+                let triggered = if buy { high >= stop_price } else { low <= stop_price };
+                if !triggered {
+                    return None;
+                }
+                match trigger_stop(buy, stop_price, price, open, slipped) {
+                    Some(fill) => fill,
+                    None => {
+                        order.kind = SimOrderKind::Limit { price };
+                        return None;
+                    }
+                }
+            }
+            SimOrderKind::Oco { price, stop_price, stop_limit_price } => {
+                let stop_triggered = if buy { high >= stop_price } else { low <= stop_price };
+                if stop_triggered {
+                    match trigger_stop(buy, stop_price, stop_limit_price, open, slipped) {
+                        Some(fill) => fill,
+                        None => {
+                            order.kind = SimOrderKind::Limit { price: stop_limit_price };
+                            return None;
+                        }
+                    }
+                } else {
+                    match_limit(buy, price, arriving, open, high, low)?
+                }
+            }
+        };
+
+        let order = self.orders.remove(&order_id)?;
+        if let Err(reason) = self.settle(order_id, &order, price, role, time) {
+            self.unlock(&order, order.locked);
+            return Some(update(order_id, &order.symbol, side, OrderStatus::Rejected, time, None, 0.0, Some(reason)));
+        }
+        let fill = self.fills.last().map(|fill| StrategyFill {
+            price: fill.price,
+            quantity: fill.quantity,
+            fee: fill.fee.clone(),
+            role: fill.role,
+        });
+        Some(update(order_id, &order.symbol, side, OrderStatus::Filled, time, fill, order.quantity, None))
+    }
+
+    // Moves the assets of a fill and charges its commission
+    fn settle(&mut self, order_id: OrderId, order: &SimOrder, price: f64, role: LiquidityRole, time: u64) -> Result<(), String> {
+        let market = self.markets.get(&order.symbol).ok_or("Unknown symbol")?.clone();
+        let (base, quote) = (market.base_asset(), market.quote_asset());
+        let notional = price * order.quantity;
+
+        if order.kind == SimOrderKind::Market {
+            market.filters.check(price, order.quantity, true).map_err(|e| e.to_string())?;
+            let (asset, needed) = if order.side == Side::Buy { (quote, notional) } else { (base, order.quantity) };
+            if self.free_balance(asset) < needed {
+                return Err("Account has insufficient balance for requested action.".to_string());
+            }
+        }
+
+        let (paid_asset, paid, received_asset, received) = match order.side {
+            Side::Buy => (quote, notional, base, order.quantity),
+            Side::Sell => (base, order.quantity, quote, notional),
+        };
+        // Limit buys locked their limit price; what the fill did not use is freed
+        self.unlock(order, order.locked);
+        let balance = self.balances.entry(paid_asset.to_string()).or_default();
+        balance.free -= paid;
+        self.balances.entry(received_asset.to_string()).or_default().free += received;
+
+        let fee = market.fee_model.expected_fee(order.side, role, order.quantity, price);
+        self.balances.entry(fee.asset.clone()).or_default().free -= fee.amount;
+        self.fills.push(SimFill {
+            order_id,
+            symbol: order.symbol.clone(),
+            side: order.side,
+            price,
+            quantity: order.quantity,
+            fee,
+            role,
+            time,
+        });
+        Ok(())
+    }
+
+    // Validates an order the way the exchange would on placement
+    fn accept(&self, intent: &OrderIntent) -> Result<SimOrder, String> {
+        let market = self.markets.get(intent.symbol()).ok_or("Invalid symbol.")?;
+        let filters = &market.filters;
+        let (side, quantity) = (intent.side(), intent.quantity());
+        let buy = side == Side::Buy;
+        let last = self.last_price(intent.symbol());
+        let check = |price: f64, is_market: bool| filters.check(price, quantity, is_market).map_err(|e| e.to_string());
+        let would_trigger = |stop_price: f64| last.is_some_and(|last| if buy { last >= stop_price } else { last <= stop_price });
+
+        let (kind, lock_price) = match *intent {
+            OrderIntent::Market { .. } => {
+                // Checked again at the fill price
+                if let Some(last) = last {
+                    check(last, true)?;
+                }
+                (SimOrderKind::Market, None)
+            }
+            OrderIntent::Limit { price, .. } => {
+                check(price, false)?;
+                (SimOrderKind::Limit { price }, Some(price))
+            }
+            OrderIntent::StopLimit { stop_price, price, .. } => {
+                check(price, false)?;
+                check(stop_price, false)?;
+                if would_trigger(stop_price) {
+                    return Err("Stop price would trigger immediately.".to_string());
+                }
+                (SimOrderKind::StopLimit { stop_price, price }, Some(price))
+            }
+            OrderIntent::Oco { price, stop_price, stop_limit_price, .. } => {
+                check(price, false)?;
+                check(stop_price, false)?;
+                check(stop_limit_price, false)?;
+                let ordered = if buy { price < stop_price } else { price > stop_price };
+                if !ordered || would_trigger(stop_price) {
+                    return Err("The relationship of the prices for the orders is not correct.".to_string());
+                }
+                (SimOrderKind::Oco { price, stop_price, stop_limit_price }, Some(price.max(stop_limit_price)))
+            }
+        };
+
+        let locked = match (side, lock_price) {
+            (_, None) => 0.0,
+            (Side::Buy, Some(price)) => price * quantity,
+            (Side::Sell, Some(_)) => quantity,
+        };
+        let lock_asset = if buy { market.quote_asset() } else { market.base_asset() };
+        if self.free_balance(lock_asset) < locked {
+            return Err("Account has insufficient balance for requested action.".to_string());
+        }
+
+        Ok(SimOrder {
+            symbol: intent.symbol().to_string(),
+            side,
+            quantity,
+            kind,
+            locked,
+            arriving: true,
+        })
+    }
+
+    fn lock(&mut self, order: &SimOrder) {
+        let asset = self.lock_asset(order);
+        let balance = self.balances.entry(asset).or_default();
+        balance.free -= order.locked;
+        balance.locked += order.locked;
+    }
+
+    fn unlock(&mut self, order: &SimOrder, amount: f64) {
+        let asset = self.lock_asset(order);
+        let balance = self.balances.entry(asset).or_default();
+        balance.locked -= amount;
+        balance.free += amount;
+    }
+
+    fn lock_asset(&self, order: &SimOrder) -> String {
+        let market = &self.markets[&order.symbol];
+        match order.side {
+            Side::Buy => market.quote_asset().to_string(),
+            Side::Sell => market.base_asset().to_string(),
+        }
+    }
+}
+
+// Fill price and role of a limit order, if the bar reaches its price
+fn match_limit(buy: bool, price: f64, arriving: bool, open: f64, high: f64, low: f64) -> Option<(f64, LiquidityRole)> {
+    let crossed_at_open = if buy { open <= price } else { open >= price };
+    let reached = if buy { low <= price } else { high >= price };
+    if arriving && crossed_at_open {
+        Some((open, LiquidityRole::Taker))
+    } else if reached {
+        Some((price, LiquidityRole::Maker))
+    } else {
+        None
+    }
+}
+
+// Fill of a triggered stop-limit order, or `None` if the market gapped through its limit price
+fn trigger_stop(buy: bool, stop_price: f64, price: f64, open: f64, slipped: impl Fn(f64) -> f64) -> Option<(f64, LiquidityRole)> {
+    let trigger_price = if buy { stop_price.max(open) } else { stop_price.min(open) };
+    let marketable = if buy { trigger_price <= price } else { trigger_price >= price };
+    if !marketable {
+        return None;
+    }
+    let fill_price = if buy { slipped(trigger_price).min(price) } else { slipped(trigger_price).max(price) };
+    Some((fill_price, LiquidityRole::Taker))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update(
+    order_id: OrderId,
+    symbol: &str,
+    side: Side,
+    status: OrderStatus,
+    time: u64,
+    fill: Option<StrategyFill>,
+    filled_quantity: f64,
+    reason: Option<String>,
+) -> OrderUpdate {
+    OrderUpdate { order_id, symbol: symbol.to_string(), side, status, time, fill, filled_quantity, reason }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn broker(slippage_bps: f64) -> SimulatedBroker {
+        let market = SimMarket::new("ETHUSDT", FeeModel::flat(0.001, 0.002, "ETH", "USDT"));
+        let mut broker = SimulatedBroker::new(vec![market], slippage_bps);
+        broker.deposit("USDT", 10_000.0);
+        broker.process_bar("ETHUSDT", 0, 2000.0, 2000.0, 2000.0, 2000.0);
+        broker
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn test_market_order_fills_at_next_open_with_slippage() {
+        let mut broker = broker(10.0);
+        assert_eq!(broker.submit(1, &OrderIntent::market("ETHUSDT", Side::Buy, 1.0), 0).status, OrderStatus::New);
+
+        let updates = broker.process_bar("ETHUSDT", 60_000, 2010.0, 2030.0, 2000.0, 2020.0);
+        let fill = updates[0].fill.clone().unwrap();
+        assert_eq!(updates[0].status, OrderStatus::Filled);
+        assert_close(fill.price, 2012.01);
+        assert_eq!(fill.role, LiquidityRole::Taker);
+        // Buy commission is charged in the base asset
+        assert_close(broker.total_balance("ETH"), 1.0 - 0.002);
+        assert_close(broker.total_balance("USDT"), 10_000.0 - 2012.01);
+        assert_close(broker.equity("USDT"), 10_000.0 - 2012.01 + 0.998 * 2020.0);
+    }
+
+    #[test]
+    fn test_limit_order_locks_balance_and_fills_as_maker() {
+        let mut broker = broker(0.0);
+        broker.submit(1, &OrderIntent::limit("ETHUSDT", Side::Buy, 2.0, 1950.0), 0);
+        assert_close(broker.free_balance("USDT"), 10_000.0 - 3900.0);
+        // Not enough free balance left for a second order
+        let rejected = broker.submit(2, &OrderIntent::limit("ETHUSDT", Side::Buy, 4.0, 1950.0), 0);
+        assert_eq!(rejected.status, OrderStatus::Rejected);
+
+        assert!(broker.process_bar("ETHUSDT", 60_000, 2000.0, 2010.0, 1960.0, 1970.0).is_empty());
+        let updates = broker.process_bar("ETHUSDT", 120_000, 1970.0, 1975.0, 1940.0, 1945.0);
+        let fill = updates[0].fill.clone().unwrap();
+        assert_eq!((fill.price, fill.role), (1950.0, LiquidityRole::Maker));
+        assert_close(broker.free_balance("USDT"), 10_000.0 - 3900.0);
+        assert_close(broker.free_balance("ETH"), 2.0 * 0.999);
+    }
+
+    #[test]
+    fn test_stop_limit_gap_and_oco() {
+        let mut broker = broker(0.0);
+        broker.deposit("ETH", 2.0);
+        // Sell stop at 1900 gapped through to 1890, still above the 1880 limit
+        broker.submit(1, &OrderIntent::stop_limit("ETHUSDT", Side::Sell, 1.0, 1900.0, 1880.0), 0);
+        assert_eq!(broker.submit(2, &OrderIntent::stop_limit("ETHUSDT", Side::Sell, 1.0, 2100.0, 2090.0), 0).status, OrderStatus::Rejected);
+        let updates = broker.process_bar("ETHUSDT", 60_000, 1890.0, 1895.0, 1850.0, 1860.0);
+        assert_eq!(updates[0].fill.as_ref().unwrap().price, 1890.0);
+
+        // Both legs are reached within the bar, the stop is assumed first
+        broker.submit(3, &OrderIntent::oco("ETHUSDT", Side::Sell, 1.0, 1900.0, 1800.0, 1790.0), 0);
+        let updates = broker.process_bar("ETHUSDT", 120_000, 1860.0, 1910.0, 1795.0, 1850.0);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].fill.as_ref().unwrap().price, 1800.0);
+        assert_close(broker.total_balance("ETH"), 0.0);
+        assert_eq!(broker.open_order_ids().count(), 0);
+    }
+}
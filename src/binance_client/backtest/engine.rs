@@ -0,0 +1,364 @@
+use std::collections::{HashSet, VecDeque};
+use std::fs::File;
+use std::io::{BufReader, Error as IOError, ErrorKind};
+use std::path::Path;
+use std::time::Duration;
+use crate::binance_client::account::order_status::OrderStatus;
+use crate::binance_client::archive_importer::read_kline_csv;
+use crate::binance_client::backtest::broker::{SimFill, SimMarket, SimulatedBroker};
+use crate::binance_client::backtest::metrics::{round_trips, BacktestMetrics, BacktestTrade, EquityPoint};
+use crate::binance_client::database_client::DatabaseClient;
+use crate::binance_client::kline_interval::KlineInterval;
+use crate::binance_client::strategy::{OrderUpdate, Strategy, StrategyCommand, StrategyContext, MAX_COMMANDS_PER_EVENT};
+use crate::binance_client::streams::kline_data::Kline;
+
+/// Starting balances, markets and fill assumptions of a backtest.
+#[derive(Debug, Clone)]
+pub struct BacktestConfig {
+    pub quote_asset: String, // Equity is measured in this asset
+    pub initial_balances: Vec<(String, f64)>,
+    pub markets: Vec<SimMarket>,
+    pub slippage_bps: f64, // Applied to market and stop fills
+    pub timer_interval: Option<Duration>, // How often `on_timer` is due, in simulated time
+}
+
+impl BacktestConfig {
+    /// Starts with `initial_capital` of `quote_asset` and no markets.
+    pub fn new(quote_asset: &str, initial_capital: f64) -> Self {
+        BacktestConfig {
+            quote_asset: quote_asset.to_string(),
+            initial_balances: vec![(quote_asset.to_string(), initial_capital)],
+            markets: Vec::new(),
+            slippage_bps: 0.0,
+            timer_interval: None,
+        }
+    }
+
+    pub fn with_market(mut self, market: SimMarket) -> Self {
+        self.markets.push(market);
+        self
+    }
+
+    pub fn with_balance(mut self, asset: &str, amount: f64) -> Self {
+        self.initial_balances.push((asset.to_string(), amount));
+        self
+    }
+
+    pub fn with_slippage_bps(mut self, slippage_bps: f64) -> Self {
+        self.slippage_bps = slippage_bps;
+        self
+    }
+
+    pub fn with_timer(mut self, interval: Duration) -> Self {
+        self.timer_interval = Some(interval);
+        self
+    }
+}
+
+/// Outcome of a backtest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BacktestReport {
+    pub equity_curve: Vec<EquityPoint>, // The starting equity, then one point per bar close
+    pub fills: Vec<SimFill>,
+    pub trades: Vec<BacktestTrade>,
+    pub rejections: Vec<OrderUpdate>,
+    pub metrics: BacktestMetrics,
+}
+
+/// Replays closed klines through a [`Strategy`] against a [`SimulatedBroker`].
+///
+/// Klines of all symbols are replayed in close time order. For each kline the orders
+/// resting on its symbol are matched first, then the strategy sees the kline, so an
+/// order placed in `on_kline` is matched from the next kline on. Equity is recorded
+/// after all klines closing at the same time were handled.
+///
+/// Time only moves from one kline close to the next, so a due timer fires once at the
+/// first close at or after it was due, after the strategy saw the klines of that close.
+#[derive(Debug, Clone)]
+pub struct Backtester {
+    config: BacktestConfig,
+    klines: Vec<Kline>,
+}
+
+impl Backtester {
+    pub fn new(config: BacktestConfig) -> Self {
+        Backtester { config, klines: Vec::new() }
+    }
+
+    pub fn add_klines(&mut self, klines: impl IntoIterator<Item = Kline>) {
+        self.klines.extend(klines);
+    }
+
+    /// Adds the stored klines opening in `[start_time, end_time)` and returns how many.
+    pub async fn load_klines(
+        &mut self,
+        db: &DatabaseClient,
+        symbol: &str,
+        interval: KlineInterval,
+        start_time: u64,
+        end_time: u64,
+    ) -> Result<usize, IOError> {
+        let klines = db.klines(symbol, interval, start_time, end_time).await?;
+        let count = klines.len();
+        self.add_klines(klines);
+        Ok(count)
+    }
+
+    /// Adds the klines of a CSV file in the data.binance.vision kline format.
+    pub fn load_csv(&mut self, path: &Path, symbol: &str, interval: KlineInterval) -> Result<usize, IOError> {
+        let klines = read_kline_csv(BufReader::new(File::open(path)?), &symbol.to_uppercase(), interval)?;
+        let count = klines.len();
+        self.add_klines(klines);
+        Ok(count)
+    }
+
+    pub fn run<S: Strategy + ?Sized>(&self, strategy: &mut S) -> Result<BacktestReport, IOError> {
+        let mut klines: Vec<&Kline> = self.klines.iter().filter(|kline| kline.is_kline_closed).collect();
+        klines.sort_by(|a, b| (a.end_time, &a.symbol).cmp(&(b.end_time, &b.symbol)));
+        let first = *klines.first().ok_or_else(|| IOError::new(ErrorKind::InvalidInput, "No closed klines to backtest"))?;
+        for market in &self.config.markets {
+            if market.quote_asset() != self.config.quote_asset {
+                return Err(IOError::new(ErrorKind::InvalidInput, format!(
+                    "{} is not quoted in {}", market.symbol, self.config.quote_asset
+                )));
+            }
+        }
+
+        let mut broker = SimulatedBroker::new(self.config.markets.clone(), self.config.slippage_bps);
+        for (asset, amount) in &self.config.initial_balances {
+            broker.deposit(asset, *amount);
+        }
+        // Value starting balances at the first open of each symbol
+        let mut priced = HashSet::new();
+        for kline in &klines {
+            if broker.market(&kline.symbol).is_none() {
+                return Err(IOError::new(ErrorKind::InvalidInput, format!("No market configured for {}", kline.symbol)));
+            }
+            if priced.insert(&kline.symbol) {
+                broker.process_bar(&kline.symbol, kline.start_time, kline.open_price, kline.open_price, kline.open_price, kline.open_price);
+            }
+        }
+
+        let mut run = Run { broker, rejections: Vec::new() };
+        let mut ctx = StrategyContext::new();
+        ctx.set_time(first.start_time);
+        for kline in &klines {
+            ctx.set_price(&kline.symbol, run.broker.last_price(&kline.symbol).unwrap_or(kline.open_price));
+        }
+        run.sync(&mut ctx);
+        let mut equity_curve = vec![EquityPoint { time: first.start_time, equity: run.broker.equity(&self.config.quote_asset) }];
+
+        strategy.on_start(&mut ctx);
+        run.execute(strategy, &mut ctx, Vec::new())?;
+
+        // Interval and due time in milliseconds
+        let mut timer = self.config.timer_interval.map(|interval| {
+            let interval = (interval.as_millis() as u64).max(1);
+            (interval, first.start_time + interval)
+        });
+
+        for (index, kline) in klines.iter().enumerate() {
+            ctx.set_time(kline.end_time);
+            let updates = run.broker.process_kline(kline);
+            ctx.set_price(&kline.symbol, kline.close_price);
+            run.execute(strategy, &mut ctx, updates)?;
+
+            strategy.on_kline(&mut ctx, kline);
+            run.execute(strategy, &mut ctx, Vec::new())?;
+
+            let closes_group = klines.get(index + 1).is_none_or(|next| next.end_time != kline.end_time);
+            if let Some((interval, due)) = timer.filter(|(_, due)| closes_group && *due <= kline.end_time) {
+                strategy.on_timer(&mut ctx);
+                run.execute(strategy, &mut ctx, Vec::new())?;
+                timer = Some((interval, due + ((kline.end_time - due) / interval + 1) * interval));
+            }
+            if closes_group {
+                equity_curve.push(EquityPoint { time: kline.end_time, equity: run.broker.equity(&self.config.quote_asset) });
+            }
+        }
+
+        let fills = run.broker.fills().to_vec();
+        let trades = round_trips(&fills, &self.config.markets);
+        let metrics = BacktestMetrics::new(&equity_curve, &trades);
+        Ok(BacktestReport {
+            equity_curve,
+            fills,
+            trades,
+            rejections: run.rejections,
+            metrics,
+        })
+    }
+}
+
+// State of one run that the strategy callbacks do not borrow
+struct Run {
+    broker: SimulatedBroker,
+    rejections: Vec<OrderUpdate>,
+}
+
+impl Run {
+    // Executes queued commands and delivers updates until the strategy stops reacting
+    fn execute<S: Strategy + ?Sized>(&mut self, strategy: &mut S, ctx: &mut StrategyContext, updates: Vec<OrderUpdate>) -> Result<(), IOError> {
+        let mut pending = VecDeque::from(updates);
+        let mut commands = 0;
+        loop {
+            for command in ctx.take_commands() {
+                commands += 1;
+                match command {
+                    StrategyCommand::Submit(order_id, intent) => pending.push_back(self.broker.submit(order_id, &intent, ctx.time())),
+                    StrategyCommand::Cancel(order_id) => pending.extend(self.broker.cancel(order_id, ctx.time())),
+                }
+            }
+            if commands > MAX_COMMANDS_PER_EVENT {
+                return Err(IOError::new(ErrorKind::Other, format!(
+                    "Strategy queued more than {} commands at {}", MAX_COMMANDS_PER_EVENT, ctx.time()
+                )));
+            }
+
+            let Some(update) = pending.pop_front() else { return Ok(()) };
+            self.sync(ctx);
+            ctx.apply_update(&update);
+            if update.status == OrderStatus::Rejected {
+                self.rejections.push(update.clone());
+            }
+            strategy.on_order_update(ctx, &update);
+        }
+    }
+
+    fn sync(&self, ctx: &mut StrategyContext) {
+        for (asset, free) in self.broker.free_balances() {
+            ctx.set_balance(asset, free);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binance_client::fee_model::FeeModel;
+    use crate::binance_client::order_types::side::Side;
+    use crate::binance_client::strategy::{OrderIntent, OrderId};
+
+    const MINUTE: u64 = 60_000;
+
+    fn kline(index: u64, open: f64, high: f64, low: f64, close: f64) -> Kline {
+        Kline {
+            start_time: index * MINUTE,
+            end_time: (index + 1) * MINUTE - 1,
+            symbol: "ETHUSDT".to_string(),
+            interval: "1m".to_string(),
+            first_trade_id: 0,
+            last_trade_id: 0,
+            open_price: open,
+            close_price: close,
+            high_price: high,
+            low_price: low,
+            base_asset_volume: 1.0,
+            number_of_trades: 1,
+            is_kline_closed: true,
+            quote_asset_volume: close,
+            taker_buy_base_asset_volume: 0.5,
+            taker_buy_quote_asset_volume: close / 2.0,
+            ignore: "0".to_string(),
+        }
+    }
+
+    // Buys on the first kline and protects the position with an OCO order once filled
+    #[derive(Default)]
+    struct BuyWithBracket {
+        entry: Option<OrderId>,
+        updates: Vec<OrderStatus>,
+    }
+
+    impl Strategy for BuyWithBracket {
+        fn on_kline(&mut self, ctx: &mut StrategyContext, _kline: &Kline) {
+            if self.entry.is_none() {
+                self.entry = Some(ctx.submit(OrderIntent::market("ETHUSDT", Side::Buy, 1.0)));
+            }
+        }
+
+        fn on_order_update(&mut self, ctx: &mut StrategyContext, update: &OrderUpdate) {
+            self.updates.push(update.status);
+            if Some(update.order_id) == self.entry && update.status == OrderStatus::Filled {
+                let quantity = ctx.balance("ETH");
+                ctx.submit(OrderIntent::oco("ETHUSDT", Side::Sell, quantity, 110.0, 95.0, 94.0));
+            }
+        }
+    }
+
+    #[test]
+    fn test_backtest_round_trip() {
+        let config = BacktestConfig::new("USDT", 1_000.0)
+            .with_market(SimMarket::new("ETHUSDT", FeeModel::flat(0.0, 0.001, "ETH", "USDT")));
+        let mut backtester = Backtester::new(config);
+        backtester.add_klines(vec![
+            kline(0, 100.0, 101.0, 99.0, 100.0),
+            kline(1, 100.0, 102.0, 99.0, 101.0),
+            kline(2, 101.0, 111.0, 100.0, 108.0),
+            kline(3, 108.0, 109.0, 90.0, 91.0),
+        ]);
+
+        let mut strategy = BuyWithBracket::default();
+        let report = backtester.run(&mut strategy).unwrap();
+        assert_eq!(strategy.updates, vec![OrderStatus::New, OrderStatus::Filled, OrderStatus::New, OrderStatus::Filled]);
+
+        // Bought at the second open, sold by the take profit leg
+        assert_eq!(report.fills.iter().map(|fill| fill.price).collect::<Vec<_>>(), vec![100.0, 110.0]);
+        assert_eq!(report.trades.len(), 1);
+        let trade = &report.trades[0];
+        let expected_pnl = 0.999 * 110.0 - 100.0;
+        assert!((trade.pnl - expected_pnl).abs() < 1e-9, "{}", trade.pnl);
+        assert_eq!(report.metrics.win_rate, 1.0);
+
+        assert_eq!(report.equity_curve.len(), 5);
+        assert_eq!(report.equity_curve[0].equity, 1_000.0);
+        let final_equity = report.equity_curve.last().unwrap().equity;
+        assert!((final_equity - (1_000.0 + expected_pnl)).abs() < 1e-9);
+        assert_eq!(report.metrics.max_drawdown, 0.0);
+        assert!((report.metrics.total_return - expected_pnl / 1_000.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_metrics() {
+        let curve: Vec<EquityPoint> = [100.0, 110.0, 99.0, 120.0]
+            .iter()
+            .enumerate()
+            .map(|(day, equity)| EquityPoint { time: day as u64 * 86_400_000, equity: *equity })
+            .collect();
+        let metrics = BacktestMetrics::new(&curve, &[]);
+        assert!((metrics.total_return - 0.2).abs() < 1e-12);
+        assert!((metrics.max_drawdown - 0.1).abs() < 1e-12);
+        assert!(metrics.sharpe > 0.0 && metrics.sortino > metrics.sharpe);
+        assert_eq!((metrics.win_rate, metrics.expectancy, metrics.trades), (0.0, 0.0, 0));
+    }
+
+    #[test]
+    fn test_timer_fires_at_bar_closes() {
+        #[derive(Default)]
+        struct Timed(Vec<u64>);
+
+        impl Strategy for Timed {
+            fn on_timer(&mut self, ctx: &mut StrategyContext) {
+                self.0.push(ctx.time());
+            }
+        }
+
+        let config = BacktestConfig::new("USDT", 1_000.0)
+            .with_market(SimMarket::new("ETHUSDT", FeeModel::flat(0.0, 0.0, "ETH", "USDT")))
+            .with_timer(Duration::from_secs(120));
+        let mut backtester = Backtester::new(config);
+        backtester.add_klines((0..5).map(|index| kline(index, 1.0, 1.0, 1.0, 1.0)));
+
+        let mut strategy = Timed::default();
+        backtester.run(&mut strategy).unwrap();
+        assert_eq!(strategy.0, vec![3 * MINUTE - 1, 5 * MINUTE - 1]);
+    }
+
+    #[test]
+    fn test_unknown_symbol_is_rejected() {
+        let mut backtester = Backtester::new(BacktestConfig::new("USDT", 1_000.0));
+        backtester.add_klines(vec![kline(0, 1.0, 1.0, 1.0, 1.0)]);
+        assert_eq!(backtester.run(&mut BuyWithBracket::default()).unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use crate::binance_client::backtest::broker::{SimFill, SimMarket};
+use crate::binance_client::order_types::side::Side;
+
+// Milliseconds in an average Gregorian year
+const YEAR_MS: f64 = 365.2425 * 24.0 * 60.0 * 60.0 * 1000.0;
+
+/// Account value in the backtest's quote asset after a bar.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EquityPoint {
+    pub time: u64,
+    pub equity: f64,
+}
+
+/// A round trip: from the first buy while flat until the position is sold off again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BacktestTrade {
+    pub symbol: String,
+    pub entry_time: u64,
+    pub exit_time: u64,
+    pub quantity: f64, // Bought, before commissions taken from it
+    pub entry_price: f64, // Average buy price
+    pub exit_price: f64, // Average sell price
+    pub fees: f64, // In the quote asset
+    pub pnl: f64, // Net of fees, in the quote asset
+    pub return_pct: f64, // PnL relative to the cost of the buys, 0 to 100
+}
+
+/// Standard performance statistics of a backtest.
+///
+/// Ratios are annualised from the bar spacing of the equity curve, with a zero risk-free
+/// rate. Statistics that are undefined, such as the Sharpe ratio of a flat equity curve
+/// or the win rate without trades, are zero.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct BacktestMetrics {
+    pub total_return: f64, // Fraction, 0.1 is 10%
+    pub cagr: f64,
+    pub sharpe: f64,
+    pub sortino: f64,
+    pub max_drawdown: f64, // Largest fall from a peak, as a fraction of the peak
+    pub win_rate: f64, // Fraction of trades with a positive PnL
+    pub expectancy: f64, // Mean PnL per trade in the quote asset
+    pub trades: usize,
+}
+
+impl BacktestMetrics {
+    pub fn new(equity_curve: &[EquityPoint], trades: &[BacktestTrade]) -> Self {
+        let mut metrics = BacktestMetrics {
+            trades: trades.len(),
+            ..Default::default()
+        };
+        if !trades.is_empty() {
+            metrics.win_rate = trades.iter().filter(|trade| trade.pnl > 0.0).count() as f64 / trades.len() as f64;
+            metrics.expectancy = trades.iter().map(|trade| trade.pnl).sum::<f64>() / trades.len() as f64;
+        }
+
+        let (Some(first), Some(last)) = (equity_curve.first(), equity_curve.last()) else { return metrics };
+        if first.equity <= 0.0 {
+            return metrics;
+        }
+        metrics.total_return = last.equity / first.equity - 1.0;
+        metrics.max_drawdown = max_drawdown(equity_curve);
+
+        let duration = last.time.saturating_sub(first.time) as f64;
+        if duration > 0.0 && last.equity > 0.0 {
+            metrics.cagr = (last.equity / first.equity).powf(YEAR_MS / duration) - 1.0;
+        }
+
+        let returns: Vec<f64> = equity_curve
+            .windows(2)
+            .filter(|pair| pair[0].equity > 0.0)
+            .map(|pair| pair[1].equity / pair[0].equity - 1.0)
+            .collect();
+        if returns.len() < 2 {
+            return metrics;
+        }
+        let periods_per_year = YEAR_MS / (duration / returns.len() as f64);
+        let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+        let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (returns.len() - 1) as f64;
+        let downside = (returns.iter().map(|r| r.min(0.0).powi(2)).sum::<f64>() / returns.len() as f64).sqrt();
+        if variance > 0.0 {
+            metrics.sharpe = mean / variance.sqrt() * periods_per_year.sqrt();
+        }
+        if downside > 0.0 {
+            metrics.sortino = mean / downside * periods_per_year.sqrt();
+        }
+        metrics
+    }
+}
+
+fn max_drawdown(equity_curve: &[EquityPoint]) -> f64 {
+    let mut peak = f64::MIN;
+    let mut drawdown: f64 = 0.0;
+    for point in equity_curve {
+        peak = peak.max(point.equity);
+        if peak > 0.0 {
+            drawdown = drawdown.max((peak - point.equity) / peak);
+        }
+    }
+    drawdown
+}
+
+/// Groups fills into round trips per symbol.
+///
+/// A trade closes once what is left of the position is less than the lot step size of
+/// its market, which cannot be sold anyway. Commissions charged in the base asset shrink
+/// the position, commissions in other assets only count towards `fees`. Sells while flat
+/// and a position still open at the end are not trades.
+pub fn round_trips(fills: &[SimFill], markets: &[SimMarket]) -> Vec<BacktestTrade> {
+    struct OpenTrade {
+        entry_time: u64,
+        position: f64,
+        bought: f64,
+        cost: f64,
+        sold: f64,
+        proceeds: f64,
+        fees: f64,
+        // Base asset commissions already shrank the proceeds, the others are charged on top
+        charged_fees: f64,
+    }
+
+    let mut open: HashMap<&str, OpenTrade> = HashMap::new();
+    let mut trades = Vec::new();
+    for fill in fills {
+        let Some(market) = markets.iter().find(|market| market.symbol == fill.symbol) else { continue };
+        let notional = fill.price * fill.quantity;
+        let base_fee = if fill.fee.asset == market.base_asset() { fill.fee.amount } else { 0.0 };
+
+        match fill.side {
+            Side::Buy => {
+                let trade = open.entry(&fill.symbol).or_insert(OpenTrade {
+                    entry_time: fill.time,
+                    position: 0.0,
+                    bought: 0.0,
+                    cost: 0.0,
+                    sold: 0.0,
+                    proceeds: 0.0,
+                    fees: 0.0,
+                    charged_fees: 0.0,
+                });
+                trade.position += fill.quantity - base_fee;
+                trade.bought += fill.quantity;
+                trade.cost += notional;
+                trade.fees += fill.fee.quote_value;
+                trade.charged_fees += fill.fee.quote_value - base_fee * fill.price;
+            }
+            Side::Sell => {
+                let Some(trade) = open.get_mut(fill.symbol.as_str()) else { continue };
+                trade.position -= fill.quantity + base_fee;
+                trade.sold += fill.quantity;
+                trade.proceeds += notional;
+                trade.fees += fill.fee.quote_value;
+                trade.charged_fees += fill.fee.quote_value - base_fee * fill.price;
+
+                if trade.position < market.filters.step_size.max(1e-12) {
+                    let trade = open.remove(fill.symbol.as_str()).unwrap();
+                    let pnl = trade.proceeds - trade.cost - trade.charged_fees;
+                    trades.push(BacktestTrade {
+                        symbol: fill.symbol.clone(),
+                        entry_time: trade.entry_time,
+                        exit_time: fill.time,
+                        quantity: trade.bought,
+                        entry_price: trade.cost / trade.bought,
+                        exit_price: trade.proceeds / trade.sold,
+                        fees: trade.fees,
+                        pnl,
+                        return_pct: pnl / trade.cost * 100.0,
+                    });
+                }
+            }
+        }
+    }
+    trades
+}
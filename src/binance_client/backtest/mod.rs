@@ -0,0 +1,3 @@
+pub mod broker;
+pub mod engine;
+pub mod metrics;
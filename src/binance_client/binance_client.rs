@@ -5,9 +5,9 @@ use std::time::{SystemTime, UNIX_EPOCH};
 use async_tungstenite::tungstenite::http::Uri;
 use async_tungstenite::tungstenite::WebSocket;
 use hmac::{Hmac, KeyInit, Mac};
-use log::{error, info, trace};
+use log::{error, info, trace, warn};
 use sha2::Sha256;
-use futures::StreamExt;
+use futures::{Stream, StreamExt};
 use futures::FutureExt;
 use futures_util::SinkExt;
 use serde::de::DeserializeOwned;
@@ -17,16 +17,24 @@ use std::fs::File;
 use std::io::{Error as IOError, ErrorKind, Write};
 
 use crate::binance_client::account::order::Order;
+use crate::binance_client::account::commission::AccountCommission;
+use crate::binance_client::account::history::{paginate, HistoryQuery, HistoryStart, IdParam, DEFAULT_PAGE_DELAY};
 use crate::binance_client::deserialization::deserialize_string_to_f64;
 use crate::binance_client::account::open_order::OpenOrder;
 use crate::binance_client::account::trades::Trade;
 use crate::binance_client::binance_error::BinanceError;
 use crate::binance_client::database_client::DatabaseClient;
+use crate::binance_client::database_config::DatabaseConfig;
 use crate::binance_client::exchange_info::ExchangeInfo;
+use crate::binance_client::market_data::{DepthSnapshot, MarketTrade};
+use crate::binance_client::order_response::OrderListResponse;
+use crate::binance_client::sbe;
+use crate::binance_client::sbe::{SbeDecode, SBE_SCHEMA_ID, SBE_SCHEMA_VERSION};
 use crate::binance_client::position_size::round;
 use crate::binance_client::streams::binance_stream::BinanceStreamTypes;
-use crate::binance_client::streams::kline_data::KlineMessage;
-use crate::binance_client::ticker_price::TickerPrice;
+use crate::binance_client::streams::kline_data::{KlineMessage, RestKline};
+use crate::binance_client::kline_interval::KlineInterval;
+use crate::binance_client::ticker_price::{TickerPrice, TickerStatistics};
 
 const BINANCE_API_URL: &str = "https://api.binance.com/api";
 const BINANCE_API_TEST_URL: &str = "https://testnet.binance.vision/api";
@@ -37,6 +45,9 @@ const BINANCE_WS_TEST_URL: &str = "wss://testnet.binance.vision/ws";
 const BINANCE_STREAM_URL: &str = "wss://stream.binance.com:9443/stream";
 const BINANCE_STREAM_TEST_URL: &str = "wss://testnet.binance.vision/stream";
 
+const BINANCE_WS_API_URL: &str = "wss://ws-api.binance.com:443/ws-api/v3";
+const BINANCE_WS_API_TEST_URL: &str = "wss://testnet.binance.vision/ws-api/v3";
+
 #[derive(Debug)]
 pub struct BinanceClient {
     api_key: String,
@@ -47,6 +58,7 @@ pub struct BinanceClient {
     pub api_url: String,
     pub websocket_url: String,
     pub stream_url: String,
+    pub ws_api_url: String,
     // user: String,
     // pwd: String,
     // dbname: String,
@@ -54,10 +66,10 @@ pub struct BinanceClient {
 
 impl BinanceClient {
     pub async fn new(api_key: String, api_secret: String, is_live: bool) -> Self {
-        let (api_url, websocket_url, stream_url) = if is_live {
-            (BINANCE_API_URL.to_string(), BINANCE_WS_URL.to_string(), BINANCE_STREAM_URL.to_string())
+        let (api_url, websocket_url, stream_url, ws_api_url) = if is_live {
+            (BINANCE_API_URL.to_string(), BINANCE_WS_URL.to_string(), BINANCE_STREAM_URL.to_string(), BINANCE_WS_API_URL.to_string())
         } else {
-            (BINANCE_API_TEST_URL.to_string(), BINANCE_WS_TEST_URL.to_string(), BINANCE_STREAM_TEST_URL.to_string())
+            (BINANCE_API_TEST_URL.to_string(), BINANCE_WS_TEST_URL.to_string(), BINANCE_STREAM_TEST_URL.to_string(), BINANCE_WS_API_TEST_URL.to_string())
         };
 
         BinanceClient {
@@ -69,6 +81,7 @@ impl BinanceClient {
             api_url,
             websocket_url,
             stream_url,
+            ws_api_url,
         }
     }
 
@@ -77,16 +90,13 @@ impl BinanceClient {
         self.api_url = if is_live { BINANCE_API_URL } else { BINANCE_API_TEST_URL }.to_string();
         self.websocket_url = if is_live { BINANCE_WS_URL } else { BINANCE_WS_TEST_URL }.to_string();
         self.stream_url = if is_live { BINANCE_STREAM_URL } else { BINANCE_STREAM_TEST_URL }.to_string();
+        self.ws_api_url = if is_live { BINANCE_WS_API_URL } else { BINANCE_WS_API_TEST_URL }.to_string();
     }
 
 
     // Method to optionally initialize the database client
-    pub async fn init_db_client(&mut self, user: &str, pwd: &str, dbname: &str) -> Result<(), IOError> {
-        // let user = user.unwrap_or("default_user");
-        // let pwd = pwd.unwrap_or("default_password");
-        // let dbname = dbname.unwrap_or("BinanceData");
-
-        match DatabaseClient::connect_or_create_if_not_exist(dbname, user, pwd).await {
+    pub async fn init_db_client(&mut self, config: &DatabaseConfig) -> Result<(), IOError> {
+        match DatabaseClient::connect_or_create_if_not_exist(config).await {
             Ok(db_client) => {
                 self.db_client = Some(db_client);
                 Ok(())
@@ -97,6 +107,12 @@ impl BinanceClient {
         }
     }
 
+    /// The pooled database client, if [`BinanceClient::init_db_client`] was called.
+    /// Clone it to share the pool with other tasks.
+    pub fn db_client(&self) -> Option<&DatabaseClient> {
+        self.db_client.as_ref()
+    }
+
     pub async fn ping(&self) -> Result<(), IOError> {
         let url = format!("{}/v3/ping", self.api_url);
 
@@ -179,6 +195,10 @@ impl BinanceClient {
 
     // Generic function to fetch and deserialize data from Binance API
     async fn fetch_from_api<T: DeserializeOwned>(&self, endpoint: &str, params: &str) -> Result<Vec<T>, IOError> {
+        self.fetch_signed::<Vec<T>>(endpoint, params).await
+    }
+
+    async fn fetch_signed<T: DeserializeOwned>(&self, endpoint: &str, params: &str) -> Result<T, IOError> {
         let signature = self.sign(params);
         let url = format!("{}{}?{}&signature={}", self.api_url, endpoint, params, signature);
 
@@ -192,7 +212,7 @@ impl BinanceClient {
 
         if response.status().is_success() {
             response
-                .json::<Vec<T>>()
+                .json::<T>()
                 .await
                 .map_err(|err| IOError::new(ErrorKind::Other, format!("Binance client: Failed to deserialize response: {}", err)))
         } else {
@@ -203,20 +223,118 @@ impl BinanceClient {
     }
 
     pub async fn fetch_all_orders(&self, symbol: &str) -> Result<Vec<Order>, IOError> {
-        let params = format!("symbol={}&timestamp={}", symbol, Self::generate_timestamp().unwrap());
+        self.fetch_all_orders_with(symbol, &HistoryQuery::default()).await
+    }
+
+    /// Fetches one page of `/v3/allOrders`. With `orderId` set, returns orders from that id on.
+    pub async fn fetch_all_orders_with(&self, symbol: &str, query: &HistoryQuery) -> Result<Vec<Order>, IOError> {
+        query.validate()?;
+        let params = format!("symbol={}&{}timestamp={}", symbol, query.to_params(), Self::generate_timestamp().unwrap());
         self.fetch_from_api::<Order>("/v3/allOrders", &params).await
     }
 
+    /// Looks up an order of `symbol` by the `newClientOrderId` it was placed with, `None`
+    /// if there is none.
+    pub async fn fetch_order_by_client_id(&self, symbol: &str, client_order_id: &str) -> Result<Option<Order>, IOError> {
+        let params = format!("symbol={}&origClientOrderId={}&timestamp={}", symbol, client_order_id, Self::generate_timestamp()?);
+        let signature = self.sign(&params);
+        let url = format!("{}/v3/order?{}&signature={}", self.api_url, params, signature);
+
+        let response = self.client
+            .get(&url)
+            .header("X-MBX-APIKEY", self.api_key.clone())
+            .send()
+            .await
+            .map_err(|err| IOError::new(ErrorKind::Other, format!("HTTP request failed: {}", err)))?;
+
+        if response.status().is_success() {
+            return response
+                .json::<Order>()
+                .await
+                .map(Some)
+                .map_err(|err| IOError::new(ErrorKind::Other, format!("Binance client: Failed to deserialize response: {}", err)));
+        }
+        let error_body = response.text().await.unwrap_or_else(|_| "Failed to read error message".to_string());
+        match serde_json::from_str::<BinanceError>(&error_body) {
+            Ok(error) if error.code == -2013 => Ok(None), // Order does not exist
+            _ => Err(IOError::new(ErrorKind::Other, format!("Failed to fetch order {}: {}", client_order_id, error_body))),
+        }
+    }
+
+    /// Every open order of `symbol`; `/v3/openOrders` is not paginated.
     pub async fn fetch_open_orders(&self, symbol: &str) -> Result<Vec<OpenOrder>, IOError> {
         let params = format!("symbol={}&timestamp={}", symbol, Self::generate_timestamp().unwrap());
         self.fetch_from_api::<OpenOrder>("/v3/openOrders", &params).await
     }
 
     pub async fn fetch_my_trades(&self, symbol: &str) -> Result<Vec<Trade>, IOError> {
-        let params = format!("symbol={}&timestamp={}", symbol, Self::generate_timestamp().unwrap());
+        self.fetch_my_trades_with(symbol, &HistoryQuery::default()).await
+    }
+
+    /// Fetches one page of `/v3/myTrades`. With `fromId` set, returns trades from that id on.
+    pub async fn fetch_my_trades_with(&self, symbol: &str, query: &HistoryQuery) -> Result<Vec<Trade>, IOError> {
+        query.validate()?;
+        let params = format!("symbol={}&{}timestamp={}", symbol, query.to_params(), Self::generate_timestamp().unwrap());
         self.fetch_from_api::<Trade>("/v3/myTrades", &params).await
     }
 
+    /// Fetches one page of `/v3/allOrderList` across all symbols.
+    pub async fn fetch_all_order_lists(&self, query: &HistoryQuery) -> Result<Vec<OrderListResponse>, IOError> {
+        query.validate()?;
+        let params = format!("{}timestamp={}", query.to_params(), Self::generate_timestamp().unwrap());
+        self.fetch_from_api::<OrderListResponse>("/v3/allOrderList", &params).await
+    }
+
+    /// Streams every order of `symbol` from `start` until `end_time` (now if `None`), oldest first.
+    pub fn order_history<'a>(
+        &'a self,
+        symbol: &str,
+        start: HistoryStart,
+        end_time: Option<u64>,
+    ) -> impl Stream<Item = Result<Order, IOError>> + 'a {
+        let symbol = symbol.to_string();
+        let fetch = move |query: HistoryQuery| {
+            let symbol = symbol.clone();
+            async move { self.fetch_all_orders_with(&symbol, &query).await }
+        };
+        paginate(fetch, IdParam::OrderId, start, self.history_end(end_time), DEFAULT_PAGE_DELAY)
+    }
+
+    /// Streams every trade of `symbol` from `start` until `end_time` (now if `None`), oldest first.
+    pub fn trade_history<'a>(
+        &'a self,
+        symbol: &str,
+        start: HistoryStart,
+        end_time: Option<u64>,
+    ) -> impl Stream<Item = Result<Trade, IOError>> + 'a {
+        let symbol = symbol.to_string();
+        let fetch = move |query: HistoryQuery| {
+            let symbol = symbol.clone();
+            async move { self.fetch_my_trades_with(&symbol, &query).await }
+        };
+        paginate(fetch, IdParam::FromId, start, self.history_end(end_time), DEFAULT_PAGE_DELAY)
+    }
+
+    /// Streams every order list from `start` until `end_time` (now if `None`), oldest first.
+    pub fn order_list_history(
+        &self,
+        start: HistoryStart,
+        end_time: Option<u64>,
+    ) -> impl Stream<Item = Result<OrderListResponse, IOError>> + '_ {
+        let fetch = move |query: HistoryQuery| async move { self.fetch_all_order_lists(&query).await };
+        paginate(fetch, IdParam::FromId, start, self.history_end(end_time), DEFAULT_PAGE_DELAY)
+    }
+
+    fn history_end(&self, end_time: Option<u64>) -> u64 {
+        end_time.unwrap_or_else(|| Self::generate_timestamp().unwrap())
+    }
+
+    /// Fetches the standard, tax and special commission rates and the BNB discount for `symbol`.
+    pub async fn fetch_account_commission(&self, symbol: &str) -> Result<AccountCommission, IOError> {
+        let params = format!("symbol={}&timestamp={}", symbol, Self::generate_timestamp().unwrap());
+        self.fetch_signed::<AccountCommission>("/v3/account/commission", &params).await
+    }
+
     pub async fn cancel_all_open_orders(&self, symbol: &str) -> Result<Vec<Value>, IOError> {
         /*
         
@@ -291,6 +409,150 @@ impl BinanceClient {
         Ok(ticker_price)
     }
 
+    // Fetches the latest price of every symbol in a single request
+    pub async fn get_all_prices(&self) -> Result<Vec<TickerPrice>, IOError> {
+        let request_url = format!("{}/v3/ticker/price", self.api_url);
+        self.fetch_public_json(&request_url, "prices").await
+    }
+
+    // Fetches the rolling 24 hour statistics of a symbol
+    pub async fn fetch_24hr_ticker(&self, symbol: &str) -> Result<TickerStatistics, IOError> {
+        let request_url = format!("{}/v3/ticker/24hr?symbol={}", self.api_url, symbol);
+        self.fetch_public_json(&request_url, "24hr ticker").await
+    }
+
+    // Fetches klines from the REST api. Without start and end time the most recent klines are returned.
+    pub async fn fetch_klines(
+        &self,
+        symbol: &str,
+        interval: KlineInterval,
+        start_time: Option<u64>,
+        end_time: Option<u64>,
+        limit: Option<u16>,
+    ) -> Result<Vec<RestKline>, IOError> {
+        let params = Self::kline_params(symbol, interval, start_time, end_time, limit);
+        let url = format!("{}/v3/klines?{}", self.api_url, params);
+
+        let response = self.client
+            .get(&url)
+            .send()
+            .await
+            .map_err(|err| IOError::new(ErrorKind::Other, format!("HTTP request failed: {}", err)))?;
+
+        if response.status().is_success() {
+            response
+                .json::<Vec<RestKline>>()
+                .await
+                .map_err(|err| IOError::new(ErrorKind::Other, format!("Binance client: Failed to deserialize klines: {}", err)))
+        } else {
+            let error_msg = response.text().await.unwrap_or_else(|_| "Failed to read error message".to_string());
+            Err(IOError::new(ErrorKind::Other, format!("Failed to fetch klines: {}", error_msg)))
+        }
+    }
+
+    // Fetches the order book for a symbol. Valid limits are 1 to 5000, the default is 100.
+    pub async fn fetch_depth(&self, symbol: &str, limit: Option<u16>) -> Result<DepthSnapshot, IOError> {
+        let url = format!("{}/v3/depth?{}", self.api_url, Self::symbol_limit_params(symbol, limit));
+        self.fetch_public_json(&url, "depth").await
+    }
+
+    // Fetches the most recent public trades for a symbol.
+    pub async fn fetch_trades(&self, symbol: &str, limit: Option<u16>) -> Result<Vec<MarketTrade>, IOError> {
+        let url = format!("{}/v3/trades?{}", self.api_url, Self::symbol_limit_params(symbol, limit));
+        self.fetch_public_json(&url, "trades").await
+    }
+
+    /// Same as [`BinanceClient::fetch_depth`], with the response SBE encoded.
+    pub async fn fetch_depth_sbe(&self, symbol: &str, limit: Option<u16>) -> Result<DepthSnapshot, IOError> {
+        self.fetch_sbe("/v3/depth", &Self::symbol_limit_params(symbol, limit)).await
+    }
+
+    /// Same as [`BinanceClient::fetch_trades`], with the response SBE encoded.
+    pub async fn fetch_trades_sbe(&self, symbol: &str, limit: Option<u16>) -> Result<Vec<MarketTrade>, IOError> {
+        self.fetch_sbe("/v3/trades", &Self::symbol_limit_params(symbol, limit)).await
+    }
+
+    /// Same as [`BinanceClient::fetch_klines`], with the response SBE encoded.
+    pub async fn fetch_klines_sbe(
+        &self,
+        symbol: &str,
+        interval: KlineInterval,
+        start_time: Option<u64>,
+        end_time: Option<u64>,
+        limit: Option<u16>,
+    ) -> Result<Vec<RestKline>, IOError> {
+        self.fetch_sbe("/v3/klines", &Self::kline_params(symbol, interval, start_time, end_time, limit)).await
+    }
+
+    /// Same as [`BinanceClient::fetch_exchange_info`], with the response SBE encoded.
+    ///
+    /// SBE responses omit the server time, so `server_time` is left at zero.
+    pub async fn fetch_exchange_info_sbe(&self) -> Result<ExchangeInfo, IOError> {
+        self.fetch_sbe("/v3/exchangeInfo", "").await
+    }
+
+    // Sends an unsigned GET asking for an SBE response and decodes it into T
+    async fn fetch_sbe<T: SbeDecode>(&self, endpoint: &str, params: &str) -> Result<T, IOError> {
+        let url = format!("{}{}?{}", self.api_url, endpoint, params);
+        let response = self.client
+            .get(&url)
+            .header("Accept", "application/sbe")
+            .header("X-MBX-SBE", format!("{}:{}", SBE_SCHEMA_ID, SBE_SCHEMA_VERSION))
+            .send()
+            .await
+            .map_err(|err| IOError::new(ErrorKind::Other, format!("HTTP request failed: {}", err)))?;
+
+        if response.headers().contains_key("X-MBX-SBE-DEPRECATED") {
+            warn!("SBE schema {}:{} is deprecated", SBE_SCHEMA_ID, SBE_SCHEMA_VERSION);
+        }
+        if response.status() == reqwest::StatusCode::NOT_ACCEPTABLE {
+            return Err(IOError::new(ErrorKind::Unsupported, "SBE is not enabled on this exchange"));
+        }
+        // Errors are SBE encoded too, so the body is decoded regardless of the status
+        let body = response.bytes().await
+            .map_err(|err| IOError::new(ErrorKind::Other, format!("Failed to read response body: {}", err)))?;
+        sbe::decode(&body)
+    }
+
+    async fn fetch_public_json<T: DeserializeOwned>(&self, url: &str, what: &str) -> Result<T, IOError> {
+        let response = self.client
+            .get(url)
+            .send()
+            .await
+            .map_err(|err| IOError::new(ErrorKind::Other, format!("HTTP request failed: {}", err)))?;
+
+        if response.status().is_success() {
+            response
+                .json::<T>()
+                .await
+                .map_err(|err| IOError::new(ErrorKind::Other, format!("Binance client: Failed to deserialize {}: {}", what, err)))
+        } else {
+            let error_msg = response.text().await.unwrap_or_else(|_| "Failed to read error message".to_string());
+            Err(IOError::new(ErrorKind::Other, format!("Failed to fetch {}: {}", what, error_msg)))
+        }
+    }
+
+    fn symbol_limit_params(symbol: &str, limit: Option<u16>) -> String {
+        match limit {
+            Some(limit) => format!("symbol={}&limit={}", symbol, limit),
+            None => format!("symbol={}", symbol),
+        }
+    }
+
+    fn kline_params(symbol: &str, interval: KlineInterval, start_time: Option<u64>, end_time: Option<u64>, limit: Option<u16>) -> String {
+        let mut params = format!("symbol={}&interval={}", symbol, interval);
+        if let Some(start_time) = start_time {
+            params.push_str(&format!("&startTime={}", start_time));
+        }
+        if let Some(end_time) = end_time {
+            params.push_str(&format!("&endTime={}", end_time));
+        }
+        if let Some(limit) = limit {
+            params.push_str(&format!("&limit={}", limit));
+        }
+        params
+    }
+
     pub async fn get_listen_key(&self) -> Result<String, IOError> {
         let url = format!("{}/v3/userDataStream", self.api_url);
         let res = self.client.post(&url)
@@ -307,6 +569,37 @@ impl BinanceClient {
         }
     }
 
+    // Binance closes a user data stream 60 minutes after its listen key was created or last kept alive
+    pub async fn keep_alive_listen_key(&self, listen_key: &str) -> Result<(), IOError> {
+        let url = format!("{}/v3/userDataStream?listenKey={}", self.api_url, listen_key);
+        let res = self.client.put(&url)
+            .header("X-MBX-APIKEY", &self.api_key)
+            .send()
+            .await
+            .map_err(|err| IOError::new(ErrorKind::Other, format!("Failed to keep listen key alive: {}", err)))?;
+
+        if res.status().is_success() {
+            Ok(())
+        } else {
+            Err(IOError::new(ErrorKind::Other, format!("Failed to keep listen key alive: {}", res.status())))
+        }
+    }
+
+    pub async fn close_listen_key(&self, listen_key: &str) -> Result<(), IOError> {
+        let url = format!("{}/v3/userDataStream?listenKey={}", self.api_url, listen_key);
+        let res = self.client.delete(&url)
+            .header("X-MBX-APIKEY", &self.api_key)
+            .send()
+            .await
+            .map_err(|err| IOError::new(ErrorKind::Other, format!("Failed to close listen key: {}", err)))?;
+
+        if res.status().is_success() {
+            Ok(())
+        } else {
+            Err(IOError::new(ErrorKind::Other, format!("Failed to close listen key: {}", res.status())))
+        }
+    }
+
     pub async fn create_websocket_stream_with_listen_key(&self) -> Result<(), IOError> {
         match self.get_listen_key().await {
             Ok(listen_key) => {
@@ -477,11 +770,11 @@ mod tests {
         trace!("vars: {:?}", vars);
 
         async fn drop_if_database_exists(vars: &EnvVars) {
-            DatabaseClient::drop_database_if_exists(vars.name.as_str(), vars.user.as_str(), vars.pwd.as_str())
+            DatabaseClient::drop_database_if_exists(&vars.database_config())
                 .await.expect("Failed to delete the database")
         }
         async fn db_exist(vars: &EnvVars) -> bool {
-            DatabaseClient::database_exists(vars.name.as_str(), vars.user.as_str(), vars.pwd.as_str()).await.unwrap()
+            DatabaseClient::database_exists(&vars.database_config()).await.unwrap()
         }
 
         // Ensure the database does not exist
@@ -491,11 +784,7 @@ mod tests {
         // Assuming you have a method or process to do this
         // For example, using `init_db_client` if it creates the database when it doesn't exist
         let mut client = BinanceClient::new(vars.api_key.to_string(), vars.api_secret.to_string(), false).await;
-        client.init_db_client(
-            vars.name.as_str(),
-            vars.user.as_str(),
-            vars.pwd.as_str(),
-        ).await.expect("Failed to initialize or create the database");
+        client.init_db_client(&vars.database_config()).await.expect("Failed to initialize or create the database");
 
         // Verify the database now exists
         assert!(db_exist(&vars).await, "Database should exist after creation");
@@ -505,7 +794,7 @@ mod tests {
         // Delete the database
         drop_if_database_exists(&vars).await;
         // Ensure the database no longer exists
-        assert!(db_exist(&vars).await, "Database should be deleted by the end of the test");
+        assert!(!db_exist(&vars).await, "Database should be deleted by the end of the test");
     }
 
 
@@ -0,0 +1,604 @@
+use std::collections::HashSet;
+use std::io::{Error as IOError, ErrorKind};
+use log::{info, trace, warn};
+use crate::binance_client::account::order_status::OrderStatus;
+use crate::binance_client::exchange_info::{SymbolFilters, SymbolInfo};
+use crate::binance_client::order_api::OrderApi;
+use crate::binance_client::order_response::OrderResponse;
+use crate::binance_client::order_types::cancel_replace_order::CancelReplaceOrder;
+use crate::binance_client::order_types::limit_order::LimitOrder;
+use crate::binance_client::order_types::market_order::MarketOrder;
+use crate::binance_client::order_types::side::Side;
+use crate::binance_client::order_types::stop_limit_order::StopLimitOrder;
+use crate::binance_client::order_types::time_in_force::TimeInForce;
+use crate::binance_client::position_size::PositionPlan;
+use crate::binance_client::streams::user_data::{ExecutionReport, ExecutionType};
+
+/// How far a trailing stop follows the best price since the entry.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrailingStop {
+    Ticks(u32),
+    Percent(f64),
+    // Multiple of the ATR last passed to `BracketPosition::set_atr`
+    Atr(f64),
+}
+
+/// What a [`BracketPosition`] trades and how it manages the exits.
+#[derive(Debug, Clone)]
+pub struct BracketConfig {
+    pub symbol: String,
+    pub base_asset: String, // Commissions in it reduce the position
+    pub filters: SymbolFilters,
+    pub side: Side, // Of the entry, a buy for a long position
+    pub quantity: f64,
+    pub entry_price: Option<f64>, // A limit entry, at market if `None`
+    pub stop_loss_price: f64,
+    pub stop_limit_offset: f64, // Distance of the stop's limit price beyond its stop price
+    pub targets: Vec<(f64, f64)>, // Price and fraction of the entry quantity to take there
+    pub break_even_trigger: Option<f64>, // Profit per unit after which the stop moves to the entry
+    pub break_even_offset: f64, // Distance beyond the entry price to cover the fees
+    pub trailing_stop: Option<TrailingStop>,
+}
+
+impl BracketConfig {
+    pub fn new(symbol: &SymbolInfo, side: Side, quantity: f64, stop_loss_price: f64) -> Self {
+        BracketConfig {
+            symbol: symbol.symbol.clone(),
+            base_asset: symbol.base_asset.clone(),
+            filters: symbol.trading_filters(),
+            side,
+            quantity,
+            entry_price: None,
+            stop_loss_price,
+            stop_limit_offset: 0.0,
+            targets: Vec::new(),
+            break_even_trigger: None,
+            break_even_offset: 0.0,
+            trailing_stop: None,
+        }
+    }
+
+    /// A bracket around a sized trade, with its take profit as the only target.
+    pub fn from_plan(symbol: &SymbolInfo, plan: &PositionPlan, is_long: bool) -> Result<Self, IOError> {
+        let stop_loss_price = plan.stop_loss_price
+            .ok_or_else(|| IOError::new(ErrorKind::InvalidInput, "Position plan has no stop loss"))?;
+        let side = if is_long { Side::Buy } else { Side::Sell };
+        let config = BracketConfig::new(symbol, side, plan.quantity, stop_loss_price);
+        Ok(match plan.take_profit_price {
+            Some(take_profit_price) => config.with_target(take_profit_price, 1.0),
+            None => config,
+        })
+    }
+
+    pub fn with_limit_entry(mut self, price: f64) -> Self {
+        self.entry_price = Some(price);
+        self
+    }
+
+    pub fn with_stop_limit_offset(mut self, offset: f64) -> Self {
+        self.stop_limit_offset = offset;
+        self
+    }
+
+    /// Takes `fraction` of the entry quantity off at `price`. Targets are taken in the order
+    /// they are added, and the last one takes whatever is left.
+    pub fn with_target(mut self, price: f64, fraction: f64) -> Self {
+        self.targets.push((price, fraction));
+        self
+    }
+
+    pub fn with_break_even(mut self, trigger: f64, offset: f64) -> Self {
+        self.break_even_trigger = Some(trigger);
+        self.break_even_offset = offset;
+        self
+    }
+
+    pub fn with_trailing_stop(mut self, trailing_stop: TrailingStop) -> Self {
+        self.trailing_stop = Some(trailing_stop);
+        self
+    }
+
+    fn is_long(&self) -> bool {
+        self.side == Side::Buy
+    }
+
+    fn exit_side(&self) -> Side {
+        if self.is_long() { Side::Sell } else { Side::Buy }
+    }
+
+    // +1 when higher prices are a profit, -1 otherwise
+    fn direction(&self) -> f64 {
+        if self.is_long() { 1.0 } else { -1.0 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BracketStatus {
+    Pending, // The entry is resting and nothing has filled yet
+    Open,
+    Closed,
+}
+
+/// A position together with its entry order and the stop protecting it.
+///
+/// The stop is a single stop-limit order for the whole position; spot orders each lock
+/// their own quantity, so resting take profit orders next to it are not possible. Targets
+/// are therefore watched on [`on_price`](Self::on_price) and taken with market orders, after
+/// the stop was reduced by the same quantity. Every change to the stop, whether a partial
+/// fill of the entry, a target, the break-even move or the trailing stop, goes through one
+/// `cancelReplace` request, so the position is never without a stop.
+///
+/// Feed it the execution reports of the user data stream with
+/// [`on_execution_report`](Self::on_execution_report) and prices with `on_price`. When the
+/// first target is reached, what did not fill of a limit entry is canceled; the same
+/// happens when the stop is hit.
+pub struct BracketPosition<'a> {
+    order_api: &'a dyn OrderApi,
+    config: BracketConfig,
+    status: BracketStatus,
+    entry_order_id: i64,
+    entry_open: bool,
+    entry_filled: f64,
+    entry_cost: f64, // In the quote asset
+    position: f64, // What is held of the entry, in the base asset
+    stop_price: f64,
+    stop: Option<PlacedStop>,
+    best_price: Option<f64>,
+    atr: Option<f64>,
+    targets_taken: usize,
+    seen_trades: HashSet<(i64, i64)>, // Order and trade id
+}
+
+// The stop order on the exchange
+#[derive(Debug, Clone, Copy)]
+struct PlacedStop {
+    order_id: i64,
+    stop_price: f64,
+    quantity: f64,
+}
+
+impl<'a> BracketPosition<'a> {
+    /// Places the entry and, for what fills right away, the stop.
+    pub async fn open(order_api: &'a dyn OrderApi, config: BracketConfig) -> Result<BracketPosition<'a>, IOError> {
+        let direction = config.direction();
+        let reference = config.entry_price.unwrap_or(config.stop_loss_price + direction);
+        let fractions: f64 = config.targets.iter().map(|(_, fraction)| fraction).sum();
+        if config.quantity <= 0.0 || (reference - config.stop_loss_price) * direction <= 0.0 || fractions > 1.0 + 1e-9 {
+            return Err(IOError::new(ErrorKind::InvalidInput, "Invalid bracket: check quantity, stop loss and target fractions"));
+        }
+
+        let response = match config.entry_price {
+            Some(price) => {
+                let order = LimitOrder::new(&config.symbol, config.side, config.quantity, price, 0);
+                order_api.create_limit_order(order).await?
+            }
+            None => {
+                let order = MarketOrder::new_with_base_asset(&config.symbol, config.side, config.quantity);
+                order_api.create_market_order(order).await?
+            }
+        };
+        info!("Bracket entry {} placed on {}", response.order_id, config.symbol);
+
+        let mut bracket = BracketPosition {
+            order_api,
+            status: BracketStatus::Pending,
+            entry_order_id: response.order_id,
+            entry_open: matches!(response.status.as_deref(), None | Some("NEW") | Some("PARTIALLY_FILLED")),
+            entry_filled: 0.0,
+            entry_cost: 0.0,
+            position: 0.0,
+            stop_price: config.stop_loss_price,
+            stop: None,
+            best_price: None,
+            atr: None,
+            targets_taken: 0,
+            seen_trades: HashSet::new(),
+            config,
+        };
+        bracket.record_entry_fills(&response);
+        bracket.sync_stop().await?;
+        Ok(bracket)
+    }
+
+    pub fn status(&self) -> BracketStatus {
+        self.status
+    }
+
+    pub fn config(&self) -> &BracketConfig {
+        &self.config
+    }
+
+    /// What is held, in the base asset.
+    pub fn position(&self) -> f64 {
+        self.position
+    }
+
+    /// Average fill price of the entry.
+    pub fn entry_price(&self) -> Option<f64> {
+        (self.entry_filled > 0.0).then(|| self.entry_cost / self.entry_filled)
+    }
+
+    pub fn stop_price(&self) -> f64 {
+        self.stop_price
+    }
+
+    pub fn stop_order_id(&self) -> Option<i64> {
+        self.stop.map(|stop| stop.order_id)
+    }
+
+    /// The latest ATR, for a [`TrailingStop::Atr`].
+    pub fn set_atr(&mut self, atr: f64) {
+        self.atr = Some(atr);
+    }
+
+    /// Follows the fills of the entry and the stop.
+    pub async fn on_execution_report(&mut self, report: &ExecutionReport) -> Result<(), IOError> {
+        if report.symbol != self.config.symbol || self.status == BracketStatus::Closed {
+            return Ok(());
+        }
+        let is_trade = report.execution_type == ExecutionType::Trade
+            && self.seen_trades.insert((report.order_id, report.trade_id));
+        let is_final = !matches!(report.order_status, OrderStatus::New | OrderStatus::PartiallyFilled | OrderStatus::PendingCancel);
+
+        if report.order_id == self.entry_order_id {
+            if is_trade {
+                let commission = report.commission_asset.as_deref().filter(|asset| *asset == self.config.base_asset).map(|_| report.commission);
+                self.add_entry_fill(report.last_executed_qty, report.last_executed_price, commission.unwrap_or_default());
+            }
+            self.entry_open &= !is_final;
+            if self.status == BracketStatus::Pending && is_final && self.entry_filled == 0.0 {
+                info!("Bracket entry {} ended without a fill", self.entry_order_id);
+                self.status = BracketStatus::Closed;
+                return Ok(());
+            }
+            return self.sync_stop().await;
+        }
+
+        let Some(stop) = self.stop.filter(|stop| stop.order_id == report.order_id) else { return Ok(()) };
+        if is_trade {
+            self.position = (self.position - report.last_executed_qty).max(0.0);
+            self.stop = Some(PlacedStop { quantity: (stop.quantity - report.last_executed_qty).max(0.0), ..stop });
+        }
+        if is_final {
+            self.stop = None;
+        }
+        if report.order_status == OrderStatus::Filled {
+            info!("Bracket stop {} filled at {}", report.order_id, report.last_executed_price);
+            self.cancel_entry().await?;
+            self.close_if_flat();
+            return Ok(());
+        }
+        if is_final {
+            // Canceled or expired by someone else: put it back
+            return self.sync_stop().await;
+        }
+        Ok(())
+    }
+
+    /// Takes targets and moves the stop for the latest price.
+    pub async fn on_price(&mut self, price: f64) -> Result<(), IOError> {
+        if self.status != BracketStatus::Open {
+            return Ok(());
+        }
+        let direction = self.config.direction();
+        self.best_price = Some(match self.best_price {
+            Some(best) if (best - price) * direction >= 0.0 => best,
+            _ => price,
+        });
+
+        while let Some((target, fraction)) = self.config.targets.get(self.targets_taken).copied() {
+            if (price - target) * direction < 0.0 {
+                break;
+            }
+            self.targets_taken += 1;
+            let is_last = self.targets_taken == self.config.targets.len();
+            let quantity = if is_last { self.position } else { (fraction * self.entry_filled).min(self.position) };
+            if let Err(e) = self.take(quantity, &format!("target {} at {}", self.targets_taken, target)).await {
+                // Not taken, so the next price at the target tries again
+                self.targets_taken -= 1;
+                return Err(e);
+            }
+            if self.status == BracketStatus::Closed {
+                return Ok(());
+            }
+        }
+
+        let entry_price = self.entry_price().unwrap_or(self.config.stop_loss_price);
+        let mut stop_price = self.stop_price;
+        if let Some(trigger) = self.config.break_even_trigger {
+            if (price - entry_price) * direction >= trigger {
+                let break_even = entry_price + direction * self.config.break_even_offset;
+                stop_price = better_stop(stop_price, break_even, direction);
+            }
+        }
+        if let (Some(trailing_stop), Some(best_price)) = (self.config.trailing_stop, self.best_price) {
+            let distance = match trailing_stop {
+                TrailingStop::Ticks(ticks) => Some(f64::from(ticks) * self.config.filters.tick_size),
+                TrailingStop::Percent(percent) => Some(best_price * percent / 100.0),
+                TrailingStop::Atr(multiple) => self.atr.map(|atr| atr * multiple),
+            };
+            if let Some(distance) = distance.filter(|distance| *distance > 0.0) {
+                stop_price = better_stop(stop_price, best_price - direction * distance, direction);
+            }
+        }
+
+        // A stop at or through the price would trigger right away
+        let stop_price = self.config.filters.round_price(stop_price);
+        let min_move = self.config.filters.tick_size.max(f64::EPSILON);
+        if (stop_price - self.stop_price) * direction >= min_move - 1e-12 && (price - stop_price) * direction > 0.0 {
+            trace!("Bracket stop on {} moves from {} to {}", self.config.symbol, self.stop_price, stop_price);
+            self.stop_price = stop_price;
+            self.sync_stop().await?;
+        }
+        Ok(())
+    }
+
+    /// Cancels the entry and the stop and exits what is held at market.
+    pub async fn close(&mut self) -> Result<(), IOError> {
+        self.cancel_entry().await?;
+        let position = self.position;
+        self.take(position, "close").await?;
+        self.status = BracketStatus::Closed;
+        Ok(())
+    }
+
+    // Reduces the stop by `quantity`, then exits it at market
+    async fn take(&mut self, quantity: f64, reason: &str) -> Result<(), IOError> {
+        self.cancel_entry().await?;
+        let quantity = self.config.filters.floor_quantity(quantity, true);
+        if quantity <= 0.0 {
+            return Ok(());
+        }
+        self.position -= quantity;
+        if let Err(e) = self.sync_stop().await {
+            self.position += quantity;
+            return Err(e);
+        }
+        let order = MarketOrder::new_with_base_asset(&self.config.symbol, self.config.exit_side(), quantity);
+        let response = match self.order_api.create_market_order(order).await {
+            Ok(response) => response,
+            Err(e) => {
+                // Still held, so the stop has to cover it again
+                self.position += quantity;
+                if let Err(stop_error) = self.sync_stop().await {
+                    warn!("Failed to restore the bracket stop on {}: {}", self.config.symbol, stop_error);
+                }
+                return Err(e);
+            }
+        };
+        let executed = response.executed_qty.unwrap_or(quantity);
+        info!("Bracket on {} took {} for {}", self.config.symbol, executed, reason);
+        if executed < quantity {
+            // What did not fill stays in the position, protected by the stop again
+            self.position += quantity - executed;
+            self.sync_stop().await?;
+        }
+        self.close_if_flat();
+        Ok(())
+    }
+
+    async fn cancel_entry(&mut self) -> Result<(), IOError> {
+        if !self.entry_open {
+            return Ok(());
+        }
+        self.entry_open = false;
+        if let Err(e) = self.order_api.cancel_order(&self.config.symbol, self.entry_order_id).await {
+            // The entry may have filled in the meantime, its reports still count
+            trace!("Failed to cancel bracket entry {}: {}", self.entry_order_id, e);
+        }
+        Ok(())
+    }
+
+    fn close_if_flat(&mut self) {
+        if !self.entry_open && self.config.filters.floor_quantity(self.position, false) <= 0.0 && self.entry_filled > 0.0 {
+            info!("Bracket on {} closed", self.config.symbol);
+            self.status = BracketStatus::Closed;
+        }
+    }
+
+    fn record_entry_fills(&mut self, response: &OrderResponse) {
+        for fill in response.fills.iter().flatten() {
+            if self.seen_trades.insert((response.order_id, fill.trade_id)) {
+                let commission = if fill.commission_asset == self.config.base_asset { fill.commission } else { 0.0 };
+                self.add_entry_fill(fill.qty, fill.price, commission);
+            }
+        }
+    }
+
+    fn add_entry_fill(&mut self, quantity: f64, price: f64, base_commission: f64) {
+        self.entry_filled += quantity;
+        self.entry_cost += quantity * price;
+        self.position += quantity - base_commission;
+        if self.status == BracketStatus::Pending {
+            self.status = BracketStatus::Open;
+        }
+    }
+
+    // Makes the stop order match the position and stop price
+    async fn sync_stop(&mut self) -> Result<(), IOError> {
+        let quantity = self.config.filters.floor_quantity(self.position, false);
+        let stop_price = self.config.filters.round_price(self.stop_price);
+        let limit_price = self.config.filters.round_price(stop_price - self.config.direction() * self.config.stop_limit_offset);
+        let (symbol, exit_side) = (self.config.symbol.as_str(), self.config.exit_side());
+
+        self.stop = match self.stop {
+            Some(stop) if stop.quantity == quantity && stop.stop_price == stop_price => return Ok(()),
+            Some(stop) if quantity <= 0.0 => {
+                self.order_api.cancel_order(symbol, stop.order_id).await?;
+                None
+            }
+            Some(stop) => {
+                let order = CancelReplaceOrder::new_stop_limit(symbol, exit_side, stop.order_id, quantity, stop_price, limit_price);
+                let response = self.order_api.cancel_replace_order(order).await?;
+                Some(PlacedStop { order_id: response.new_order_response.order_id, stop_price, quantity })
+            }
+            None if quantity <= 0.0 => None,
+            None => {
+                let order = StopLimitOrder::new(symbol, exit_side, quantity, stop_price, limit_price, TimeInForce::GTC);
+                let response = self.order_api.create_stop_limit_order(order).await?;
+                Some(PlacedStop { order_id: response.order_id, stop_price, quantity })
+            }
+        };
+        Ok(())
+    }
+}
+
+// The tighter of two stops
+fn better_stop(current: f64, candidate: f64, direction: f64) -> f64 {
+    if (candidate - current) * direction > 0.0 { candidate } else { current }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use async_trait::async_trait;
+    use crate::binance_client::account::open_order::OpenOrder;
+    use crate::binance_client::order_response::{CancelReplaceResponse, OrderListResponse, OrderResponse};
+    use crate::binance_client::order_types::cancel_order_response::CancelOrderResponse;
+    use crate::binance_client::order_types::oco_order::OcoOrder;
+    use crate::binance_client::paper_trader::PaperTrader;
+    use crate::binance_client::test_support::{eth_usdt, forward, paper_trader, set_book, trade, ReportHandler};
+
+    #[async_trait]
+    impl ReportHandler for BracketPosition<'_> {
+        async fn handle(&mut self, report: &ExecutionReport) -> Result<(), IOError> {
+            self.on_execution_report(report).await
+        }
+    }
+
+    async fn stop_order(paper_trader: &PaperTrader) -> (f64, f64) {
+        let open = paper_trader.fetch_open_orders("ETHUSDT").await.unwrap();
+        let stop = open.iter().find(|order| order.r#type == "STOP_LOSS_LIMIT").expect("a stop order");
+        (stop.stop_price, stop.orig_qty)
+    }
+
+    // Fails market orders while `fail_market_orders` is set
+    struct FlakyExchange {
+        paper_trader: PaperTrader,
+        fail_market_orders: AtomicBool,
+    }
+
+    #[async_trait]
+    impl OrderApi for FlakyExchange {
+        async fn create_limit_order(&self, order: LimitOrder) -> Result<OrderResponse, IOError> {
+            self.paper_trader.create_limit_order(order).await
+        }
+
+        async fn create_stop_limit_order(&self, order: StopLimitOrder) -> Result<OrderResponse, IOError> {
+            self.paper_trader.create_stop_limit_order(order).await
+        }
+
+        async fn create_oco_order(&self, order: OcoOrder) -> Result<OrderListResponse, IOError> {
+            self.paper_trader.create_oco_order(order).await
+        }
+
+        async fn create_market_order(&self, order: MarketOrder) -> Result<OrderResponse, IOError> {
+            if self.fail_market_orders.load(Ordering::SeqCst) {
+                return Err(IOError::new(ErrorKind::TimedOut, "HTTP request failed"));
+            }
+            self.paper_trader.create_market_order(order).await
+        }
+
+        async fn cancel_order(&self, symbol: &str, order_id: i64) -> Result<CancelOrderResponse, IOError> {
+            self.paper_trader.cancel_order(symbol, order_id).await
+        }
+
+        async fn cancel_replace_order(&self, order: CancelReplaceOrder) -> Result<CancelReplaceResponse, IOError> {
+            self.paper_trader.cancel_replace_order(order).await
+        }
+
+        async fn fetch_open_orders(&self, symbol: &str) -> Result<Vec<OpenOrder>, IOError> {
+            self.paper_trader.fetch_open_orders(symbol).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_partial_entry_break_even_and_trailing() {
+        let paper_trader = paper_trader(1999.0, 2001.0);
+        let mut events = paper_trader.subscribe();
+        let config = BracketConfig::new(&eth_usdt(), Side::Buy, 2.0, 1950.0)
+            .with_limit_entry(1999.0)
+            .with_stop_limit_offset(5.0)
+            .with_break_even(20.0, 1.0)
+            .with_trailing_stop(TrailingStop::Ticks(3000));
+        let mut bracket = BracketPosition::open(&paper_trader, config).await.unwrap();
+        assert_eq!((bracket.status(), bracket.stop_order_id()), (BracketStatus::Pending, None));
+
+        // 100 are ahead at 1999, so one of the 101 sold fills the entry
+        trade(&paper_trader, 1999.0, 101.0, true);
+        forward(&mut bracket, &mut events).await;
+        assert_eq!((bracket.status(), bracket.position()), (BracketStatus::Open, 1.0));
+        assert_eq!(stop_order(&paper_trader).await, (1950.0, 1.0));
+        let first_stop = bracket.stop_order_id();
+
+        trade(&paper_trader, 1999.0, 1.0, true);
+        forward(&mut bracket, &mut events).await;
+        assert_eq!(bracket.position(), 2.0);
+        assert_eq!(stop_order(&paper_trader).await, (1950.0, 2.0));
+        assert_ne!(bracket.stop_order_id(), first_stop);
+
+        // Trails 30 behind the best price, then the break-even takes over
+        trade(&paper_trader, 2010.0, 0.1, false);
+        bracket.on_price(2010.0).await.unwrap();
+        assert_eq!(stop_order(&paper_trader).await, (1980.0, 2.0));
+        trade(&paper_trader, 2020.0, 0.1, false);
+        bracket.on_price(2020.0).await.unwrap();
+        assert_eq!(bracket.stop_price(), 2000.0);
+        // A pullback never loosens the stop
+        trade(&paper_trader, 2005.0, 0.1, true);
+        bracket.on_price(2005.0).await.unwrap();
+        assert_eq!(stop_order(&paper_trader).await, (2000.0, 2.0));
+        assert_eq!(paper_trader.fetch_open_orders("ETHUSDT").await.unwrap().len(), 1);
+
+        trade(&paper_trader, 1998.0, 0.1, true);
+        forward(&mut bracket, &mut events).await;
+        assert_eq!((bracket.status(), bracket.position()), (BracketStatus::Closed, 0.0));
+        assert_eq!(paper_trader.free_balance("ETH"), 0.0);
+        assert!(paper_trader.fetch_open_orders("ETHUSDT").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_scale_out_at_targets() {
+        let paper_trader = paper_trader(1999.0, 2001.0);
+        let config = BracketConfig::new(&eth_usdt(), Side::Buy, 2.0, 1950.0)
+            .with_target(2050.0, 0.5)
+            .with_target(2100.0, 0.5);
+        let mut bracket = BracketPosition::open(&paper_trader, config).await.unwrap();
+        assert_eq!((bracket.status(), bracket.entry_price()), (BracketStatus::Open, Some(2001.0)));
+        assert_eq!(stop_order(&paper_trader).await, (1950.0, 2.0));
+
+        // The stop shrinks before the market order, which would lack the balance otherwise
+        set_book(&paper_trader, 2050.0, 2051.0);
+        bracket.on_price(2050.0).await.unwrap();
+        assert_eq!(bracket.position(), 1.0);
+        assert_eq!(stop_order(&paper_trader).await, (1950.0, 1.0));
+        assert_eq!(paper_trader.free_balance("ETH"), 0.0);
+
+        set_book(&paper_trader, 2100.0, 2101.0);
+        bracket.on_price(2100.0).await.unwrap();
+        assert_eq!(bracket.status(), BracketStatus::Closed);
+        assert!(paper_trader.fetch_open_orders("ETHUSDT").await.unwrap().is_empty());
+        assert_eq!(paper_trader.free_balance("USDT"), 100000.0 - 4002.0 + 2050.0 + 2100.0);
+    }
+
+    #[tokio::test]
+    async fn test_failed_exit_restores_the_stop() {
+        let exchange = FlakyExchange { paper_trader: paper_trader(1999.0, 2001.0), fail_market_orders: AtomicBool::new(false) };
+        let config = BracketConfig::new(&eth_usdt(), Side::Buy, 2.0, 1950.0).with_target(2050.0, 0.5).with_target(2100.0, 0.5);
+        let mut bracket = BracketPosition::open(&exchange, config).await.unwrap();
+        assert_eq!(stop_order(&exchange.paper_trader).await, (1950.0, 2.0));
+
+        exchange.fail_market_orders.store(true, Ordering::SeqCst);
+        set_book(&exchange.paper_trader, 2050.0, 2051.0);
+        assert!(bracket.on_price(2050.0).await.is_err());
+        assert_eq!((bracket.status(), bracket.position()), (BracketStatus::Open, 2.0));
+        assert_eq!(stop_order(&exchange.paper_trader).await, (1950.0, 2.0));
+
+        // The target is still pending and goes out once the exchange answers again
+        exchange.fail_market_orders.store(false, Ordering::SeqCst);
+        bracket.on_price(2050.0).await.unwrap();
+        assert_eq!(bracket.position(), 1.0);
+        assert_eq!(stop_order(&exchange.paper_trader).await, (1950.0, 1.0));
+    }
+}
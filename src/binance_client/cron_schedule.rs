@@ -0,0 +1,225 @@
+use std::fmt;
+use std::io::{Error as IOError, ErrorKind};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, TimeDelta, TimeZone, Timelike};
+use chrono_tz::Tz;
+
+// Bounds of the search for the next run, "0 0 30 2 *" never runs
+const MAX_SEARCH_DAYS: i64 = 5 * 366;
+
+/// A cron expression evaluated in a time zone.
+///
+/// Takes the five standard fields, `minute hour day-of-month month day-of-week`, each
+/// a `*`, a value, a range `a-b` or a list of them, optionally with a step `/n`. Sunday
+/// is 0 or 7, and months and days of the week can be given as `JAN` or `MON`. As in
+/// cron, a time matches if either day field does when both are restricted. `@hourly`,
+/// `@daily`, `@weekly`, `@monthly` and `@yearly` are accepted as well.
+///
+/// Local times skipped by a daylight saving change do not run, and those repeated by
+/// one run once, the first time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronSchedule {
+    expression: String,
+    timezone: Tz,
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    any_day_of_month: bool,
+    any_day_of_week: bool,
+}
+
+impl CronSchedule {
+    /// Parses `expression` for the IANA time zone `timezone`, e.g. `Europe/Berlin`.
+    pub fn new(expression: &str, timezone: &str) -> Result<Self, IOError> {
+        let timezone: Tz = timezone
+            .parse()
+            .map_err(|_| IOError::new(ErrorKind::InvalidInput, format!("Unknown time zone {}", timezone)))?;
+        let fields = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            fields => fields,
+        };
+        let fields: Vec<&str> = fields.split_whitespace().collect();
+        let [minutes, hours, days_of_month, months, days_of_week] = fields[..] else {
+            return Err(IOError::new(ErrorKind::InvalidInput, format!("Cron expression {:?} needs five fields", expression)));
+        };
+
+        let mut days_of_week_mask = parse_field(days_of_week, 0, 7, &DAY_NAMES)?;
+        if days_of_week_mask & 1 << 7 != 0 {
+            days_of_week_mask = (days_of_week_mask | 1) & !(1 << 7);
+        }
+        Ok(CronSchedule {
+            expression: expression.trim().to_string(),
+            timezone,
+            minutes: parse_field(minutes, 0, 59, &[])?,
+            hours: parse_field(hours, 0, 23, &[])?,
+            days_of_month: parse_field(days_of_month, 1, 31, &[])?,
+            months: parse_field(months, 1, 12, &MONTH_NAMES)?,
+            days_of_week: days_of_week_mask,
+            any_day_of_month: days_of_month.starts_with('*'),
+            any_day_of_week: days_of_week.starts_with('*'),
+        })
+    }
+
+    pub fn timezone(&self) -> Tz {
+        self.timezone
+    }
+
+    /// The first run strictly after `time`, in milliseconds since the epoch.
+    pub fn next_after(&self, time: u64) -> Option<u64> {
+        let after = DateTime::from_timestamp_millis(time as i64)?.with_timezone(&self.timezone);
+        let minute = TimeDelta::try_minutes(1)?;
+        let start = after.naive_local().with_second(0)?.with_nanosecond(0)? + minute;
+        let last = start + TimeDelta::try_days(MAX_SEARCH_DAYS)?;
+
+        let mut local = start;
+        while local < last {
+            if !self.matches_day(local.date()) {
+                local = local.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if self.hours & 1 << local.hour() == 0 {
+                local = local.with_minute(0)? + TimeDelta::try_hours(1)?;
+                continue;
+            }
+            if self.minutes & 1 << local.minute() != 0 {
+                let run = self.timezone.from_local_datetime(&local).earliest();
+                if let Some(run) = run.filter(|run| *run > after) {
+                    return Some(run.timestamp_millis() as u64);
+                }
+            }
+            local += minute;
+        }
+        None
+    }
+
+    /// The runs in `(from, to]`, oldest first.
+    pub fn runs_between(&self, from: u64, to: u64) -> Vec<u64> {
+        let mut runs = Vec::new();
+        let mut time = from;
+        while let Some(run) = self.next_after(time).filter(|run| *run <= to) {
+            runs.push(run);
+            time = run;
+        }
+        runs
+    }
+
+    /// Local midnight of `date`, or the first time after it on days that skip it.
+    pub(crate) fn start_of_day(&self, date: NaiveDate) -> Option<u64> {
+        let midnight: NaiveDateTime = date.and_hms_opt(0, 0, 0)?;
+        (0..24)
+            .find_map(|hour| self.timezone.from_local_datetime(&midnight.with_hour(hour)?).earliest())
+            .map(|time| time.timestamp_millis() as u64)
+    }
+
+    /// The local date at `time`.
+    pub(crate) fn local_date(&self, time: u64) -> Option<NaiveDate> {
+        DateTime::from_timestamp_millis(time as i64).map(|time| time.with_timezone(&self.timezone).date_naive())
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        if self.months & 1 << date.month() == 0 {
+            return false;
+        }
+        let day_of_month = self.days_of_month & 1 << date.day() != 0;
+        let day_of_week = self.days_of_week & 1 << date.weekday().num_days_from_sunday() != 0;
+        match (self.any_day_of_month, self.any_day_of_week) {
+            (false, false) => day_of_month || day_of_week,
+            _ => day_of_month && day_of_week,
+        }
+    }
+}
+
+impl fmt::Display for CronSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({})", self.expression, self.timezone)
+    }
+}
+
+const DAY_NAMES: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+const MONTH_NAMES: [&str; 13] = ["", "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC"];
+
+// Bit n of the result is set if the field includes n
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, IOError> {
+    let invalid = || IOError::new(ErrorKind::InvalidInput, format!("Invalid cron field {:?}, values go from {} to {}", field, min, max));
+    let value = |text: &str| -> Result<u32, IOError> {
+        let value = match names.iter().position(|name| !name.is_empty() && name.eq_ignore_ascii_case(text)) {
+            Some(index) => index as u32,
+            None => text.parse().map_err(|_| invalid())?,
+        };
+        if value < min || value > max {
+            return Err(invalid());
+        }
+        Ok(value)
+    };
+
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|step| *step > 0).ok_or_else(invalid)?),
+            None => (part, 1),
+        };
+        let (first, last) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((first, last)) => (value(first)?, value(last)?),
+                // "5/15" runs from 5 to the end
+                None if part.contains('/') => (value(range)?, max),
+                None => (value(range)?, value(range)?),
+            },
+        };
+        if first > last {
+            return Err(invalid());
+        }
+        for value in (first..=last).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(text: &str) -> u64 {
+        DateTime::parse_from_rfc3339(text).unwrap().timestamp_millis() as u64
+    }
+
+    #[test]
+    fn test_parse_cron_expressions() {
+        assert!(CronSchedule::new("0 9 * * MON-FRI", "Europe/Berlin").is_ok());
+        assert!(CronSchedule::new("*/15 0-6,18 1,15 jan-jun 7", "UTC").is_ok());
+        assert!(CronSchedule::new("0 9 * *", "UTC").is_err());
+        assert!(CronSchedule::new("60 9 * * *", "UTC").is_err());
+        assert!(CronSchedule::new("0 9-8 * * *", "UTC").is_err());
+        assert!(CronSchedule::new("*/0 9 * * *", "UTC").is_err());
+        assert!(CronSchedule::new("0 9 * * *", "Mars/Olympus").is_err());
+    }
+
+    #[test]
+    fn test_next_run_in_timezone() {
+        let weekdays = CronSchedule::new("30 9 * * 1-5", "Europe/Berlin").unwrap();
+        // Friday 2024-03-29 09:30 in Berlin is 08:30 UTC, the next run is on Monday after the clocks changed
+        assert_eq!(weekdays.next_after(utc("2024-03-29T08:00:00Z")), Some(utc("2024-03-29T08:30:00Z")));
+        assert_eq!(weekdays.next_after(utc("2024-03-29T08:30:00Z")), Some(utc("2024-04-01T07:30:00Z")));
+
+        // Either day field matches when both are given
+        let days = CronSchedule::new("0 0 13 * FRI", "UTC").unwrap();
+        assert_eq!(days.next_after(utc("2024-09-01T00:00:00Z")), Some(utc("2024-09-06T00:00:00Z")));
+        assert_eq!(days.next_after(utc("2024-10-12T00:00:00Z")), Some(utc("2024-10-13T00:00:00Z")));
+
+        // 02:30 does not exist on the night the clocks go forward, it runs once when they go back
+        let night = CronSchedule::new("30 2 * * *", "Europe/Berlin").unwrap();
+        assert_eq!(night.next_after(utc("2024-03-30T12:00:00Z")), Some(utc("2024-04-01T00:30:00Z")));
+        assert_eq!(night.runs_between(utc("2024-10-26T12:00:00Z"), utc("2024-10-27T12:00:00Z")), vec![utc("2024-10-27T00:30:00Z")]);
+
+        let hourly = CronSchedule::new("@hourly", "UTC").unwrap();
+        assert_eq!(hourly.runs_between(utc("2024-01-01T00:00:00Z"), utc("2024-01-01T03:00:00Z")).len(), 3);
+        assert_eq!(CronSchedule::new("0 0 30 2 *", "UTC").unwrap().next_after(0), None);
+    }
+}
@@ -1,159 +1,242 @@
-use std::fmt::format;
-use std::sync::{Arc};
-use rust_decimal::Decimal;
-use tokio::sync::Mutex;
-use tokio::task::JoinHandle;
-use tokio_postgres::{NoTls, Error, Client};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use futures::{pin_mut, stream, Stream, TryStreamExt};
+use deadpool_postgres::{Manager, ManagerConfig, Object, Pool, PoolError, RecyclingMethod};
+use tokio_postgres::{Error, Client, Row};
+use tokio_postgres::binary_copy::BinaryCopyInWriter;
+use tokio_postgres::types::Type;
 use std::io::{Error as IOError, ErrorKind};
-use log::trace;
-use crate::binance_client::streams::kline_data::{Kline, KlineMessage};
+use log::{trace, warn};
+use crate::binance_client::account::trades::Trade;
+use crate::binance_client::database_config::DatabaseConfig;
+use crate::binance_client::migrations::{migration_status, pending_migrations, AppliedMigration, MigrationState, MigrationStatus, MIGRATIONS};
+use crate::binance_client::binance_client::BinanceClient;
+use crate::binance_client::kline_interval::KlineInterval;
+use crate::binance_client::streams::kline_data::{Kline, KlineMessage, RestKline};
 
-#[derive(Debug)]
+/// A pool of connections to one database.
+///
+/// Cloning is cheap and every clone shares the same pool, so one client can be handed to
+/// the kline recorder, the order journal and any other task. Each call checks a
+/// connection out for its own duration, so transactions never interleave.
+#[derive(Debug, Clone)]
 pub struct DatabaseClient {
-    pub client: Client,
-    connection_handle: Arc<Mutex<Option<JoinHandle<()>>>>,
+    pool: Pool,
+    dbname: String,
+    // Daily partitions known to exist, shared by all clones
+    pub(crate) partitions: Arc<Mutex<HashSet<String>>>,
 }
 
 
 impl DatabaseClient {
-    pub async fn new(dbname: &str, user: &str, pwd: &str) -> Result<Self, Error> {
+    /// Connects to the configured database, creating it if needed, and applies pending migrations.
+    pub async fn new(config: &DatabaseConfig) -> Result<Self, IOError> {
         // Try connecting directly first
-        let direct_conn_str = format!("host=localhost user={} password={} dbname={}", user, pwd, dbname);
-        if let Ok((client, connection)) = tokio_postgres::connect(&direct_conn_str, NoTls).await {
-            let connection_handle = tokio::spawn(async move {
-                if let Err(e) = connection.await {
-                    eprintln!("connection error: {}", e);
-                }
-            });
-
-            return Ok(DatabaseClient {
-                client,
-                connection_handle: Arc::new(Mutex::new(Some(connection_handle))),
-            });
+        match Self::connect(config).await {
+            Ok(db_client) => return Ok(db_client),
+            Err(e) => warn!("Connecting to database {} failed, creating it if missing: {}", config.name, e),
         }
 
-        // If direct connection fails, proceed with connect_and_setup to ensure the database is created
-        Self::connect_or_create_if_not_exist(dbname, user, pwd).await
+        // If direct connection fails, proceed with connect_or_create_if_not_exist to ensure the database is created
+        Self::connect_or_create_if_not_exist(config).await
     }
 
     // Connects to the default database to check for the existence of the target database
-    pub async fn connect_or_create_if_not_exist(dbname: &str, user: &str, password: &str) -> Result<Self, Error> {
-        let admin_conn_str = format!("host=localhost user={} password={} dbname=postgres", user, password);
-        let (admin_client, connection) = tokio_postgres::connect(&admin_conn_str, NoTls).await?;
-
-        // Spawning a new task for the connection to keep it alive
-        tokio::spawn(async move {
-            if let Err(e) = connection.await {
-                eprintln!("connection error: {}", e);
-            }
-        });
+    pub async fn connect_or_create_if_not_exist(config: &DatabaseConfig) -> Result<Self, IOError> {
+        let admin_client = Self::connect_admin(config).await?;
 
         // Check if the target database exists
-        let row = admin_client.query_one("SELECT 1 FROM pg_database WHERE datname = $1", &[&dbname]).await;
-        if row.is_err() {
+        let exists: bool = admin_client
+            .query_one("SELECT EXISTS(SELECT 1 FROM pg_database WHERE datname = $1)", &[&config.name])
+            .await
+            .map_err(db_error)?
+            .get(0);
+        if !exists {
             // If the database does not exist, create it
-            admin_client.execute(&format!("CREATE DATABASE {}", dbname), &[]).await?;
+            let stmt = format!("CREATE DATABASE {}", quote_identifier(&config.name));
+            if let Err(e) = admin_client.execute(&stmt, &[]).await {
+                // Another process may have created it since we checked
+                if e.code() != Some(&tokio_postgres::error::SqlState::DUPLICATE_DATABASE) {
+                    return Err(db_error(e));
+                }
+            }
         }
 
         // Now connect to the newly created or existing database
-        let db_conn_str = format!("host=localhost user={} password={} dbname={}", user, password, dbname);
-        Self::connect(&db_conn_str).await
+        Self::connect(config).await
     }
 
     // Method to check if the specified database exists
-    pub async fn database_exists(dbname: &str, user: &str, password: &str) -> Result<bool, IOError> {
-        let (client, connection) = match tokio_postgres::connect(format!("host=localhost user={} password={} dbname=postgres", user, password).as_str(), NoTls).await {
-            Ok(conn) => conn,
-            Err(e) => return Err(IOError::new(ErrorKind::ConnectionRefused, e.to_string())),
-        };
-
-        tokio::spawn(async move {
-            if let Err(e) = connection.await {
-                eprintln!("connection error: {}", e);
-            }
-        });
+    pub async fn database_exists(config: &DatabaseConfig) -> Result<bool, IOError> {
+        let client = Self::connect_admin(config).await?;
 
         let stmt = "SELECT EXISTS(SELECT 1 FROM pg_database WHERE datname = $1)";
-        match client.query_one(stmt, &[&dbname]).await {
+        match client.query_one(stmt, &[&config.name]).await {
             Ok(row) => Ok(row.get(0)),
             Err(e) => Err(IOError::new(ErrorKind::Other, e.to_string())),
         }
     }
 
-    pub async fn connect(connection_string: &str) -> Result<Self, Error> {
-        let (client, connection) =
-            tokio_postgres::connect(connection_string, NoTls).await?;
+    /// Opens a pool of up to `pool_size` connections to the configured database and
+    /// applies pending migrations.
+    pub async fn connect(config: &DatabaseConfig) -> Result<Self, IOError> {
+        let manager = Manager::from_config(
+            config.pg_config(&config.name),
+            config.tls_connector()?,
+            ManagerConfig { recycling_method: RecyclingMethod::Fast },
+        );
+        let pool = Pool::builder(manager)
+            .max_size(config.pool_size)
+            .build()
+            .map_err(|e| IOError::new(ErrorKind::InvalidInput, format!("Invalid pool configuration: {}", e)))?;
+
+        let db_client = DatabaseClient {
+            pool,
+            dbname: config.name.clone(),
+            partitions: Arc::new(Mutex::new(HashSet::new())),
+        };
+        db_client.migrate().await?;
+        Ok(db_client)
+    }
+
+    // A single connection to the `postgres` maintenance database, for creating and dropping databases
+    async fn connect_admin(config: &DatabaseConfig) -> Result<Client, IOError> {
+        let (client, connection) = config
+            .pg_config("postgres")
+            .connect(config.tls_connector()?)
+            .await
+            .map_err(|e| IOError::new(ErrorKind::ConnectionRefused, e.to_string()))?;
 
         // The connection object performs the actual communication with the database,
         // so spawn it off to run on its own.
-        let handle = tokio::spawn(async move {
+        tokio::spawn(async move {
             if let Err(e) = connection.await {
                 eprintln!("connection error: {}", e);
             }
         });
+        Ok(client)
+    }
 
-        Ok(DatabaseClient {
-            client,
-            connection_handle: Arc::new(Mutex::new(Some(handle))),
-        })
+    /// Checks a connection out of the pool. It goes back to the pool when dropped.
+    pub async fn client(&self) -> Result<Object, IOError> {
+        self.pool.get().await.map_err(pool_error)
     }
 
-    pub async fn close(&self) {
-        let mut handle = self.connection_handle.lock();
-        if let Some(h) = handle.await.take() {
-            h.abort();
-        }
+    pub fn pool(&self) -> &Pool {
+        &self.pool
     }
 
-    // Method to drop a database
-    pub async fn drop_database_if_exists(db_name: &str, user: &str, password: &str) -> Result<(), Error> {
-        // Form the connection string to the default or administration database
-        let conn_str = format!("host=localhost user={} password={} dbname=postgres", user, password);
+    pub fn database_name(&self) -> &str {
+        &self.dbname
+    }
 
-        // Establish a temporary connection
-        let (client, connection) = tokio_postgres::connect(&conn_str, NoTls).await?;
-        tokio::spawn(async move {
-            if let Err(e) = connection.await {
-                eprintln!("connection error: {}", e);
+    /// Applies every pending migration in order and returns the versions applied.
+    ///
+    /// Each migration runs in its own transaction together with its `schema_migrations`
+    /// row. A Postgres advisory lock keeps two processes from migrating at once. Fails
+    /// without applying anything if an applied migration was edited since.
+    pub async fn migrate(&self) -> Result<Vec<i64>, IOError> {
+        // The advisory lock belongs to the session, so lock, migrate and unlock on one connection
+        let mut client = self.client().await?;
+        client.execute("SELECT pg_advisory_lock($1)", &[&MIGRATION_LOCK_ID]).await.map_err(db_error)?;
+
+        // Created under the lock, two processes creating it at once on a fresh database conflict
+        let result = match client.batch_execute(CREATE_SCHEMA_MIGRATIONS).await {
+            Ok(()) => Self::apply_pending_migrations(&mut client).await,
+            Err(e) => Err(db_error(e)),
+        };
+
+        client.execute("SELECT pg_advisory_unlock($1)", &[&MIGRATION_LOCK_ID]).await.map_err(db_error)?;
+        result
+    }
+
+    /// Reports which migrations are applied, pending, edited or unknown to this build.
+    pub async fn migration_status(&self) -> Result<Vec<MigrationStatus>, IOError> {
+        let client = self.client().await?;
+        client.batch_execute(CREATE_SCHEMA_MIGRATIONS).await.map_err(db_error)?;
+        let applied = Self::query_applied_migrations(&client).await?;
+        Ok(migration_status(MIGRATIONS, &applied))
+    }
+
+    pub async fn applied_migrations(&self) -> Result<Vec<AppliedMigration>, IOError> {
+        let client = self.client().await?;
+        Self::query_applied_migrations(&client).await
+    }
+
+    async fn query_applied_migrations(client: &Client) -> Result<Vec<AppliedMigration>, IOError> {
+        let rows = client
+            .query("SELECT version, name, checksum, applied_at FROM schema_migrations ORDER BY version", &[])
+            .await
+            .map_err(db_error)?;
+        Ok(rows
+            .iter()
+            .map(|row| AppliedMigration {
+                version: row.get("version"),
+                name: row.get("name"),
+                checksum: row.get("checksum"),
+                applied_at: row.get("applied_at"),
+            })
+            .collect())
+    }
+
+    async fn apply_pending_migrations(client: &mut Object) -> Result<Vec<i64>, IOError> {
+        let applied = Self::query_applied_migrations(client).await?;
+        for status in migration_status(MIGRATIONS, &applied) {
+            if status.state == MigrationState::Unknown {
+                warn!("Database has migration {} ({}) unknown to this build", status.version, status.name);
             }
-        });
+        }
 
-        // Formulate and execute the drop database command
-        let stmt = format!("DROP DATABASE IF EXISTS {}", db_name);
-        client.execute(&stmt, &[]).await?;
+        let mut versions = Vec::new();
+        for migration in pending_migrations(MIGRATIONS, &applied)? {
+            trace!("Applying migration {} ({})", migration.version, migration.name);
+            let applied_at = chrono::Utc::now().timestamp_millis();
+            let applied = async {
+                // Rolled back when dropped without a commit
+                let transaction = client.transaction().await?;
+                transaction.batch_execute(migration.sql).await?;
+                transaction.execute(
+                    "INSERT INTO schema_migrations (version, name, checksum, applied_at) VALUES ($1, $2, $3, $4)",
+                    &[&migration.version, &migration.name, &migration.checksum(), &applied_at],
+                ).await?;
+                transaction.commit().await
+            }.await;
 
-        Ok(())
+            if let Err(e) = applied {
+                return Err(IOError::new(
+                    ErrorKind::Other,
+                    format!("Migration {} ({}) failed: {}", migration.version, migration.name, e),
+                ));
+            }
+            versions.push(migration.version);
+        }
+        Ok(versions)
+    }
+
+    /// Closes the pool. Idle connections are dropped now, checked out ones when returned.
+    pub async fn close(&self) {
+        self.pool.close();
     }
 
+    // Method to drop a database
+    pub async fn drop_database_if_exists(config: &DatabaseConfig) -> Result<(), IOError> {
+        // Establish a temporary connection to the administration database
+        let client = Self::connect_admin(config).await?;
 
-    // This method ensures the kline_data table exists
-    pub async fn ensure_kline_data_table_exists(&self) -> Result<(), Error> {
-        let create_table_stmt = "
-            CREATE TABLE IF NOT EXISTS kline_data (
-                symbol VARCHAR(20),
-                interval VARCHAR(10),
-                start_time BIGINT,
-                end_time BIGINT,
-                open_price DOUBLE PRECISION,
-                close_price DOUBLE PRECISION,
-                high_price DOUBLE PRECISION,
-                low_price DOUBLE PRECISION,
-                base_asset_volume DOUBLE PRECISION,
-                number_of_trades INT,
-                is_kline_closed BOOLEAN,
-                quote_asset_volume DOUBLE PRECISION,
-                taker_buy_base_asset_volume DOUBLE PRECISION,
-                taker_buy_quote_asset_volume DOUBLE PRECISION,
-                PRIMARY KEY (symbol, interval, start_time)
-            );
-        ";
-
-        self.client.execute(create_table_stmt, &[]).await?;
+        // Formulate and execute the drop database command
+        let stmt = format!("DROP DATABASE IF EXISTS {}", quote_identifier(&config.name));
+        client.execute(&stmt, &[]).await.map_err(db_error)?;
 
         Ok(())
     }
 
-    pub async fn insert_kline_data(&self, kline_message: &KlineMessage) -> Result<(), Error> {
+
+    // The kline_data table is created by the migrations, so this only brings the schema up to date
+    pub async fn ensure_kline_data_table_exists(&self) -> Result<(), IOError> {
+        self.migrate().await.map(|_| ())
+    }
+
+    pub async fn insert_kline_data(&self, kline_message: &KlineMessage) -> Result<(), IOError> {
         let kline = &kline_message.data.k;
 
         let stmt = "
@@ -178,7 +261,7 @@ impl DatabaseClient {
         taker_buy_quote_asset_volume = EXCLUDED.taker_buy_quote_asset_volume
     ";
 
-        self.client.execute(stmt, &[
+        self.client().await?.execute(stmt, &[
             &kline_message.data.symbol,
             &kline.interval,
             &(kline.start_time as i64),
@@ -193,12 +276,503 @@ impl DatabaseClient {
             &kline.quote_asset_volume,
             &kline.taker_buy_base_asset_volume,
             &kline.taker_buy_quote_asset_volume,
-        ]).await?;
+        ]).await.map_err(db_error)?;
 
         Ok(())
     }
+
+    /// Upserts a batch of klines with one binary `COPY` and one merge statement.
+    ///
+    /// Much faster than calling [`DatabaseClient::insert_kline_data`] per kline when
+    /// backfilling. Klines repeated in the batch are collapsed to the last occurrence.
+    /// The whole batch is staged in memory by Postgres, so split multi-million row
+    /// backfills into batches of a few hundred thousand rows.
+    pub async fn bulk_upsert_klines(&self, klines: &[Kline]) -> Result<UpsertStats, IOError> {
+        let klines = dedup_klines(klines);
+        if klines.is_empty() {
+            return Ok(UpsertStats::default());
+        }
+
+        let mut client = self.client().await?;
+        let result = async {
+            // Rolled back, dropping the staging table with it, when dropped without a commit
+            let transaction = client.transaction().await?;
+            transaction.batch_execute(
+                "CREATE TEMP TABLE kline_staging (LIKE kline_data INCLUDING DEFAULTS) ON COMMIT DROP"
+            ).await?;
+            let stats = Self::copy_and_merge_klines(&transaction, &klines).await?;
+            transaction.commit().await?;
+            Ok(stats)
+        }.await;
+
+        let stats = result.map_err(db_error)?;
+        trace!("Bulk kline upsert: {:?}", stats);
+        Ok(stats)
+    }
+
+    pub async fn bulk_upsert_kline_messages(&self, kline_messages: &[KlineMessage]) -> Result<UpsertStats, IOError> {
+        let klines: Vec<Kline> = kline_messages
+            .iter()
+            .map(|message| Kline { symbol: message.data.symbol.clone(), ..message.data.k.clone() })
+            .collect();
+        self.bulk_upsert_klines(&klines).await
+    }
+
+    /// Upserts klines from the REST `/v3/klines` endpoint, which carry neither symbol nor interval.
+    pub async fn bulk_upsert_rest_klines(
+        &self,
+        symbol: &str,
+        interval: KlineInterval,
+        rest_klines: &[RestKline],
+    ) -> Result<UpsertStats, IOError> {
+        let now = BinanceClient::generate_timestamp()?;
+        let klines: Vec<Kline> = rest_klines
+            .iter()
+            .cloned()
+            .map(|kline| kline.into_kline(symbol, interval, now))
+            .collect();
+        self.bulk_upsert_klines(&klines).await
+    }
+
+    async fn copy_and_merge_klines(transaction: &tokio_postgres::Transaction<'_>, klines: &[&Kline]) -> Result<UpsertStats, Error> {
+        let sink = transaction.copy_in("
+            COPY kline_staging (
+                symbol, interval, start_time, end_time, open_price, close_price,
+                high_price, low_price, base_asset_volume, number_of_trades,
+                is_kline_closed, quote_asset_volume, taker_buy_base_asset_volume,
+                taker_buy_quote_asset_volume
+            ) FROM STDIN BINARY
+        ").await?;
+        let writer = BinaryCopyInWriter::new(sink, &[
+            Type::VARCHAR, Type::VARCHAR, Type::INT8, Type::INT8, Type::FLOAT8, Type::FLOAT8,
+            Type::FLOAT8, Type::FLOAT8, Type::FLOAT8, Type::INT4,
+            Type::BOOL, Type::FLOAT8, Type::FLOAT8,
+            Type::FLOAT8,
+        ]);
+        pin_mut!(writer);
+
+        for kline in klines {
+            writer.as_mut().write(&[
+                &kline.symbol,
+                &kline.interval,
+                &(kline.start_time as i64),
+                &(kline.end_time as i64),
+                &kline.open_price,
+                &kline.close_price,
+                &kline.high_price,
+                &kline.low_price,
+                &kline.base_asset_volume,
+                &(kline.number_of_trades as i32),
+                &kline.is_kline_closed,
+                &kline.quote_asset_volume,
+                &kline.taker_buy_base_asset_volume,
+                &kline.taker_buy_quote_asset_volume,
+            ]).await?;
+        }
+        writer.finish().await?;
+
+        // xmax is 0 for freshly inserted rows and set for rows rewritten by DO UPDATE
+        let row = transaction.query_one("
+    WITH merged AS (
+        INSERT INTO kline_data SELECT * FROM kline_staging
+        ON CONFLICT (symbol, interval, start_time)
+        DO UPDATE SET
+            end_time = EXCLUDED.end_time,
+            open_price = EXCLUDED.open_price,
+            close_price = EXCLUDED.close_price,
+            high_price = EXCLUDED.high_price,
+            low_price = EXCLUDED.low_price,
+            base_asset_volume = EXCLUDED.base_asset_volume,
+            number_of_trades = EXCLUDED.number_of_trades,
+            is_kline_closed = EXCLUDED.is_kline_closed,
+            quote_asset_volume = EXCLUDED.quote_asset_volume,
+            taker_buy_base_asset_volume = EXCLUDED.taker_buy_base_asset_volume,
+            taker_buy_quote_asset_volume = EXCLUDED.taker_buy_quote_asset_volume
+        RETURNING (xmax = 0) AS inserted
+    )
+    SELECT COUNT(*) FILTER (WHERE inserted), COUNT(*) FILTER (WHERE NOT inserted) FROM merged
+    ", &[]).await?;
+
+        Ok(UpsertStats {
+            inserted: row.get::<_, i64>(0) as u64,
+            updated: row.get::<_, i64>(1) as u64,
+        })
+    }
+
+    /// Open times of the first and last stored kline of a stream, or `None` if it has none.
+    pub async fn kline_time_range(&self, symbol: &str, interval: KlineInterval) -> Result<Option<(u64, u64)>, IOError> {
+        let row = self.client().await?.query_one(
+            "SELECT MIN(start_time), MAX(start_time) FROM kline_data WHERE symbol = $1 AND interval = $2",
+            &[&symbol, &interval.as_str()],
+        ).await.map_err(db_error)?;
+        let first: Option<i64> = row.get(0);
+        let last: Option<i64> = row.get(1);
+        Ok(first.zip(last).map(|(first, last)| (first as u64, last as u64)))
+    }
+
+    /// Open times of stored klines of a stream that were still open when stored but close
+    /// before `now`, oldest first.
+    pub async fn stale_open_klines(&self, symbol: &str, interval: KlineInterval, now: u64) -> Result<Vec<u64>, IOError> {
+        let rows = self.client().await?.query(
+            "SELECT start_time FROM kline_data
+             WHERE symbol = $1 AND interval = $2 AND NOT is_kline_closed AND end_time < $3
+             ORDER BY start_time",
+            &[&symbol, &interval.as_str(), &(now as i64)],
+        ).await.map_err(db_error)?;
+        Ok(rows.iter().map(|row| row.get::<_, i64>(0) as u64).collect())
+    }
+
+    /// Missing klines between stored klines of a stream opened in `[start_time, end_time)`.
+    ///
+    /// Only finds holes between two stored klines; what is missing before the first or
+    /// after the last one depends on the range the caller expects, see
+    /// [`DatabaseClient::kline_coverage`].
+    pub async fn kline_gaps(&self, symbol: &str, interval: KlineInterval, start_time: u64, end_time: u64) -> Result<Vec<KlineGap>, IOError> {
+        // Consecutive monthly klines are at most 31 days apart
+        let max_step = interval.duration_ms().unwrap_or(31 * 24 * 60 * 60 * 1000) as i64;
+        let rows = self.client().await?.query("
+    SELECT previous_start_time, start_time FROM (
+        SELECT start_time, LAG(start_time) OVER (ORDER BY start_time) AS previous_start_time
+        FROM kline_data
+        WHERE symbol = $1 AND interval = $2 AND start_time >= $3 AND start_time < $4
+    ) ordered
+    WHERE start_time - previous_start_time > $5
+    ORDER BY start_time
+    ", &[&symbol, &interval.as_str(), &(start_time as i64), &(end_time as i64), &max_step]).await.map_err(db_error)?;
+
+        Ok(rows
+            .iter()
+            .map(|row| KlineGap {
+                start_time: interval.next_open_time(row.get::<_, i64>(0) as u64),
+                end_time: row.get::<_, i64>(1) as u64,
+            })
+            .collect())
+    }
+
+    /// Reports which klines of a stream opening in `[start_time, end_time)` are stored.
+    pub async fn kline_coverage(&self, symbol: &str, interval: KlineInterval, start_time: u64, end_time: u64) -> Result<KlineCoverage, IOError> {
+        let row = self.client().await?.query_one("
+    SELECT COUNT(*), MIN(start_time), MAX(start_time) FROM kline_data
+    WHERE symbol = $1 AND interval = $2 AND start_time >= $3 AND start_time < $4
+    ", &[&symbol, &interval.as_str(), &(start_time as i64), &(end_time as i64)]).await.map_err(db_error)?;
+        let stored = row.get::<_, i64>(0) as u64;
+        let first = row.get::<_, Option<i64>>(1).map(|time| time as u64);
+        let last = row.get::<_, Option<i64>>(2).map(|time| time as u64);
+
+        // Open time of the first kline expected in the range
+        let open_time = interval.open_time(start_time);
+        let expected_first = if open_time == start_time { open_time } else { interval.next_open_time(open_time) };
+
+        let mut gaps = Vec::new();
+        match first.zip(last) {
+            Some((first, last)) => {
+                if expected_first < first {
+                    gaps.push(KlineGap { start_time: expected_first, end_time: first });
+                }
+                gaps.extend(self.kline_gaps(symbol, interval, start_time, end_time).await?);
+                let next = interval.next_open_time(last);
+                if next < end_time {
+                    gaps.push(KlineGap { start_time: next, end_time });
+                }
+            }
+            None if expected_first < end_time => gaps.push(KlineGap { start_time: expected_first, end_time }),
+            None => {}
+        }
+        Ok(KlineCoverage { stored, first, last, gaps })
+    }
+
+    /// Stored klines of a stream opened in `[start_time, end_time)`, oldest first.
+    ///
+    /// The trade ids of the klines are not stored and read back as zero.
+    pub async fn klines(&self, symbol: &str, interval: KlineInterval, start_time: u64, end_time: u64) -> Result<Vec<Kline>, IOError> {
+        let rows = self.client().await?.query(&format!("
+    SELECT {} FROM kline_data
+    WHERE symbol = $1 AND interval = $2 AND start_time >= $3 AND start_time < $4
+    ORDER BY start_time
+    ", KLINE_COLUMNS), &[&symbol, &interval.as_str(), &(start_time as i64), &(end_time as i64)]).await.map_err(db_error)?;
+        Ok(rows.iter().map(kline_from_row).collect())
+    }
+
+    /// The last `count` stored klines of a stream, oldest first, e.g. to warm up indicators.
+    ///
+    /// The last one may still be open if the recorder stores open klines.
+    pub async fn latest_klines(&self, symbol: &str, interval: KlineInterval, count: u32) -> Result<Vec<Kline>, IOError> {
+        let rows = self.client().await?.query(&format!("
+    SELECT {columns} FROM (
+        SELECT {columns} FROM kline_data
+        WHERE symbol = $1 AND interval = $2
+        ORDER BY start_time DESC
+        LIMIT $3
+    ) latest
+    ORDER BY start_time
+    ", columns = KLINE_COLUMNS), &[&symbol, &interval.as_str(), &(count as i64)]).await.map_err(db_error)?;
+        Ok(rows.iter().map(kline_from_row).collect())
+    }
+
+    /// Streams the stored klines of a stream opened in `[start_time, end_time)`, oldest first.
+    ///
+    /// Reads pages of [`KLINE_PAGE_SIZE`] klines keyed on the open time, so long ranges
+    /// never sit in memory at once and no connection is held between pages.
+    pub fn kline_stream<'a>(
+        &'a self,
+        symbol: &str,
+        interval: KlineInterval,
+        start_time: u64,
+        end_time: u64,
+    ) -> impl Stream<Item = Result<Kline, IOError>> + 'a {
+        let symbol = symbol.to_string();
+        stream::try_unfold(Some(start_time), move |cursor| {
+            let symbol = symbol.clone();
+            async move {
+                let Some(cursor) = cursor else { return Ok::<_, IOError>(None) };
+                let rows = self.client().await?.query(&format!("
+    SELECT {} FROM kline_data
+    WHERE symbol = $1 AND interval = $2 AND start_time >= $3 AND start_time < $4
+    ORDER BY start_time
+    LIMIT $5
+    ", KLINE_COLUMNS), &[&symbol, &interval.as_str(), &(cursor as i64), &(end_time as i64), &KLINE_PAGE_SIZE]).await.map_err(db_error)?;
+
+                let page: Vec<Kline> = rows.iter().map(kline_from_row).collect();
+                let next = match page.last() {
+                    Some(last) if page.len() == KLINE_PAGE_SIZE as usize => Some(last.start_time + 1),
+                    Some(_) => None,
+                    None => return Ok(None),
+                };
+                Ok(Some((page, next)))
+            }
+        })
+        .map_ok(|page| stream::iter(page.into_iter().map(Ok)))
+        .try_flatten()
+    }
+
+    /// Aggregates stored `source` klines into `target` candles in SQL, e.g. 1m into 1h.
+    ///
+    /// `start_time` is rounded down to the open of its `target` candle so the first
+    /// candle is whole. A candle is closed once its last `source` kline is; candles with
+    /// missing `source` klines are built from what is stored, so check
+    /// [`DatabaseClient::kline_coverage`] first when that matters.
+    pub async fn resampled_klines(
+        &self,
+        symbol: &str,
+        source: KlineInterval,
+        target: KlineInterval,
+        start_time: u64,
+        end_time: u64,
+    ) -> Result<Vec<Kline>, IOError> {
+        let invalid = || IOError::new(ErrorKind::InvalidInput, format!("Cannot resample {} klines into {}", source, target));
+        let source_ms = source.duration_ms().ok_or_else(invalid)?;
+        let start_time = target.open_time(start_time);
+
+        let rows = match target.duration_ms() {
+            Some(target_ms) if target_ms > source_ms && target_ms.is_multiple_of(source_ms) => {
+                // Weekly candles are offset from the epoch
+                let phase = (start_time % target_ms) as i64;
+                let bucket = "start_time - MOD(start_time - $5, $6)";
+                self.client().await?
+                    .query(&resample_query(bucket), &[
+                        &symbol, &source.as_str(), &(start_time as i64), &(end_time as i64), &phase, &(target_ms as i64),
+                    ])
+                    .await
+            }
+            None if DAY_MS.is_multiple_of(source_ms) => {
+                let bucket = "(EXTRACT(EPOCH FROM date_trunc('month', to_timestamp(start_time / 1000.0) AT TIME ZONE 'UTC')) * 1000)::BIGINT";
+                self.client().await?
+                    .query(&resample_query(bucket), &[&symbol, &source.as_str(), &(start_time as i64), &(end_time as i64)])
+                    .await
+            }
+            _ => return Err(invalid()),
+        }.map_err(db_error)?;
+
+        Ok(rows
+            .iter()
+            .map(|row| {
+                let open_time = row.get::<_, i64>("bucket") as u64;
+                let close_time = target.next_open_time(open_time) - 1;
+                Kline {
+                    start_time: open_time,
+                    end_time: close_time,
+                    symbol: symbol.to_string(),
+                    interval: target.to_string(),
+                    first_trade_id: 0,
+                    last_trade_id: 0,
+                    open_price: row.get("open_price"),
+                    close_price: row.get("close_price"),
+                    high_price: row.get("high_price"),
+                    low_price: row.get("low_price"),
+                    base_asset_volume: row.get("base_asset_volume"),
+                    number_of_trades: row.get::<_, i64>("number_of_trades") as u32,
+                    is_kline_closed: row.get::<_, bool>("all_closed") && row.get::<_, i64>("last_end_time") as u64 == close_time,
+                    quote_asset_volume: row.get("quote_asset_volume"),
+                    taker_buy_base_asset_volume: row.get("taker_buy_base_asset_volume"),
+                    taker_buy_quote_asset_volume: row.get("taker_buy_quote_asset_volume"),
+                    ignore: "0".to_string(),
+                }
+            })
+            .collect())
+    }
+
+    /// Upserts `/v3/myTrades` records into `account_trades`, keyed on symbol and trade id.
+    pub async fn insert_trades(&self, trades: &[Trade]) -> Result<(), IOError> {
+        let stmt = "
+    INSERT INTO account_trades (
+        symbol, trade_id, order_id, price, qty, quote_qty, commission, commission_asset,
+        time, is_buyer, is_maker, is_best_match
+    ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+    ON CONFLICT (symbol, trade_id) DO NOTHING
+    ";
+
+        let client = self.client().await?;
+        for trade in trades {
+            client.execute(stmt, &[
+                &trade.symbol,
+                &trade.id,
+                &trade.order_id,
+                &trade.price,
+                &trade.qty,
+                &trade.quote_qty,
+                &trade.commission,
+                &trade.commission_asset,
+                &(trade.time as i64),
+                &trade.is_buyer,
+                &trade.is_maker,
+                &trade.is_best_match,
+            ]).await.map_err(db_error)?;
+        }
+
+        Ok(())
+    }
+}
+
+
+/// Rows written by a bulk upsert.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UpsertStats {
+    pub inserted: u64,
+    pub updated: u64,
 }
 
+/// A run of missing klines: those opening at or after `start_time` and before `end_time`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KlineGap {
+    pub start_time: u64,
+    pub end_time: u64,
+}
+
+/// Which klines of a time range are stored, from [`DatabaseClient::kline_coverage`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KlineCoverage {
+    pub stored: u64,
+    pub first: Option<u64>, // Open time of the first stored kline in the range
+    pub last: Option<u64>, // Open time of the last stored kline in the range
+    pub gaps: Vec<KlineGap>,
+}
+
+impl KlineCoverage {
+    pub fn is_complete(&self) -> bool {
+        self.gaps.is_empty()
+    }
+}
+
+/// Klines read per query by [`DatabaseClient::kline_stream`].
+pub const KLINE_PAGE_SIZE: i64 = 10_000;
+
+pub(crate) const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+const KLINE_COLUMNS: &str = "
+    symbol, interval, start_time, end_time, open_price, close_price,
+    high_price, low_price, base_asset_volume, number_of_trades,
+    is_kline_closed, quote_asset_volume, taker_buy_base_asset_volume,
+    taker_buy_quote_asset_volume
+";
+
+fn kline_from_row(row: &Row) -> Kline {
+    Kline {
+        start_time: row.get::<_, i64>("start_time") as u64,
+        end_time: row.get::<_, i64>("end_time") as u64,
+        symbol: row.get("symbol"),
+        interval: row.get("interval"),
+        // Trade ids are not stored
+        first_trade_id: 0,
+        last_trade_id: 0,
+        open_price: row.get("open_price"),
+        close_price: row.get("close_price"),
+        high_price: row.get("high_price"),
+        low_price: row.get("low_price"),
+        base_asset_volume: row.get("base_asset_volume"),
+        number_of_trades: row.get::<_, i32>("number_of_trades") as u32,
+        is_kline_closed: row.get("is_kline_closed"),
+        quote_asset_volume: row.get("quote_asset_volume"),
+        taker_buy_base_asset_volume: row.get("taker_buy_base_asset_volume"),
+        taker_buy_quote_asset_volume: row.get("taker_buy_quote_asset_volume"),
+        ignore: "0".to_string(),
+    }
+}
+
+// Groups source klines by `bucket`, an SQL expression of `start_time` giving the open time of the target candle
+fn resample_query(bucket: &str) -> String {
+    format!("
+    SELECT
+        {} AS bucket,
+        (ARRAY_AGG(open_price ORDER BY start_time))[1] AS open_price,
+        (ARRAY_AGG(close_price ORDER BY start_time DESC))[1] AS close_price,
+        MAX(high_price) AS high_price,
+        MIN(low_price) AS low_price,
+        SUM(base_asset_volume) AS base_asset_volume,
+        SUM(number_of_trades) AS number_of_trades,
+        BOOL_AND(is_kline_closed) AS all_closed,
+        MAX(end_time) AS last_end_time,
+        SUM(quote_asset_volume) AS quote_asset_volume,
+        SUM(taker_buy_base_asset_volume) AS taker_buy_base_asset_volume,
+        SUM(taker_buy_quote_asset_volume) AS taker_buy_quote_asset_volume
+    FROM kline_data
+    WHERE symbol = $1 AND interval = $2 AND start_time >= $3 AND start_time < $4
+    GROUP BY bucket
+    ORDER BY bucket
+    ", bucket)
+}
+
+// Keeps the last occurrence of each (symbol, interval, start_time), in first-seen order.
+// A single INSERT ... ON CONFLICT cannot touch the same row twice.
+fn dedup_klines(klines: &[Kline]) -> Vec<&Kline> {
+    let mut positions: HashMap<(&str, &str, u64), usize> = HashMap::new();
+    let mut unique: Vec<&Kline> = Vec::with_capacity(klines.len());
+    for kline in klines {
+        let key = (kline.symbol.as_str(), kline.interval.as_str(), kline.start_time);
+        match positions.get(&key) {
+            Some(&position) => unique[position] = kline,
+            None => {
+                positions.insert(key, unique.len());
+                unique.push(kline);
+            }
+        }
+    }
+    unique
+}
+
+const CREATE_SCHEMA_MIGRATIONS: &str = "
+    CREATE TABLE IF NOT EXISTS schema_migrations (
+        version BIGINT PRIMARY KEY,
+        name TEXT NOT NULL,
+        checksum CHAR(64) NOT NULL,
+        applied_at BIGINT NOT NULL
+    );
+";
+
+// Arbitrary key of the Postgres advisory lock held while migrating
+const MIGRATION_LOCK_ID: i64 = 0x62696e616e6365;
+
+pub(crate) fn db_error(e: Error) -> IOError {
+    IOError::new(ErrorKind::Other, format!("Database error: {}", e))
+}
+
+fn pool_error(e: PoolError) -> IOError {
+    IOError::new(ErrorKind::Other, format!("Database pool error: {}", e))
+}
+
+/// Quotes a database, table or role name for interpolation into SQL, doubling embedded quotes.
+pub fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
 
 #[cfg(test)]
 mod tests {
@@ -208,9 +782,9 @@ mod tests {
     use rust_decimal::Decimal;
     use serde::{Deserialize, Serialize};
     use super::DatabaseClient;
-    use tokio_postgres::NoTls;
     use crate::binance_client::load_env::EnvVars;
     use crate::binance_client::logger_conf::init_logger;
+    use crate::binance_client::kline_interval::KlineInterval;
     use crate::binance_client::streams::kline_data::{Kline, KlineData, KlineMessage};
 
     #[tokio::test]
@@ -218,26 +792,25 @@ mod tests {
         init_logger(Trace);
         // Setup unique test database parameters
         let vars = EnvVars::new();
-        let db_name = vars.name.as_str();
-        let user = vars.user.as_str();
-        let pwd = vars.pwd.as_str();
+        // The generated name contains hyphens, so creating it exercises identifier quoting
+        let config = vars.database_config();
 
         trace!("vars: {:?}", vars);
 
         // Attempt to create the database
-        let db_client = DatabaseClient::connect_or_create_if_not_exist(&db_name, &user, &pwd).await.expect("Failed to setup database");
+        let db_client = DatabaseClient::connect_or_create_if_not_exist(&config).await.expect("Failed to setup database");
 
         // Verify the database exists
-        let exists = DatabaseClient::database_exists(&db_name, &user, &pwd).await.expect("Failed to check if database exists");
+        let exists = DatabaseClient::database_exists(&config).await.expect("Failed to check if database exists");
         assert!(exists, "Database was not created successfully");
 
         db_client.close().await;
 
         // Drop the database to clean up
-        DatabaseClient::drop_database_if_exists(&db_name, &user, &pwd).await.expect("Failed to drop the test database");
+        DatabaseClient::drop_database_if_exists(&config).await.expect("Failed to drop the test database");
 
         // Verify the database no longer exists
-        let exists = DatabaseClient::database_exists(&db_name, &user, &pwd).await.expect("Failed to check if database exists after dropping");
+        let exists = DatabaseClient::database_exists(&config).await.expect("Failed to check if database exists after dropping");
         assert!(!exists, "Database was not dropped successfully");
     }
 
@@ -245,7 +818,7 @@ mod tests {
     async fn test_database_operations_with_kline_data() {
         let vars = EnvVars::new();
 
-        let database_client = DatabaseClient::connect_or_create_if_not_exist(vars.name.as_str(), vars.user.as_str(), vars.pwd.as_str()).await.expect("Failed to setup database");
+        let database_client = DatabaseClient::connect_or_create_if_not_exist(&vars.database_config()).await.expect("Failed to setup database");
 
         // Ensure kline_data table exists
         database_client.ensure_kline_data_table_exists().await.expect("Failed to ensure kline_data table exists");
@@ -283,8 +856,12 @@ mod tests {
         database_client.insert_kline_data(&kline_message).await.expect("Failed to insert kline data");
 
         // Query and validate the inserted data
-        let rows = database_client.client.query("SELECT symbol, interval, start_time, end_time, open_price FROM kline_data WHERE symbol = $1", &[&kline_message.data.symbol]).await.expect("Failed to query kline data");
-        assert!(!rows.is_empty(), "No data found after insertion");
+        let interval = KlineInterval::OneMinute;
+        let klines = database_client.klines("BTCUSDT", interval, 1609459200000, 1609459260000).await.expect("Failed to query kline data");
+        assert_eq!(klines.len(), 1, "No data found after insertion");
+        assert_eq!(klines[0].close_price, 29001.0);
+        assert_eq!(klines[0].number_of_trades, 100);
+        assert_eq!(database_client.latest_klines("BTCUSDT", interval, 10).await.unwrap().len(), 1);
 
         database_client.close().await;
         drop(database_client);
@@ -292,7 +869,158 @@ mod tests {
         // Clean up by dropping the test database
         // database_client.execute(&*format!("DROP DATABASE {}", db_name), &[]).await.expect("Failed to drop test database after tests");
 
-        DatabaseClient::drop_database_if_exists(vars.name.as_str(), vars.user.as_str(), vars.pwd.as_str()).await.expect("Failed to drop database");
+        DatabaseClient::drop_database_if_exists(&vars.database_config()).await.expect("Failed to drop database");
     }
-}
 
+    fn kline(start_time: u64, close_price: f64) -> Kline {
+        Kline {
+            start_time,
+            end_time: start_time + 59_999,
+            symbol: "BTCUSDT".to_string(),
+            interval: "1m".to_string(),
+            first_trade_id: 0,
+            last_trade_id: 0,
+            open_price: 29000.0,
+            close_price,
+            high_price: 29010.0,
+            low_price: 28990.0,
+            base_asset_volume: 1.0,
+            number_of_trades: 10,
+            is_kline_closed: true,
+            quote_asset_volume: 29000.0,
+            taker_buy_base_asset_volume: 0.5,
+            taker_buy_quote_asset_volume: 14500.0,
+            ignore: "0".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_quote_identifier() {
+        assert_eq!(super::quote_identifier("binance_data"), "\"binance_data\"");
+        assert_eq!(super::quote_identifier("test-db"), "\"test-db\"");
+        assert_eq!(super::quote_identifier("x\"; DROP DATABASE postgres; --"), "\"x\"\"; DROP DATABASE postgres; --\"");
+    }
+
+    #[tokio::test]
+    async fn test_pool_shared_across_tasks() {
+        let vars = EnvVars::new();
+        let config = vars.database_config().with_pool_size(2);
+        let database_client = DatabaseClient::connect_or_create_if_not_exist(&config).await.expect("Failed to setup database");
+
+        // More concurrent writers than pooled connections, each bulk upsert in its own transaction
+        let writers: Vec<_> = (0..4u64)
+            .map(|task| {
+                let database_client = database_client.clone();
+                tokio::spawn(async move {
+                    let klines: Vec<Kline> = (0..100).map(|i| kline((task * 100 + i) * 60_000, 29000.0)).collect();
+                    database_client.bulk_upsert_klines(&klines).await
+                })
+            })
+            .collect();
+        for writer in writers {
+            assert_eq!(writer.await.unwrap().expect("Concurrent upsert failed").inserted, 100);
+        }
+
+        database_client.close().await;
+        drop(database_client);
+        DatabaseClient::drop_database_if_exists(&config).await.expect("Failed to drop database");
+    }
+
+    #[tokio::test]
+    async fn test_read_and_resample_klines() {
+        use futures::TryStreamExt;
+
+        let vars = EnvVars::new();
+        let database_client = DatabaseClient::connect_or_create_if_not_exist(&vars.database_config()).await.expect("Failed to setup database");
+
+        // Two and a half hours of one minute klines, the last one still open
+        let mut klines: Vec<Kline> = (0..150).map(|i| kline(i * 60_000, 29000.0 + i as f64)).collect();
+        klines.last_mut().unwrap().is_kline_closed = false;
+        database_client.bulk_upsert_klines(&klines).await.expect("Failed to insert klines");
+
+        let latest = database_client.latest_klines("BTCUSDT", KlineInterval::OneMinute, 3).await.unwrap();
+        assert_eq!(latest.iter().map(|kline| kline.start_time / 60_000).collect::<Vec<_>>(), vec![147, 148, 149]);
+
+        let streamed: Vec<Kline> = database_client
+            .kline_stream("BTCUSDT", KlineInterval::OneMinute, 60_000, 120 * 60_000)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(streamed.len(), 119);
+        assert!(streamed.windows(2).all(|pair| pair[0].start_time < pair[1].start_time));
+
+        let hours = database_client
+            .resampled_klines("BTCUSDT", KlineInterval::OneMinute, KlineInterval::OneHour, 0, 150 * 60_000)
+            .await
+            .unwrap();
+        assert_eq!(hours.len(), 3);
+        assert_eq!((hours[0].start_time, hours[0].end_time), (0, 3_599_999));
+        assert_eq!((hours[0].open_price, hours[0].close_price), (29000.0, 29059.0));
+        assert_eq!(hours[0].base_asset_volume, 60.0);
+        assert_eq!(hours[1].number_of_trades, 600);
+        assert!(hours[0].is_kline_closed && hours[1].is_kline_closed);
+        // Only half of the last hour is stored
+        assert!(!hours[2].is_kline_closed);
+
+        let months = database_client
+            .resampled_klines("BTCUSDT", KlineInterval::OneMinute, KlineInterval::OneMonth, 0, 150 * 60_000)
+            .await
+            .unwrap();
+        assert_eq!(months.len(), 1);
+        assert_eq!(months[0].end_time, 31 * 24 * 3_600_000 - 1);
+        assert!(database_client
+            .resampled_klines("BTCUSDT", KlineInterval::OneHour, KlineInterval::OneMinute, 0, 150 * 60_000)
+            .await
+            .is_err());
+
+        database_client.close().await;
+        drop(database_client);
+        DatabaseClient::drop_database_if_exists(&vars.database_config()).await.expect("Failed to drop database");
+    }
+
+    #[test]
+    fn test_dedup_klines_keeps_last_occurrence() {
+        let klines = vec![kline(0, 1.0), kline(60_000, 2.0), kline(0, 3.0)];
+        let unique = super::dedup_klines(&klines);
+        assert_eq!(unique.len(), 2);
+        assert_eq!(unique[0].close_price, 3.0);
+        assert_eq!(unique[1].start_time, 60_000);
+    }
+
+    #[tokio::test]
+    async fn test_bulk_upsert_klines() {
+        let vars = EnvVars::new();
+        let database_client = DatabaseClient::connect_or_create_if_not_exist(&vars.database_config()).await.expect("Failed to setup database");
+
+        let klines: Vec<Kline> = (0..1_000).map(|i| kline(i * 60_000, 29000.0)).collect();
+        let stats = database_client.bulk_upsert_klines(&klines).await.expect("Failed to bulk insert klines");
+        assert_eq!(stats, super::UpsertStats { inserted: 1_000, updated: 0 });
+
+        let stats = database_client.bulk_upsert_klines(&klines[..10]).await.expect("Failed to bulk update klines");
+        assert_eq!(stats, super::UpsertStats { inserted: 0, updated: 10 });
+
+        // Remove two runs of klines and find them again
+        database_client.client().await.unwrap()
+            .execute("DELETE FROM kline_data WHERE start_time IN ($1, $2, $3)", &[&(60_000i64), &(600_000i64), &(660_000i64)])
+            .await
+            .unwrap();
+        let interval = KlineInterval::OneMinute;
+        let gaps = database_client.kline_gaps("BTCUSDT", interval, 0, u32::MAX as u64).await.unwrap();
+        assert_eq!(gaps, vec![
+            super::KlineGap { start_time: 60_000, end_time: 120_000 },
+            super::KlineGap { start_time: 600_000, end_time: 720_000 },
+        ]);
+        assert_eq!(database_client.kline_gaps("BTCUSDT", interval, 300_000, u32::MAX as u64).await.unwrap().len(), 1);
+        assert_eq!(database_client.kline_time_range("BTCUSDT", interval).await.unwrap(), Some((0, 999 * 60_000)));
+
+        let coverage = database_client.kline_coverage("BTCUSDT", interval, 30_000, 1_100 * 60_000).await.unwrap();
+        assert_eq!(coverage.stored, 996);
+        assert_eq!(coverage.gaps.first(), Some(&super::KlineGap { start_time: 60_000, end_time: 120_000 }));
+        assert_eq!(coverage.gaps.last(), Some(&super::KlineGap { start_time: 1_000 * 60_000, end_time: 1_100 * 60_000 }));
+        assert!(!coverage.is_complete());
+
+        database_client.close().await;
+        drop(database_client);
+        DatabaseClient::drop_database_if_exists(&vars.database_config()).await.expect("Failed to drop database");
+    }
+}
@@ -1,4 +1,6 @@
 use std::fs;
+use std::io::{Error as IOError, ErrorKind};
+use postgres_native_tls::MakeTlsConnector;
 use serde::Deserialize;
 
 #[derive(Deserialize, Debug)]
@@ -6,11 +8,110 @@ pub struct Config {
     pub database: DatabaseConfig,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
 pub struct DatabaseConfig {
     pub name: String,
     pub user: String,
     pub password: String,
+    #[serde(default = "default_host")]
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub ssl_mode: SslMode,
+    #[serde(default = "default_pool_size")]
+    pub pool_size: usize,
+}
+
+/// How the connection is encrypted, named after the libpq `sslmode` values.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SslMode {
+    Disable,
+    // Use TLS when the server supports it, without verifying its certificate
+    #[default]
+    Prefer,
+    // Always use TLS, without verifying the server certificate
+    Require,
+    // Always use TLS and verify the certificate chain and host name
+    VerifyFull,
+}
+
+fn default_host() -> String {
+    "localhost".to_string()
+}
+
+fn default_port() -> u16 {
+    5432
+}
+
+fn default_pool_size() -> usize {
+    16
+}
+
+impl DatabaseConfig {
+    /// A config for a local server on the default port.
+    pub fn new(name: &str, user: &str, password: &str) -> Self {
+        DatabaseConfig {
+            name: name.to_string(),
+            user: user.to_string(),
+            password: password.to_string(),
+            host: default_host(),
+            port: default_port(),
+            ssl_mode: SslMode::default(),
+            pool_size: default_pool_size(),
+        }
+    }
+
+    pub fn with_host(mut self, host: &str, port: u16) -> Self {
+        self.host = host.to_string();
+        self.port = port;
+        self
+    }
+
+    pub fn with_ssl_mode(mut self, ssl_mode: SslMode) -> Self {
+        self.ssl_mode = ssl_mode;
+        self
+    }
+
+    pub fn with_pool_size(mut self, pool_size: usize) -> Self {
+        self.pool_size = pool_size;
+        self
+    }
+
+    /// Connection settings for `dbname` on the configured server.
+    ///
+    /// Built field by field rather than as a connection string, so values containing
+    /// spaces or quotes need no escaping.
+    pub fn pg_config(&self, dbname: &str) -> tokio_postgres::Config {
+        let ssl_mode = match self.ssl_mode {
+            SslMode::Disable => tokio_postgres::config::SslMode::Disable,
+            SslMode::Prefer => tokio_postgres::config::SslMode::Prefer,
+            SslMode::Require | SslMode::VerifyFull => tokio_postgres::config::SslMode::Require,
+        };
+
+        let mut config = tokio_postgres::Config::new();
+        config
+            .host(&self.host)
+            .port(self.port)
+            .user(&self.user)
+            .password(&self.password)
+            .dbname(dbname)
+            .ssl_mode(ssl_mode)
+            .application_name("binance_api");
+        config
+    }
+
+    /// The TLS connector for the configured `ssl_mode`. Unused when TLS is disabled.
+    pub fn tls_connector(&self) -> Result<MakeTlsConnector, IOError> {
+        let verify = self.ssl_mode == SslMode::VerifyFull;
+        let connector = native_tls::TlsConnector::builder()
+            .danger_accept_invalid_certs(!verify)
+            .danger_accept_invalid_hostnames(!verify)
+            .build()
+            .map_err(|e| IOError::new(ErrorKind::Other, format!("Failed to build TLS connector: {}", e)))?;
+        Ok(MakeTlsConnector::new(connector))
+    }
 }
 
 
@@ -66,5 +167,31 @@ mod tests {
         println!("Loaded database user: {}", database_user);
         println!("Loaded database password: {}", database_password);
     }
+
+    #[test]
+    fn test_database_config_defaults_and_overrides() {
+        let minimal: Config = toml::from_str("[database]\nname = \"testdb\"\nuser = \"test\"\npassword = \"1\"").unwrap();
+        assert_eq!(minimal.database, DatabaseConfig::new("testdb", "test", "1"));
+        assert_eq!(minimal.database.ssl_mode, SslMode::Prefer);
+
+        let remote: Config = toml::from_str("
+            [database]
+            name = \"market data\"
+            user = \"trader\"
+            password = \"it's secret\"
+            host = \"db.internal\"
+            port = 6432
+            ssl_mode = \"verify-full\"
+            pool_size = 4
+        ").unwrap();
+        assert_eq!(remote.database.ssl_mode, SslMode::VerifyFull);
+        assert_eq!(remote.database.pool_size, 4);
+
+        let pg_config = remote.database.pg_config("market data");
+        assert_eq!(pg_config.get_ports(), &[6432]);
+        assert_eq!(pg_config.get_dbname(), Some("market data"));
+        assert_eq!(pg_config.get_password(), Some("it's secret".as_bytes()));
+        assert_eq!(pg_config.get_ssl_mode(), tokio_postgres::config::SslMode::Require);
+    }
 }
 
@@ -0,0 +1,615 @@
+use std::io::{Error as IOError, ErrorKind};
+use std::time::Duration;
+use async_trait::async_trait;
+use chrono::{Datelike, Days};
+use log::{info, warn};
+use tokio_postgres::Row;
+use crate::binance_client::account::order::Order;
+use crate::binance_client::binance_client::BinanceClient;
+use crate::binance_client::cron_schedule::CronSchedule;
+use crate::binance_client::database_client::{db_error, DatabaseClient};
+use crate::binance_client::exchange_info::SymbolInfo;
+use crate::binance_client::kline_interval::KlineInterval;
+use crate::binance_client::order_api::OrderApi;
+use crate::binance_client::order_types::client_order_id::{is_client_order_id_text, MAX_CLIENT_ORDER_ID_LEN};
+use crate::binance_client::order_types::market_order::MarketOrder;
+use crate::binance_client::order_types::side::Side;
+
+/// What a [`DcaScheduler`] reads from the exchange: recent prices for the moving average
+/// filter of a [`DcaPlan`], and the orders of runs that were not recorded.
+#[async_trait]
+pub trait DcaMarketData: Send + Sync {
+    /// Closes of the last `limit` klines, oldest first. The last kline is still open, so
+    /// its close is the current price.
+    async fn recent_closes(&self, symbol: &str, interval: KlineInterval, limit: u16) -> Result<Vec<f64>, IOError>;
+
+    /// The order of `symbol` placed with `client_order_id`, if there is one.
+    async fn order_by_client_id(&self, symbol: &str, client_order_id: &str) -> Result<Option<Order>, IOError>;
+}
+
+#[async_trait]
+impl DcaMarketData for BinanceClient {
+    async fn recent_closes(&self, symbol: &str, interval: KlineInterval, limit: u16) -> Result<Vec<f64>, IOError> {
+        let klines = self.fetch_klines(symbol, interval, None, None, Some(limit)).await?;
+        Ok(klines.iter().map(|kline| kline.close_price).collect())
+    }
+
+    async fn order_by_client_id(&self, symbol: &str, client_order_id: &str) -> Result<Option<Order>, IOError> {
+        self.fetch_order_by_client_id(symbol, client_order_id).await
+    }
+}
+
+/// What happens to runs that were due while the scheduler was not running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MissedRuns {
+    Skip, // Recorded as missed, the plan waits for its next run
+    CatchUp, // Each one buys, oldest first, within the spending cap
+}
+
+/// Calendar period of a spending cap, in the time zone of the plan's schedule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpendingPeriod {
+    Day,
+    Week, // From Monday
+    Month,
+}
+
+/// Buys `quote_amount` of the quote asset worth of a symbol at market on a schedule.
+///
+/// Each purchase has the client order id `dca-{name}-{scheduled time}`, which Binance
+/// limits to 36 letters, digits and `.:/_-`, so names are up to 18 of them.
+#[derive(Debug, Clone)]
+pub struct DcaPlan {
+    pub name: String, // Identifies the plan's runs in the store and its orders
+    pub symbol: String,
+    pub base_asset: String,
+    pub quote_asset: String,
+    pub quote_amount: f64,
+    pub schedule: CronSchedule,
+    pub moving_average: Option<(KlineInterval, usize)>,
+    pub spending_cap: Option<(f64, SpendingPeriod)>,
+}
+
+impl DcaPlan {
+    pub fn new(name: &str, symbol: &SymbolInfo, quote_amount: f64, schedule: CronSchedule) -> Result<Self, IOError> {
+        // The scheduled time takes 13 digits until the year 2286
+        let longest = MAX_CLIENT_ORDER_ID_LEN - "dca--".len() - 13;
+        if name.is_empty() || name.len() > longest || !is_client_order_id_text(name) {
+            return Err(IOError::new(ErrorKind::InvalidInput, format!(
+                "DCA plan name {} needs 1 to {} letters, digits or .:/_- to fit in client order ids", name, longest,
+            )));
+        }
+        Ok(DcaPlan {
+            name: name.to_string(),
+            symbol: symbol.symbol.clone(),
+            base_asset: symbol.base_asset.clone(),
+            quote_asset: symbol.quote_asset.clone(),
+            quote_amount,
+            schedule,
+            moving_average: None,
+            spending_cap: None,
+        })
+    }
+
+    /// Only buys while the price is below the simple moving average of the last `period`
+    /// closed klines of `interval`.
+    pub fn with_moving_average_filter(mut self, interval: KlineInterval, period: usize) -> Self {
+        self.moving_average = Some((interval, period.max(1)));
+        self
+    }
+
+    /// Skips runs that would take the quote spent in the current `period` above `amount`.
+    pub fn with_spending_cap(mut self, amount: f64, period: SpendingPeriod) -> Self {
+        self.spending_cap = Some((amount, period));
+        self
+    }
+
+    // Start of the spending period that `time` falls in
+    fn period_start(&self, period: SpendingPeriod, time: u64) -> Option<u64> {
+        let date = self.schedule.local_date(time)?;
+        let first_day = match period {
+            SpendingPeriod::Day => date,
+            SpendingPeriod::Week => date.checked_sub_days(Days::new(date.weekday().num_days_from_monday() as u64))?,
+            SpendingPeriod::Month => date.with_day(1)?,
+        };
+        self.schedule.start_of_day(first_day)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DcaOutcome {
+    Bought,
+    Missed, // Due while the scheduler was down, see `MissedRuns::Skip`
+    AboveAverage,
+    CapReached,
+    Failed,
+}
+
+impl DcaOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DcaOutcome::Bought => "BOUGHT",
+            DcaOutcome::Missed => "MISSED",
+            DcaOutcome::AboveAverage => "ABOVE_AVERAGE",
+            DcaOutcome::CapReached => "CAP_REACHED",
+            DcaOutcome::Failed => "FAILED",
+        }
+    }
+
+    pub fn parse(outcome: &str) -> Option<Self> {
+        [DcaOutcome::Bought, DcaOutcome::Missed, DcaOutcome::AboveAverage, DcaOutcome::CapReached, DcaOutcome::Failed]
+            .into_iter()
+            .find(|candidate| candidate.as_str() == outcome)
+    }
+}
+
+/// Quantity bought by a plan and what it cost, commissions included.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CostBasis {
+    pub quantity: f64,
+    pub cost: f64,
+}
+
+impl CostBasis {
+    pub fn average_price(&self) -> Option<f64> {
+        (self.quantity > 0.0).then(|| self.cost / self.quantity)
+    }
+
+    pub fn unrealized_pnl(&self, price: f64) -> f64 {
+        self.quantity * price - self.cost
+    }
+}
+
+/// One scheduled run of a plan, as stored in `dca_runs`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DcaRun {
+    pub plan: String,
+    pub symbol: String,
+    pub scheduled_time: u64,
+    pub executed_at: u64,
+    pub outcome: DcaOutcome,
+    pub order_id: Option<i64>,
+    pub price: Option<f64>, // Average fill price, or the price the filter compared
+    pub quantity: f64, // Net of commission paid in the base asset
+    pub quote_spent: f64, // Including commission paid in the quote asset
+    pub cost_basis: CostBasis, // Of the plan after the run
+    pub reason: Option<String>,
+}
+
+impl From<&Row> for DcaRun {
+    fn from(row: &Row) -> Self {
+        DcaRun {
+            plan: row.get("plan"),
+            symbol: row.get("symbol"),
+            scheduled_time: row.get::<_, i64>("scheduled_time") as u64,
+            executed_at: row.get::<_, i64>("executed_at") as u64,
+            outcome: DcaOutcome::parse(row.get("outcome")).unwrap_or(DcaOutcome::Failed),
+            order_id: row.get("order_id"),
+            price: row.get("price"),
+            quantity: row.get("quantity"),
+            quote_spent: row.get("quote_spent"),
+            cost_basis: CostBasis { quantity: row.get("total_quantity"), cost: row.get("total_cost") },
+            reason: row.get("reason"),
+        }
+    }
+}
+
+struct PlanState {
+    plan: DcaPlan,
+    last_run: u64, // Scheduled time of the latest run handled
+    cost_basis: CostBasis,
+    purchases: Vec<(u64, f64)>, // Execution time and quote spent, for the spending cap
+}
+
+/// Runs [`DcaPlan`]s on their schedules.
+///
+/// A run is due at its scheduled time and missed once it is more than the grace
+/// period late, which happens when the scheduler was not running or a previous run
+/// took long. Every run yields a [`DcaRun`], which [`run`](Self::run) records in a
+/// [`DcaStore`]; passing those records back to [`add_plan`](Self::add_plan) after a
+/// restart resumes the plan where it stopped. A run that bought but was not recorded
+/// finds its order by client order id and is recorded from it instead of buying again.
+pub struct DcaScheduler<'a> {
+    order_api: &'a dyn OrderApi,
+    market_data: &'a dyn DcaMarketData,
+    missed_runs: MissedRuns,
+    grace_period_ms: u64,
+    plans: Vec<PlanState>,
+}
+
+impl<'a> DcaScheduler<'a> {
+    pub fn new(order_api: &'a dyn OrderApi, market_data: &'a dyn DcaMarketData, missed_runs: MissedRuns) -> Self {
+        DcaScheduler { order_api, market_data, missed_runs, grace_period_ms: 5 * 60 * 1000, plans: Vec::new() }
+    }
+
+    /// How late a run may start before it counts as missed, 5 minutes by default.
+    pub fn with_grace_period(mut self, grace_period: Duration) -> Self {
+        self.grace_period_ms = grace_period.as_millis() as u64;
+        self
+    }
+
+    /// Adds a plan with its recorded runs, oldest first, from [`DcaStore::runs`].
+    ///
+    /// Without history the plan starts with the first run after `now`; otherwise the runs
+    /// since the last recorded one are due.
+    pub fn add_plan(&mut self, plan: DcaPlan, history: &[DcaRun], now: u64) {
+        let history: Vec<&DcaRun> = history.iter().filter(|run| run.plan == plan.name).collect();
+        let purchases = history
+            .iter()
+            .filter(|run| run.outcome == DcaOutcome::Bought)
+            .map(|run| (run.executed_at, run.quote_spent))
+            .collect();
+        let state = PlanState {
+            last_run: history.iter().map(|run| run.scheduled_time).max().unwrap_or(now),
+            cost_basis: history.last().map(|run| run.cost_basis).unwrap_or_default(),
+            purchases,
+            plan,
+        };
+        self.plans.push(state);
+    }
+
+    pub fn cost_basis(&self, plan: &str) -> Option<CostBasis> {
+        self.plans.iter().find(|state| state.plan.name == plan).map(|state| state.cost_basis)
+    }
+
+    /// The earliest next run of any plan.
+    pub fn next_run_time(&self) -> Option<u64> {
+        self.plans.iter().filter_map(|state| state.plan.schedule.next_after(state.last_run)).min()
+    }
+
+    /// Handles every run due at `now`, plan by plan and oldest first.
+    ///
+    /// A failed purchase is recorded as such and does not stop the other runs.
+    pub async fn run_due(&mut self, now: u64) -> Vec<DcaRun> {
+        let mut runs = Vec::new();
+        while let Some(run) = self.run_next(now).await {
+            runs.push(run);
+        }
+        runs
+    }
+
+    /// Runs the plans until they have no runs left, recording each run in `store` as
+    /// soon as it is handled so a crash cannot lose a purchase.
+    pub async fn run(&mut self, store: &DcaStore) -> Result<(), IOError> {
+        while let Some(next_run_time) = self.next_run_time() {
+            let now = BinanceClient::generate_timestamp()?;
+            tokio::time::sleep(Duration::from_millis(next_run_time.saturating_sub(now))).await;
+            let now = BinanceClient::generate_timestamp()?;
+            while let Some(run) = self.run_next(now).await {
+                store.record(&run).await?;
+            }
+        }
+        Ok(())
+    }
+
+    // Handles the oldest due run of the first plan that has one
+    async fn run_next(&mut self, now: u64) -> Option<DcaRun> {
+        let (index, scheduled_time) = self.plans.iter().enumerate().find_map(|(index, state)| {
+            state.plan.schedule.next_after(state.last_run).filter(|run| *run <= now).map(|run| (index, run))
+        })?;
+        let missed = now.saturating_sub(scheduled_time) > self.grace_period_ms;
+        let run = if missed && self.missed_runs == MissedRuns::Skip {
+            self.outcome(index, scheduled_time, now, DcaOutcome::Missed, None, None)
+        } else {
+            self.buy(index, scheduled_time, now).await
+        };
+        info!("DCA plan {} run at {}: {:?}", run.plan, scheduled_time, run.outcome);
+        self.plans[index].last_run = scheduled_time;
+        Some(run)
+    }
+
+    async fn buy(&mut self, index: usize, scheduled_time: u64, now: u64) -> DcaRun {
+        let plan = &self.plans[index].plan;
+        let mut price = None;
+        if let Some((interval, period)) = plan.moving_average {
+            let closes = match self.market_data.recent_closes(&plan.symbol, interval, period as u16 + 1).await {
+                Ok(closes) if closes.len() > period => closes,
+                Ok(closes) => {
+                    let reason = format!("{} klines for a moving average of {}", closes.len(), period);
+                    return self.outcome(index, scheduled_time, now, DcaOutcome::Failed, None, Some(reason));
+                }
+                Err(e) => return self.outcome(index, scheduled_time, now, DcaOutcome::Failed, None, Some(e.to_string())),
+            };
+            let (closed, current) = closes.split_at(closes.len() - 1);
+            let average = closed[closed.len() - period..].iter().sum::<f64>() / period as f64;
+            if current[0] >= average {
+                return self.outcome(index, scheduled_time, now, DcaOutcome::AboveAverage, Some(current[0]), None);
+            }
+            price = Some(current[0]);
+        }
+
+        if let Some((cap, period)) = plan.spending_cap {
+            let period_start = plan.period_start(period, now).unwrap_or(now);
+            let spent: f64 = self.plans[index].purchases.iter().filter(|(time, _)| *time >= period_start).map(|(_, quote)| quote).sum();
+            if spent + plan.quote_amount > cap {
+                let reason = format!("{} of {} {} spent", spent, cap, plan.quote_asset);
+                return self.outcome(index, scheduled_time, now, DcaOutcome::CapReached, price, Some(reason));
+            }
+        }
+
+        // The same id for every attempt at a run, so a purchase made before a crash that
+        // was not recorded is found instead of made twice
+        let client_order_id = format!("dca-{}-{}", plan.name, scheduled_time);
+        match self.market_data.order_by_client_id(&plan.symbol, &client_order_id).await {
+            Ok(Some(order)) if order.executed_qty > 0.0 => {
+                info!("DCA plan {} already bought with order {}", plan.name, order.order_id);
+                self.book_purchase(index, now, order.executed_qty, order.cummulative_quote_qty);
+                let reason = "Placed before a restart, commissions are not included".to_string();
+                return DcaRun {
+                    order_id: Some(order.order_id),
+                    price: Some(order.cummulative_quote_qty / order.executed_qty),
+                    quantity: order.executed_qty,
+                    quote_spent: order.cummulative_quote_qty,
+                    ..self.outcome(index, scheduled_time, now, DcaOutcome::Bought, None, Some(reason))
+                };
+            }
+            Ok(_) => {}
+            Err(e) => return self.outcome(index, scheduled_time, now, DcaOutcome::Failed, price, Some(e.to_string())),
+        }
+        let order = MarketOrder::new_with_quote_asset(&plan.symbol, Side::Buy, plan.quote_amount)
+            .with_client_order_id(&client_order_id);
+        let response = match self.order_api.create_market_order(order).await {
+            Ok(response) => response,
+            Err(e) => {
+                warn!("DCA plan {} failed to buy {}: {}", plan.name, plan.symbol, e);
+                return self.outcome(index, scheduled_time, now, DcaOutcome::Failed, price, Some(e.to_string()));
+            }
+        };
+        let fills = response.fills.unwrap_or_default();
+        let executed_qty = response.executed_qty.unwrap_or_else(|| fills.iter().map(|fill| fill.qty).sum());
+        let mut quantity = executed_qty;
+        let mut quote_spent = response.cummulative_quote_qty.unwrap_or_else(|| fills.iter().map(|fill| fill.price * fill.qty).sum());
+        let average_price = (executed_qty > 0.0).then(|| quote_spent / executed_qty);
+        for fill in &fills {
+            // Commissions in other assets, like BNB, are not part of the cost basis
+            if fill.commission_asset == plan.base_asset {
+                quantity -= fill.commission;
+            } else if fill.commission_asset == plan.quote_asset {
+                quote_spent += fill.commission;
+            }
+        }
+        if executed_qty <= 0.0 {
+            return self.outcome(index, scheduled_time, now, DcaOutcome::Failed, price, Some("Nothing was executed".to_string()));
+        }
+
+        self.book_purchase(index, now, quantity, quote_spent);
+        DcaRun {
+            order_id: Some(response.order_id),
+            price: average_price,
+            quantity,
+            quote_spent,
+            ..self.outcome(index, scheduled_time, now, DcaOutcome::Bought, None, None)
+        }
+    }
+
+    fn book_purchase(&mut self, index: usize, now: u64, quantity: f64, quote_spent: f64) {
+        let state = &mut self.plans[index];
+        state.cost_basis.quantity += quantity;
+        state.cost_basis.cost += quote_spent;
+        state.purchases.push((now, quote_spent));
+    }
+
+    // A run without a purchase
+    fn outcome(&self, index: usize, scheduled_time: u64, now: u64, outcome: DcaOutcome, price: Option<f64>, reason: Option<String>) -> DcaRun {
+        let state = &self.plans[index];
+        DcaRun {
+            plan: state.plan.name.clone(),
+            symbol: state.plan.symbol.clone(),
+            scheduled_time,
+            executed_at: now,
+            outcome,
+            order_id: None,
+            price,
+            quantity: 0.0,
+            quote_spent: 0.0,
+            cost_basis: state.cost_basis,
+            reason,
+        }
+    }
+}
+
+/// Records the runs of DCA plans in Postgres.
+///
+/// The table is created by [`DatabaseClient::migrate`], which callers run first.
+#[derive(Debug, Clone)]
+pub struct DcaStore {
+    db: DatabaseClient,
+}
+
+impl DcaStore {
+    pub fn new(db: DatabaseClient) -> Self {
+        DcaStore { db }
+    }
+
+    /// Records a run. A run that is already recorded is kept as it is.
+    pub async fn record(&self, run: &DcaRun) -> Result<(), IOError> {
+        self.db.client().await?.execute(
+            "INSERT INTO dca_runs (plan, symbol, scheduled_time, executed_at, outcome, order_id, price, quantity,
+                                   quote_spent, total_quantity, total_cost, reason)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+             ON CONFLICT (plan, scheduled_time) DO NOTHING",
+            &[
+                &run.plan,
+                &run.symbol,
+                &(run.scheduled_time as i64),
+                &(run.executed_at as i64),
+                &run.outcome.as_str(),
+                &run.order_id,
+                &run.price,
+                &run.quantity,
+                &run.quote_spent,
+                &run.cost_basis.quantity,
+                &run.cost_basis.cost,
+                &run.reason,
+            ],
+        ).await.map_err(db_error)?;
+        Ok(())
+    }
+
+    /// Runs of a plan, oldest first.
+    pub async fn runs(&self, plan: &str) -> Result<Vec<DcaRun>, IOError> {
+        let rows = self.db.client().await?
+            .query("SELECT * FROM dca_runs WHERE plan = $1 ORDER BY scheduled_time", &[&plan])
+            .await
+            .map_err(db_error)?;
+        Ok(rows.iter().map(DcaRun::from).collect())
+    }
+
+    /// Purchases of every plan buying `symbol`, oldest first.
+    pub async fn purchases(&self, symbol: &str) -> Result<Vec<DcaRun>, IOError> {
+        let rows = self.db.client().await?
+            .query(
+                "SELECT * FROM dca_runs WHERE symbol = $1 AND outcome = $2 ORDER BY executed_at, plan",
+                &[&symbol, &DcaOutcome::Bought.as_str()],
+            )
+            .await
+            .map_err(db_error)?;
+        Ok(rows.iter().map(DcaRun::from).collect())
+    }
+
+    /// Cost basis of every plan buying `symbol` together.
+    pub async fn cost_basis(&self, symbol: &str) -> Result<CostBasis, IOError> {
+        let purchases = self.purchases(symbol).await?;
+        Ok(purchases.iter().fold(CostBasis::default(), |total, run| CostBasis {
+            quantity: total.quantity + run.quantity,
+            cost: total.cost + run.quote_spent,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use chrono::DateTime;
+    use crate::binance_client::test_support::{eth_usdt, paper_trader};
+
+    #[derive(Default)]
+    struct MarketData {
+        closes: Mutex<Vec<f64>>,
+        placed: Mutex<Option<Order>>, // Found by its client order id once
+    }
+
+    #[async_trait]
+    impl DcaMarketData for MarketData {
+        async fn recent_closes(&self, _symbol: &str, _interval: KlineInterval, limit: u16) -> Result<Vec<f64>, IOError> {
+            let closes = self.closes.lock().unwrap();
+            Ok(closes[closes.len().saturating_sub(limit as usize)..].to_vec())
+        }
+
+        async fn order_by_client_id(&self, _symbol: &str, client_order_id: &str) -> Result<Option<Order>, IOError> {
+            let mut placed = self.placed.lock().unwrap();
+            Ok(placed.take_if(|order| order.client_order_id == client_order_id))
+        }
+    }
+
+    fn market_data(closes: Vec<f64>) -> MarketData {
+        MarketData { closes: Mutex::new(closes), ..Default::default() }
+    }
+
+    fn utc(text: &str) -> u64 {
+        DateTime::parse_from_rfc3339(text).unwrap().timestamp_millis() as u64
+    }
+
+    fn outcomes(runs: &[DcaRun]) -> Vec<DcaOutcome> {
+        runs.iter().map(|run| run.outcome).collect()
+    }
+
+    #[tokio::test]
+    async fn test_dca_filters_cap_and_missed_runs() {
+        let paper_trader = paper_trader(1999.0, 2000.0);
+        let market_data = market_data(vec![2100.0, 2100.0, 2100.0, 2000.0]);
+        let schedule = CronSchedule::new("0 * * * *", "America/New_York").unwrap();
+        let plan = DcaPlan::new("eth-hourly", &eth_usdt(), 100.0, schedule).unwrap()
+            .with_moving_average_filter(KlineInterval::OneHour, 3)
+            .with_spending_cap(250.0, SpendingPeriod::Day);
+        let mut scheduler = DcaScheduler::new(&paper_trader, &market_data, MissedRuns::Skip);
+        scheduler.add_plan(plan, &[], utc("2024-06-03T05:10:00Z"));
+        assert_eq!(scheduler.next_run_time(), Some(utc("2024-06-03T06:00:00Z")));
+
+        let runs = scheduler.run_due(utc("2024-06-03T06:00:05Z")).await;
+        assert_eq!(outcomes(&runs), vec![DcaOutcome::Bought]);
+        assert_eq!((runs[0].quantity, runs[0].quote_spent, runs[0].price), (0.05, 100.0, Some(2000.0)));
+
+        *market_data.closes.lock().unwrap() = vec![1900.0, 1900.0, 1900.0, 2000.0];
+        assert_eq!(outcomes(&scheduler.run_due(utc("2024-06-03T07:00:00Z")).await), vec![DcaOutcome::AboveAverage]);
+        *market_data.closes.lock().unwrap() = vec![2100.0, 2100.0, 2100.0, 2000.0];
+        assert_eq!(outcomes(&scheduler.run_due(utc("2024-06-03T08:00:00Z")).await), vec![DcaOutcome::Bought]);
+        let runs = scheduler.run_due(utc("2024-06-03T09:00:00Z")).await;
+        assert_eq!((runs[0].outcome, runs[0].reason.as_deref()), (DcaOutcome::CapReached, Some("200 of 250 USDT spent")));
+
+        // Down until the first run of the next day in New York, which resets the cap
+        let runs = scheduler.run_due(utc("2024-06-04T04:01:00Z")).await;
+        assert_eq!(runs.len(), 19);
+        assert!(runs[..18].iter().all(|run| run.outcome == DcaOutcome::Missed));
+        assert_eq!(runs[18].outcome, DcaOutcome::Bought);
+        let cost_basis = scheduler.cost_basis("eth-hourly").unwrap();
+        assert_eq!((cost_basis.cost, runs[18].cost_basis), (300.0, cost_basis));
+        assert!((cost_basis.average_price().unwrap() - 2000.0).abs() < 1e-9);
+        assert_eq!(paper_trader.free_balance("USDT"), 99700.0);
+    }
+
+    #[tokio::test]
+    async fn test_dca_catches_up_after_restart() {
+        let paper_trader = paper_trader(1999.0, 2000.0);
+        let market_data = market_data(Vec::new());
+        let schedule = CronSchedule::new("@daily", "UTC").unwrap();
+        let plan = DcaPlan::new("eth-daily", &eth_usdt(), 50.0, schedule).unwrap();
+        let recorded = DcaRun {
+            plan: "eth-daily".to_string(),
+            symbol: "ETHUSDT".to_string(),
+            scheduled_time: utc("2024-06-01T00:00:00Z"),
+            executed_at: utc("2024-06-01T00:00:01Z"),
+            outcome: DcaOutcome::Bought,
+            order_id: Some(1),
+            price: Some(1000.0),
+            quantity: 0.1,
+            quote_spent: 100.0,
+            cost_basis: CostBasis { quantity: 0.1, cost: 100.0 },
+            reason: None,
+        };
+        let mut scheduler = DcaScheduler::new(&paper_trader, &market_data, MissedRuns::CatchUp);
+        scheduler.add_plan(plan, &[recorded], utc("2024-06-03T12:00:00Z"));
+
+        let runs = scheduler.run_due(utc("2024-06-03T12:00:00Z")).await;
+        assert_eq!(outcomes(&runs), vec![DcaOutcome::Bought, DcaOutcome::Bought]);
+        assert_eq!(runs[0].scheduled_time, utc("2024-06-02T00:00:00Z"));
+        assert_eq!(scheduler.cost_basis("eth-daily"), Some(CostBasis { quantity: 0.15, cost: 200.0 }));
+        assert!(scheduler.run_due(utc("2024-06-03T12:00:00Z")).await.is_empty());
+        assert_eq!(scheduler.next_run_time(), Some(utc("2024-06-04T00:00:00Z")));
+    }
+
+    #[tokio::test]
+    async fn test_dca_records_an_unrecorded_purchase_instead_of_buying_again() {
+        let paper_trader = paper_trader(1999.0, 2000.0);
+        let market_data = market_data(Vec::new());
+        // Bought at the run of June 2 before a crash kept it from being recorded
+        let scheduled_time = utc("2024-06-02T00:00:00Z");
+        *market_data.placed.lock().unwrap() = Some(serde_json::from_str(&format!(r#"{{
+            "symbol": "ETHUSDT", "orderId": 7, "orderListId": -1, "clientOrderId": "dca-eth-daily-{scheduled_time}",
+            "price": "0.0", "origQty": "0.05", "executedQty": "0.05", "cummulativeQuoteQty": "95.0",
+            "status": "FILLED", "timeInForce": "GTC", "type": "MARKET", "side": "BUY", "stopPrice": "0.0",
+            "icebergQty": "0.0", "time": {scheduled_time}, "updateTime": {scheduled_time}, "isWorking": true, "origQuoteOrderQty": "100.0"
+        }}"#)).unwrap());
+        let schedule = CronSchedule::new("@daily", "UTC").unwrap();
+        let plan = DcaPlan::new("eth-daily", &eth_usdt(), 100.0, schedule).unwrap();
+        let mut scheduler = DcaScheduler::new(&paper_trader, &market_data, MissedRuns::CatchUp);
+        scheduler.add_plan(plan, &[], utc("2024-06-01T12:00:00Z"));
+
+        let runs = scheduler.run_due(utc("2024-06-03T00:00:01Z")).await;
+        assert_eq!(outcomes(&runs), vec![DcaOutcome::Bought, DcaOutcome::Bought]);
+        assert_eq!((runs[0].order_id, runs[0].quantity, runs[0].quote_spent, runs[0].price), (Some(7), 0.05, 95.0, Some(1900.0)));
+        assert_eq!(scheduler.cost_basis("eth-daily"), Some(CostBasis { quantity: 0.1, cost: 195.0 }));
+        assert_eq!(paper_trader.free_balance("USDT"), 99900.0);
+    }
+
+    #[test]
+    fn test_dca_plan_names_fit_in_client_order_ids() {
+        let plan = |name: &str| DcaPlan::new(name, &eth_usdt(), 100.0, CronSchedule::new("@daily", "UTC").unwrap());
+        assert!(plan("eth-daily_2024.v1").is_ok());
+        assert_eq!(plan("eth-daily-2024.v12").unwrap().name.len(), 18);
+        assert_eq!(plan("eth-daily-2024.v123").unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert!(plan("eth daily").is_err());
+        assert!(plan("").is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use std::io::{Error as IOError, ErrorKind};
 use serde::{Deserialize, Serialize};
 use crate::binance_client::deserialization::{
     deserialize_string_to_f64,
@@ -5,45 +6,46 @@ use crate::binance_client::deserialization::{
     deserialize_optional_string_to_i64,
     deserialize_optional_string_to_f64
 };
+use crate::binance_client::position_size::round;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ExchangeInfo {
-    timezone: String,
+    pub timezone: String,
     #[serde(rename = "serverTime")]
-    server_time: u64,
+    pub server_time: u64,
     #[serde(rename = "rateLimits")]
-    rate_limits: Vec<RateLimit>,
-    symbols: Vec<SymbolInfo>,
+    pub rate_limits: Vec<RateLimit>,
+    pub symbols: Vec<SymbolInfo>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RateLimit {
     #[serde(rename = "rateLimitType")]
-    rate_limit_type: String,
-    interval: String,
+    pub rate_limit_type: String,
+    pub interval: String,
     #[serde(rename = "intervalNum")]
-    interval_num: i64,
+    pub interval_num: i64,
     #[serde(rename = "limit")]
-    limit: i64,
+    pub limit: i64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SymbolInfo {
     #[serde(rename = "symbol")]
-    symbol: String,
+    pub symbol: String,
     #[serde(rename = "status")]
-    status: String,
+    pub status: String,
     #[serde(rename = "baseAsset")]
-    base_asset: String,
+    pub base_asset: String,
     #[serde(rename = "quoteAsset")]
-    quote_asset: String,
+    pub quote_asset: String,
     #[serde(rename = "filters")]
-    filters: Vec<Filter>,
+    pub filters: Vec<Filter>,
     #[serde(rename = "permissions")]
-    permissions: Vec<String>,
+    pub permissions: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -194,3 +196,168 @@ pub enum Filter {
     },
 }
 
+
+impl SymbolInfo {
+    /// The price, quantity and notional rules orders on this symbol are checked against.
+    pub fn trading_filters(&self) -> SymbolFilters {
+        let mut filters = SymbolFilters::default();
+        for filter in &self.filters {
+            match *filter {
+                Filter::PriceFilter { min_price, max_price, tick_size } => {
+                    filters.min_price = min_price;
+                    filters.max_price = max_price;
+                    filters.tick_size = tick_size;
+                }
+                Filter::LotSize { min_qty, max_qty, step_size } => {
+                    filters.min_qty = min_qty;
+                    filters.max_qty = max_qty;
+                    filters.step_size = step_size;
+                }
+                Filter::MarketLotSize { min_qty, max_qty, step_size } => {
+                    filters.market_min_qty = min_qty;
+                    filters.market_max_qty = max_qty;
+                    filters.market_step_size = step_size;
+                }
+                Filter::Notional { min_notional, apply_min_to_market, max_notional, apply_max_to_market, .. } => {
+                    filters.min_notional = min_notional;
+                    filters.apply_min_notional_to_market = apply_min_to_market;
+                    filters.max_notional = max_notional.unwrap_or(0.0);
+                    filters.apply_max_notional_to_market = apply_max_to_market.unwrap_or(false);
+                }
+                Filter::MinNotional { min_notional, apply_to_market, .. } => {
+                    filters.min_notional = min_notional;
+                    filters.apply_min_notional_to_market = apply_to_market;
+                }
+                _ => {}
+            }
+        }
+        filters
+    }
+}
+
+/// The `PRICE_FILTER`, `LOT_SIZE`, `MARKET_LOT_SIZE` and `NOTIONAL` rules of a symbol.
+///
+/// Like on the exchange, a zero tick size, step size or maximum disables that check,
+/// so `SymbolFilters::default()` accepts any order.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SymbolFilters {
+    pub tick_size: f64,
+    pub min_price: f64,
+    pub max_price: f64,
+    pub step_size: f64,
+    pub min_qty: f64,
+    pub max_qty: f64,
+    pub market_step_size: f64,
+    pub market_min_qty: f64,
+    pub market_max_qty: f64,
+    pub min_notional: f64,
+    pub apply_min_notional_to_market: bool,
+    pub max_notional: f64,
+    pub apply_max_notional_to_market: bool,
+}
+
+impl SymbolFilters {
+    /// Rounds `price` to the nearest tick.
+    pub fn round_price(&self, price: f64) -> f64 {
+        round_to_step(price, self.tick_size, f64::round)
+    }
+
+    /// Rounds `quantity` down to the lot step of limit orders, or of market orders if `is_market`.
+    pub fn floor_quantity(&self, quantity: f64, is_market: bool) -> f64 {
+        round_to_step(quantity, self.lot_step(is_market), |steps| (steps + 1e-9).floor())
+    }
+
+    /// Checks an order of `quantity` at `price` the way the matching engine would.
+    ///
+    /// For market orders pass the expected fill price; only the notional checks that
+    /// apply to market orders use it.
+    pub fn check(&self, price: f64, quantity: f64, is_market: bool) -> Result<(), IOError> {
+        let price_valid = price >= self.min_price
+            && (self.max_price <= 0.0 || price <= self.max_price)
+            && is_multiple(price - self.min_price, self.tick_size);
+        if !is_market && !price_valid {
+            return Err(filter_failure("PRICE_FILTER"));
+        }
+
+        let (min_qty, max_qty) = if is_market && self.market_step_size > 0.0 {
+            (self.market_min_qty, self.market_max_qty)
+        } else {
+            (self.min_qty, self.max_qty)
+        };
+        let lot_filter = if is_market && self.market_step_size > 0.0 { "MARKET_LOT_SIZE" } else { "LOT_SIZE" };
+        if quantity <= 0.0 || quantity < min_qty || (max_qty > 0.0 && quantity > max_qty)
+            || !is_multiple(quantity - min_qty, self.lot_step(is_market)) {
+            return Err(filter_failure(lot_filter));
+        }
+
+        let notional = price * quantity;
+        if (!is_market || self.apply_min_notional_to_market) && notional < self.min_notional {
+            return Err(filter_failure("NOTIONAL"));
+        }
+        if (!is_market || self.apply_max_notional_to_market) && self.max_notional > 0.0 && notional > self.max_notional {
+            return Err(filter_failure("NOTIONAL"));
+        }
+        Ok(())
+    }
+
+    fn lot_step(&self, is_market: bool) -> f64 {
+        if is_market && self.market_step_size > 0.0 {
+            self.market_step_size
+        } else {
+            self.step_size
+        }
+    }
+}
+
+fn filter_failure(filter: &str) -> IOError {
+    IOError::new(ErrorKind::InvalidInput, format!("Filter failure: {}", filter))
+}
+
+fn is_multiple(value: f64, step: f64) -> bool {
+    if step <= 0.0 {
+        return true;
+    }
+    let steps = value / step;
+    (steps - steps.round()).abs() < 1e-6
+}
+
+// Rounds to a multiple of `step`, dropping the float noise a multiplication leaves behind
+fn round_to_step(value: f64, step: f64, round_steps: impl Fn(f64) -> f64) -> f64 {
+    if step <= 0.0 {
+        return value;
+    }
+    let decimals = (-step.log10()).ceil().max(0.0) as u32;
+    round(round_steps(value / step) * step, decimals)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eth_usdt() -> SymbolInfo {
+        serde_json::from_str(r#"{
+            "symbol": "ETHUSDT", "status": "TRADING", "baseAsset": "ETH", "quoteAsset": "USDT", "permissions": ["SPOT"],
+            "filters": [
+                {"filterType": "PRICE_FILTER", "minPrice": "0.01000000", "maxPrice": "1000000.00000000", "tickSize": "0.01000000"},
+                {"filterType": "LOT_SIZE", "minQty": "0.00010000", "maxQty": "9000.00000000", "stepSize": "0.00010000"},
+                {"filterType": "NOTIONAL", "minNotional": "5.00000000", "applyMinToMarket": true, "maxNotional": "9000000.00000000", "applyMaxToMarket": false, "avgPriceMins": 5}
+            ]
+        }"#).unwrap()
+    }
+
+    #[test]
+    fn test_trading_filters() {
+        let filters = eth_usdt().trading_filters();
+        assert_eq!(filters.tick_size, 0.01);
+        assert_eq!(filters.round_price(2001.234), 2001.23);
+        assert_eq!(filters.floor_quantity(0.123456, false), 0.1234);
+        assert_eq!(filters.floor_quantity(0.3, true), 0.3);
+
+        assert!(filters.check(2001.23, 0.1234, false).is_ok());
+        assert_eq!(filters.check(2001.234, 0.1234, false).unwrap_err().to_string(), "Filter failure: PRICE_FILTER");
+        assert_eq!(filters.check(2001.23, 0.12345, false).unwrap_err().to_string(), "Filter failure: LOT_SIZE");
+        assert_eq!(filters.check(2000.0, 0.002, true).unwrap_err().to_string(), "Filter failure: NOTIONAL");
+        assert!(SymbolFilters::default().check(1.2345, 0.000001, false).is_ok());
+    }
+}
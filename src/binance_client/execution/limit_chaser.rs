@@ -0,0 +1,222 @@
+use std::time::Duration;
+use crate::binance_client::account::order_status::OrderStatus;
+use crate::binance_client::execution::parent_order::{Execution, ExecutionHandle, ExecutionProgress, ParentOrder};
+use crate::binance_client::market_data_store::{BookTick, TradePrint};
+use crate::binance_client::order_types::side::Side;
+use crate::binance_client::strategy::{OrderId, OrderUpdate, Strategy, StrategyContext};
+
+/// Passive execution: one limit order that joins the best bid of a buy, or the best ask of
+/// a sell, and follows it when it moves.
+///
+/// The order is canceled when the touch moves away and placed again at the new price
+/// once the cancellation is confirmed. It never goes beyond `limit_price` if set; after the
+/// `deadline` the rest crosses the spread with a market order. Prices come from the book
+/// ticker stream.
+///
+/// With a `display_quantity` it works like an iceberg: the order shows at most that much,
+/// and the next slice goes out once one is done.
+#[derive(Debug)]
+pub struct LimitChaser {
+    execution: Execution,
+    limit_price: Option<f64>,
+    deadline_ms: Option<u64>, // After the start
+    display_quantity: Option<f64>,
+    touch: Option<f64>,
+    order: Option<(OrderId, f64)>, // The working limit order and its price
+}
+
+impl LimitChaser {
+    pub fn new(parent: ParentOrder) -> Self {
+        LimitChaser { execution: Execution::new(parent), limit_price: None, deadline_ms: None, display_quantity: None, touch: None, order: None }
+    }
+
+    pub fn with_limit_price(mut self, price: f64) -> Self {
+        self.limit_price = Some(price);
+        self
+    }
+
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline_ms = Some(deadline.as_millis() as u64);
+        self
+    }
+
+    /// Limits the quantity of the working limit order, the deadline's market order takes
+    /// the whole rest.
+    pub fn with_display_quantity(mut self, quantity: f64) -> Self {
+        self.display_quantity = Some(quantity);
+        self
+    }
+
+    pub fn handle(&self) -> ExecutionHandle {
+        self.execution.handle()
+    }
+
+    pub fn progress(&self) -> &ExecutionProgress {
+        self.execution.progress()
+    }
+
+    /// Price of the working limit order.
+    pub fn order_price(&self) -> Option<f64> {
+        self.order.map(|(_, price)| price)
+    }
+
+    fn desired_price(&self) -> Option<f64> {
+        let touch = self.touch?;
+        let price = match (self.execution.parent().side, self.limit_price) {
+            (Side::Buy, Some(limit_price)) => touch.min(limit_price),
+            (Side::Sell, Some(limit_price)) => touch.max(limit_price),
+            (_, None) => touch,
+        };
+        Some(self.execution.parent().filters.round_price(price))
+    }
+
+    fn step(&mut self, ctx: &mut StrategyContext) {
+        if !self.execution.poll(ctx) {
+            return;
+        }
+        let past_deadline = self.deadline_ms.is_some_and(|deadline| ctx.time() >= self.execution.progress().start_time + deadline);
+        if let Some((order_id, price)) = self.order {
+            if past_deadline || self.desired_price().is_some_and(|desired| desired != price) {
+                self.execution.cancel(ctx, order_id);
+            }
+            return;
+        }
+
+        let quantity = self.execution.unassigned_quantity();
+        if past_deadline {
+            self.execution.submit_market(ctx, quantity);
+        } else if let Some(price) = self.desired_price() {
+            let quantity = self.display_quantity.map_or(quantity, |display_quantity| quantity.min(display_quantity));
+            self.order = self.execution.submit_limit(ctx, quantity, price).map(|order_id| (order_id, price));
+        }
+    }
+}
+
+impl Strategy for LimitChaser {
+    fn on_start(&mut self, ctx: &mut StrategyContext) {
+        self.execution.start(ctx);
+    }
+
+    fn on_trade(&mut self, ctx: &mut StrategyContext, trade: &TradePrint) {
+        self.execution.observe_price(&trade.symbol, trade.price);
+        self.step(ctx);
+    }
+
+    fn on_book(&mut self, ctx: &mut StrategyContext, book: &BookTick) {
+        if book.symbol == self.execution.parent().symbol {
+            self.execution.observe_price(&book.symbol, (book.bid_price + book.ask_price) / 2.0);
+            self.touch = Some(if self.execution.parent().side == Side::Buy { book.bid_price } else { book.ask_price });
+        }
+        self.step(ctx);
+    }
+
+    fn on_order_update(&mut self, ctx: &mut StrategyContext, update: &OrderUpdate) {
+        self.execution.on_order_update(update);
+        let is_final = !matches!(update.status, OrderStatus::New | OrderStatus::PartiallyFilled | OrderStatus::PendingCancel);
+        if is_final && self.order.is_some_and(|(order_id, _)| order_id == update.order_id) {
+            self.order = None;
+            self.step(ctx);
+        }
+    }
+
+    fn on_timer(&mut self, ctx: &mut StrategyContext) {
+        self.step(ctx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binance_client::execution::parent_order::ExecutionState;
+    use crate::binance_client::execution::parent_order::tests::{filled, parent, submitted, update};
+    use crate::binance_client::strategy::{OrderIntent, StrategyCommand};
+
+    fn book(bid_price: f64) -> BookTick {
+        BookTick {
+            symbol: "ETHUSDT".to_string(),
+            update_id: 1,
+            time: 0,
+            bid_price,
+            bid_quantity: 1.0,
+            ask_price: bid_price + 0.5,
+            ask_quantity: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_limit_chaser_follows_the_bid() {
+        let mut chaser = LimitChaser::new(parent(Side::Buy, 1.0)).with_limit_price(2001.0).with_deadline(Duration::from_secs(60));
+        let handle = chaser.handle();
+        let mut ctx = StrategyContext::new();
+        chaser.on_start(&mut ctx);
+        chaser.on_book(&mut ctx, &book(2000.0));
+        let (first, intent) = submitted(&mut ctx).remove(0);
+        assert_eq!(intent, OrderIntent::limit("ETHUSDT", Side::Buy, 1.0, 2000.0));
+
+        // The bid moves up twice before the cancellation is confirmed
+        chaser.on_book(&mut ctx, &book(2000.5));
+        chaser.on_book(&mut ctx, &book(2000.8));
+        assert_eq!(ctx.take_commands(), vec![StrategyCommand::Cancel(first)]);
+        chaser.on_order_update(&mut ctx, &update(first, OrderStatus::PartiallyFilled, Some((2000.0, 0.4))));
+        chaser.on_order_update(&mut ctx, &update(first, OrderStatus::Canceled, None));
+        let (second, intent) = submitted(&mut ctx).remove(0);
+        assert_eq!(intent, OrderIntent::limit("ETHUSDT", Side::Buy, 0.6, 2000.8));
+
+        // Never above the limit price
+        chaser.on_book(&mut ctx, &book(2002.0));
+        assert_eq!(ctx.take_commands(), vec![StrategyCommand::Cancel(second)]);
+        chaser.on_order_update(&mut ctx, &update(second, OrderStatus::Canceled, None));
+        assert_eq!(chaser.order_price(), Some(2001.0));
+        let (third, _) = submitted(&mut ctx).remove(0);
+
+        handle.pause();
+        chaser.on_timer(&mut ctx);
+        assert_eq!(ctx.take_commands(), vec![StrategyCommand::Cancel(third)]);
+        chaser.on_order_update(&mut ctx, &update(third, OrderStatus::Canceled, None));
+        assert!(submitted(&mut ctx).is_empty());
+
+        // Past the deadline the rest crosses the spread
+        handle.resume();
+        ctx.set_time(60_000);
+        chaser.on_timer(&mut ctx);
+        let (market, intent) = submitted(&mut ctx).remove(0);
+        assert_eq!(intent, OrderIntent::market("ETHUSDT", Side::Buy, 0.6));
+        chaser.on_order_update(&mut ctx, &filled(market, 2002.5, 0.6));
+        let report = handle.progress();
+        assert_eq!(report.state, ExecutionState::Completed);
+        assert!((report.average_price().unwrap() - (0.4 * 2000.0 + 0.6 * 2002.5)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_limit_chaser_shows_a_display_quantity() {
+        let mut chaser = LimitChaser::new(parent(Side::Buy, 1.0)).with_display_quantity(0.4).with_deadline(Duration::from_secs(60));
+        let handle = chaser.handle();
+        let mut ctx = StrategyContext::new();
+        chaser.on_start(&mut ctx);
+        chaser.on_book(&mut ctx, &book(2000.0));
+        let (first, intent) = submitted(&mut ctx).remove(0);
+        assert_eq!(intent, OrderIntent::limit("ETHUSDT", Side::Buy, 0.4, 2000.0));
+
+        // The next slice waits until the first is done
+        chaser.on_order_update(&mut ctx, &update(first, OrderStatus::PartiallyFilled, Some((2000.0, 0.1))));
+        assert!(submitted(&mut ctx).is_empty());
+        chaser.on_order_update(&mut ctx, &update(first, OrderStatus::Filled, Some((2000.0, 0.3))));
+        let (second, intent) = submitted(&mut ctx).remove(0);
+        assert_eq!(intent, OrderIntent::limit("ETHUSDT", Side::Buy, 0.4, 2000.0));
+
+        // A slice chasing the bid is replaced by one of the same size
+        chaser.on_book(&mut ctx, &book(2000.5));
+        assert_eq!(ctx.take_commands(), vec![StrategyCommand::Cancel(second)]);
+        chaser.on_order_update(&mut ctx, &update(second, OrderStatus::Canceled, None));
+        let (third, intent) = submitted(&mut ctx).remove(0);
+        assert_eq!(intent, OrderIntent::limit("ETHUSDT", Side::Buy, 0.4, 2000.5));
+        chaser.on_order_update(&mut ctx, &filled(third, 2000.5, 0.4));
+
+        // The last slice is what is left
+        let (fourth, intent) = submitted(&mut ctx).remove(0);
+        assert_eq!(intent, OrderIntent::limit("ETHUSDT", Side::Buy, 0.2, 2000.5));
+        chaser.on_order_update(&mut ctx, &filled(fourth, 2000.5, 0.2));
+        assert_eq!(handle.progress().state, ExecutionState::Completed);
+        assert_eq!(handle.progress().child_orders, 4);
+    }
+}
@@ -0,0 +1,5 @@
+pub mod parent_order;
+pub mod twap;
+pub mod vwap;
+pub mod pov;
+pub mod limit_chaser;
@@ -0,0 +1,403 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use log::{info, warn};
+use uuid::Uuid;
+use crate::binance_client::account::order_status::OrderStatus;
+use crate::binance_client::exchange_info::{SymbolFilters, SymbolInfo};
+use crate::binance_client::order_types::side::Side;
+use crate::binance_client::strategy::{OrderId, OrderIntent, OrderUpdate, StrategyContext};
+
+// No child order goes out this soon after one was rejected
+const REJECTION_BACKOFF_MS: u64 = 1000;
+
+/// The quantity an execution algo works, and the filters its child orders have to pass.
+#[derive(Debug, Clone)]
+pub struct ParentOrder {
+    pub symbol: String,
+    pub side: Side,
+    pub quantity: f64,
+    pub filters: SymbolFilters,
+}
+
+impl ParentOrder {
+    pub fn new(symbol: &SymbolInfo, side: Side, quantity: f64) -> Self {
+        ParentOrder { symbol: symbol.symbol.clone(), side, quantity, filters: symbol.trading_filters() }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExecutionState {
+    Pending, // Not started yet
+    Running,
+    Paused,
+    Canceled,
+    Completed,
+}
+
+/// Where an execution stands, and once it is over, its report.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecutionProgress {
+    pub state: ExecutionState,
+    pub side: Side,
+    pub target_quantity: f64,
+    pub filled_quantity: f64,
+    pub working_quantity: f64, // Of child orders that are open
+    pub filled_quote: f64, // Sum of price times quantity of the fills
+    pub fees: f64, // Quote value of the commissions
+    pub child_orders: usize,
+    pub rejected_orders: usize,
+    pub arrival_price: Option<f64>, // The price when the execution started
+    pub start_time: u64,
+    pub end_time: Option<u64>, // When it completed or was canceled
+}
+
+impl ExecutionProgress {
+    pub fn remaining_quantity(&self) -> f64 {
+        (self.target_quantity - self.filled_quantity).max(0.0)
+    }
+
+    /// Filled share of the target quantity, from 0 to 1.
+    pub fn fill_ratio(&self) -> f64 {
+        if self.target_quantity > 0.0 { (self.filled_quantity / self.target_quantity).min(1.0) } else { 0.0 }
+    }
+
+    pub fn average_price(&self) -> Option<f64> {
+        (self.filled_quantity > 0.0).then(|| self.filled_quote / self.filled_quantity)
+    }
+
+    /// Implementation shortfall of the average fill price against the arrival price, in basis
+    /// points. Positive when the execution paid more on a buy or got less on a sell.
+    pub fn slippage_bps(&self) -> Option<f64> {
+        let (average_price, arrival_price) = (self.average_price()?, self.arrival_price?);
+        let direction = if self.side == Side::Buy { 1.0 } else { -1.0 };
+        Some(direction * (average_price - arrival_price) / arrival_price * 10_000.0)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self.state, ExecutionState::Canceled | ExecutionState::Completed)
+    }
+}
+
+/// Controls an execution algo while an engine runs it, and reads its progress.
+///
+/// Clones share the same execution. Requests take effect with the next callback of the
+/// algo, so engines running live should have a timer. Pausing cancels the open child
+/// orders; canceling does too and ends the execution for good.
+#[derive(Debug, Clone)]
+pub struct ExecutionHandle {
+    shared: Arc<Mutex<Shared>>,
+}
+
+#[derive(Debug)]
+struct Shared {
+    pause_requested: bool,
+    cancel_requested: bool,
+    progress: ExecutionProgress,
+}
+
+impl ExecutionHandle {
+    fn new(progress: ExecutionProgress) -> Self {
+        ExecutionHandle { shared: Arc::new(Mutex::new(Shared { pause_requested: false, cancel_requested: false, progress })) }
+    }
+
+    pub fn pause(&self) {
+        self.shared.lock().unwrap().pause_requested = true;
+    }
+
+    pub fn resume(&self) {
+        self.shared.lock().unwrap().pause_requested = false;
+    }
+
+    pub fn cancel(&self) {
+        self.shared.lock().unwrap().cancel_requested = true;
+    }
+
+    pub fn progress(&self) -> ExecutionProgress {
+        self.shared.lock().unwrap().progress.clone()
+    }
+}
+
+// Child orders and fills of a parent order, shared by the algos
+#[derive(Debug)]
+pub(crate) struct Execution {
+    parent: ParentOrder,
+    handle: ExecutionHandle,
+    progress: ExecutionProgress,
+    children: HashMap<OrderId, f64>, // Unfilled quantity of open child orders
+    canceling: HashSet<OrderId>,
+    rejected_at: Option<u64>,
+}
+
+impl Execution {
+    pub(crate) fn new(parent: ParentOrder) -> Self {
+        let progress = ExecutionProgress {
+            state: ExecutionState::Pending,
+            side: parent.side,
+            target_quantity: parent.quantity,
+            filled_quantity: 0.0,
+            working_quantity: 0.0,
+            filled_quote: 0.0,
+            fees: 0.0,
+            child_orders: 0,
+            rejected_orders: 0,
+            arrival_price: None,
+            start_time: 0,
+            end_time: None,
+        };
+        Execution { handle: ExecutionHandle::new(progress.clone()), parent, progress, children: HashMap::new(), canceling: HashSet::new(), rejected_at: None }
+    }
+
+    pub(crate) fn parent(&self) -> &ParentOrder {
+        &self.parent
+    }
+
+    pub(crate) fn handle(&self) -> ExecutionHandle {
+        self.handle.clone()
+    }
+
+    pub(crate) fn progress(&self) -> &ExecutionProgress {
+        &self.progress
+    }
+
+    pub(crate) fn start(&mut self, ctx: &StrategyContext) {
+        self.progress.state = ExecutionState::Running;
+        self.progress.start_time = ctx.time();
+        self.progress.arrival_price = ctx.price(&self.parent.symbol).filter(|price| *price > 0.0);
+        info!("Executing {:?} {} {}", self.parent.side, self.parent.quantity, self.parent.symbol);
+        self.publish();
+    }
+
+    // The first price seen is the arrival price when the context had none at the start
+    pub(crate) fn observe_price(&mut self, symbol: &str, price: f64) {
+        if symbol == self.parent.symbol && self.progress.arrival_price.is_none() && price > 0.0 {
+            self.progress.arrival_price = Some(price);
+            self.publish();
+        }
+    }
+
+    /// Applies pause, resume and cancel requests; `true` while child orders may be placed.
+    pub(crate) fn poll(&mut self, ctx: &mut StrategyContext) -> bool {
+        let (pause_requested, cancel_requested) = {
+            let shared = self.handle.shared.lock().unwrap();
+            (shared.pause_requested, shared.cancel_requested)
+        };
+        match self.progress.state {
+            ExecutionState::Pending | ExecutionState::Canceled | ExecutionState::Completed => return false,
+            _ if cancel_requested => {
+                info!("Execution on {} canceled", self.parent.symbol);
+                self.cancel_children(ctx);
+                self.finish(ExecutionState::Canceled, ctx.time());
+                return false;
+            }
+            ExecutionState::Running if pause_requested => {
+                info!("Execution on {} paused", self.parent.symbol);
+                self.cancel_children(ctx);
+                self.progress.state = ExecutionState::Paused;
+                self.publish();
+            }
+            ExecutionState::Paused if !pause_requested => {
+                info!("Execution on {} resumed", self.parent.symbol);
+                self.progress.state = ExecutionState::Running;
+                self.publish();
+            }
+            _ => {}
+        }
+        self.progress.state == ExecutionState::Running
+    }
+
+    /// What is neither filled nor worked by an open child order.
+    pub(crate) fn unassigned_quantity(&self) -> f64 {
+        (self.parent.quantity - self.progress.filled_quantity - self.progress.working_quantity).max(0.0)
+    }
+
+    /// Places a market child order for up to `quantity`, `None` if it is too small to trade.
+    pub(crate) fn submit_market(&mut self, ctx: &mut StrategyContext, quantity: f64) -> Option<OrderId> {
+        let quantity = self.parent.filters.floor_quantity(quantity.min(self.unassigned_quantity()), true);
+        let price = ctx.price(&self.parent.symbol).or(self.progress.arrival_price).unwrap_or_default();
+        self.parent.filters.check(price, quantity, true).ok()?;
+        self.submit(ctx, OrderIntent::market(&self.parent.symbol, self.parent.side, quantity), quantity)
+    }
+
+    /// Places a limit child order for up to `quantity` at `price` rounded to the tick.
+    pub(crate) fn submit_limit(&mut self, ctx: &mut StrategyContext, quantity: f64, price: f64) -> Option<OrderId> {
+        let quantity = self.parent.filters.floor_quantity(quantity.min(self.unassigned_quantity()), false);
+        let price = self.parent.filters.round_price(price);
+        self.parent.filters.check(price, quantity, false).ok()?;
+        self.submit(ctx, OrderIntent::limit(&self.parent.symbol, self.parent.side, quantity, price), quantity)
+    }
+
+    fn submit(&mut self, ctx: &mut StrategyContext, intent: OrderIntent, quantity: f64) -> Option<OrderId> {
+        if self.rejected_at.is_some_and(|rejected_at| ctx.time() < rejected_at + REJECTION_BACKOFF_MS) {
+            return None;
+        }
+        let order_id = ctx.submit(intent);
+        self.children.insert(order_id, quantity);
+        self.progress.working_quantity += quantity;
+        self.progress.child_orders += 1;
+        self.publish();
+        Some(order_id)
+    }
+
+    pub(crate) fn cancel(&mut self, ctx: &mut StrategyContext, order_id: OrderId) {
+        if self.children.contains_key(&order_id) && self.canceling.insert(order_id) {
+            ctx.cancel(order_id);
+        }
+    }
+
+    pub(crate) fn cancel_children(&mut self, ctx: &mut StrategyContext) {
+        let mut order_ids: Vec<OrderId> = self.children.keys().copied().collect();
+        order_ids.sort_unstable();
+        for order_id in order_ids {
+            self.cancel(ctx, order_id);
+        }
+    }
+
+    /// Books the fills of a child order; `false` for updates of other orders.
+    pub(crate) fn on_order_update(&mut self, update: &OrderUpdate) -> bool {
+        let Some(working) = self.children.get_mut(&update.order_id) else { return false };
+        if let Some(fill) = &update.fill {
+            let quantity = fill.quantity.min(*working);
+            *working -= quantity;
+            self.progress.working_quantity = (self.progress.working_quantity - quantity).max(0.0);
+            self.progress.filled_quantity += fill.quantity;
+            self.progress.filled_quote += fill.quantity * fill.price;
+            self.progress.fees += fill.fee.quote_value;
+        }
+        if !matches!(update.status, OrderStatus::New | OrderStatus::PartiallyFilled | OrderStatus::PendingCancel) {
+            if update.status == OrderStatus::Rejected {
+                warn!("Child order {} on {} rejected: {}", update.order_id, self.parent.symbol, update.reason.as_deref().unwrap_or_default());
+                self.progress.rejected_orders += 1;
+                self.rejected_at = Some(update.time);
+            }
+            let working = self.children.remove(&update.order_id).unwrap_or_default();
+            self.canceling.remove(&update.order_id);
+            self.progress.working_quantity = (self.progress.working_quantity - working).max(0.0);
+        }
+        if self.children.is_empty() && self.is_filled() && matches!(self.progress.state, ExecutionState::Running | ExecutionState::Paused) {
+            self.finish(ExecutionState::Completed, update.time);
+        } else {
+            self.publish();
+        }
+        true
+    }
+
+    // Less than the lot size left is as good as done
+    fn is_filled(&self) -> bool {
+        let remaining = self.parent.filters.floor_quantity(self.parent.quantity - self.progress.filled_quantity, true);
+        remaining <= 1e-9 || remaining < self.parent.filters.min_qty
+    }
+
+    fn finish(&mut self, state: ExecutionState, time: u64) {
+        self.progress.state = state;
+        self.progress.end_time = Some(time);
+        if state == ExecutionState::Completed {
+            info!(
+                "Execution on {} completed: {} at {:?}, {:?} bps slippage",
+                self.parent.symbol, self.progress.filled_quantity, self.progress.average_price(), self.progress.slippage_bps(),
+            );
+        }
+        self.publish();
+    }
+
+    fn publish(&self) {
+        self.handle.shared.lock().unwrap().progress = self.progress.clone();
+    }
+}
+
+// Randomness for schedules, reproducible with a seed (SplitMix64)
+#[derive(Debug, Clone)]
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    pub(crate) fn from_entropy() -> Self {
+        Rng(Uuid::new_v4().as_u64_pair().0)
+    }
+
+    // Uniform in [0, 1)
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::binance_client::fee_model::{Fee, LiquidityRole};
+    use crate::binance_client::strategy::{StrategyCommand, StrategyFill};
+
+    pub(crate) fn parent(side: Side, quantity: f64) -> ParentOrder {
+        let filters = SymbolFilters { tick_size: 0.01, step_size: 0.001, min_qty: 0.001, ..Default::default() };
+        ParentOrder { symbol: "ETHUSDT".to_string(), side, quantity, filters }
+    }
+
+    pub(crate) fn update(order_id: OrderId, status: OrderStatus, fill: Option<(f64, f64)>) -> OrderUpdate {
+        OrderUpdate {
+            order_id,
+            symbol: "ETHUSDT".to_string(),
+            side: Side::Buy,
+            status,
+            time: 0,
+            fill: fill.map(|(price, quantity)| StrategyFill {
+                price,
+                quantity,
+                fee: Fee { asset: "USDT".to_string(), amount: 0.0, quote_value: 0.0 },
+                role: LiquidityRole::Taker,
+            }),
+            filled_quantity: 0.0,
+            reason: None,
+        }
+    }
+
+    pub(crate) fn filled(order_id: OrderId, price: f64, quantity: f64) -> OrderUpdate {
+        update(order_id, OrderStatus::Filled, Some((price, quantity)))
+    }
+
+    // The orders queued since the last call
+    pub(crate) fn submitted(ctx: &mut StrategyContext) -> Vec<(OrderId, OrderIntent)> {
+        ctx.take_commands()
+            .into_iter()
+            .filter_map(|command| match command {
+                StrategyCommand::Submit(order_id, intent) => Some((order_id, intent)),
+                StrategyCommand::Cancel(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_progress_report() {
+        let mut execution = Execution::new(parent(Side::Sell, 1.0));
+        let mut ctx = StrategyContext::new();
+        ctx.set_price("ETHUSDT", 2000.0);
+        execution.start(&ctx);
+        let first = execution.submit_market(&mut ctx, 0.6).unwrap();
+        // Nothing below the lot size, and never more than the parent
+        assert_eq!(execution.submit_market(&mut ctx, 0.0004), None);
+        let second = execution.submit_market(&mut ctx, 5.0).unwrap();
+        assert_eq!(execution.progress().working_quantity, 1.0);
+
+        execution.on_order_update(&filled(first, 1990.0, 0.6));
+        execution.on_order_update(&update(second, OrderStatus::Rejected, None));
+        let progress = execution.handle().progress();
+        assert_eq!((progress.state, progress.rejected_orders, progress.working_quantity), (ExecutionState::Running, 1, 0.0));
+        assert!((progress.remaining_quantity() - 0.4).abs() < 1e-9);
+        assert_eq!(execution.submit_market(&mut ctx, 0.4), None);
+
+        ctx.set_time(REJECTION_BACKOFF_MS);
+        let third = execution.submit_market(&mut ctx, 0.4).unwrap();
+        execution.on_order_update(&filled(third, 1980.0, 0.4));
+        let progress = execution.handle().progress();
+        assert_eq!(progress.state, ExecutionState::Completed);
+        assert!((progress.average_price().unwrap() - 1986.0).abs() < 1e-9);
+        // Selling 14 below the arrival price
+        assert!((progress.slippage_bps().unwrap() - 70.0).abs() < 1e-9);
+    }
+}
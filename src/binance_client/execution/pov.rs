@@ -0,0 +1,134 @@
+use crate::binance_client::execution::parent_order::{Execution, ExecutionHandle, ExecutionProgress, ParentOrder};
+use crate::binance_client::market_data_store::{BookTick, TradePrint};
+use crate::binance_client::strategy::{OrderUpdate, Strategy, StrategyContext};
+
+/// Percentage of volume: keeps the executed quantity at `participation_rate` of the volume
+/// on the trade stream since the start, with market orders.
+///
+/// Live, the stream includes the algo's own fills, so its share of the whole volume is the
+/// rate. A child order goes out once what is due is enough to trade, at most
+/// `max_slice` at a time if set.
+#[derive(Debug)]
+pub struct PovAlgo {
+    execution: Execution,
+    participation_rate: f64,
+    max_slice: Option<f64>,
+    market_volume: f64,
+}
+
+impl PovAlgo {
+    pub fn new(parent: ParentOrder, participation_rate: f64) -> Self {
+        PovAlgo { execution: Execution::new(parent), participation_rate: participation_rate.clamp(0.0, 1.0), max_slice: None, market_volume: 0.0 }
+    }
+
+    pub fn with_max_slice(mut self, quantity: f64) -> Self {
+        self.max_slice = Some(quantity);
+        self
+    }
+
+    pub fn handle(&self) -> ExecutionHandle {
+        self.execution.handle()
+    }
+
+    pub fn progress(&self) -> &ExecutionProgress {
+        self.execution.progress()
+    }
+
+    /// Volume traded on the symbol since the start.
+    pub fn market_volume(&self) -> f64 {
+        self.market_volume
+    }
+
+    fn step(&mut self, ctx: &mut StrategyContext) {
+        if !self.execution.poll(ctx) {
+            return;
+        }
+        let progress = self.execution.progress();
+        let due = self.participation_rate * self.market_volume - progress.filled_quantity - progress.working_quantity;
+        self.execution.submit_market(ctx, self.max_slice.map_or(due, |max_slice| due.min(max_slice)));
+    }
+}
+
+impl Strategy for PovAlgo {
+    fn on_start(&mut self, ctx: &mut StrategyContext) {
+        self.execution.start(ctx);
+    }
+
+    fn on_trade(&mut self, ctx: &mut StrategyContext, trade: &TradePrint) {
+        if trade.symbol == self.execution.parent().symbol && trade.trade_time as u64 >= self.execution.progress().start_time {
+            self.execution.observe_price(&trade.symbol, trade.price);
+            self.market_volume += trade.quantity;
+        }
+        self.step(ctx);
+    }
+
+    fn on_book(&mut self, ctx: &mut StrategyContext, book: &BookTick) {
+        self.execution.observe_price(&book.symbol, (book.bid_price + book.ask_price) / 2.0);
+        self.step(ctx);
+    }
+
+    fn on_order_update(&mut self, _ctx: &mut StrategyContext, update: &OrderUpdate) {
+        self.execution.on_order_update(update);
+    }
+
+    fn on_timer(&mut self, ctx: &mut StrategyContext) {
+        self.step(ctx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binance_client::account::order_status::OrderStatus;
+    use crate::binance_client::execution::parent_order::ExecutionState;
+    use crate::binance_client::execution::parent_order::tests::{filled, parent, submitted, update};
+    use crate::binance_client::order_types::side::Side;
+
+    fn trade(time: i64, quantity: f64) -> TradePrint {
+        TradePrint {
+            symbol: "ETHUSDT".to_string(),
+            trade_id: time,
+            price: 2000.0,
+            quantity,
+            buyer_order_id: -1,
+            seller_order_id: -1,
+            trade_time: time,
+            is_buyer_maker: true,
+        }
+    }
+
+    #[test]
+    fn test_pov_follows_market_volume() {
+        let mut pov = PovAlgo::new(parent(Side::Sell, 1.0), 0.1).with_max_slice(0.4);
+        let mut ctx = StrategyContext::new();
+        ctx.set_time(1000);
+        pov.on_start(&mut ctx);
+
+        // Trades before the start do not count
+        pov.on_trade(&mut ctx, &trade(500, 100.0));
+        pov.on_trade(&mut ctx, &trade(1000, 3.0));
+        let orders = submitted(&mut ctx);
+        assert_eq!((pov.market_volume(), orders[0].1.quantity()), (3.0, 0.3));
+        pov.on_order_update(&mut ctx, &filled(orders[0].0, 2000.0, 0.3));
+
+        // Capped at the maximum slice, and the rejected one is retried after a pause
+        pov.on_trade(&mut ctx, &trade(1100, 7.0));
+        let orders = submitted(&mut ctx);
+        assert_eq!(orders[0].1.quantity(), 0.4);
+        let rejected = OrderUpdate { time: 1100, ..update(orders[0].0, OrderStatus::Rejected, None) };
+        pov.on_order_update(&mut ctx, &rejected);
+        pov.on_trade(&mut ctx, &trade(1200, 0.5));
+        assert!(submitted(&mut ctx).is_empty());
+        ctx.set_time(2100);
+        pov.on_timer(&mut ctx);
+        let orders = submitted(&mut ctx);
+        assert_eq!(orders[0].1.quantity(), 0.4);
+        pov.on_order_update(&mut ctx, &filled(orders[0].0, 1990.0, 0.4));
+
+        pov.on_trade(&mut ctx, &trade(2100, 50.0));
+        let orders = submitted(&mut ctx);
+        assert_eq!(orders[0].1.quantity(), 0.3);
+        pov.on_order_update(&mut ctx, &filled(orders[0].0, 1980.0, 0.3));
+        assert_eq!(pov.progress().state, ExecutionState::Completed);
+    }
+}
@@ -0,0 +1,204 @@
+use std::time::Duration;
+use crate::binance_client::execution::parent_order::{Execution, ExecutionHandle, ExecutionProgress, ParentOrder, Rng};
+use crate::binance_client::market_data_store::{BookTick, TradePrint};
+use crate::binance_client::strategy::{OrderUpdate, Strategy, StrategyContext};
+use crate::binance_client::streams::kline_data::Kline;
+
+/// Time weighted execution: `slices` market orders spread evenly over `duration`.
+///
+/// Each slice is due somewhere in its share of the duration; without randomization at
+/// its start, with a randomization of 1 anywhere in it, so the child orders do not trade
+/// on a fixed clock. A slice brings the executed quantity up to its share of the parent,
+/// so the next slice makes up for one a pause or a rejection left behind. Once every
+/// slice is due the rest is sent on each callback, which retries a rejected last slice.
+/// Slices are placed on any callback once due, so give the engine a timer.
+#[derive(Debug)]
+pub struct TwapAlgo {
+    execution: Execution,
+    duration_ms: u64,
+    slices: usize,
+    randomization: f64,
+    rng: Rng,
+    schedule: Vec<u64>, // Due time of every slice
+    due_slices: usize,
+}
+
+impl TwapAlgo {
+    pub fn new(parent: ParentOrder, duration: Duration, slices: usize) -> Self {
+        TwapAlgo {
+            execution: Execution::new(parent),
+            duration_ms: duration.as_millis() as u64,
+            slices: slices.max(1),
+            randomization: 0.0,
+            rng: Rng::from_entropy(),
+            schedule: Vec::new(),
+            due_slices: 0,
+        }
+    }
+
+    /// How much of its interval a slice may be delayed by, from 0 to 1.
+    pub fn with_randomization(mut self, randomization: f64) -> Self {
+        self.randomization = randomization.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Makes the randomized schedule reproducible.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = Rng::new(seed);
+        self
+    }
+
+    pub fn handle(&self) -> ExecutionHandle {
+        self.execution.handle()
+    }
+
+    pub fn progress(&self) -> &ExecutionProgress {
+        self.execution.progress()
+    }
+
+    /// Due times of the slices, in milliseconds since the epoch; empty before the start.
+    pub fn schedule(&self) -> &[u64] {
+        &self.schedule
+    }
+
+    fn step(&mut self, ctx: &mut StrategyContext) {
+        if !self.execution.poll(ctx) {
+            return;
+        }
+        let due_slices = self.schedule.iter().take_while(|due| **due <= ctx.time()).count();
+        if due_slices == self.due_slices && due_slices < self.slices {
+            return;
+        }
+        self.due_slices = due_slices;
+
+        let target = self.execution.parent().quantity * due_slices as f64 / self.slices as f64;
+        let progress = self.execution.progress();
+        let quantity = if due_slices == self.slices {
+            self.execution.unassigned_quantity()
+        } else {
+            target - progress.filled_quantity - progress.working_quantity
+        };
+        self.execution.submit_market(ctx, quantity);
+    }
+}
+
+impl Strategy for TwapAlgo {
+    fn on_start(&mut self, ctx: &mut StrategyContext) {
+        self.execution.start(ctx);
+        let interval = self.duration_ms as f64 / self.slices as f64;
+        self.schedule = (0..self.slices)
+            .map(|slice| ctx.time() + (interval * (slice as f64 + self.randomization * self.rng.next_f64())) as u64)
+            .collect();
+        self.step(ctx);
+    }
+
+    fn on_kline(&mut self, ctx: &mut StrategyContext, kline: &Kline) {
+        self.execution.observe_price(&kline.symbol, kline.close_price);
+        self.step(ctx);
+    }
+
+    fn on_trade(&mut self, ctx: &mut StrategyContext, trade: &TradePrint) {
+        self.execution.observe_price(&trade.symbol, trade.price);
+        self.step(ctx);
+    }
+
+    fn on_book(&mut self, ctx: &mut StrategyContext, book: &BookTick) {
+        self.execution.observe_price(&book.symbol, (book.bid_price + book.ask_price) / 2.0);
+        self.step(ctx);
+    }
+
+    fn on_order_update(&mut self, _ctx: &mut StrategyContext, update: &OrderUpdate) {
+        self.execution.on_order_update(update);
+    }
+
+    fn on_timer(&mut self, ctx: &mut StrategyContext) {
+        self.step(ctx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binance_client::execution::parent_order::ExecutionState;
+    use crate::binance_client::account::order_status::OrderStatus;
+    use crate::binance_client::execution::parent_order::tests::{filled, parent, submitted, update};
+    use crate::binance_client::order_types::side::Side;
+    use crate::binance_client::strategy::OrderIntent;
+
+    #[test]
+    fn test_twap_slices() {
+        let mut twap = TwapAlgo::new(parent(Side::Buy, 1.0), Duration::from_secs(600), 4).with_randomization(0.5).with_seed(7);
+        let handle = twap.handle();
+        let mut ctx = StrategyContext::new();
+        ctx.set_time(1_000_000);
+        ctx.set_price("ETHUSDT", 2000.0);
+        twap.on_start(&mut ctx);
+        // Every slice in the first half of its 150 seconds
+        let schedule = twap.schedule().to_vec();
+        for (slice, due) in schedule.iter().enumerate() {
+            let interval_start = 1_000_000 + slice as u64 * 150_000;
+            assert!((interval_start..interval_start + 75_000).contains(due));
+        }
+
+        let fill_next_slice = |twap: &mut TwapAlgo, ctx: &mut StrategyContext, slice: usize, price: f64| {
+            ctx.set_time(schedule[slice]);
+            twap.on_timer(ctx);
+            let orders = submitted(ctx);
+            for (order_id, intent) in &orders {
+                twap.on_order_update(ctx, &filled(*order_id, price, intent.quantity()));
+            }
+            orders.into_iter().map(|(_, intent)| intent).collect::<Vec<_>>()
+        };
+        assert_eq!(fill_next_slice(&mut twap, &mut ctx, 0, 2010.0), vec![OrderIntent::market("ETHUSDT", Side::Buy, 0.25)]);
+
+        // A paused slice is made up for with the next one
+        handle.pause();
+        assert!(fill_next_slice(&mut twap, &mut ctx, 1, 2020.0).is_empty());
+        assert_eq!(handle.progress().state, ExecutionState::Paused);
+        handle.resume();
+        assert_eq!(fill_next_slice(&mut twap, &mut ctx, 2, 2020.0), vec![OrderIntent::market("ETHUSDT", Side::Buy, 0.5)]);
+        assert_eq!(fill_next_slice(&mut twap, &mut ctx, 3, 2030.0), vec![OrderIntent::market("ETHUSDT", Side::Buy, 0.25)]);
+
+        let report = handle.progress();
+        assert_eq!((report.state, report.child_orders), (ExecutionState::Completed, 3));
+        assert!(report.is_finished() && report.end_time.is_some());
+        assert!((report.average_price().unwrap() - 2020.0).abs() < 1e-9);
+        assert!((report.slippage_bps().unwrap() - 100.0).abs() < 1e-9);
+
+        let mut twap = TwapAlgo::new(parent(Side::Sell, 1.0), Duration::from_secs(600), 4);
+        twap.on_start(&mut ctx);
+        submitted(&mut ctx);
+        twap.handle().cancel();
+        ctx.set_time(ctx.time() + 600_000);
+        twap.on_timer(&mut ctx);
+        assert!(submitted(&mut ctx).is_empty());
+        assert_eq!(twap.progress().state, ExecutionState::Canceled);
+    }
+
+    #[test]
+    fn test_twap_retries_a_rejected_last_slice() {
+        let mut twap = TwapAlgo::new(parent(Side::Buy, 1.0), Duration::from_secs(60), 1);
+        let mut ctx = StrategyContext::new();
+        ctx.set_time(1_000_000);
+        ctx.set_price("ETHUSDT", 2000.0);
+        twap.on_start(&mut ctx);
+        let (order_id, _) = submitted(&mut ctx)[0];
+        let mut rejected = update(order_id, OrderStatus::Rejected, None);
+        rejected.time = 1_000_000;
+        twap.on_order_update(&mut ctx, &rejected);
+
+        // Nothing until the backoff after the rejection has passed, then the whole parent again
+        ctx.set_time(1_000_500);
+        twap.on_timer(&mut ctx);
+        assert!(submitted(&mut ctx).is_empty());
+        ctx.set_time(1_001_000);
+        twap.on_timer(&mut ctx);
+        let orders = submitted(&mut ctx);
+        assert_eq!(orders.iter().map(|(_, intent)| intent.clone()).collect::<Vec<_>>(), vec![OrderIntent::market("ETHUSDT", Side::Buy, 1.0)]);
+        twap.on_order_update(&mut ctx, &filled(orders[0].0, 2000.0, 1.0));
+        ctx.set_time(1_002_000);
+        twap.on_timer(&mut ctx);
+        assert!(submitted(&mut ctx).is_empty());
+        assert_eq!(twap.progress().state, ExecutionState::Completed);
+    }
+}
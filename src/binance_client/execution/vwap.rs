@@ -0,0 +1,237 @@
+use std::io::{Error as IOError, ErrorKind};
+use std::time::Duration;
+use crate::binance_client::binance_client::BinanceClient;
+use crate::binance_client::execution::parent_order::{Execution, ExecutionHandle, ExecutionProgress, ParentOrder};
+use crate::binance_client::kline_interval::KlineInterval;
+use crate::binance_client::market_data_store::{BookTick, TradePrint};
+use crate::binance_client::strategy::{OrderUpdate, Strategy, StrategyContext};
+use crate::binance_client::streams::kline_data::Kline;
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// Average traded volume by time of day (UTC), in buckets of one kline interval.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VolumeProfile {
+    bucket_ms: u64,
+    volumes: Vec<f64>, // Average volume of each bucket of the day
+}
+
+impl VolumeProfile {
+    /// Averages the volume of `klines` by the time of day they open at.
+    ///
+    /// Their interval has to divide a day, `None` if it does not or there are no klines.
+    pub fn from_klines(klines: &[Kline]) -> Option<Self> {
+        let first = klines.first()?;
+        let bucket_ms = first.end_time + 1 - first.start_time;
+        if !DAY_MS.is_multiple_of(bucket_ms) {
+            return None;
+        }
+        let buckets = (DAY_MS / bucket_ms) as usize;
+        let (mut volumes, mut counts) = (vec![0.0; buckets], vec![0usize; buckets]);
+        for kline in klines {
+            let bucket = (kline.start_time % DAY_MS / bucket_ms) as usize;
+            volumes[bucket] += kline.base_asset_volume;
+            counts[bucket] += 1;
+        }
+        for (volume, count) in volumes.iter_mut().zip(counts) {
+            *volume /= count.max(1) as f64;
+        }
+        Some(VolumeProfile { bucket_ms, volumes })
+    }
+
+    /// Builds the profile from the klines of the last `days` days.
+    pub async fn fetch(binance_client: &BinanceClient, symbol: &str, interval: KlineInterval, days: u64) -> Result<Self, IOError> {
+        let now = BinanceClient::generate_timestamp()?;
+        let mut start_time = now.saturating_sub(days * DAY_MS);
+        let mut klines = Vec::new();
+        while start_time < now {
+            let page = binance_client.fetch_klines(symbol, interval, Some(start_time), Some(now), Some(1000)).await?;
+            let Some(last) = page.last() else { break };
+            start_time = last.close_time + 1;
+            let closed = page.into_iter().filter(|kline| kline.close_time < now);
+            klines.extend(closed.map(|kline| kline.into_kline(symbol, interval, now)));
+        }
+        VolumeProfile::from_klines(&klines)
+            .ok_or_else(|| IOError::new(ErrorKind::InvalidInput, format!("No volume profile from {} {} klines", symbol, interval)))
+    }
+
+    /// Expected volume traded in `[from, to)`, pro rata within the buckets.
+    pub fn volume_between(&self, from: u64, to: u64) -> f64 {
+        let mut volume = 0.0;
+        let mut time = from;
+        while time < to {
+            let bucket_start = time - time % self.bucket_ms;
+            let end = (bucket_start + self.bucket_ms).min(to);
+            let bucket = (bucket_start % DAY_MS / self.bucket_ms) as usize;
+            volume += self.volumes[bucket] * (end - time) as f64 / self.bucket_ms as f64;
+            time = end;
+        }
+        volume
+    }
+}
+
+/// Volume weighted execution: market orders that follow a [`VolumeProfile`] over `duration`.
+///
+/// Every slice interval the executed quantity is brought up to the share of the
+/// expected volume of the whole duration that has traded since the start, so more is
+/// executed when the market is usually busy. A profile without volume in the duration
+/// executes evenly in time. Once the duration is over the rest goes out at once.
+#[derive(Debug)]
+pub struct VwapAlgo {
+    execution: Execution,
+    profile: VolumeProfile,
+    duration_ms: u64,
+    slice_interval_ms: u64,
+    next_slice_time: u64,
+}
+
+impl VwapAlgo {
+    pub fn new(parent: ParentOrder, profile: VolumeProfile, duration: Duration) -> Self {
+        VwapAlgo {
+            execution: Execution::new(parent),
+            profile,
+            duration_ms: (duration.as_millis() as u64).max(1),
+            slice_interval_ms: 60_000,
+            next_slice_time: 0,
+        }
+    }
+
+    /// How often a child order is placed, every minute by default.
+    pub fn with_slice_interval(mut self, interval: Duration) -> Self {
+        self.slice_interval_ms = (interval.as_millis() as u64).max(1);
+        self
+    }
+
+    pub fn handle(&self) -> ExecutionHandle {
+        self.execution.handle()
+    }
+
+    pub fn progress(&self) -> &ExecutionProgress {
+        self.execution.progress()
+    }
+
+    /// Share of the parent quantity that should be executed at `time`, from 0 to 1.
+    pub fn target_fraction(&self, time: u64) -> f64 {
+        let start = self.execution.progress().start_time;
+        let end = start + self.duration_ms;
+        let total = self.profile.volume_between(start, end);
+        let time = time.clamp(start, end);
+        if total > 0.0 {
+            self.profile.volume_between(start, time) / total
+        } else {
+            (time - start) as f64 / self.duration_ms as f64
+        }
+    }
+
+    fn step(&mut self, ctx: &mut StrategyContext) {
+        if !self.execution.poll(ctx) || ctx.time() < self.next_slice_time {
+            return;
+        }
+        let start = self.execution.progress().start_time;
+        self.next_slice_time = ctx.time() - (ctx.time() - start) % self.slice_interval_ms + self.slice_interval_ms;
+
+        let quantity = if ctx.time() >= start + self.duration_ms {
+            self.execution.unassigned_quantity()
+        } else {
+            let progress = self.execution.progress();
+            self.execution.parent().quantity * self.target_fraction(ctx.time()) - progress.filled_quantity - progress.working_quantity
+        };
+        self.execution.submit_market(ctx, quantity);
+    }
+}
+
+impl Strategy for VwapAlgo {
+    fn on_start(&mut self, ctx: &mut StrategyContext) {
+        self.execution.start(ctx);
+        self.next_slice_time = ctx.time() + self.slice_interval_ms;
+    }
+
+    fn on_kline(&mut self, ctx: &mut StrategyContext, kline: &Kline) {
+        self.execution.observe_price(&kline.symbol, kline.close_price);
+        self.step(ctx);
+    }
+
+    fn on_trade(&mut self, ctx: &mut StrategyContext, trade: &TradePrint) {
+        self.execution.observe_price(&trade.symbol, trade.price);
+        self.step(ctx);
+    }
+
+    fn on_book(&mut self, ctx: &mut StrategyContext, book: &BookTick) {
+        self.execution.observe_price(&book.symbol, (book.bid_price + book.ask_price) / 2.0);
+        self.step(ctx);
+    }
+
+    fn on_order_update(&mut self, _ctx: &mut StrategyContext, update: &OrderUpdate) {
+        self.execution.on_order_update(update);
+    }
+
+    fn on_timer(&mut self, ctx: &mut StrategyContext) {
+        self.step(ctx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binance_client::execution::parent_order::ExecutionState;
+    use crate::binance_client::execution::parent_order::tests::{filled, parent, submitted};
+    use crate::binance_client::order_types::side::Side;
+
+    const HOUR: u64 = 60 * 60 * 1000;
+
+    fn kline(start_time: u64, volume: f64) -> Kline {
+        Kline {
+            start_time,
+            end_time: start_time + 6 * HOUR - 1,
+            symbol: "ETHUSDT".to_string(),
+            interval: "6h".to_string(),
+            first_trade_id: 0,
+            last_trade_id: 0,
+            open_price: 2000.0,
+            close_price: 2000.0,
+            high_price: 2000.0,
+            low_price: 2000.0,
+            base_asset_volume: volume,
+            number_of_trades: 1,
+            is_kline_closed: true,
+            quote_asset_volume: volume * 2000.0,
+            taker_buy_base_asset_volume: volume / 2.0,
+            taker_buy_quote_asset_volume: volume * 1000.0,
+            ignore: "0".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_vwap_follows_volume_profile() {
+        // Two days of 6 hour klines, busy in the morning
+        let volumes = [1.0, 3.0, 0.0, 0.0, 3.0, 5.0, 0.0, 0.0];
+        let klines: Vec<Kline> = volumes.iter().enumerate().map(|(index, volume)| kline(index as u64 * 6 * HOUR, *volume)).collect();
+        let profile = VolumeProfile::from_klines(&klines).unwrap();
+        assert_eq!(profile.volume_between(2 * DAY_MS, 2 * DAY_MS + 9 * HOUR), 2.0 + 2.0);
+        assert_eq!(VolumeProfile::from_klines(&[]), None);
+
+        let mut vwap = VwapAlgo::new(parent(Side::Buy, 1.0), profile, Duration::from_secs(12 * 3600))
+            .with_slice_interval(Duration::from_secs(3 * 3600));
+        let mut ctx = StrategyContext::new();
+        ctx.set_time(2 * DAY_MS);
+        vwap.on_start(&mut ctx);
+        assert_eq!(vwap.target_fraction(2 * DAY_MS + 6 * HOUR), 2.0 / 6.0);
+
+        // Not due before the first slice interval
+        ctx.set_time(2 * DAY_MS + HOUR);
+        vwap.on_timer(&mut ctx);
+        assert!(submitted(&mut ctx).is_empty());
+        ctx.set_time(2 * DAY_MS + 3 * HOUR);
+        vwap.on_timer(&mut ctx);
+        let orders = submitted(&mut ctx);
+        assert_eq!(orders[0].1.quantity(), 0.166);
+        vwap.on_order_update(&mut ctx, &filled(orders[0].0, 2000.0, 0.166));
+
+        ctx.set_time(2 * DAY_MS + 12 * HOUR);
+        vwap.on_timer(&mut ctx);
+        let orders = submitted(&mut ctx);
+        assert_eq!(orders[0].1.quantity(), 0.834);
+        vwap.on_order_update(&mut ctx, &filled(orders[0].0, 2000.0, 0.834));
+        assert_eq!(vwap.progress().state, ExecutionState::Completed);
+    }
+}
@@ -0,0 +1,330 @@
+use std::io::{Error as IOError, ErrorKind};
+use crate::binance_client::account::account_info::AccountInfoClient;
+use crate::binance_client::account::commission::{AccountCommission, CommissionDiscount};
+use crate::binance_client::account::commission_rates::CommissionRates;
+use crate::binance_client::account::portfolio::PriceBook;
+use crate::binance_client::account::trades::Trade;
+use crate::binance_client::binance_client::BinanceClient;
+use crate::binance_client::order_response::Fill;
+use crate::binance_client::order_types::side::Side;
+use crate::binance_client::position_size::calculate_position_size_with_fees;
+
+/// Whether an order added liquidity to the book (maker) or took it (taker).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiquidityRole {
+    Maker,
+    Taker,
+}
+
+impl LiquidityRole {
+    pub fn from_is_maker(is_maker: bool) -> Self {
+        if is_maker {
+            LiquidityRole::Maker
+        } else {
+            LiquidityRole::Taker
+        }
+    }
+}
+
+/// A commission in the asset it is charged in, together with its value in the quote asset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fee {
+    pub asset: String,
+    pub amount: f64,
+    pub quote_value: f64,
+}
+
+/// Expected and actually charged commission of a single fill.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeeReconciliation {
+    pub expected: Fee,
+    pub actual: Fee,
+    pub difference: f64, // Actual minus expected, in the quote asset
+}
+
+/// Computes the commission of trades on one symbol.
+///
+/// Binance charges a buy in the base asset and a sell in the quote asset, unless the BNB
+/// discount is active, in which case the discounted commission is paid in the discount
+/// asset. The model needs the discount asset's price in the quote asset to apply the
+/// discount; without it fees are computed as if the discount were off.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeeModel {
+    pub base_asset: String,
+    pub quote_asset: String,
+    pub standard: CommissionRates,
+    pub tax: CommissionRates,
+    pub special: CommissionRates,
+    pub discount: Option<CommissionDiscount>,
+    pub discount_asset_price: Option<f64>, // Price of the discount asset in the quote asset
+}
+
+impl FeeModel {
+    pub fn new(commission: &AccountCommission, base_asset: &str, quote_asset: &str) -> Self {
+        FeeModel {
+            base_asset: base_asset.to_string(),
+            quote_asset: quote_asset.to_string(),
+            standard: commission.standard_commission,
+            tax: commission.tax_commission,
+            special: commission.special_commission,
+            discount: Some(commission.discount.clone()).filter(|discount| discount.is_active()),
+            discount_asset_price: None,
+        }
+    }
+
+    /// A model with fixed maker and taker rates and no discount, for backtests and paper trading.
+    pub fn flat(maker_rate: f64, taker_rate: f64, base_asset: &str, quote_asset: &str) -> Self {
+        FeeModel {
+            base_asset: base_asset.to_string(),
+            quote_asset: quote_asset.to_string(),
+            standard: CommissionRates { maker: maker_rate, taker: taker_rate, ..Default::default() },
+            tax: CommissionRates::default(),
+            special: CommissionRates::default(),
+            discount: None,
+            discount_asset_price: None,
+        }
+    }
+
+    /// Builds a model from the account's standard maker and taker commission.
+    ///
+    /// Uses the fractional `commissionRates` of `/v3/account`, which come without tax,
+    /// special rates or discount, so prefer [`FeeModel::fetch`] when those matter.
+    pub fn from_account_info(account_info: &AccountInfoClient, base_asset: &str, quote_asset: &str) -> Self {
+        FeeModel {
+            standard: account_info.commission_rates,
+            ..Self::flat(0.0, 0.0, base_asset, quote_asset)
+        }
+    }
+
+    /// Fetches the commission of `base_asset``quote_asset` and, if the BNB discount is
+    /// active, the discount asset's price.
+    pub async fn fetch(api: &BinanceClient, base_asset: &str, quote_asset: &str) -> Result<Self, IOError> {
+        let symbol = format!("{}{}", base_asset, quote_asset);
+        let commission = api.fetch_account_commission(&symbol).await?;
+        let mut model = Self::new(&commission, base_asset, quote_asset);
+
+        if let Some(discount) = &model.discount {
+            let price_book = PriceBook::new(&api.get_all_prices().await?);
+            let price = price_book.price_in(&discount.discount_asset, quote_asset).ok_or_else(|| {
+                IOError::new(ErrorKind::Other, format!("No price for {} in {}", discount.discount_asset, quote_asset))
+            })?;
+            model.discount_asset_price = Some(price);
+        }
+        Ok(model)
+    }
+
+    pub fn with_discount_asset_price(mut self, price: f64) -> Self {
+        self.discount_asset_price = Some(price);
+        self
+    }
+
+    /// Commission rate before any discount.
+    pub fn rate(&self, side: Side, role: LiquidityRole) -> f64 {
+        [self.standard, self.tax, self.special]
+            .iter()
+            .map(|rates| Self::rate_of(rates, side, role))
+            .sum()
+    }
+
+    /// Commission rate actually paid, with the discount applied to the standard commission.
+    pub fn effective_rate(&self, side: Side, role: LiquidityRole) -> f64 {
+        match self.active_discount() {
+            Some((discount, _)) => {
+                let standard = Self::rate_of(&self.standard, side, role) * (1.0 - discount.discount);
+                standard + Self::rate_of(&self.tax, side, role) + Self::rate_of(&self.special, side, role)
+            }
+            None => self.rate(side, role),
+        }
+    }
+
+    /// The commission Binance should charge for filling `qty` at `price`.
+    pub fn expected_fee(&self, side: Side, role: LiquidityRole, qty: f64, price: f64) -> Fee {
+        let quote_value = qty * price * self.effective_rate(side, role);
+
+        match (self.active_discount(), side) {
+            (Some((discount, discount_price)), _) => Fee {
+                asset: discount.discount_asset.clone(),
+                amount: quote_value / discount_price,
+                quote_value,
+            },
+            (None, Side::Buy) => Fee { asset: self.base_asset.clone(), amount: quote_value / price, quote_value },
+            (None, Side::Sell) => Fee { asset: self.quote_asset.clone(), amount: quote_value, quote_value },
+        }
+    }
+
+    /// Values a commission that was charged in `commission_asset` in the quote asset.
+    ///
+    /// The base asset is valued at the fill price; any other asset needs `price_book`.
+    /// Returns `None` when the asset cannot be priced.
+    pub fn actual_fee(
+        &self,
+        commission: f64,
+        commission_asset: &str,
+        fill_price: f64,
+        price_book: Option<&PriceBook>,
+    ) -> Option<Fee> {
+        let price = if commission_asset == self.quote_asset {
+            Some(1.0)
+        } else if commission_asset == self.base_asset {
+            Some(fill_price)
+        } else {
+            self.discount_price_of(commission_asset)
+                .or_else(|| price_book?.price_in(commission_asset, &self.quote_asset))
+        }?;
+
+        Some(Fee {
+            asset: commission_asset.to_string(),
+            amount: commission,
+            quote_value: commission * price,
+        })
+    }
+
+    pub fn reconcile_fill(
+        &self,
+        fill: &Fill,
+        side: Side,
+        role: LiquidityRole,
+        price_book: Option<&PriceBook>,
+    ) -> Option<FeeReconciliation> {
+        let actual = self.actual_fee(fill.commission, &fill.commission_asset, fill.price, price_book)?;
+        Some(self.reconcile(self.expected_fee(side, role, fill.qty, fill.price), actual))
+    }
+
+    pub fn reconcile_trade(&self, trade: &Trade, price_book: Option<&PriceBook>) -> Option<FeeReconciliation> {
+        let side = if trade.is_buyer { Side::Buy } else { Side::Sell };
+        let role = LiquidityRole::from_is_maker(trade.is_maker);
+        let actual = self.actual_fee(trade.commission, &trade.commission_asset, trade.price, price_book)?;
+        Some(self.reconcile(self.expected_fee(side, role, trade.qty, trade.price), actual))
+    }
+
+    /// Position size in the base asset that risks `risk_percentage` of the account
+    /// including the entry and stop loss commissions.
+    pub fn position_size(
+        &self,
+        account_size: f64,
+        risk_percentage: f64,
+        entry_price: f64,
+        stop_loss_price: f64,
+        entry_role: LiquidityRole,
+        exit_role: LiquidityRole,
+    ) -> f64 {
+        let (entry_side, exit_side) = if stop_loss_price < entry_price {
+            (Side::Buy, Side::Sell)
+        } else {
+            (Side::Sell, Side::Buy)
+        };
+        calculate_position_size_with_fees(
+            account_size,
+            risk_percentage,
+            entry_price,
+            stop_loss_price,
+            self.effective_rate(entry_side, entry_role),
+            self.effective_rate(exit_side, exit_role),
+        )
+    }
+
+    fn reconcile(&self, expected: Fee, actual: Fee) -> FeeReconciliation {
+        let difference = actual.quote_value - expected.quote_value;
+        FeeReconciliation { expected, actual, difference }
+    }
+
+    fn active_discount(&self) -> Option<(&CommissionDiscount, f64)> {
+        Some((self.discount.as_ref()?, self.discount_asset_price?))
+    }
+
+    fn discount_price_of(&self, asset: &str) -> Option<f64> {
+        self.active_discount()
+            .filter(|(discount, _)| discount.discount_asset == asset)
+            .map(|(_, price)| price)
+    }
+
+    fn rate_of(rates: &CommissionRates, side: Side, role: LiquidityRole) -> f64 {
+        let role_rate = match role {
+            LiquidityRole::Maker => rates.maker,
+            LiquidityRole::Taker => rates.taker,
+        };
+        let side_rate = match side {
+            Side::Buy => rates.buyer,
+            Side::Sell => rates.seller,
+        };
+        role_rate + side_rate
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binance_client::ticker_price::TickerPrice;
+
+    fn commission(discount_enabled: bool) -> AccountCommission {
+        AccountCommission {
+            symbol: "ETHUSDT".to_string(),
+            standard_commission: CommissionRates { buyer: 0.0, maker: 0.001, seller: 0.0, taker: 0.002 },
+            tax_commission: CommissionRates { buyer: 0.0001, maker: 0.0, seller: 0.0, taker: 0.0 },
+            special_commission: CommissionRates::default(),
+            discount: CommissionDiscount {
+                enabled_for_account: discount_enabled,
+                enabled_for_symbol: true,
+                discount_asset: "BNB".to_string(),
+                discount: 0.25,
+            },
+        }
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn test_expected_fee_without_discount() {
+        let model = FeeModel::new(&commission(false), "ETH", "USDT");
+        assert!(model.discount.is_none());
+
+        let buy = model.expected_fee(Side::Buy, LiquidityRole::Taker, 2.0, 1000.0);
+        assert_eq!(buy.asset, "ETH");
+        assert_close(buy.amount, 2.0 * 0.0021);
+        assert_close(buy.quote_value, 2000.0 * 0.0021);
+
+        let sell = model.expected_fee(Side::Sell, LiquidityRole::Maker, 2.0, 1000.0);
+        assert_eq!(sell.asset, "USDT");
+        assert_close(sell.amount, 2.0);
+    }
+
+    #[test]
+    fn test_expected_fee_with_discount() {
+        let model = FeeModel::new(&commission(true), "ETH", "USDT").with_discount_asset_price(500.0);
+        assert_close(model.effective_rate(Side::Sell, LiquidityRole::Taker), 0.0015);
+
+        let fee = model.expected_fee(Side::Sell, LiquidityRole::Taker, 1.0, 1000.0);
+        assert_eq!(fee.asset, "BNB");
+        assert_close(fee.quote_value, 1.5);
+        assert_close(fee.amount, 0.003);
+    }
+
+    #[test]
+    fn test_reconcile_fill_in_third_asset() {
+        let model = FeeModel::flat(0.001, 0.001, "ETH", "USDT");
+        let fill = Fill {
+            price: 1000.0,
+            qty: 1.0,
+            commission: 0.002,
+            commission_asset: "BNB".to_string(),
+            trade_id: 1,
+        };
+        assert!(model.reconcile_fill(&fill, Side::Buy, LiquidityRole::Taker, None).is_none());
+
+        let price_book = PriceBook::new(&[TickerPrice { symbol: "BNBUSDT".to_string(), price: 400.0 }]);
+        let reconciliation = model.reconcile_fill(&fill, Side::Buy, LiquidityRole::Taker, Some(&price_book)).unwrap();
+        assert_close(reconciliation.expected.quote_value, 1.0);
+        assert_close(reconciliation.actual.quote_value, 0.8);
+        assert_close(reconciliation.difference, -0.2);
+    }
+
+    #[test]
+    fn test_position_size_is_fee_inclusive() {
+        let model = FeeModel::flat(0.0, 0.001, "ETH", "USDT");
+        let size = model.position_size(1000.0, 1.0, 100.0, 98.0, LiquidityRole::Taker, LiquidityRole::Taker);
+        assert_close(size, 10.0 / (2.0 + 0.1 + 0.098));
+    }
+}
@@ -0,0 +1,623 @@
+use std::io::{Error as IOError, ErrorKind};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use crate::binance_client::account::order::Order;
+use crate::binance_client::account::order_status::OrderStatus;
+use crate::binance_client::binance_client::BinanceClient;
+use crate::binance_client::database_client::{db_error, DatabaseClient};
+use crate::binance_client::exchange_info::{SymbolFilters, SymbolInfo};
+use crate::binance_client::order_api::OrderApi;
+use crate::binance_client::order_types::limit_order::LimitOrder;
+use crate::binance_client::order_types::market_order::MarketOrder;
+use crate::binance_client::order_types::client_order_id::{is_client_order_id_text, MAX_CLIENT_ORDER_ID_LEN};
+use crate::binance_client::order_types::side::Side;
+use crate::binance_client::streams::user_data::{ExecutionReport, ExecutionType};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GridSpacing {
+    Arithmetic, // The same price difference between lines
+    Geometric, // The same ratio between lines
+}
+
+/// The ladder a [`GridBot`] trades and when it gives up.
+#[derive(Debug, Clone)]
+pub struct GridConfig {
+    pub name: String, // Identifies the persisted state and prefixes the client order ids
+    pub symbol: String,
+    pub base_asset: String,
+    pub quote_asset: String,
+    pub filters: SymbolFilters,
+    pub lower_price: f64,
+    pub upper_price: f64,
+    pub grid_lines: usize,
+    pub spacing: GridSpacing,
+    pub quantity: f64, // Of every order
+    pub stop_loss_price: Option<f64>,
+    pub take_profit_price: Option<f64>,
+}
+
+impl GridConfig {
+    pub fn new(name: &str, symbol: &SymbolInfo, lower_price: f64, upper_price: f64, grid_lines: usize, quantity: f64) -> Self {
+        GridConfig {
+            name: name.to_string(),
+            symbol: symbol.symbol.clone(),
+            base_asset: symbol.base_asset.clone(),
+            quote_asset: symbol.quote_asset.clone(),
+            filters: symbol.trading_filters(),
+            lower_price,
+            upper_price,
+            grid_lines,
+            spacing: GridSpacing::Arithmetic,
+            quantity,
+            stop_loss_price: None,
+            take_profit_price: None,
+        }
+    }
+
+    pub fn with_spacing(mut self, spacing: GridSpacing) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    /// Stops the grid and sells what it bought once the price falls to `price`.
+    pub fn with_stop_loss(mut self, price: f64) -> Self {
+        self.stop_loss_price = Some(price);
+        self
+    }
+
+    /// Stops the grid and sells what it bought once the price rises to `price`.
+    pub fn with_take_profit(mut self, price: f64) -> Self {
+        self.take_profit_price = Some(price);
+        self
+    }
+
+    /// The `newClientOrderId` of the `placement`th order of the grid line `line`.
+    pub fn client_order_id(&self, line: usize, side: Side, placement: u32) -> String {
+        let side = match side {
+            Side::Buy => 'b',
+            Side::Sell => 's',
+        };
+        format!("{}-{}-{}{}", self.name, line, side, placement)
+    }
+
+    // The name ends up in every client order id, which Binance limits to 36 characters
+    fn check_name(&self) -> Result<(), IOError> {
+        let longest = self.client_order_id(self.grid_lines.saturating_sub(1), Side::Sell, u32::MAX);
+        if self.name.is_empty() || !is_client_order_id_text(&self.name) || longest.len() > MAX_CLIENT_ORDER_ID_LEN {
+            return Err(IOError::new(ErrorKind::InvalidInput, format!(
+                "Grid name {} needs letters, digits or .:/_- short enough for client order ids like {}", self.name, longest,
+            )));
+        }
+        Ok(())
+    }
+
+    /// Prices of the grid lines, lowest first, snapped to the tick.
+    ///
+    /// Fails if the bounds are invalid, two lines round to the same tick, or an order of
+    /// `quantity` at one of the lines would not pass the symbol's filters.
+    pub fn prices(&self) -> Result<Vec<f64>, IOError> {
+        if self.grid_lines < 2 || self.lower_price <= 0.0 || self.upper_price <= self.lower_price {
+            return Err(IOError::new(ErrorKind::InvalidInput, "A grid needs two lines and 0 < lower price < upper price"));
+        }
+        if self.filters.floor_quantity(self.quantity, false) != self.quantity {
+            return Err(IOError::new(ErrorKind::InvalidInput, format!("Grid quantity {} is not a multiple of the lot step", self.quantity)));
+        }
+        let steps = (self.grid_lines - 1) as f64;
+        let prices: Vec<f64> = (0..self.grid_lines)
+            .map(|line| match self.spacing {
+                GridSpacing::Arithmetic => self.lower_price + (self.upper_price - self.lower_price) * line as f64 / steps,
+                GridSpacing::Geometric => self.lower_price * (self.upper_price / self.lower_price).powf(line as f64 / steps),
+            })
+            .map(|price| self.filters.round_price(price))
+            .collect();
+        if prices.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(IOError::new(ErrorKind::InvalidInput, "Grid lines are closer than a tick"));
+        }
+        for price in &prices {
+            self.filters.check(*price, self.quantity, false)?;
+        }
+        Ok(prices)
+    }
+}
+
+/// The exchange order of a grid line.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GridOrder {
+    pub order_id: i64,
+    pub executed_qty: f64,
+    pub last_trade_id: Option<i64>, // Trades up to this one are booked
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GridLevel {
+    pub price: f64,
+    pub side: Option<Side>, // The order the line should have, `None` for the gap
+    pub paired_price: Option<f64>, // Fill price of the order this one closes
+    pub order: Option<GridOrder>,
+    pub placed: u32, // Orders placed for the line, which number their client order ids
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum StopReason {
+    StopLoss,
+    TakeProfit,
+    Manual,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum GridStatus {
+    Running,
+    Stopped(StopReason),
+}
+
+/// Everything a [`GridBot`] needs to resume, see [`GridStore`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GridState {
+    pub name: String,
+    pub symbol: String,
+    pub status: GridStatus,
+    pub levels: Vec<GridLevel>,
+    pub realized_profit: f64, // Of the closed round trips, before fees
+    pub fees: f64, // Quote value of the commissions paid in the base or quote asset
+    pub round_trips: u64,
+    pub position: f64, // Base asset bought by the grid, net of what it sold
+}
+
+impl GridState {
+    pub fn net_profit(&self) -> f64 {
+        self.realized_profit - self.fees
+    }
+
+    pub fn open_orders(&self) -> usize {
+        self.levels.iter().filter(|level| level.order.is_some()).count()
+    }
+}
+
+/// A ladder of limit orders between two prices that buys low and sells high.
+///
+/// Lines below the price start with a buy, lines above with a sell, and the line closest
+/// to the price stays empty. When the order of a line fills, the neighbouring line on the
+/// other side of it gets the opposite order: a filled buy is followed by a sell one line
+/// up, a filled sell by a buy one line down. Each of those closes a round trip and
+/// realizes the difference between the two lines. The sells of the initial ladder need
+/// the base asset, so buy it before starting.
+///
+/// Feed it the execution reports of the user data stream and prices, and save the
+/// [`state`](Self::state) after every call; [`resume`](Self::resume) picks it up again.
+/// Every order gets a client order id from the grid name, its line, its side and the
+/// number of orders the line had before, so a restart recognizes orders placed after
+/// the last save.
+pub struct GridBot<'a> {
+    order_api: &'a dyn OrderApi,
+    config: GridConfig,
+    state: GridState,
+}
+
+impl<'a> GridBot<'a> {
+    /// A new grid around `price`. No order is placed before [`sync`](Self::sync).
+    pub fn new(order_api: &'a dyn OrderApi, config: GridConfig, price: f64) -> Result<Self, IOError> {
+        config.check_name()?;
+        let prices = config.prices()?;
+        let gap = (0..prices.len())
+            .min_by(|a, b| (prices[*a] - price).abs().total_cmp(&(prices[*b] - price).abs()))
+            .unwrap_or_default();
+        let levels = prices
+            .iter()
+            .enumerate()
+            .map(|(line, price)| GridLevel {
+                price: *price,
+                side: (line != gap).then_some(if line < gap { Side::Buy } else { Side::Sell }),
+                paired_price: None,
+                order: None,
+                placed: 0,
+            })
+            .collect();
+        let state = GridState {
+            name: config.name.clone(),
+            symbol: config.symbol.clone(),
+            status: GridStatus::Running,
+            levels,
+            realized_profit: 0.0,
+            fees: 0.0,
+            round_trips: 0,
+            position: 0.0,
+        };
+        Ok(GridBot { order_api, config, state })
+    }
+
+    /// Picks up a saved grid, matching it against `history`, the orders of the symbol as
+    /// [`BinanceClient::fetch_all_orders`] returns them.
+    ///
+    /// Lines whose order filled while the bot was down get their opposite order, canceled
+    /// orders are placed again by the next [`sync`](Self::sync), and orders placed after
+    /// the state was saved are matched to their lines by client order id instead of
+    /// placed twice.
+    pub fn resume(order_api: &'a dyn OrderApi, config: GridConfig, state: GridState, history: &[Order]) -> Result<Self, IOError> {
+        config.check_name()?;
+        let prices = config.prices()?;
+        let matches = state.name == config.name && state.symbol == config.symbol && state.levels.len() == prices.len()
+            && state.levels.iter().zip(&prices).all(|(level, price)| (level.price - price).abs() < 1e-9);
+        if !matches {
+            return Err(IOError::new(ErrorKind::InvalidInput, format!("Saved grid {} does not match its config", state.name)));
+        }
+        let mut bot = GridBot { order_api, config, state };
+
+        let mut history: Vec<&Order> = history.iter().filter(|order| order.symbol == bot.config.symbol).collect();
+        history.sort_by_key(|order| (order.update_time, order.order_id));
+        for order in history {
+            let index = match bot.level_of(order.order_id) {
+                Some(index) => index,
+                // Placed after the state was saved, as the next order of an empty line
+                None if bot.state.status == GridStatus::Running => {
+                    let unclaimed = bot.state.levels.iter().enumerate().position(|(line, level)| {
+                        level.order.is_none() && level.side.is_some_and(|side| {
+                            order.client_order_id == bot.config.client_order_id(line, side, level.placed)
+                        })
+                    });
+                    let Some(index) = unclaimed else { continue };
+                    info!("Grid {} adopts order {} at {}", bot.state.name, order.order_id, order.price);
+                    let level = &mut bot.state.levels[index];
+                    level.order = Some(GridOrder { order_id: order.order_id, executed_qty: 0.0, last_trade_id: None });
+                    level.placed += 1;
+                    index
+                }
+                None => continue,
+            };
+            bot.book_execution(index, order.executed_qty);
+            match order.status {
+                OrderStatus::Filled => {
+                    let average_price = if order.cummulative_quote_qty > 0.0 { order.cummulative_quote_qty / order.executed_qty } else { order.price };
+                    bot.complete(index, average_price);
+                }
+                OrderStatus::New | OrderStatus::PartiallyFilled | OrderStatus::PendingCancel => {}
+                _ => bot.state.levels[index].order = None,
+            }
+        }
+        Ok(bot)
+    }
+
+    pub fn config(&self) -> &GridConfig {
+        &self.config
+    }
+
+    pub fn state(&self) -> &GridState {
+        &self.state
+    }
+
+    /// Places the orders the lines are missing.
+    ///
+    /// On an error the orders placed so far are kept in the state; calling it again
+    /// places the rest.
+    pub async fn sync(&mut self) -> Result<(), IOError> {
+        if self.state.status != GridStatus::Running {
+            return Ok(());
+        }
+        for index in 0..self.state.levels.len() {
+            let level = &self.state.levels[index];
+            let (Some(side), None) = (level.side, &level.order) else { continue };
+            let order = LimitOrder::new(&self.config.symbol, side, self.config.quantity, level.price, 0)
+                .with_client_order_id(&self.config.client_order_id(index, side, level.placed));
+            let response = self.order_api.create_limit_order(order).await?;
+            let level = &mut self.state.levels[index];
+            level.order = Some(GridOrder { order_id: response.order_id, executed_qty: 0.0, last_trade_id: None });
+            level.placed += 1;
+        }
+        Ok(())
+    }
+
+    /// Books fills of the grid's orders and replaces the filled ones.
+    pub async fn on_execution_report(&mut self, report: &ExecutionReport) -> Result<(), IOError> {
+        if report.symbol != self.config.symbol {
+            return Ok(());
+        }
+        let Some(index) = self.level_of(report.order_id) else { return Ok(()) };
+        if report.execution_type == ExecutionType::Trade {
+            let Some(order) = self.state.levels[index].order.as_mut() else { return Ok(()) };
+            if order.last_trade_id.is_some_and(|last_trade_id| report.trade_id <= last_trade_id) {
+                return Ok(());
+            }
+            order.last_trade_id = Some(report.trade_id);
+            match report.commission_asset.as_deref() {
+                Some(asset) if asset == self.config.quote_asset => self.state.fees += report.commission,
+                Some(asset) if asset == self.config.base_asset => self.state.fees += report.commission * report.last_executed_price,
+                _ => {}
+            }
+            self.book_execution(index, report.cumulative_filled_qty);
+        }
+        match report.order_status {
+            OrderStatus::Filled => {
+                let average_price = if report.cumulative_filled_qty > 0.0 {
+                    report.cumulative_quote_qty / report.cumulative_filled_qty
+                } else {
+                    self.state.levels[index].price
+                };
+                self.complete(index, average_price);
+            }
+            OrderStatus::Canceled | OrderStatus::Rejected | OrderStatus::Expired | OrderStatus::ExpiredInMatch => {
+                if self.state.status == GridStatus::Running {
+                    // Placed again with the next sync
+                    warn!("Grid {} order {} at {} ended as {:?}", self.state.name, report.order_id, self.state.levels[index].price, report.order_status);
+                }
+                self.state.levels[index].order = None;
+            }
+            _ => return Ok(()),
+        }
+        match self.state.status {
+            GridStatus::Running => self.sync().await,
+            GridStatus::Stopped(_) => self.flatten().await,
+        }
+    }
+
+    /// Checks the stop loss and take profit against the latest price.
+    pub async fn on_price(&mut self, price: f64) -> Result<(), IOError> {
+        if self.state.status != GridStatus::Running {
+            return Ok(());
+        }
+        if self.config.stop_loss_price.is_some_and(|stop_loss| price <= stop_loss) {
+            self.halt(StopReason::StopLoss).await
+        } else if self.config.take_profit_price.is_some_and(|take_profit| price >= take_profit) {
+            self.halt(StopReason::TakeProfit).await
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Cancels the grid's orders and sells what it bought at market.
+    ///
+    /// The sale waits for the reports of the canceled orders, so keep feeding them.
+    pub async fn stop(&mut self) -> Result<(), IOError> {
+        self.halt(StopReason::Manual).await
+    }
+
+    async fn halt(&mut self, reason: StopReason) -> Result<(), IOError> {
+        info!("Stopping grid {}: {:?}", self.state.name, reason);
+        self.state.status = GridStatus::Stopped(reason);
+        for level in &self.state.levels {
+            let Some(order) = &level.order else { continue };
+            // Kept until its report arrives, it may have filled in the meantime
+            if let Err(e) = self.order_api.cancel_order(&self.config.symbol, order.order_id).await {
+                warn!("Failed to cancel grid order {}: {}", order.order_id, e);
+            }
+        }
+        self.flatten().await
+    }
+
+    // Sells the position of a stopped grid once none of its orders can fill anymore
+    async fn flatten(&mut self) -> Result<(), IOError> {
+        if self.state.open_orders() > 0 {
+            return Ok(());
+        }
+        let quantity = self.config.filters.floor_quantity(self.state.position, true);
+        if quantity > 0.0 {
+            let order = MarketOrder::new_with_base_asset(&self.config.symbol, Side::Sell, quantity);
+            let response = self.order_api.create_market_order(order).await?;
+            self.state.position -= response.executed_qty.unwrap_or(quantity);
+        }
+        Ok(())
+    }
+
+    fn level_of(&self, order_id: i64) -> Option<usize> {
+        self.state.levels.iter().position(|level| level.order.as_ref().is_some_and(|order| order.order_id == order_id))
+    }
+
+    // Adds what the line's order executed since it was last booked to the position
+    fn book_execution(&mut self, index: usize, executed_qty: f64) {
+        let level = &mut self.state.levels[index];
+        let Some(order) = level.order.as_mut() else { return };
+        let quantity = executed_qty - order.executed_qty;
+        if quantity <= 0.0 {
+            return;
+        }
+        order.executed_qty = executed_qty;
+        self.state.position += if level.side == Some(Side::Buy) { quantity } else { -quantity };
+    }
+
+    // Frees the line of a filled order and gives its neighbour the opposite order
+    fn complete(&mut self, index: usize, average_price: f64) {
+        let level = &mut self.state.levels[index];
+        let quantity = level.order.take().map_or(0.0, |order| order.executed_qty);
+        let Some(side) = level.side.take() else { return };
+        if let Some(paired_price) = level.paired_price.take() {
+            // Negative when a fill slips past the price of the order it closes
+            let profit = match side {
+                Side::Sell => average_price - paired_price,
+                Side::Buy => paired_price - average_price,
+            };
+            self.state.realized_profit += quantity * profit;
+            self.state.round_trips += 1;
+        }
+        info!("Grid {} {:?} {} at {}", self.state.name, side, quantity, average_price);
+
+        let (neighbour, opposite) = match side {
+            Side::Buy => (index + 1, Side::Sell),
+            Side::Sell => (index.wrapping_sub(1), Side::Buy),
+        };
+        match self.state.levels.get_mut(neighbour) {
+            Some(next) if next.side.is_none() => {
+                next.side = Some(opposite);
+                next.paired_price = Some(average_price);
+            }
+            Some(next) => warn!("Grid {} line at {} already has a {:?} order", self.state.name, next.price, next.side),
+            None => {}
+        }
+    }
+}
+
+/// Saves and loads [`GridState`]s in Postgres, one row per grid name.
+///
+/// The table is created by [`DatabaseClient::migrate`], which callers run first.
+#[derive(Debug, Clone)]
+pub struct GridStore {
+    db: DatabaseClient,
+}
+
+impl GridStore {
+    pub fn new(db: DatabaseClient) -> Self {
+        GridStore { db }
+    }
+
+    pub async fn save(&self, state: &GridState) -> Result<(), IOError> {
+        let json = serde_json::to_string(state).map_err(|e| IOError::new(ErrorKind::InvalidData, e))?;
+        let updated_at = BinanceClient::generate_timestamp()? as i64;
+        self.db.client().await?.execute(
+            "INSERT INTO grid_bots (name, symbol, state, updated_at) VALUES ($1, $2, $3, $4)
+             ON CONFLICT (name) DO UPDATE SET symbol = EXCLUDED.symbol, state = EXCLUDED.state, updated_at = EXCLUDED.updated_at",
+            &[&state.name, &state.symbol, &json, &updated_at],
+        ).await.map_err(db_error)?;
+        Ok(())
+    }
+
+    pub async fn load(&self, name: &str) -> Result<Option<GridState>, IOError> {
+        let row = self.db.client().await?
+            .query_opt("SELECT state FROM grid_bots WHERE name = $1", &[&name])
+            .await
+            .map_err(db_error)?;
+        row.map(|row| serde_json::from_str(row.get("state")).map_err(|e| IOError::new(ErrorKind::InvalidData, e)))
+            .transpose()
+    }
+
+    pub async fn delete(&self, name: &str) -> Result<bool, IOError> {
+        let deleted = self.db.client().await?
+            .execute("DELETE FROM grid_bots WHERE name = $1", &[&name])
+            .await
+            .map_err(db_error)?;
+        Ok(deleted > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use crate::binance_client::paper_trader::PaperTrader;
+    use crate::binance_client::test_support::{self, eth_usdt, forward, set_book, ReportHandler};
+
+    #[async_trait]
+    impl ReportHandler for GridBot<'_> {
+        async fn handle(&mut self, report: &ExecutionReport) -> Result<(), IOError> {
+            self.on_execution_report(report).await
+        }
+    }
+
+    // Holds 2 ETH besides the USDT of the shared fixture to place the sell levels
+    fn paper_trader() -> PaperTrader {
+        let paper_trader = test_support::paper_trader(1999.0, 2001.0);
+        paper_trader.deposit("ETH", 2.0);
+        paper_trader
+    }
+
+    fn trade(paper_trader: &PaperTrader, price: f64, is_buyer_maker: bool) {
+        test_support::trade(paper_trader, price, 1.0, is_buyer_maker);
+    }
+
+    async fn open_orders(paper_trader: &PaperTrader) -> Vec<(String, f64)> {
+        let mut open: Vec<(String, f64)> = paper_trader.fetch_open_orders("ETHUSDT").await.unwrap()
+            .into_iter()
+            .map(|order| (order.side, order.price))
+            .collect();
+        open.sort_by(|a, b| a.1.total_cmp(&b.1));
+        open
+    }
+
+    fn order(order_id: i64, client_order_id: &str, side: &str, price: f64, status: &str, executed_qty: f64) -> Order {
+        serde_json::from_str(&format!(r#"{{
+            "symbol": "ETHUSDT", "orderId": {order_id}, "orderListId": -1, "clientOrderId": "{client_order_id}",
+            "price": "{price}", "origQty": "1.0", "executedQty": "{executed_qty}", "cummulativeQuoteQty": "{quote}",
+            "status": "{status}", "timeInForce": "GTC", "type": "LIMIT", "side": "{side}", "stopPrice": "0.0",
+            "icebergQty": "0.0", "time": {order_id}, "updateTime": {order_id}, "isWorking": true, "origQuoteOrderQty": "0.0"
+        }}"#, quote = executed_qty * price)).unwrap()
+    }
+
+    #[test]
+    fn test_grid_prices() {
+        let config = GridConfig::new("eth", &eth_usdt(), 1000.0, 4000.0, 3, 0.1).with_spacing(GridSpacing::Geometric);
+        assert_eq!(config.prices().unwrap(), vec![1000.0, 2000.0, 4000.0]);
+        let config = GridConfig::new("eth", &eth_usdt(), 1000.0, 1000.02, 4, 0.1);
+        assert_eq!(config.prices().unwrap_err().kind(), ErrorKind::InvalidInput);
+        let config = GridConfig::new("eth", &eth_usdt(), 1000.0, 2000.0, 3, 0.00015);
+        assert_eq!(config.prices().unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_round_trip_profit_is_signed() {
+        let paper_trader = paper_trader();
+        let config = GridConfig::new("eth", &eth_usdt(), 1900.0, 2100.0, 5, 1.0);
+        let mut grid = GridBot::new(&paper_trader, config, 2000.0).unwrap();
+        let mut close = |index: usize, side: Side, paired_price: f64, average_price: f64| {
+            let level = &mut grid.state.levels[index];
+            level.side = Some(side);
+            level.paired_price = Some(paired_price);
+            level.order = Some(GridOrder { order_id: 1, executed_qty: 1.0, last_trade_id: None });
+            grid.complete(index, average_price);
+            grid.state.realized_profit
+        };
+        assert_eq!(close(3, Side::Sell, 2000.0, 2050.0), 50.0);
+        assert_eq!(close(1, Side::Buy, 2000.0, 1950.0), 100.0);
+        // A sell filled below the buy it closes is a loss
+        assert_eq!(close(3, Side::Sell, 2000.0, 1990.0), 90.0);
+    }
+
+    #[tokio::test]
+    async fn test_grid_round_trip_and_resume() {
+        let paper_trader = paper_trader();
+        let mut events = paper_trader.subscribe();
+        let config = GridConfig::new("eth", &eth_usdt(), 1900.0, 2100.0, 5, 1.0).with_stop_loss(1920.0).with_take_profit(2100.0);
+        let mut grid = GridBot::new(&paper_trader, config.clone(), 2000.0).unwrap();
+        grid.sync().await.unwrap();
+        let sell = |price: f64| ("SELL".to_string(), price);
+        let buy = |price: f64| ("BUY".to_string(), price);
+        assert_eq!(open_orders(&paper_trader).await, vec![buy(1900.0), buy(1950.0), sell(2050.0), sell(2100.0)]);
+        let saved = serde_json::to_string(grid.state()).unwrap();
+
+        // The filled buy is sold one line up, which closes a round trip
+        trade(&paper_trader, 1949.0, true);
+        forward(&mut grid, &mut events).await;
+        assert_eq!(grid.state().position, 1.0);
+        assert_eq!(open_orders(&paper_trader).await, vec![buy(1900.0), sell(2000.0), sell(2050.0), sell(2100.0)]);
+        trade(&paper_trader, 2001.0, false);
+        forward(&mut grid, &mut events).await;
+        assert_eq!((grid.state().realized_profit, grid.state().round_trips, grid.state().position), (50.0, 1, 0.0));
+        assert_eq!(open_orders(&paper_trader).await, vec![buy(1900.0), buy(1950.0), sell(2050.0), sell(2100.0)]);
+
+        // Restarted from the state saved before the fills, the history brings it up to date
+        let mut history: Vec<Order> = Vec::new();
+        for (line, level) in grid.state().levels.iter().enumerate() {
+            let (Some(side), Some(grid_order)) = (level.side, &level.order) else { continue };
+            let client_order_id = config.client_order_id(line, side, level.placed - 1);
+            history.push(order(grid_order.order_id, &client_order_id, &side.to_string().to_uppercase(), level.price, "NEW", 0.0));
+        }
+        assert_eq!(history[1].client_order_id, "eth-1-b1");
+        let saved: GridState = serde_json::from_str(&saved).unwrap();
+        let first_buy = saved.levels[1].order.as_ref().unwrap().order_id;
+        history.push(order(first_buy, "eth-1-b0", "BUY", 1950.0, "FILLED", 1.0));
+        let first_sell = history.iter().map(|order| order.order_id).max().unwrap() - 1;
+        history.push(order(first_sell, "eth-2-s0", "SELL", 2000.0, "FILLED", 1.0));
+        // Some other order of the account on the same side and price is left alone
+        history.push(order(first_sell + 100, "manual", "SELL", 2000.0, "NEW", 0.0));
+        let mut grid = GridBot::resume(&paper_trader, config.clone(), saved, &history).unwrap();
+        grid.sync().await.unwrap();
+        assert_eq!(grid.state().open_orders(), 4);
+        assert_eq!((grid.state().realized_profit, grid.state().position), (50.0, 0.0));
+        assert_eq!(open_orders(&paper_trader).await, vec![buy(1900.0), buy(1950.0), sell(2050.0), sell(2100.0)]);
+
+        let other = GridConfig::new("eth", &eth_usdt(), 1900.0, 2200.0, 5, 1.0);
+        assert!(GridBot::resume(&paper_trader, other, grid.state().clone(), &[]).is_err());
+        let unfit_name = GridConfig::new("eth grid", &eth_usdt(), 1900.0, 2100.0, 5, 1.0);
+        assert_eq!(GridBot::new(&paper_trader, unfit_name, 2000.0).err().unwrap().kind(), ErrorKind::InvalidInput);
+        let long_name = GridConfig::new("an-eth-grid-with-a-long-name", &eth_usdt(), 1900.0, 2100.0, 5, 1.0);
+        assert!(GridBot::new(&paper_trader, long_name, 2000.0).is_err());
+
+        // The stop loss cancels the ladder, then sells what the grid holds
+        trade(&paper_trader, 1949.0, true);
+        forward(&mut grid, &mut events).await;
+        set_book(&paper_trader, 1919.0, 1921.0);
+        grid.on_price(1920.0).await.unwrap();
+        assert_eq!((grid.state().position, grid.state().open_orders()), (1.0, 4));
+        forward(&mut grid, &mut events).await;
+        assert_eq!(grid.state().status, GridStatus::Stopped(StopReason::StopLoss));
+        assert_eq!((grid.state().position, grid.state().open_orders()), (0.0, 0));
+        assert!(open_orders(&paper_trader).await.is_empty());
+        assert_eq!(paper_trader.free_balance("ETH"), 2.0);
+        assert_eq!(paper_trader.free_balance("USDT"), 100000.0 - 1950.0 + 2000.0 - 1950.0 + 1919.0);
+    }
+}
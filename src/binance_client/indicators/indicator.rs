@@ -0,0 +1,243 @@
+use std::collections::VecDeque;
+use crate::binance_client::streams::kline_data::Kline;
+
+/// The prices and volume of one candle, as indicators see it.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Bar {
+    pub start_time: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64, // In the base asset
+}
+
+impl Bar {
+    /// A bar of a raw price series, without range or volume.
+    pub fn price(price: f64) -> Self {
+        Bar { start_time: 0, open: price, high: price, low: price, close: price, volume: 0.0 }
+    }
+}
+
+impl From<&Kline> for Bar {
+    fn from(kline: &Kline) -> Self {
+        Bar {
+            start_time: kline.start_time,
+            open: kline.open_price,
+            high: kline.high_price,
+            low: kline.low_price,
+            close: kline.close_price,
+            volume: kline.base_asset_volume,
+        }
+    }
+}
+
+/// An indicator formula, fed one closed bar at a time.
+///
+/// Implementations keep only what the next bar needs, so a clone is a snapshot of
+/// the calculation that [`Indicator`] can go back to.
+pub trait Calculation: Clone {
+    type Output: Clone;
+
+    /// Takes the next bar, returning the indicator's value once enough bars were seen.
+    fn next(&mut self, bar: &Bar) -> Option<Self::Output>;
+}
+
+/// Streams a [`Calculation`] over klines, including the candle still in progress.
+///
+/// Updates of a kline with the same start time replace each other: the calculation goes
+/// back to where it was before that candle and takes the latest version instead. Once
+/// the next candle starts, the last version seen of the previous one is kept. The values
+/// are therefore the same as [`calculate`] over the closed klines, however many in-progress
+/// updates arrived in between. Klines older than the current candle are ignored.
+#[derive(Debug, Clone)]
+pub struct Indicator<C: Calculation> {
+    initial: C,
+    calculation: C,
+    before: C, // The calculation before the current candle
+    start_time: Option<u64>,
+    value: Option<C::Output>,
+}
+
+impl<C: Calculation> Indicator<C> {
+    pub fn new(calculation: C) -> Self {
+        Indicator { initial: calculation.clone(), before: calculation.clone(), calculation, start_time: None, value: None }
+    }
+
+    /// Takes a closed or in-progress kline and returns the indicator's current value.
+    pub fn update(&mut self, kline: &Kline) -> Option<C::Output> {
+        match self.start_time {
+            Some(start_time) if kline.start_time == start_time => self.calculation = self.before.clone(),
+            Some(start_time) if kline.start_time < start_time => return self.value.clone(),
+            _ => {
+                self.before = self.calculation.clone();
+                self.start_time = Some(kline.start_time);
+            }
+        }
+        self.value = self.calculation.next(&Bar::from(kline));
+        self.value.clone()
+    }
+
+    /// Takes the next value of a raw price series, which is always final.
+    pub fn update_price(&mut self, price: f64) -> Option<C::Output> {
+        self.start_time = None;
+        self.value = self.calculation.next(&Bar::price(price));
+        self.before = self.calculation.clone();
+        self.value.clone()
+    }
+
+    pub fn value(&self) -> Option<&C::Output> {
+        self.value.as_ref()
+    }
+
+    /// Forgets all bars, e.g. after a gap in the data.
+    pub fn reset(&mut self) {
+        *self = Indicator::new(self.initial.clone());
+    }
+}
+
+/// The value of `calculation` at every kline, `None` until enough klines were seen.
+pub fn calculate<C: Calculation>(mut calculation: C, klines: &[Kline]) -> Vec<Option<C::Output>> {
+    klines.iter().map(|kline| calculation.next(&Bar::from(kline))).collect()
+}
+
+/// The value of `calculation` at every price of a raw price series.
+pub fn calculate_prices<C: Calculation>(mut calculation: C, prices: &[f64]) -> Vec<Option<C::Output>> {
+    prices.iter().map(|price| calculation.next(&Bar::price(*price))).collect()
+}
+
+/// The last `period` values of a series.
+#[derive(Debug, Clone)]
+pub(crate) struct Window {
+    values: VecDeque<f64>,
+    period: usize,
+}
+
+impl Window {
+    pub(crate) fn new(period: usize) -> Self {
+        let period = period.max(1);
+        Window { values: VecDeque::with_capacity(period + 1), period }
+    }
+
+    /// Adds a value, returning whether the window is full.
+    pub(crate) fn push(&mut self, value: f64) -> bool {
+        self.values.push_back(value);
+        if self.values.len() > self.period {
+            self.values.pop_front();
+        }
+        self.is_full()
+    }
+
+    pub(crate) fn is_full(&self) -> bool {
+        self.values.len() == self.period
+    }
+
+    pub(crate) fn iter(&self) -> impl DoubleEndedIterator<Item = &f64> + ExactSizeIterator {
+        self.values.iter()
+    }
+
+    // Summed in a fixed order, so the result does not depend on earlier values
+    pub(crate) fn mean(&self) -> f64 {
+        self.values.iter().sum::<f64>() / self.values.len() as f64
+    }
+
+    pub(crate) fn max(&self) -> f64 {
+        self.values.iter().copied().fold(f64::MIN, f64::max)
+    }
+
+    pub(crate) fn min(&self) -> f64 {
+        self.values.iter().copied().fold(f64::MAX, f64::min)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binance_client::indicators::moving_average::{Ema, Sma, Wma};
+    use crate::binance_client::indicators::oscillators::{Macd, Rsi, Stochastic};
+    use crate::binance_client::indicators::trend::Adx;
+    use crate::binance_client::indicators::volatility::{Atr, BollingerBands, DonchianChannels, KeltnerChannels};
+    use crate::binance_client::indicators::volume::{Obv, Vwap};
+
+    const MINUTE: u64 = 60_000;
+
+    fn kline(index: u64, close: f64, is_kline_closed: bool) -> Kline {
+        Kline {
+            start_time: index * MINUTE,
+            end_time: (index + 1) * MINUTE - 1,
+            symbol: "ETHUSDT".to_string(),
+            interval: "1m".to_string(),
+            first_trade_id: 0,
+            last_trade_id: 0,
+            open_price: close - 1.0,
+            close_price: close,
+            high_price: close + 2.0,
+            low_price: close - 3.0,
+            base_asset_volume: 1.0 + (index % 4) as f64,
+            number_of_trades: 1,
+            is_kline_closed,
+            quote_asset_volume: close,
+            taker_buy_base_asset_volume: 0.5,
+            taker_buy_quote_asset_volume: close / 2.0,
+            ignore: "0".to_string(),
+        }
+    }
+
+    fn closed_klines() -> Vec<Kline> {
+        (0..60).map(|index| kline(index, 100.0 + (index as f64 * 0.7).sin() * 10.0 + index as f64 * 0.1, true)).collect()
+    }
+
+    // Streams each kline as a few in-progress updates before the closed one
+    fn assert_streams_like_batch<C>(calculation: C)
+    where
+        C: Calculation,
+        C::Output: PartialEq + std::fmt::Debug,
+    {
+        let klines = closed_klines();
+        let expected = calculate(calculation.clone(), &klines);
+        let mut indicator = Indicator::new(calculation);
+        for (closed, expected) in klines.iter().zip(expected) {
+            for offset in [5.0, -7.0, 0.5] {
+                indicator.update(&kline(closed.start_time / MINUTE, closed.close_price + offset, false));
+            }
+            assert_eq!(indicator.update(closed), expected);
+            assert_eq!(indicator.value(), expected.as_ref());
+            // A repeated closed kline changes nothing
+            assert_eq!(indicator.update(closed), expected);
+        }
+    }
+
+    #[test]
+    fn test_streaming_matches_batch() {
+        assert_streams_like_batch(Sma::new(5));
+        assert_streams_like_batch(Ema::new(5));
+        assert_streams_like_batch(Wma::new(5));
+        assert_streams_like_batch(Rsi::new(14));
+        assert_streams_like_batch(Macd::default());
+        assert_streams_like_batch(BollingerBands::default());
+        assert_streams_like_batch(Atr::new(14));
+        assert_streams_like_batch(Adx::new(14));
+        assert_streams_like_batch(Stochastic::new(14, 3));
+        assert_streams_like_batch(Obv::new());
+        assert_streams_like_batch(Vwap::new().with_session(std::time::Duration::from_secs(600)));
+        assert_streams_like_batch(KeltnerChannels::default());
+        assert_streams_like_batch(DonchianChannels::new(20));
+    }
+
+    #[test]
+    fn test_indicator_updates() {
+        let mut sma = Indicator::new(Sma::new(2));
+        assert_eq!(sma.update(&kline(0, 10.0, true)), None);
+        assert_eq!(sma.update(&kline(1, 30.0, false)), Some(20.0));
+        assert_eq!(sma.update(&kline(1, 20.0, false)), Some(15.0));
+        // A late update of the previous candle is ignored
+        assert_eq!(sma.update(&kline(0, 50.0, true)), Some(15.0));
+        assert_eq!(sma.update(&kline(2, 40.0, false)), Some(30.0));
+
+        sma.reset();
+        assert_eq!(sma.value(), None);
+        assert_eq!(sma.update_price(4.0), None);
+        assert_eq!(sma.update_price(6.0), Some(5.0));
+        assert_eq!(calculate_prices(Sma::new(2), &[4.0, 6.0]), vec![None, Some(5.0)]);
+    }
+}
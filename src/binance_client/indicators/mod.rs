@@ -0,0 +1,6 @@
+pub mod indicator;
+pub mod moving_average;
+pub mod oscillators;
+pub mod volatility;
+pub mod trend;
+pub mod volume;
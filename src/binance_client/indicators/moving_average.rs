@@ -0,0 +1,100 @@
+use crate::binance_client::indicators::indicator::{Bar, Calculation, Window};
+
+/// Simple moving average of the close over `period` bars.
+#[derive(Debug, Clone)]
+pub struct Sma {
+    window: Window,
+}
+
+impl Sma {
+    pub fn new(period: usize) -> Self {
+        Sma { window: Window::new(period) }
+    }
+
+    pub(crate) fn next_value(&mut self, value: f64) -> Option<f64> {
+        self.window.push(value).then(|| self.window.mean())
+    }
+}
+
+impl Calculation for Sma {
+    type Output = f64;
+
+    fn next(&mut self, bar: &Bar) -> Option<f64> {
+        self.next_value(bar.close)
+    }
+}
+
+/// Exponential moving average of the close, weighting each bar by `2 / (period + 1)`.
+///
+/// Starts from the simple average of the first `period` bars, like most charting tools.
+#[derive(Debug, Clone)]
+pub struct Ema {
+    alpha: f64,
+    seed: Window,
+    value: Option<f64>,
+}
+
+impl Ema {
+    pub fn new(period: usize) -> Self {
+        Ema { alpha: 2.0 / (period.max(1) as f64 + 1.0), seed: Window::new(period), value: None }
+    }
+
+    pub(crate) fn next_value(&mut self, value: f64) -> Option<f64> {
+        self.value = match self.value {
+            Some(previous) => Some(previous + self.alpha * (value - previous)),
+            None => self.seed.push(value).then(|| self.seed.mean()),
+        };
+        self.value
+    }
+}
+
+impl Calculation for Ema {
+    type Output = f64;
+
+    fn next(&mut self, bar: &Bar) -> Option<f64> {
+        self.next_value(bar.close)
+    }
+}
+
+/// Linearly weighted moving average of the close, the latest bar weighing `period`.
+#[derive(Debug, Clone)]
+pub struct Wma {
+    window: Window,
+}
+
+impl Wma {
+    pub fn new(period: usize) -> Self {
+        Wma { window: Window::new(period) }
+    }
+}
+
+impl Calculation for Wma {
+    type Output = f64;
+
+    fn next(&mut self, bar: &Bar) -> Option<f64> {
+        if !self.window.push(bar.close) {
+            return None;
+        }
+        let period = self.window.iter().len();
+        let weighted: f64 = self.window.iter().enumerate().map(|(index, value)| (index + 1) as f64 * value).sum();
+        let weights = (period * (period + 1) / 2) as f64;
+        Some(weighted / weights)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binance_client::indicators::indicator::calculate_prices;
+
+    #[test]
+    fn test_moving_averages() {
+        let prices = [1.0, 2.0, 3.0, 4.0, 5.0];
+        assert_eq!(calculate_prices(Sma::new(3), &prices), vec![None, None, Some(2.0), Some(3.0), Some(4.0)]);
+        // Seeded with the average 2.0, then halfway towards each new price
+        assert_eq!(calculate_prices(Ema::new(3), &prices), vec![None, None, Some(2.0), Some(3.0), Some(4.0)]);
+        assert_eq!(calculate_prices(Ema::new(3), &[2.0, 2.0, 2.0, 6.0, 2.0])[3..], [Some(4.0), Some(3.0)]);
+        // (1 * 1 + 2 * 2 + 3 * 3) / 6
+        assert_eq!(calculate_prices(Wma::new(3), &prices)[2], Some(14.0 / 6.0));
+    }
+}
@@ -0,0 +1,164 @@
+use crate::binance_client::indicators::indicator::{Bar, Calculation, Window};
+use crate::binance_client::indicators::moving_average::{Ema, Sma};
+
+/// Relative strength index of the close, between 0 and 100, with Wilder's smoothing.
+///
+/// The first average gain and loss are the simple averages of the first `period`
+/// changes, so the first value comes with bar `period + 1`.
+#[derive(Debug, Clone)]
+pub struct Rsi {
+    period: usize,
+    previous_close: Option<f64>,
+    changes: usize,
+    average_gain: f64,
+    average_loss: f64,
+}
+
+impl Rsi {
+    pub fn new(period: usize) -> Self {
+        Rsi { period: period.max(1), previous_close: None, changes: 0, average_gain: 0.0, average_loss: 0.0 }
+    }
+}
+
+impl Calculation for Rsi {
+    type Output = f64;
+
+    fn next(&mut self, bar: &Bar) -> Option<f64> {
+        let previous_close = self.previous_close.replace(bar.close)?;
+        let change = bar.close - previous_close;
+        let (gain, loss) = (change.max(0.0), (-change).max(0.0));
+        let period = self.period as f64;
+        self.changes += 1;
+        if self.changes <= self.period {
+            // Summed first, averaged once the period is complete
+            self.average_gain += gain;
+            self.average_loss += loss;
+            if self.changes < self.period {
+                return None;
+            }
+            self.average_gain /= period;
+            self.average_loss /= period;
+        } else {
+            self.average_gain = (self.average_gain * (period - 1.0) + gain) / period;
+            self.average_loss = (self.average_loss * (period - 1.0) + loss) / period;
+        }
+
+        Some(if self.average_loss == 0.0 {
+            if self.average_gain == 0.0 { 50.0 } else { 100.0 }
+        } else {
+            100.0 - 100.0 / (1.0 + self.average_gain / self.average_loss)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MacdValue {
+    pub macd: f64,
+    pub signal: f64,
+    pub histogram: f64, // MACD minus signal
+}
+
+/// Moving average convergence divergence of the close, 12, 26 and 9 bars by default.
+///
+/// The MACD line is the fast minus the slow EMA, the signal line an EMA of the MACD line.
+/// Values start once the signal line has its first value.
+#[derive(Debug, Clone)]
+pub struct Macd {
+    fast: Ema,
+    slow: Ema,
+    signal: Ema,
+}
+
+impl Macd {
+    pub fn new(fast_period: usize, slow_period: usize, signal_period: usize) -> Self {
+        Macd { fast: Ema::new(fast_period), slow: Ema::new(slow_period), signal: Ema::new(signal_period) }
+    }
+}
+
+impl Default for Macd {
+    fn default() -> Self {
+        Macd::new(12, 26, 9)
+    }
+}
+
+impl Calculation for Macd {
+    type Output = MacdValue;
+
+    fn next(&mut self, bar: &Bar) -> Option<MacdValue> {
+        let fast = self.fast.next_value(bar.close);
+        let slow = self.slow.next_value(bar.close);
+        let macd = fast? - slow?;
+        let signal = self.signal.next_value(macd)?;
+        Some(MacdValue { macd, signal, histogram: macd - signal })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StochasticValue {
+    pub k: f64,
+    pub d: f64, // Simple average of %K
+}
+
+/// Stochastic oscillator: where the close is in the range of the last `k_period` bars,
+/// from 0 at the lowest low to 100 at the highest high, and its `d_period` bar average.
+#[derive(Debug, Clone)]
+pub struct Stochastic {
+    highs: Window,
+    lows: Window,
+    d: Sma,
+}
+
+impl Stochastic {
+    pub fn new(k_period: usize, d_period: usize) -> Self {
+        Stochastic { highs: Window::new(k_period), lows: Window::new(k_period), d: Sma::new(d_period) }
+    }
+}
+
+impl Calculation for Stochastic {
+    type Output = StochasticValue;
+
+    fn next(&mut self, bar: &Bar) -> Option<StochasticValue> {
+        self.lows.push(bar.low);
+        if !self.highs.push(bar.high) {
+            return None;
+        }
+        let (highest, lowest) = (self.highs.max(), self.lows.min());
+        // A flat range puts the close in the middle
+        let k = if highest > lowest { 100.0 * (bar.close - lowest) / (highest - lowest) } else { 50.0 };
+        let d = self.d.next_value(k)?;
+        Some(StochasticValue { k, d })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binance_client::indicators::indicator::calculate_prices;
+
+    #[test]
+    fn test_rsi() {
+        let values = calculate_prices(Rsi::new(2), &[10.0, 11.0, 12.0, 11.0, 11.0]);
+        assert_eq!(values[..3], [None, None, Some(100.0)]);
+        // Gains average 1.0, then (1.0 + 0.0) / 2 against a loss of (0.0 + 1.0) / 2
+        assert_eq!(values[3], Some(50.0));
+        // Both halve again
+        assert_eq!(values[4], Some(50.0));
+        assert_eq!(calculate_prices(Rsi::new(2), &[5.0, 5.0, 5.0])[2], Some(50.0));
+    }
+
+    #[test]
+    fn test_macd_and_stochastic() {
+        let prices: Vec<f64> = (0..5).map(f64::from).collect();
+        // A straight line keeps every EMA the same distance behind the price
+        let values = calculate_prices(Macd::new(2, 3, 2), &prices);
+        assert_eq!(values[..3], [None, None, None]);
+        assert_eq!(values[3], Some(MacdValue { macd: 0.5, signal: 0.5, histogram: 0.0 }));
+
+        let bar = |high, low, close| Bar { high, low, close, ..Default::default() };
+        let mut stochastic = Stochastic::new(2, 2);
+        assert_eq!(stochastic.next(&bar(10.0, 8.0, 9.0)), None);
+        assert_eq!(stochastic.next(&bar(12.0, 9.0, 11.0)), None);
+        // %K 75 then 100, with a range of 8 to 12 and 9 to 14
+        assert_eq!(stochastic.next(&bar(14.0, 10.0, 14.0)), Some(StochasticValue { k: 100.0, d: 87.5 }));
+    }
+}
@@ -0,0 +1,110 @@
+use crate::binance_client::indicators::indicator::{Bar, Calculation};
+use crate::binance_client::indicators::volatility::true_range;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdxValue {
+    pub adx: f64,
+    pub plus_di: f64,
+    pub minus_di: f64,
+}
+
+/// Average directional index over `period` bars, with Wilder's smoothing.
+///
+/// Directional movement starts with the second bar. The directional indicators are
+/// smoothed sums of `period` bars, and the ADX averages `period` of their DX values,
+/// so the first value comes with bar `2 * period`.
+#[derive(Debug, Clone)]
+pub struct Adx {
+    period: usize,
+    previous: Option<Bar>,
+    movements: usize,
+    true_range: f64,
+    plus_dm: f64,
+    minus_dm: f64,
+    dx_values: usize,
+    adx: f64,
+}
+
+impl Adx {
+    pub fn new(period: usize) -> Self {
+        Adx {
+            period: period.max(1),
+            previous: None,
+            movements: 0,
+            true_range: 0.0,
+            plus_dm: 0.0,
+            minus_dm: 0.0,
+            dx_values: 0,
+            adx: 0.0,
+        }
+    }
+}
+
+impl Calculation for Adx {
+    type Output = AdxValue;
+
+    fn next(&mut self, bar: &Bar) -> Option<AdxValue> {
+        let previous = self.previous.replace(*bar)?;
+        let (up, down) = (bar.high - previous.high, previous.low - bar.low);
+        let plus_dm = if up > down && up > 0.0 { up } else { 0.0 };
+        let minus_dm = if down > up && down > 0.0 { down } else { 0.0 };
+        let true_range = true_range(bar, Some(previous.close));
+
+        let period = self.period as f64;
+        self.movements += 1;
+        if self.movements <= self.period {
+            self.true_range += true_range;
+            self.plus_dm += plus_dm;
+            self.minus_dm += minus_dm;
+            if self.movements < self.period {
+                return None;
+            }
+        } else {
+            self.true_range += true_range - self.true_range / period;
+            self.plus_dm += plus_dm - self.plus_dm / period;
+            self.minus_dm += minus_dm - self.minus_dm / period;
+        }
+
+        let (plus_di, minus_di) = if self.true_range > 0.0 {
+            (100.0 * self.plus_dm / self.true_range, 100.0 * self.minus_dm / self.true_range)
+        } else {
+            (0.0, 0.0)
+        };
+        let dx = if plus_di + minus_di > 0.0 { 100.0 * (plus_di - minus_di).abs() / (plus_di + minus_di) } else { 0.0 };
+
+        self.dx_values += 1;
+        if self.dx_values <= self.period {
+            self.adx += dx;
+            if self.dx_values < self.period {
+                return None;
+            }
+            self.adx /= period;
+        } else {
+            self.adx = (self.adx * (period - 1.0) + dx) / period;
+        }
+        Some(AdxValue { adx: self.adx, plus_di, minus_di })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adx() {
+        let mut adx = Adx::new(2);
+        // Every bar one higher: all movement is up, so the trend is at full strength, while
+        // the gaps from the previous close keep +DI at 2 of 3 of the true range
+        let values: Vec<Option<AdxValue>> = (0..5)
+            .map(|index| {
+                let low = f64::from(index);
+                adx.next(&Bar { high: low + 1.0, low, close: low + 0.5, ..Default::default() })
+            })
+            .collect();
+        assert_eq!(values[..3], [None, None, None]);
+        let value = values[3].unwrap();
+        assert_eq!((value.adx, value.minus_di), (100.0, 0.0));
+        assert!((value.plus_di - 200.0 / 3.0).abs() < 1e-9);
+        assert_eq!(values[4].unwrap().adx, 100.0);
+    }
+}
@@ -0,0 +1,184 @@
+use crate::binance_client::indicators::indicator::{Bar, Calculation, Window};
+use crate::binance_client::indicators::moving_average::Ema;
+
+/// A channel around the price.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bands {
+    pub lower: f64,
+    pub middle: f64,
+    pub upper: f64,
+}
+
+/// Bollinger bands: the simple average of the close over `period` bars, plus and minus
+/// `multiplier` population standard deviations.
+#[derive(Debug, Clone)]
+pub struct BollingerBands {
+    window: Window,
+    multiplier: f64,
+}
+
+impl BollingerBands {
+    pub fn new(period: usize, multiplier: f64) -> Self {
+        BollingerBands { window: Window::new(period), multiplier }
+    }
+}
+
+impl Default for BollingerBands {
+    fn default() -> Self {
+        BollingerBands::new(20, 2.0)
+    }
+}
+
+impl Calculation for BollingerBands {
+    type Output = Bands;
+
+    fn next(&mut self, bar: &Bar) -> Option<Bands> {
+        if !self.window.push(bar.close) {
+            return None;
+        }
+        let middle = self.window.mean();
+        let variance = self.window.iter().map(|close| (close - middle).powi(2)).sum::<f64>() / self.window.iter().len() as f64;
+        let width = self.multiplier * variance.sqrt();
+        Some(Bands { lower: middle - width, middle, upper: middle + width })
+    }
+}
+
+/// Average true range over `period` bars, with Wilder's smoothing.
+///
+/// The true range includes gaps from the previous close; the first bar has none, so
+/// its range is high minus low. The first value is the simple average of `period` ranges.
+#[derive(Debug, Clone)]
+pub struct Atr {
+    period: usize,
+    previous_close: Option<f64>,
+    ranges: usize,
+    value: f64,
+}
+
+impl Atr {
+    pub fn new(period: usize) -> Self {
+        Atr { period: period.max(1), previous_close: None, ranges: 0, value: 0.0 }
+    }
+}
+
+impl Calculation for Atr {
+    type Output = f64;
+
+    fn next(&mut self, bar: &Bar) -> Option<f64> {
+        let true_range = true_range(bar, self.previous_close.replace(bar.close));
+        let period = self.period as f64;
+        self.ranges += 1;
+        if self.ranges < self.period {
+            self.value += true_range;
+            return None;
+        }
+        self.value = if self.ranges == self.period {
+            (self.value + true_range) / period
+        } else {
+            (self.value * (period - 1.0) + true_range) / period
+        };
+        Some(self.value)
+    }
+}
+
+pub(crate) fn true_range(bar: &Bar, previous_close: Option<f64>) -> f64 {
+    match previous_close {
+        Some(close) => (bar.high - bar.low).max((bar.high - close).abs()).max((bar.low - close).abs()),
+        None => bar.high - bar.low,
+    }
+}
+
+/// Keltner channels: an EMA of the close over `period` bars, plus and minus `multiplier`
+/// times the ATR over `atr_period` bars.
+#[derive(Debug, Clone)]
+pub struct KeltnerChannels {
+    middle: Ema,
+    atr: Atr,
+    multiplier: f64,
+}
+
+impl KeltnerChannels {
+    pub fn new(period: usize, atr_period: usize, multiplier: f64) -> Self {
+        KeltnerChannels { middle: Ema::new(period), atr: Atr::new(atr_period), multiplier }
+    }
+}
+
+impl Default for KeltnerChannels {
+    fn default() -> Self {
+        KeltnerChannels::new(20, 10, 2.0)
+    }
+}
+
+impl Calculation for KeltnerChannels {
+    type Output = Bands;
+
+    fn next(&mut self, bar: &Bar) -> Option<Bands> {
+        let middle = self.middle.next_value(bar.close);
+        let atr = self.atr.next(bar);
+        let (middle, width) = (middle?, self.multiplier * atr?);
+        Some(Bands { lower: middle - width, middle, upper: middle + width })
+    }
+}
+
+/// Donchian channels: the highest high and lowest low of the last `period` bars.
+#[derive(Debug, Clone)]
+pub struct DonchianChannels {
+    highs: Window,
+    lows: Window,
+}
+
+impl DonchianChannels {
+    pub fn new(period: usize) -> Self {
+        DonchianChannels { highs: Window::new(period), lows: Window::new(period) }
+    }
+}
+
+impl Calculation for DonchianChannels {
+    type Output = Bands;
+
+    fn next(&mut self, bar: &Bar) -> Option<Bands> {
+        self.lows.push(bar.low);
+        if !self.highs.push(bar.high) {
+            return None;
+        }
+        let (lower, upper) = (self.lows.min(), self.highs.max());
+        Some(Bands { lower, middle: (lower + upper) / 2.0, upper })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binance_client::indicators::indicator::calculate_prices;
+
+    fn bar(high: f64, low: f64, close: f64) -> Bar {
+        Bar { high, low, close, ..Default::default() }
+    }
+
+    #[test]
+    fn test_bollinger_bands() {
+        // Mean 5, population standard deviation 2
+        let prices = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        let values = calculate_prices(BollingerBands::new(8, 2.0), &prices);
+        assert_eq!(values[7], Some(Bands { lower: 1.0, middle: 5.0, upper: 9.0 }));
+        assert!(values[6].is_none());
+    }
+
+    #[test]
+    fn test_atr_and_channels() {
+        let bars = [bar(11.0, 9.0, 10.0), bar(14.0, 12.0, 13.0), bar(13.0, 12.0, 12.0), bar(12.0, 11.0, 11.5)];
+        let mut atr = Atr::new(2);
+        // True ranges 2, 4 (gap up from 10), 1 and 1
+        let values: Vec<Option<f64>> = bars.iter().map(|bar| atr.next(bar)).collect();
+        assert_eq!(values, vec![None, Some(3.0), Some(2.0), Some(1.5)]);
+
+        let mut keltner = KeltnerChannels::new(2, 2, 1.0);
+        let values: Vec<Option<Bands>> = bars.iter().map(|bar| keltner.next(bar)).collect();
+        assert_eq!(values[1], Some(Bands { lower: 8.5, middle: 11.5, upper: 14.5 }));
+
+        let mut donchian = DonchianChannels::new(3);
+        let values: Vec<Option<Bands>> = bars.iter().map(|bar| donchian.next(bar)).collect();
+        assert_eq!(values[2], Some(Bands { lower: 9.0, middle: 11.5, upper: 14.0 }));
+        assert_eq!(values[3], Some(Bands { lower: 11.0, middle: 12.5, upper: 14.0 }));
+    }
+}
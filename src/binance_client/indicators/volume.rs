@@ -0,0 +1,103 @@
+use std::time::Duration;
+use crate::binance_client::indicators::indicator::{Bar, Calculation};
+
+/// On-balance volume: the running total of volume, added on up closes and subtracted on
+/// down closes. Starts at zero with the first bar.
+#[derive(Debug, Clone, Default)]
+pub struct Obv {
+    previous_close: Option<f64>,
+    value: f64,
+}
+
+impl Obv {
+    pub fn new() -> Self {
+        Obv::default()
+    }
+}
+
+impl Calculation for Obv {
+    type Output = f64;
+
+    fn next(&mut self, bar: &Bar) -> Option<f64> {
+        if let Some(previous_close) = self.previous_close.replace(bar.close) {
+            if bar.close > previous_close {
+                self.value += bar.volume;
+            } else if bar.close < previous_close {
+                self.value -= bar.volume;
+            }
+        }
+        Some(self.value)
+    }
+}
+
+/// Volume weighted average of the typical price `(high + low + close) / 3`.
+///
+/// Accumulates from the first bar, or from the start of each session, e.g. each UTC day
+/// with a session of 24 hours. `None` while the accumulated volume is zero, which is
+/// always the case for raw price series.
+#[derive(Debug, Clone, Default)]
+pub struct Vwap {
+    session_ms: Option<u64>,
+    session: u64,
+    price_volume: f64,
+    volume: f64,
+}
+
+impl Vwap {
+    pub fn new() -> Self {
+        Vwap::default()
+    }
+
+    /// Starts over whenever a bar starts in a new session of this length.
+    pub fn with_session(mut self, session: Duration) -> Self {
+        self.session_ms = Some((session.as_millis() as u64).max(1));
+        self
+    }
+}
+
+impl Calculation for Vwap {
+    type Output = f64;
+
+    fn next(&mut self, bar: &Bar) -> Option<f64> {
+        if let Some(session_ms) = self.session_ms {
+            let session = bar.start_time / session_ms;
+            if session != self.session {
+                self.session = session;
+                self.price_volume = 0.0;
+                self.volume = 0.0;
+            }
+        }
+        self.price_volume += (bar.high + bar.low + bar.close) / 3.0 * bar.volume;
+        self.volume += bar.volume;
+        (self.volume > 0.0).then(|| self.price_volume / self.volume)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bar(start_time: u64, price: f64, volume: f64) -> Bar {
+        Bar { start_time, open: price, high: price, low: price, close: price, volume }
+    }
+
+    #[test]
+    fn test_obv_and_vwap() {
+        let bars = [bar(0, 10.0, 1.0), bar(60_000, 11.0, 3.0), bar(120_000, 11.0, 5.0), bar(180_000, 8.0, 2.0)];
+        let mut obv = Obv::new();
+        let values: Vec<Option<f64>> = bars.iter().map(|bar| obv.next(bar)).collect();
+        assert_eq!(values, vec![Some(0.0), Some(3.0), Some(3.0), Some(1.0)]);
+
+        let mut vwap = Vwap::new();
+        let values: Vec<Option<f64>> = bars.iter().map(|bar| vwap.next(bar)).collect();
+        assert_eq!(values[1], Some(10.75));
+        assert_eq!(values[3], Some((10.0 + 33.0 + 55.0 + 16.0) / 11.0));
+
+        // Two minute sessions start over with the third bar
+        let mut vwap = Vwap::new().with_session(Duration::from_secs(120));
+        let values: Vec<Option<f64>> = bars.iter().map(|bar| vwap.next(bar)).collect();
+        assert_eq!(values[2], Some(11.0));
+        assert_eq!(values[3], Some((55.0 + 16.0) / 7.0));
+        assert_eq!(Vwap::new().next(&Bar::price(10.0)), None);
+    }
+}
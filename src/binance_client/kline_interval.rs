@@ -0,0 +1,207 @@
+use std::fmt;
+use std::str::FromStr;
+use chrono::{DateTime, Datelike, Months, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Kline/candlestick intervals supported by Binance.
+///
+/// Used both for subscribing to `<symbol>@kline_<interval>` streams and for the
+/// `interval` parameter of the REST `/v3/klines` endpoint, so that a typo in an
+/// interval is a compile error rather than a silently rejected subscription.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum KlineInterval {
+    #[serde(rename = "1s")]
+    OneSecond,
+    #[serde(rename = "1m")]
+    OneMinute,
+    #[serde(rename = "3m")]
+    ThreeMinutes,
+    #[serde(rename = "5m")]
+    FiveMinutes,
+    #[serde(rename = "15m")]
+    FifteenMinutes,
+    #[serde(rename = "30m")]
+    ThirtyMinutes,
+    #[serde(rename = "1h")]
+    OneHour,
+    #[serde(rename = "2h")]
+    TwoHours,
+    #[serde(rename = "4h")]
+    FourHours,
+    #[serde(rename = "6h")]
+    SixHours,
+    #[serde(rename = "8h")]
+    EightHours,
+    #[serde(rename = "12h")]
+    TwelveHours,
+    #[serde(rename = "1d")]
+    OneDay,
+    #[serde(rename = "3d")]
+    ThreeDays,
+    #[serde(rename = "1w")]
+    OneWeek,
+    #[serde(rename = "1M")]
+    OneMonth,
+}
+
+impl KlineInterval {
+    pub const ALL: [KlineInterval; 16] = [
+        KlineInterval::OneSecond,
+        KlineInterval::OneMinute,
+        KlineInterval::ThreeMinutes,
+        KlineInterval::FiveMinutes,
+        KlineInterval::FifteenMinutes,
+        KlineInterval::ThirtyMinutes,
+        KlineInterval::OneHour,
+        KlineInterval::TwoHours,
+        KlineInterval::FourHours,
+        KlineInterval::SixHours,
+        KlineInterval::EightHours,
+        KlineInterval::TwelveHours,
+        KlineInterval::OneDay,
+        KlineInterval::ThreeDays,
+        KlineInterval::OneWeek,
+        KlineInterval::OneMonth,
+    ];
+
+    /// The interval as Binance spells it, e.g. `"1m"` or `"1M"`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KlineInterval::OneSecond => "1s",
+            KlineInterval::OneMinute => "1m",
+            KlineInterval::ThreeMinutes => "3m",
+            KlineInterval::FiveMinutes => "5m",
+            KlineInterval::FifteenMinutes => "15m",
+            KlineInterval::ThirtyMinutes => "30m",
+            KlineInterval::OneHour => "1h",
+            KlineInterval::TwoHours => "2h",
+            KlineInterval::FourHours => "4h",
+            KlineInterval::SixHours => "6h",
+            KlineInterval::EightHours => "8h",
+            KlineInterval::TwelveHours => "12h",
+            KlineInterval::OneDay => "1d",
+            KlineInterval::ThreeDays => "3d",
+            KlineInterval::OneWeek => "1w",
+            KlineInterval::OneMonth => "1M",
+        }
+    }
+
+    /// Length of one candle in milliseconds.
+    ///
+    /// Returns `None` for `1M`, whose length depends on the calendar month.
+    pub fn duration_ms(&self) -> Option<u64> {
+        const SECOND: u64 = 1_000;
+        const MINUTE: u64 = 60 * SECOND;
+        const HOUR: u64 = 60 * MINUTE;
+        const DAY: u64 = 24 * HOUR;
+        match self {
+            KlineInterval::OneSecond => Some(SECOND),
+            KlineInterval::OneMinute => Some(MINUTE),
+            KlineInterval::ThreeMinutes => Some(3 * MINUTE),
+            KlineInterval::FiveMinutes => Some(5 * MINUTE),
+            KlineInterval::FifteenMinutes => Some(15 * MINUTE),
+            KlineInterval::ThirtyMinutes => Some(30 * MINUTE),
+            KlineInterval::OneHour => Some(HOUR),
+            KlineInterval::TwoHours => Some(2 * HOUR),
+            KlineInterval::FourHours => Some(4 * HOUR),
+            KlineInterval::SixHours => Some(6 * HOUR),
+            KlineInterval::EightHours => Some(8 * HOUR),
+            KlineInterval::TwelveHours => Some(12 * HOUR),
+            KlineInterval::OneDay => Some(DAY),
+            KlineInterval::ThreeDays => Some(3 * DAY),
+            KlineInterval::OneWeek => Some(7 * DAY),
+            KlineInterval::OneMonth => None,
+        }
+    }
+
+    /// Open time of the candle containing `time`, in milliseconds.
+    ///
+    /// Candles are aligned to the Unix epoch, except weekly ones, which open on Monday
+    /// 00:00 UTC, and monthly ones, which open on the first day of the month.
+    pub fn open_time(&self, time: u64) -> u64 {
+        // The epoch was a Thursday, the first Monday four days later
+        const FIRST_MONDAY: u64 = 4 * 24 * 60 * 60 * 1000;
+        match (self, self.duration_ms()) {
+            (KlineInterval::OneWeek, Some(duration)) if time >= FIRST_MONDAY => time - (time - FIRST_MONDAY) % duration,
+            (_, Some(duration)) => time - time % duration,
+            (_, None) => DateTime::from_timestamp_millis(time as i64)
+                .and_then(|time| Utc.with_ymd_and_hms(time.year(), time.month(), 1, 0, 0, 0).single())
+                .map(|month| month.timestamp_millis() as u64)
+                .expect("Kline open time out of range"),
+        }
+    }
+
+    /// Open time of the candle following the one opened at `open_time`, in milliseconds.
+    pub fn next_open_time(&self, open_time: u64) -> u64 {
+        match self.duration_ms() {
+            Some(duration) => open_time + duration,
+            None => DateTime::from_timestamp_millis(open_time as i64)
+                .and_then(|time| time.checked_add_months(Months::new(1)))
+                .map(|time| time.timestamp_millis() as u64)
+                .expect("Kline open time out of range"),
+        }
+    }
+}
+
+impl fmt::Display for KlineInterval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for KlineInterval {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        KlineInterval::ALL
+            .iter()
+            .find(|interval| interval.as_str() == s)
+            .copied()
+            .ok_or_else(|| format!("Unknown kline interval: {}", s))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interval_round_trip() {
+        for interval in KlineInterval::ALL {
+            assert_eq!(interval.as_str().parse::<KlineInterval>().unwrap(), interval);
+            let json = serde_json::to_string(&interval).unwrap();
+            assert_eq!(json, format!("\"{}\"", interval));
+        }
+    }
+
+    #[test]
+    fn test_minute_and_month_are_distinct() {
+        assert_eq!("1m".parse::<KlineInterval>().unwrap(), KlineInterval::OneMinute);
+        assert_eq!("1M".parse::<KlineInterval>().unwrap(), KlineInterval::OneMonth);
+        assert!("2m".parse::<KlineInterval>().is_err());
+    }
+
+    #[test]
+    fn test_duration_ms() {
+        assert_eq!(KlineInterval::OneMinute.duration_ms(), Some(60_000));
+        assert_eq!(KlineInterval::OneWeek.duration_ms(), Some(604_800_000));
+        assert_eq!(KlineInterval::OneMonth.duration_ms(), None);
+    }
+
+    #[test]
+    fn test_next_open_time() {
+        assert_eq!(KlineInterval::FiveMinutes.next_open_time(1_700_000_100_000), 1_700_000_400_000);
+        // 2024-01-01, 2024-02-01 and 2024-03-01 UTC, across a leap February
+        assert_eq!(KlineInterval::OneMonth.next_open_time(1_704_067_200_000), 1_706_745_600_000);
+        assert_eq!(KlineInterval::OneMonth.next_open_time(1_706_745_600_000), 1_709_251_200_000);
+    }
+
+    #[test]
+    fn test_open_time() {
+        assert_eq!(KlineInterval::FifteenMinutes.open_time(1_700_000_160_000), 1_700_000_100_000);
+        // 2017-07-05 12:00 UTC falls in the week opened on Monday 2017-07-03
+        assert_eq!(KlineInterval::OneWeek.open_time(1_499_256_000_000), 1_499_040_000_000);
+        assert_eq!(KlineInterval::OneMonth.open_time(1_709_251_200_000 - 1), 1_706_745_600_000);
+    }
+}
@@ -0,0 +1,483 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{Error as IOError, ErrorKind};
+use crate::binance_client::account::portfolio::PriceBook;
+use crate::binance_client::account::trades::Trade;
+use crate::binance_client::exchange_info::ExchangeInfo;
+use crate::binance_client::order_types::side::Side;
+use crate::binance_client::streams::user_data::{ExecutionReport, ExecutionType};
+
+// Lots smaller than this are float dust left over from closing
+const DUST: f64 = 1e-12;
+
+/// Which lots a sell closes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LotMethod {
+    Fifo,
+    Lifo,
+    AverageCost,
+}
+
+/// Prices used to value commissions and to convert PnL into the reporting currency.
+///
+/// [`PriceBook`] gives current prices, which is fine for live trading. When replaying
+/// old trades, pass a source with historical prices to value commissions at trade time.
+pub trait PriceSource {
+    fn price_in(&self, asset: &str, quote_asset: &str) -> Option<f64>;
+}
+
+impl PriceSource for PriceBook {
+    fn price_in(&self, asset: &str, quote_asset: &str) -> Option<f64> {
+        PriceBook::price_in(self, asset, quote_asset)
+    }
+}
+
+/// A single fill, from `myTrades` history or a live `executionReport`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Execution {
+    pub symbol: String,
+    pub order_id: i64,
+    pub trade_id: i64,
+    pub side: Side,
+    pub price: f64,
+    pub qty: f64,
+    pub commission: f64,
+    pub commission_asset: String,
+    pub time: u64,
+    pub is_maker: bool,
+}
+
+impl From<&Trade> for Execution {
+    fn from(trade: &Trade) -> Self {
+        Execution {
+            symbol: trade.symbol.clone(),
+            order_id: trade.order_id,
+            trade_id: trade.id,
+            side: if trade.is_buyer { Side::Buy } else { Side::Sell },
+            price: trade.price,
+            qty: trade.qty,
+            commission: trade.commission,
+            commission_asset: trade.commission_asset.clone(),
+            time: trade.time,
+            is_maker: trade.is_maker,
+        }
+    }
+}
+
+impl Execution {
+    /// The fill carried by an execution report, or `None` if the report is not a trade.
+    pub fn from_report(report: &ExecutionReport) -> Option<Self> {
+        if report.execution_type != ExecutionType::Trade {
+            return None;
+        }
+        Some(Execution {
+            symbol: report.symbol.clone(),
+            order_id: report.order_id,
+            trade_id: report.trade_id,
+            side: report.side,
+            price: report.last_executed_price,
+            qty: report.last_executed_qty,
+            commission: report.commission,
+            commission_asset: report.commission_asset.clone().unwrap_or_default(),
+            time: report.transaction_time,
+            is_maker: report.is_maker,
+        })
+    }
+}
+
+/// Quantity bought at one price. The price includes the commission paid to buy it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lot {
+    pub qty: f64,
+    pub price: f64,
+    pub time: u64,
+}
+
+/// The open lots and realized result of one symbol.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Position {
+    pub symbol: String,
+    pub base_asset: String,
+    pub quote_asset: String,
+    pub lots: VecDeque<Lot>,
+    pub realized_pnl: f64, // In the quote asset, net of commissions
+    pub realized_pnl_reporting: f64, // In the ledger's reporting currency
+    pub fees_reporting: f64, // Every commission paid, in the reporting currency
+    pub unmatched_sell_qty: f64, // Sold without a known buy, e.g. coins held before the history starts
+}
+
+impl Position {
+    fn new(symbol: &str, base_asset: &str, quote_asset: &str) -> Self {
+        Position {
+            symbol: symbol.to_string(),
+            base_asset: base_asset.to_string(),
+            quote_asset: quote_asset.to_string(),
+            lots: VecDeque::new(),
+            realized_pnl: 0.0,
+            realized_pnl_reporting: 0.0,
+            fees_reporting: 0.0,
+            unmatched_sell_qty: 0.0,
+        }
+    }
+
+    pub fn quantity(&self) -> f64 {
+        self.lots.iter().map(|lot| lot.qty).sum()
+    }
+
+    /// What the open lots cost in the quote asset, commissions included.
+    pub fn cost_basis(&self) -> f64 {
+        self.lots.iter().map(|lot| lot.qty * lot.price).sum()
+    }
+
+    pub fn average_price(&self) -> Option<f64> {
+        let quantity = self.quantity();
+        (quantity > DUST).then(|| self.cost_basis() / quantity)
+    }
+
+    /// Unrealized PnL of the open lots at `price`, in the quote asset.
+    pub fn unrealized_pnl(&self, price: f64) -> f64 {
+        self.quantity() * price - self.cost_basis()
+    }
+
+    fn add_lot(&mut self, lot: Lot, method: LotMethod) {
+        match (method, self.lots.front_mut()) {
+            (LotMethod::AverageCost, Some(average)) => {
+                let qty = average.qty + lot.qty;
+                average.price = (average.qty * average.price + lot.qty * lot.price) / qty;
+                average.qty = qty;
+                average.time = lot.time;
+            }
+            _ => self.lots.push_back(lot),
+        }
+    }
+
+    // Closes up to `qty` and returns the closed quantity and its cost
+    fn close_lots(&mut self, mut qty: f64, method: LotMethod) -> (f64, f64) {
+        let mut closed_qty = 0.0;
+        let mut closed_cost = 0.0;
+        while qty > DUST {
+            let lot = match method {
+                LotMethod::Fifo | LotMethod::AverageCost => self.lots.front_mut(),
+                LotMethod::Lifo => self.lots.back_mut(),
+            };
+            let Some(lot) = lot else { break };
+
+            let take = lot.qty.min(qty);
+            closed_qty += take;
+            closed_cost += take * lot.price;
+            lot.qty -= take;
+            qty -= take;
+
+            if lot.qty <= DUST {
+                match method {
+                    LotMethod::Fifo | LotMethod::AverageCost => self.lots.pop_front(),
+                    LotMethod::Lifo => self.lots.pop_back(),
+                };
+            }
+        }
+        (closed_qty, closed_cost)
+    }
+}
+
+/// One row of [`Ledger::report`], with money in the reporting currency.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionReport {
+    pub symbol: String,
+    pub quantity: f64,
+    pub average_price: Option<f64>, // In the quote asset
+    pub market_price: Option<f64>, // In the quote asset
+    pub realized_pnl: f64,
+    pub unrealized_pnl: Option<f64>, // None when the symbol cannot be priced
+    pub fees: f64,
+}
+
+/// Turns fills into per-symbol positions and realized and unrealized PnL.
+///
+/// Commissions are folded into the result: a commission paid in the base asset on a buy
+/// reduces the quantity received, any other commission on a buy raises the lot's cost,
+/// and a commission on a sell reduces the proceeds. Commissions paid in a third asset,
+/// such as BNB, are valued through the [`PriceSource`] given to [`Ledger::apply`].
+///
+/// Fills are keyed on symbol and trade id, so history and live reports that overlap are
+/// only counted once.
+#[derive(Debug, Clone)]
+pub struct Ledger {
+    pub reporting_currency: String,
+    pub method: LotMethod,
+    symbols: HashMap<String, (String, String)>,
+    positions: HashMap<String, Position>,
+    seen_trades: HashSet<(String, i64)>,
+}
+
+impl Ledger {
+    pub fn new(reporting_currency: &str, method: LotMethod) -> Self {
+        Ledger {
+            reporting_currency: reporting_currency.to_string(),
+            method,
+            symbols: HashMap::new(),
+            positions: HashMap::new(),
+            seen_trades: HashSet::new(),
+        }
+    }
+
+    /// Registers the base and quote asset of a symbol. Fills of unknown symbols are rejected.
+    pub fn add_symbol(&mut self, symbol: &str, base_asset: &str, quote_asset: &str) {
+        self.symbols
+            .insert(symbol.to_string(), (base_asset.to_string(), quote_asset.to_string()));
+    }
+
+    pub fn add_symbols_from(&mut self, exchange_info: &ExchangeInfo) {
+        for info in &exchange_info.symbols {
+            self.add_symbol(&info.symbol, &info.base_asset, &info.quote_asset);
+        }
+    }
+
+    pub fn position(&self, symbol: &str) -> Option<&Position> {
+        self.positions.get(symbol)
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = &Position> {
+        self.positions.values()
+    }
+
+    /// Applies `myTrades` history, oldest first.
+    pub fn apply_trades(&mut self, trades: &[Trade], prices: &dyn PriceSource) -> Result<(), IOError> {
+        let mut executions: Vec<Execution> = trades.iter().map(Execution::from).collect();
+        executions.sort_by_key(|execution| (execution.time, execution.trade_id));
+        for execution in &executions {
+            self.apply(execution, prices)?;
+        }
+        Ok(())
+    }
+
+    /// Applies a live execution report. Reports that are not trades are ignored.
+    pub fn apply_report(&mut self, report: &ExecutionReport, prices: &dyn PriceSource) -> Result<bool, IOError> {
+        match Execution::from_report(report) {
+            Some(execution) => self.apply(&execution, prices),
+            None => Ok(false),
+        }
+    }
+
+    /// Applies one fill. Returns `false` if the fill had already been applied.
+    pub fn apply(&mut self, execution: &Execution, prices: &dyn PriceSource) -> Result<bool, IOError> {
+        let (base_asset, quote_asset) = self.symbols.get(&execution.symbol).cloned().ok_or_else(|| {
+            IOError::new(ErrorKind::InvalidInput, format!("Unknown symbol {}", execution.symbol))
+        })?;
+        if self.seen_trades.contains(&(execution.symbol.clone(), execution.trade_id)) {
+            return Ok(false);
+        }
+
+        let commission_in_base = execution.commission_asset == base_asset;
+        let fee_quote = if execution.commission == 0.0 || execution.commission_asset == quote_asset {
+            execution.commission
+        } else if commission_in_base {
+            execution.commission * execution.price
+        } else {
+            execution.commission * self.price(prices, &execution.commission_asset, &quote_asset)?
+        };
+        let quote_to_reporting = self.price(prices, &quote_asset, &self.reporting_currency)?;
+
+        let method = self.method;
+        let position = self
+            .positions
+            .entry(execution.symbol.clone())
+            .or_insert_with(|| Position::new(&execution.symbol, &base_asset, &quote_asset));
+        position.fees_reporting += fee_quote * quote_to_reporting;
+
+        match execution.side {
+            Side::Buy => {
+                let (qty, cost) = if commission_in_base {
+                    (execution.qty - execution.commission, execution.qty * execution.price)
+                } else {
+                    (execution.qty, execution.qty * execution.price + fee_quote)
+                };
+                if qty > DUST {
+                    position.add_lot(Lot { qty, price: cost / qty, time: execution.time }, method);
+                } else {
+                    // The commission took the whole fill, so what it cost is a fee rather than a lot
+                    position.fees_reporting += (cost - fee_quote) * quote_to_reporting;
+                    position.realized_pnl -= cost;
+                    position.realized_pnl_reporting -= cost * quote_to_reporting;
+                }
+            }
+            Side::Sell => {
+                let (qty, proceeds) = if commission_in_base {
+                    (execution.qty + execution.commission, execution.qty * execution.price)
+                } else {
+                    (execution.qty, execution.qty * execution.price - fee_quote)
+                };
+                let (closed_qty, closed_cost) = position.close_lots(qty, method);
+                let pnl = proceeds * (closed_qty / qty) - closed_cost;
+                position.realized_pnl += pnl;
+                position.realized_pnl_reporting += pnl * quote_to_reporting;
+                position.unmatched_sell_qty += qty - closed_qty;
+            }
+        }
+
+        self.seen_trades.insert((execution.symbol.clone(), execution.trade_id));
+        Ok(true)
+    }
+
+    /// Realized PnL of all symbols in the reporting currency.
+    pub fn realized_pnl(&self) -> f64 {
+        self.positions.values().map(|position| position.realized_pnl_reporting).sum()
+    }
+
+    /// Unrealized PnL of all open lots at current prices, in the reporting currency.
+    pub fn unrealized_pnl(&self, prices: &dyn PriceSource) -> Result<f64, IOError> {
+        self.report(prices)
+            .into_iter()
+            .map(|row| {
+                row.unrealized_pnl
+                    .ok_or_else(|| IOError::new(ErrorKind::NotFound, format!("No price for {}", row.symbol)))
+            })
+            .sum()
+    }
+
+    /// One row per symbol, sorted by symbol.
+    pub fn report(&self, prices: &dyn PriceSource) -> Vec<PositionReport> {
+        let mut rows: Vec<PositionReport> = self
+            .positions
+            .values()
+            .map(|position| {
+                let market_price = prices.price_in(&position.base_asset, &position.quote_asset);
+                let to_reporting = prices.price_in(&position.quote_asset, &self.reporting_currency);
+                let unrealized_pnl = match (market_price, to_reporting) {
+                    (Some(price), Some(rate)) => Some(position.unrealized_pnl(price) * rate),
+                    // Nothing open, nothing to price
+                    _ if position.quantity() <= DUST => Some(0.0),
+                    _ => None,
+                };
+                PositionReport {
+                    symbol: position.symbol.clone(),
+                    quantity: position.quantity(),
+                    average_price: position.average_price(),
+                    market_price,
+                    realized_pnl: position.realized_pnl_reporting,
+                    unrealized_pnl,
+                    fees: position.fees_reporting,
+                }
+            })
+            .collect();
+        rows.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        rows
+    }
+
+    fn price(&self, prices: &dyn PriceSource, asset: &str, quote_asset: &str) -> Result<f64, IOError> {
+        if asset == quote_asset {
+            return Ok(1.0);
+        }
+        prices
+            .price_in(asset, quote_asset)
+            .ok_or_else(|| IOError::new(ErrorKind::NotFound, format!("No price for {} in {}", asset, quote_asset)))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binance_client::ticker_price::TickerPrice;
+
+    fn prices(eth_usdt: f64) -> PriceBook {
+        PriceBook::new(&[
+            TickerPrice { symbol: "ETHUSDT".to_string(), price: eth_usdt },
+            TickerPrice { symbol: "BNBUSDT".to_string(), price: 500.0 },
+            TickerPrice { symbol: "EURUSDT".to_string(), price: 1.25 },
+        ])
+    }
+
+    fn fill(trade_id: i64, side: Side, qty: f64, price: f64) -> Execution {
+        Execution {
+            symbol: "ETHUSDT".to_string(),
+            order_id: trade_id,
+            trade_id,
+            side,
+            price,
+            qty,
+            commission: 0.0,
+            commission_asset: "USDT".to_string(),
+            time: trade_id as u64,
+            is_maker: false,
+        }
+    }
+
+    fn ledger(method: LotMethod) -> Ledger {
+        let mut ledger = Ledger::new("USDT", method);
+        ledger.add_symbol("ETHUSDT", "ETH", "USDT");
+        let book = prices(0.0);
+        ledger.apply(&fill(1, Side::Buy, 1.0, 100.0), &book).unwrap();
+        ledger.apply(&fill(2, Side::Buy, 1.0, 200.0), &book).unwrap();
+        ledger.apply(&fill(3, Side::Sell, 1.0, 250.0), &book).unwrap();
+        ledger
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn test_lot_methods() {
+        assert_close(ledger(LotMethod::Fifo).realized_pnl(), 150.0);
+        assert_close(ledger(LotMethod::Lifo).realized_pnl(), 50.0);
+        assert_close(ledger(LotMethod::AverageCost).realized_pnl(), 100.0);
+
+        let fifo = ledger(LotMethod::Fifo);
+        let position = fifo.position("ETHUSDT").unwrap();
+        assert_close(position.quantity(), 1.0);
+        assert_close(position.average_price().unwrap(), 200.0);
+        assert_close(fifo.unrealized_pnl(&prices(300.0)).unwrap(), 100.0);
+    }
+
+    #[test]
+    fn test_buy_eaten_by_commission() {
+        let mut ledger = Ledger::new("USDT", LotMethod::Fifo);
+        ledger.add_symbol("ETHUSDT", "ETH", "USDT");
+        let book = prices(100.0);
+        ledger.apply(&fill(1, Side::Buy, 1.0, 100.0), &book).unwrap();
+        let dust = Execution { commission: 0.001, commission_asset: "ETH".to_string(), ..fill(2, Side::Buy, 0.001, 100.0) };
+        ledger.apply(&dust, &book).unwrap();
+
+        let position = ledger.position("ETHUSDT").unwrap();
+        assert_eq!(position.lots.len(), 1);
+        assert_close(position.average_price().unwrap(), 100.0);
+        assert_close(position.fees_reporting, 0.1);
+        assert_close(ledger.realized_pnl(), -0.1);
+    }
+
+    #[test]
+    fn test_commissions_and_reporting_currency() {
+        let mut ledger = Ledger::new("EUR", LotMethod::Fifo);
+        ledger.add_symbol("ETHUSDT", "ETH", "USDT");
+        let book = prices(100.0);
+
+        // Paid in ETH: 0.01 fewer coins for the same cost
+        let buy = Execution { commission: 0.01, commission_asset: "ETH".to_string(), ..fill(1, Side::Buy, 1.0, 100.0) };
+        // Paid in BNB: 0.002 BNB at 500 USDT costs 1 USDT
+        let sell = Execution { commission: 0.002, commission_asset: "BNB".to_string(), ..fill(2, Side::Sell, 0.99, 120.0) };
+        ledger.apply(&buy, &book).unwrap();
+        ledger.apply(&sell, &book).unwrap();
+
+        let position = ledger.position("ETHUSDT").unwrap();
+        assert!(position.lots.is_empty());
+        // 0.99 * 120 - 1 - 100
+        assert_close(position.realized_pnl, 17.8);
+        assert_close(ledger.realized_pnl(), 17.8 / 1.25);
+        assert_close(position.fees_reporting, 2.0 / 1.25);
+    }
+
+    #[test]
+    fn test_duplicates_unknown_symbols_and_unmatched_sells() {
+        let mut ledger = Ledger::new("USDT", LotMethod::Fifo);
+        let book = prices(100.0);
+        assert!(ledger.apply(&fill(1, Side::Buy, 1.0, 100.0), &book).is_err());
+
+        ledger.add_symbol("ETHUSDT", "ETH", "USDT");
+        assert!(ledger.apply(&fill(1, Side::Buy, 1.0, 100.0), &book).unwrap());
+        assert!(!ledger.apply(&fill(1, Side::Buy, 1.0, 100.0), &book).unwrap());
+
+        ledger.apply(&fill(2, Side::Sell, 3.0, 130.0), &book).unwrap();
+        let position = ledger.position("ETHUSDT").unwrap();
+        assert_close(position.unmatched_sell_qty, 2.0);
+        assert_close(position.realized_pnl, 30.0);
+    }
+}
@@ -2,6 +2,7 @@ use std::collections::HashMap;
 use std::{env, hash};
 use dotenv::dotenv;
 use uuid::Uuid;
+use crate::binance_client::database_config::DatabaseConfig;
 
 #[derive(Debug)]
 pub struct EnvVars {
@@ -26,4 +27,9 @@ impl EnvVars {
             api_secret: env::var("TEST_NET_API_SECRET").expect("TEST_NET_API_SECRET must be set"),
         }
     }
-}
\ No newline at end of file
+
+    /// Connection settings for the test database on the local server.
+    pub fn database_config(&self) -> DatabaseConfig {
+        DatabaseConfig::new(&self.name, &self.user, &self.pwd)
+    }
+}
@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+
+/// Order book snapshot returned by `GET /api/v3/depth`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DepthSnapshot {
+    pub last_update_id: u64,
+    pub bids: Vec<PriceLevel>, // Best bid first
+    pub asks: Vec<PriceLevel>, // Best ask first
+}
+
+/// One price level of the order book, sent by Binance as a `["price", "quantity"]` pair.
+#[serde_as]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct PriceLevel {
+    #[serde_as(as = "DisplayFromStr")]
+    pub price: f64,
+    #[serde_as(as = "DisplayFromStr")]
+    pub quantity: f64,
+}
+
+/// A public trade returned by `GET /api/v3/trades` and `GET /api/v3/historicalTrades`.
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MarketTrade {
+    pub id: i64,
+    #[serde_as(as = "DisplayFromStr")]
+    pub price: f64,
+    #[serde_as(as = "DisplayFromStr")]
+    pub qty: f64,
+    #[serde_as(as = "DisplayFromStr")]
+    pub quote_qty: f64,
+    pub time: u64,
+    pub is_buyer_maker: bool,
+    pub is_best_match: bool,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_depth_snapshot() {
+        let json = r#"{
+            "lastUpdateId": 1027024,
+            "bids": [["4.00000000", "431.00000000"]],
+            "asks": [["4.00000200", "12.00000000"], ["4.00000300", "1.50000000"]]
+        }"#;
+        let depth: DepthSnapshot = serde_json::from_str(json).unwrap();
+        assert_eq!(depth.last_update_id, 1027024);
+        assert_eq!(depth.bids[0], PriceLevel { price: 4.0, quantity: 431.0 });
+        assert_eq!(depth.asks[1].quantity, 1.5);
+    }
+
+    #[test]
+    fn test_deserialize_market_trade() {
+        let json = r#"[{
+            "id": 28457,
+            "price": "4.00000100",
+            "qty": "12.00000000",
+            "quoteQty": "48.000012",
+            "time": 1499865549590,
+            "isBuyerMaker": true,
+            "isBestMatch": true
+        }]"#;
+        let trades: Vec<MarketTrade> = serde_json::from_str(json).unwrap();
+        assert_eq!(trades[0].id, 28457);
+        assert_eq!(trades[0].quote_qty, 48.000012);
+    }
+}
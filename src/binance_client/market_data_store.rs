@@ -0,0 +1,665 @@
+use std::collections::HashMap;
+use std::io::{Error as IOError, ErrorKind};
+use std::num::ParseFloatError;
+use std::time::Duration;
+use chrono::{DateTime, NaiveDate};
+use futures::pin_mut;
+use log::{info, warn};
+use tokio_postgres::binary_copy::BinaryCopyInWriter;
+use tokio_postgres::error::SqlState;
+use tokio_postgres::types::{ToSql, Type};
+use tokio_postgres::Row;
+use crate::binance_client::database_client::{db_error, quote_identifier, DatabaseClient, DAY_MS};
+use crate::binance_client::market_data::DepthSnapshot;
+use crate::binance_client::streams::agg_trade_stream::AggTradeData;
+use crate::binance_client::streams::book_ticker_stream::BookTickerData;
+use crate::binance_client::streams::partial_depth_stream::PartialDepthData;
+use crate::binance_client::streams::trade_stream::TradeData;
+
+/// The day-partitioned market data tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MarketDataTable {
+    Trades,
+    AggTrades,
+    BookSnapshots,
+    BookTicks,
+}
+
+impl MarketDataTable {
+    pub const ALL: [MarketDataTable; 4] = [
+        MarketDataTable::Trades,
+        MarketDataTable::AggTrades,
+        MarketDataTable::BookSnapshots,
+        MarketDataTable::BookTicks,
+    ];
+
+    pub fn table_name(&self) -> &'static str {
+        match self {
+            MarketDataTable::Trades => "market_trades",
+            MarketDataTable::AggTrades => "market_agg_trades",
+            MarketDataTable::BookSnapshots => "book_snapshots",
+            MarketDataTable::BookTicks => "book_tickers",
+        }
+    }
+
+    /// The millisecond timestamp column the table is partitioned on.
+    pub fn time_column(&self) -> &'static str {
+        match self {
+            MarketDataTable::Trades | MarketDataTable::AggTrades => "trade_time",
+            MarketDataTable::BookSnapshots => "snapshot_time",
+            MarketDataTable::BookTicks => "time",
+        }
+    }
+}
+
+/// Keep today's partition of `table` and those of the `keep_days` days before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    pub table: MarketDataTable,
+    pub keep_days: u32,
+}
+
+impl RetentionPolicy {
+    pub fn new(table: MarketDataTable, keep_days: u32) -> Self {
+        RetentionPolicy { table, keep_days }
+    }
+}
+
+/// A print of the `<symbol>@trade` stream as stored in `market_trades`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradePrint {
+    pub symbol: String,
+    pub trade_id: i64,
+    pub price: f64,
+    pub quantity: f64,
+    pub buyer_order_id: i64,
+    pub seller_order_id: i64,
+    pub trade_time: i64,
+    pub is_buyer_maker: bool,
+}
+
+impl TryFrom<&TradeData> for TradePrint {
+    type Error = ParseFloatError;
+
+    fn try_from(trade: &TradeData) -> Result<Self, Self::Error> {
+        Ok(TradePrint {
+            symbol: trade.symbol.clone(),
+            trade_id: trade.trade_id as i64,
+            price: trade.price.parse()?,
+            quantity: trade.quantity.parse()?,
+            buyer_order_id: trade.buyer_order_id as i64,
+            seller_order_id: trade.seller_order_id as i64,
+            trade_time: trade.trade_time as i64,
+            is_buyer_maker: trade.is_market_maker,
+        })
+    }
+}
+
+impl From<&Row> for TradePrint {
+    fn from(row: &Row) -> Self {
+        TradePrint {
+            symbol: row.get("symbol"),
+            trade_id: row.get("trade_id"),
+            price: row.get("price"),
+            quantity: row.get("quantity"),
+            buyer_order_id: row.get("buyer_order_id"),
+            seller_order_id: row.get("seller_order_id"),
+            trade_time: row.get("trade_time"),
+            is_buyer_maker: row.get("is_buyer_maker"),
+        }
+    }
+}
+
+/// A print of the `<symbol>@aggTrade` stream as stored in `market_agg_trades`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AggTradePrint {
+    pub symbol: String,
+    pub agg_trade_id: i64,
+    pub price: f64,
+    pub quantity: f64,
+    pub first_trade_id: i64,
+    pub last_trade_id: i64,
+    pub trade_time: i64,
+    pub is_buyer_maker: bool,
+}
+
+impl TryFrom<&AggTradeData> for AggTradePrint {
+    type Error = ParseFloatError;
+
+    fn try_from(trade: &AggTradeData) -> Result<Self, Self::Error> {
+        Ok(AggTradePrint {
+            symbol: trade.symbol.clone(),
+            agg_trade_id: trade.aggregate_trade_id as i64,
+            price: trade.price.parse()?,
+            quantity: trade.quantity.parse()?,
+            first_trade_id: trade.first_trade_id as i64,
+            last_trade_id: trade.last_trade_id as i64,
+            trade_time: trade.trade_time as i64,
+            is_buyer_maker: trade.is_market_maker,
+        })
+    }
+}
+
+impl From<&Row> for AggTradePrint {
+    fn from(row: &Row) -> Self {
+        AggTradePrint {
+            symbol: row.get("symbol"),
+            agg_trade_id: row.get("agg_trade_id"),
+            price: row.get("price"),
+            quantity: row.get("quantity"),
+            first_trade_id: row.get("first_trade_id"),
+            last_trade_id: row.get("last_trade_id"),
+            trade_time: row.get("trade_time"),
+            is_buyer_maker: row.get("is_buyer_maker"),
+        }
+    }
+}
+
+/// The top levels of an order book at one moment, as stored in `book_snapshots`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookSnapshot {
+    pub symbol: String,
+    pub snapshot_time: i64,
+    pub last_update_id: i64,
+    pub bid_prices: Vec<f64>, // Best bid first
+    pub bid_quantities: Vec<f64>,
+    pub ask_prices: Vec<f64>, // Best ask first
+    pub ask_quantities: Vec<f64>,
+}
+
+impl BookSnapshot {
+    /// The best `depth` levels of a partial depth stream message received at `time`.
+    pub fn from_partial_depth(symbol: &str, time: u64, depth_data: &PartialDepthData, depth: usize) -> Result<Self, ParseFloatError> {
+        let parse = |levels: Vec<(&String, &String)>| -> Result<(Vec<f64>, Vec<f64>), ParseFloatError> {
+            let mut prices = Vec::with_capacity(levels.len());
+            let mut quantities = Vec::with_capacity(levels.len());
+            for (price, quantity) in levels {
+                prices.push(price.parse()?);
+                quantities.push(quantity.parse()?);
+            }
+            Ok((prices, quantities))
+        };
+        let (bid_prices, bid_quantities) = parse(depth_data.bids.iter().take(depth).map(|bid| (&bid.price, &bid.quantity)).collect())?;
+        let (ask_prices, ask_quantities) = parse(depth_data.asks.iter().take(depth).map(|ask| (&ask.price, &ask.quantity)).collect())?;
+
+        Ok(BookSnapshot {
+            symbol: symbol.to_uppercase(),
+            snapshot_time: time as i64,
+            last_update_id: depth_data.last_update_id as i64,
+            bid_prices,
+            bid_quantities,
+            ask_prices,
+            ask_quantities,
+        })
+    }
+
+    /// The best `depth` levels of a REST `/v3/depth` snapshot taken at `time`.
+    pub fn from_depth(symbol: &str, time: u64, depth_snapshot: &DepthSnapshot, depth: usize) -> Self {
+        let bids = &depth_snapshot.bids[..depth.min(depth_snapshot.bids.len())];
+        let asks = &depth_snapshot.asks[..depth.min(depth_snapshot.asks.len())];
+        BookSnapshot {
+            symbol: symbol.to_uppercase(),
+            snapshot_time: time as i64,
+            last_update_id: depth_snapshot.last_update_id as i64,
+            bid_prices: bids.iter().map(|level| level.price).collect(),
+            bid_quantities: bids.iter().map(|level| level.quantity).collect(),
+            ask_prices: asks.iter().map(|level| level.price).collect(),
+            ask_quantities: asks.iter().map(|level| level.quantity).collect(),
+        }
+    }
+}
+
+impl From<&Row> for BookSnapshot {
+    fn from(row: &Row) -> Self {
+        BookSnapshot {
+            symbol: row.get("symbol"),
+            snapshot_time: row.get("snapshot_time"),
+            last_update_id: row.get("last_update_id"),
+            bid_prices: row.get("bid_prices"),
+            bid_quantities: row.get("bid_quantities"),
+            ask_prices: row.get("ask_prices"),
+            ask_quantities: row.get("ask_quantities"),
+        }
+    }
+}
+
+/// A best bid and ask update from `<symbol>@bookTicker`, as stored in `book_tickers`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookTick {
+    pub symbol: String,
+    pub update_id: i64,
+    pub time: i64, // When the update was received
+    pub bid_price: f64,
+    pub bid_quantity: f64,
+    pub ask_price: f64,
+    pub ask_quantity: f64,
+}
+
+impl BookTick {
+    pub fn from_book_ticker(book_ticker: &BookTickerData, received_time: u64) -> Result<Self, ParseFloatError> {
+        Ok(BookTick {
+            symbol: book_ticker.symbol.clone(),
+            update_id: book_ticker.update_id as i64,
+            time: received_time as i64,
+            bid_price: book_ticker.best_bid_price.parse()?,
+            bid_quantity: book_ticker.best_bid_qty.parse()?,
+            ask_price: book_ticker.best_ask_price.parse()?,
+            ask_quantity: book_ticker.best_ask_qty.parse()?,
+        })
+    }
+}
+
+impl From<&Row> for BookTick {
+    fn from(row: &Row) -> Self {
+        BookTick {
+            symbol: row.get("symbol"),
+            update_id: row.get("update_id"),
+            time: row.get("time"),
+            bid_price: row.get("bid_price"),
+            bid_quantity: row.get("bid_quantity"),
+            ask_price: row.get("ask_price"),
+            ask_quantity: row.get("ask_quantity"),
+        }
+    }
+}
+
+/// Turns a partial depth stream into one [`BookSnapshot`] per symbol and period.
+///
+/// `@depth20@100ms` sends ten books a second; research rarely needs more than one a
+/// second or minute, and storing every message would dwarf the trade tables.
+#[derive(Debug, Clone)]
+pub struct BookSnapshotSampler {
+    period_ms: u64,
+    depth: usize,
+    last_period: HashMap<String, u64>,
+}
+
+impl BookSnapshotSampler {
+    pub fn new(period: Duration, depth: usize) -> Self {
+        BookSnapshotSampler {
+            period_ms: (period.as_millis() as u64).max(1),
+            depth,
+            last_period: HashMap::new(),
+        }
+    }
+
+    /// The snapshot to store for a message received at `time`, if its period has none yet.
+    pub fn sample(&mut self, symbol: &str, time: u64, depth_data: &PartialDepthData) -> Option<BookSnapshot> {
+        let period = time / self.period_ms;
+        if self.last_period.get(symbol) == Some(&period) {
+            return None;
+        }
+        match BookSnapshot::from_partial_depth(symbol, time, depth_data, self.depth) {
+            Ok(snapshot) => {
+                self.last_period.insert(symbol.to_string(), period);
+                Some(snapshot)
+            }
+            Err(e) => {
+                warn!("Failed to parse {} depth levels: {}", symbol, e);
+                None
+            }
+        }
+    }
+}
+
+// A row written with binary COPY into one of the partitioned tables
+trait CopyRow {
+    const TABLE: MarketDataTable;
+    const COLUMNS: &'static [&'static str];
+    const TYPES: &'static [Type];
+
+    fn time(&self) -> i64;
+    fn values(&self) -> Vec<&(dyn ToSql + Sync)>;
+}
+
+impl CopyRow for TradePrint {
+    const TABLE: MarketDataTable = MarketDataTable::Trades;
+    const COLUMNS: &'static [&'static str] = &[
+        "symbol", "trade_id", "price", "quantity", "buyer_order_id", "seller_order_id", "trade_time", "is_buyer_maker",
+    ];
+    const TYPES: &'static [Type] = &[
+        Type::VARCHAR, Type::INT8, Type::FLOAT8, Type::FLOAT8, Type::INT8, Type::INT8, Type::INT8, Type::BOOL,
+    ];
+
+    fn time(&self) -> i64 {
+        self.trade_time
+    }
+
+    fn values(&self) -> Vec<&(dyn ToSql + Sync)> {
+        vec![
+            &self.symbol, &self.trade_id, &self.price, &self.quantity,
+            &self.buyer_order_id, &self.seller_order_id, &self.trade_time, &self.is_buyer_maker,
+        ]
+    }
+}
+
+impl CopyRow for AggTradePrint {
+    const TABLE: MarketDataTable = MarketDataTable::AggTrades;
+    const COLUMNS: &'static [&'static str] = &[
+        "symbol", "agg_trade_id", "price", "quantity", "first_trade_id", "last_trade_id", "trade_time", "is_buyer_maker",
+    ];
+    const TYPES: &'static [Type] = &[
+        Type::VARCHAR, Type::INT8, Type::FLOAT8, Type::FLOAT8, Type::INT8, Type::INT8, Type::INT8, Type::BOOL,
+    ];
+
+    fn time(&self) -> i64 {
+        self.trade_time
+    }
+
+    fn values(&self) -> Vec<&(dyn ToSql + Sync)> {
+        vec![
+            &self.symbol, &self.agg_trade_id, &self.price, &self.quantity,
+            &self.first_trade_id, &self.last_trade_id, &self.trade_time, &self.is_buyer_maker,
+        ]
+    }
+}
+
+impl CopyRow for BookSnapshot {
+    const TABLE: MarketDataTable = MarketDataTable::BookSnapshots;
+    const COLUMNS: &'static [&'static str] = &[
+        "symbol", "snapshot_time", "last_update_id", "bid_prices", "bid_quantities", "ask_prices", "ask_quantities",
+    ];
+    const TYPES: &'static [Type] = &[
+        Type::VARCHAR, Type::INT8, Type::INT8, Type::FLOAT8_ARRAY, Type::FLOAT8_ARRAY, Type::FLOAT8_ARRAY, Type::FLOAT8_ARRAY,
+    ];
+
+    fn time(&self) -> i64 {
+        self.snapshot_time
+    }
+
+    fn values(&self) -> Vec<&(dyn ToSql + Sync)> {
+        vec![
+            &self.symbol, &self.snapshot_time, &self.last_update_id,
+            &self.bid_prices, &self.bid_quantities, &self.ask_prices, &self.ask_quantities,
+        ]
+    }
+}
+
+impl CopyRow for BookTick {
+    const TABLE: MarketDataTable = MarketDataTable::BookTicks;
+    const COLUMNS: &'static [&'static str] = &[
+        "symbol", "update_id", "time", "bid_price", "bid_quantity", "ask_price", "ask_quantity",
+    ];
+    const TYPES: &'static [Type] = &[
+        Type::VARCHAR, Type::INT8, Type::INT8, Type::FLOAT8, Type::FLOAT8, Type::FLOAT8, Type::FLOAT8,
+    ];
+
+    fn time(&self) -> i64 {
+        self.time
+    }
+
+    fn values(&self) -> Vec<&(dyn ToSql + Sync)> {
+        vec![
+            &self.symbol, &self.update_id, &self.time,
+            &self.bid_price, &self.bid_quantity, &self.ask_price, &self.ask_quantity,
+        ]
+    }
+}
+
+impl DatabaseClient {
+    /// Stores trade prints, skipping ones already stored. Returns how many were new.
+    pub async fn insert_trade_prints(&self, trades: &[TradePrint]) -> Result<u64, IOError> {
+        self.copy_rows(trades).await
+    }
+
+    /// Stores `@trade` stream messages, see [`DatabaseClient::insert_trade_prints`].
+    pub async fn insert_trade_data(&self, trades: &[TradeData]) -> Result<u64, IOError> {
+        let prints = trades
+            .iter()
+            .map(TradePrint::try_from)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| IOError::new(ErrorKind::InvalidData, format!("Invalid trade price or quantity: {}", e)))?;
+        self.insert_trade_prints(&prints).await
+    }
+
+    /// Stores aggregated trade prints, skipping ones already stored. Returns how many were new.
+    pub async fn insert_agg_trade_prints(&self, trades: &[AggTradePrint]) -> Result<u64, IOError> {
+        self.copy_rows(trades).await
+    }
+
+    /// Stores `@aggTrade` stream messages, see [`DatabaseClient::insert_agg_trade_prints`].
+    pub async fn insert_agg_trade_data(&self, trades: &[AggTradeData]) -> Result<u64, IOError> {
+        let prints = trades
+            .iter()
+            .map(AggTradePrint::try_from)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| IOError::new(ErrorKind::InvalidData, format!("Invalid aggregate trade price or quantity: {}", e)))?;
+        self.insert_agg_trade_prints(&prints).await
+    }
+
+    pub async fn insert_book_snapshots(&self, snapshots: &[BookSnapshot]) -> Result<u64, IOError> {
+        self.copy_rows(snapshots).await
+    }
+
+    pub async fn insert_book_ticks(&self, ticks: &[BookTick]) -> Result<u64, IOError> {
+        self.copy_rows(ticks).await
+    }
+
+    /// Trade prints of `symbol` in `[start_time, end_time)`, oldest first.
+    pub async fn trade_prints(&self, symbol: &str, start_time: u64, end_time: u64) -> Result<Vec<TradePrint>, IOError> {
+        let rows = self.rows_between(MarketDataTable::Trades, symbol, start_time, end_time, "trade_id").await?;
+        Ok(rows.iter().map(TradePrint::from).collect())
+    }
+
+    /// Aggregated trade prints of `symbol` in `[start_time, end_time)`, oldest first.
+    pub async fn agg_trade_prints(&self, symbol: &str, start_time: u64, end_time: u64) -> Result<Vec<AggTradePrint>, IOError> {
+        let rows = self.rows_between(MarketDataTable::AggTrades, symbol, start_time, end_time, "agg_trade_id").await?;
+        Ok(rows.iter().map(AggTradePrint::from).collect())
+    }
+
+    pub async fn book_snapshots(&self, symbol: &str, start_time: u64, end_time: u64) -> Result<Vec<BookSnapshot>, IOError> {
+        let rows = self.rows_between(MarketDataTable::BookSnapshots, symbol, start_time, end_time, "last_update_id").await?;
+        Ok(rows.iter().map(BookSnapshot::from).collect())
+    }
+
+    pub async fn book_ticks(&self, symbol: &str, start_time: u64, end_time: u64) -> Result<Vec<BookTick>, IOError> {
+        let rows = self.rows_between(MarketDataTable::BookTicks, symbol, start_time, end_time, "update_id").await?;
+        Ok(rows.iter().map(BookTick::from).collect())
+    }
+
+    /// Creates the daily partitions of `table` covering `[start_time, end_time]` that are missing.
+    pub async fn ensure_partitions(&self, table: MarketDataTable, start_time: u64, end_time: u64) -> Result<(), IOError> {
+        let mut day = start_time - start_time % DAY_MS;
+        let mut client = None;
+        while day <= end_time {
+            let name = partition_name(table, day);
+            if !self.partitions.lock().unwrap().contains(&name) {
+                if client.is_none() {
+                    client = Some(self.client().await?);
+                }
+                let stmt = format!(
+                    "CREATE TABLE IF NOT EXISTS {} PARTITION OF {} FOR VALUES FROM ({}) TO ({})",
+                    quote_identifier(&name), table.table_name(), day, day + DAY_MS,
+                );
+                if let Err(e) = client.as_ref().unwrap().batch_execute(&stmt).await {
+                    // Another writer created it since IF NOT EXISTS checked
+                    if e.code() != Some(&SqlState::DUPLICATE_TABLE) && e.code() != Some(&SqlState::UNIQUE_VIOLATION) {
+                        return Err(db_error(e));
+                    }
+                }
+                self.partitions.lock().unwrap().insert(name);
+            }
+            day += DAY_MS;
+        }
+        Ok(())
+    }
+
+    /// Names of the daily partitions of `table`, oldest first.
+    pub async fn partitions(&self, table: MarketDataTable) -> Result<Vec<String>, IOError> {
+        let rows = self.client().await?.query("
+    SELECT child.relname::TEXT FROM pg_inherits
+    JOIN pg_class child ON child.oid = pg_inherits.inhrelid
+    JOIN pg_class parent ON parent.oid = pg_inherits.inhparent
+    WHERE parent.relname = $1
+    ORDER BY child.relname
+    ", &[&table.table_name()]).await.map_err(db_error)?;
+        Ok(rows.iter().map(|row| row.get(0)).collect())
+    }
+
+    /// Drops the partitions older than each policy allows and returns their names.
+    pub async fn apply_retention(&self, policies: &[RetentionPolicy], now: u64) -> Result<Vec<String>, IOError> {
+        let mut dropped = Vec::new();
+        for policy in policies {
+            let cutoff = (now - now % DAY_MS).saturating_sub(policy.keep_days as u64 * DAY_MS);
+            for name in self.partitions(policy.table).await? {
+                match partition_day(policy.table, &name) {
+                    Some(day) if day < cutoff => {
+                        let stmt = format!("DROP TABLE IF EXISTS {}", quote_identifier(&name));
+                        self.client().await?.batch_execute(&stmt).await.map_err(db_error)?;
+                        self.partitions.lock().unwrap().remove(&name);
+                        info!("Dropped partition {} under retention", name);
+                        dropped.push(name);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(dropped)
+    }
+
+    // Copies rows into a staging table, then moves the new ones into their partitions
+    async fn copy_rows<R: CopyRow>(&self, rows: &[R]) -> Result<u64, IOError> {
+        let Some(first) = rows.iter().map(R::time).min() else { return Ok(0) };
+        let last = rows.iter().map(R::time).max().unwrap_or(first);
+        self.ensure_partitions(R::TABLE, first as u64, last as u64).await?;
+
+        let table = R::TABLE.table_name();
+        let columns = R::COLUMNS.join(", ");
+        let mut client = self.client().await?;
+        let result = async {
+            // Rolled back, dropping the staging table with it, when dropped without a commit
+            let transaction = client.transaction().await?;
+            transaction
+                .batch_execute(&format!("CREATE TEMP TABLE {table}_staging (LIKE {table}) ON COMMIT DROP"))
+                .await?;
+            let sink = transaction.copy_in(&format!("COPY {table}_staging ({columns}) FROM STDIN BINARY")).await?;
+            let writer = BinaryCopyInWriter::new(sink, R::TYPES);
+            pin_mut!(writer);
+            for row in rows {
+                writer.as_mut().write(&row.values()).await?;
+            }
+            writer.finish().await?;
+
+            let inserted = transaction
+                .execute(&format!("INSERT INTO {table} ({columns}) SELECT {columns} FROM {table}_staging ON CONFLICT DO NOTHING"), &[])
+                .await?;
+            transaction.commit().await?;
+            Ok(inserted)
+        }.await;
+        result.map_err(db_error)
+    }
+
+    async fn rows_between(&self, table: MarketDataTable, symbol: &str, start_time: u64, end_time: u64, order_by: &str) -> Result<Vec<Row>, IOError> {
+        let time = table.time_column();
+        let stmt = format!(
+            "SELECT * FROM {} WHERE symbol = $1 AND {time} >= $2 AND {time} < $3 ORDER BY {time}, {}",
+            table.table_name(), order_by,
+        );
+        self.client().await?
+            .query(&stmt, &[&symbol, &(start_time as i64), &(end_time as i64)])
+            .await
+            .map_err(db_error)
+    }
+}
+
+// Daily partitions are named after their table and UTC day, e.g. `market_trades_p20240131`
+fn partition_name(table: MarketDataTable, day: u64) -> String {
+    let date = DateTime::from_timestamp_millis(day as i64).expect("Partition day out of range");
+    format!("{}_p{}", table.table_name(), date.format("%Y%m%d"))
+}
+
+// Start of the day a partition holds, in milliseconds
+fn partition_day(table: MarketDataTable, name: &str) -> Option<u64> {
+    let suffix = name.strip_prefix(table.table_name())?.strip_prefix("_p")?;
+    let date = NaiveDate::parse_from_str(suffix, "%Y%m%d").ok()?;
+    Some(date.and_hms_opt(0, 0, 0)?.and_utc().timestamp_millis() as u64)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binance_client::load_env::EnvVars;
+    use crate::binance_client::streams::partial_depth_stream::PartialDepthMessage;
+
+    // 2024-01-31 00:00 UTC
+    const DAY: u64 = 1_706_659_200_000;
+
+    fn trade(trade_id: i64, trade_time: u64) -> TradePrint {
+        TradePrint {
+            symbol: "BTCUSDT".to_string(),
+            trade_id,
+            price: 42_000.0,
+            quantity: 0.01,
+            buyer_order_id: 1,
+            seller_order_id: 2,
+            trade_time: trade_time as i64,
+            is_buyer_maker: false,
+        }
+    }
+
+    #[test]
+    fn test_partition_names() {
+        assert_eq!(partition_name(MarketDataTable::Trades, DAY), "market_trades_p20240131");
+        assert_eq!(partition_day(MarketDataTable::Trades, "market_trades_p20240131"), Some(DAY));
+        // Agg trade partitions are not trade partitions
+        assert_eq!(partition_day(MarketDataTable::Trades, "market_agg_trades_p20240131"), None);
+        assert_eq!(partition_day(MarketDataTable::Trades, "market_trades_default"), None);
+    }
+
+    #[test]
+    fn test_book_snapshot_sampler() {
+        let message: PartialDepthMessage = serde_json::from_str(r#"{
+            "stream": "bnbbtc@depth5@100ms",
+            "data": {"lastUpdateId": 160, "bids": [["0.0024", "10"], ["0.0023", "5"]], "asks": [["0.0026", "100"]]}
+        }"#).unwrap();
+        let mut sampler = BookSnapshotSampler::new(Duration::from_secs(1), 1);
+
+        let snapshot = sampler.sample("bnbbtc", DAY + 100, &message.data).expect("First message is sampled");
+        assert_eq!(snapshot.symbol, "BNBBTC");
+        assert_eq!(snapshot.bid_prices, vec![0.0024]);
+        assert_eq!(snapshot.ask_quantities, vec![100.0]);
+
+        assert!(sampler.sample("bnbbtc", DAY + 900, &message.data).is_none());
+        assert!(sampler.sample("ethbtc", DAY + 900, &message.data).is_some());
+        assert!(sampler.sample("bnbbtc", DAY + 1_000, &message.data).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_partitioned_trades_and_retention() {
+        let vars = EnvVars::new();
+        let db = DatabaseClient::connect_or_create_if_not_exist(&vars.database_config()).await.expect("Failed to setup database");
+
+        let trades = vec![trade(1, DAY + 1_000), trade(2, DAY + 2_000), trade(3, DAY + DAY_MS + 1_000)];
+        assert_eq!(db.insert_trade_prints(&trades).await.unwrap(), 3);
+        // Replayed prints are skipped
+        assert_eq!(db.insert_trade_prints(&trades[1..]).await.unwrap(), 0);
+        assert_eq!(db.partitions(MarketDataTable::Trades).await.unwrap(), vec!["market_trades_p20240131", "market_trades_p20240201"]);
+        assert_eq!(db.trade_prints("BTCUSDT", DAY, DAY + DAY_MS).await.unwrap(), trades[..2].to_vec());
+
+        let ticks = vec![BookTick {
+            symbol: "BTCUSDT".to_string(),
+            update_id: 400900217,
+            time: (DAY + 5) as i64,
+            bid_price: 41_999.0,
+            bid_quantity: 1.5,
+            ask_price: 42_001.0,
+            ask_quantity: 2.0,
+        }];
+        assert_eq!(db.insert_book_ticks(&ticks).await.unwrap(), 1);
+        assert_eq!(db.book_ticks("BTCUSDT", DAY, DAY + 10).await.unwrap(), ticks);
+
+        // Keeping one day on February 2nd drops January 31st only
+        let policies = [RetentionPolicy::new(MarketDataTable::Trades, 1)];
+        let dropped = db.apply_retention(&policies, DAY + 2 * DAY_MS + 60_000).await.unwrap();
+        assert_eq!(dropped, vec!["market_trades_p20240131"]);
+        assert_eq!(db.trade_prints("BTCUSDT", 0, u32::MAX as u64 * 1_000).await.unwrap(), trades[2..].to_vec());
+
+        // The partition comes back when older prints arrive again
+        assert_eq!(db.insert_trade_prints(&trades[..1]).await.unwrap(), 1);
+
+        db.close().await;
+        drop(db);
+        DatabaseClient::drop_database_if_exists(&vars.database_config()).await.expect("Failed to drop database");
+    }
+}
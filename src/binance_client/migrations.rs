@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+use std::io::{Error as IOError, ErrorKind};
+use sha2::{Digest, Sha256};
+
+/// A schema change compiled into the crate.
+///
+/// Migrations are applied in `version` order and must never be edited once released;
+/// change the schema by adding a new migration instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+impl Migration {
+    /// Hex SHA-256 of the SQL, stored when the migration is applied.
+    pub fn checksum(&self) -> String {
+        hex::encode(Sha256::digest(self.sql.as_bytes()))
+    }
+}
+
+/// Every migration of the crate, oldest first.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "create_kline_data",
+        sql: include_str!("migrations/0001_create_kline_data.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "create_order_journal",
+        sql: include_str!("migrations/0002_create_order_journal.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "create_account_trades",
+        sql: include_str!("migrations/0003_create_account_trades.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "create_market_data",
+        sql: include_str!("migrations/0004_create_market_data.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "create_grid_bots",
+        sql: include_str!("migrations/0005_create_grid_bots.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "create_dca_runs",
+        sql: include_str!("migrations/0006_create_dca_runs.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "key_order_journal_by_symbol",
+        sql: include_str!("migrations/0007_key_order_journal_by_symbol.sql"),
+    },
+];
+
+/// A row of the `schema_migrations` table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppliedMigration {
+    pub version: i64,
+    pub name: String,
+    pub checksum: String,
+    pub applied_at: i64, // Milliseconds since the epoch
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrationState {
+    Applied { applied_at: i64 },
+    Pending,
+    // Applied, but the SQL in this build no longer matches what was run
+    Modified { applied_checksum: String },
+    // Applied by a newer build of the crate
+    Unknown,
+}
+
+/// One line of the migration status report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub name: String,
+    pub state: MigrationState,
+}
+
+/// Compares the compiled migrations with the applied ones, ordered by version.
+pub fn migration_status(migrations: &[Migration], applied: &[AppliedMigration]) -> Vec<MigrationStatus> {
+    let applied_by_version: HashMap<i64, &AppliedMigration> =
+        applied.iter().map(|migration| (migration.version, migration)).collect();
+
+    let mut report: Vec<MigrationStatus> = migrations
+        .iter()
+        .map(|migration| {
+            let state = match applied_by_version.get(&migration.version) {
+                None => MigrationState::Pending,
+                Some(row) if row.checksum != migration.checksum() => {
+                    MigrationState::Modified { applied_checksum: row.checksum.clone() }
+                }
+                Some(row) => MigrationState::Applied { applied_at: row.applied_at },
+            };
+            MigrationStatus { version: migration.version, name: migration.name.to_string(), state }
+        })
+        .collect();
+
+    report.extend(
+        applied
+            .iter()
+            .filter(|row| !migrations.iter().any(|migration| migration.version == row.version))
+            .map(|row| MigrationStatus { version: row.version, name: row.name.clone(), state: MigrationState::Unknown }),
+    );
+    report.sort_by_key(|status| status.version);
+    report
+}
+
+/// The migrations still to apply, or an error if an applied migration was edited.
+pub fn pending_migrations<'a>(migrations: &'a [Migration], applied: &[AppliedMigration]) -> Result<Vec<&'a Migration>, IOError> {
+    let status = migration_status(migrations, applied);
+    if let Some(modified) = status.iter().find(|status| matches!(status.state, MigrationState::Modified { .. })) {
+        return Err(IOError::new(
+            ErrorKind::InvalidData,
+            format!("Migration {} ({}) was edited after it was applied", modified.version, modified.name),
+        ));
+    }
+    Ok(migrations
+        .iter()
+        .filter(|migration| status.iter().any(|s| s.version == migration.version && s.state == MigrationState::Pending))
+        .collect())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn applied(migration: &Migration) -> AppliedMigration {
+        AppliedMigration {
+            version: migration.version,
+            name: migration.name.to_string(),
+            checksum: migration.checksum(),
+            applied_at: 1_700_000_000_000,
+        }
+    }
+
+    #[test]
+    fn test_migrations_are_ordered_and_unique() {
+        assert!(MIGRATIONS.windows(2).all(|pair| pair[0].version < pair[1].version));
+        assert!(MIGRATIONS.iter().all(|migration| !migration.sql.trim().is_empty()));
+        assert_eq!(MIGRATIONS[0].checksum().len(), 64);
+    }
+
+    #[test]
+    fn test_pending_migrations() {
+        assert_eq!(pending_migrations(MIGRATIONS, &[]).unwrap().len(), MIGRATIONS.len());
+
+        let first = applied(&MIGRATIONS[0]);
+        let pending = pending_migrations(MIGRATIONS, &[first]).unwrap();
+        assert_eq!(pending[0].version, MIGRATIONS[1].version);
+
+        let all: Vec<AppliedMigration> = MIGRATIONS.iter().map(applied).collect();
+        assert!(pending_migrations(MIGRATIONS, &all).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_edited_and_unknown_migrations() {
+        let mut edited = applied(&MIGRATIONS[0]);
+        edited.checksum = "0".repeat(64);
+        assert_eq!(pending_migrations(MIGRATIONS, &[edited.clone()]).unwrap_err().kind(), ErrorKind::InvalidData);
+
+        let newer = AppliedMigration { version: 999, name: "from_the_future".to_string(), ..applied(&MIGRATIONS[0]) };
+        let status = migration_status(MIGRATIONS, &[edited, newer]);
+        assert!(matches!(status[0].state, MigrationState::Modified { .. }));
+        assert_eq!(status[1].state, MigrationState::Pending);
+        assert_eq!(status.last().unwrap().state, MigrationState::Unknown);
+    }
+}
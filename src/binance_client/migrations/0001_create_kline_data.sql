@@ -0,0 +1,18 @@
+-- Klines from streams and REST backfills. IF NOT EXISTS adopts tables created before migrations.
+CREATE TABLE IF NOT EXISTS kline_data (
+    symbol VARCHAR(20),
+    interval VARCHAR(10),
+    start_time BIGINT,
+    end_time BIGINT,
+    open_price DOUBLE PRECISION,
+    close_price DOUBLE PRECISION,
+    high_price DOUBLE PRECISION,
+    low_price DOUBLE PRECISION,
+    base_asset_volume DOUBLE PRECISION,
+    number_of_trades INT,
+    is_kline_closed BOOLEAN,
+    quote_asset_volume DOUBLE PRECISION,
+    taker_buy_base_asset_volume DOUBLE PRECISION,
+    taker_buy_quote_asset_volume DOUBLE PRECISION,
+    PRIMARY KEY (symbol, interval, start_time)
+);
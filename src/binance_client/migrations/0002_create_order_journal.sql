@@ -0,0 +1,68 @@
+-- Order journal written by OrderJournal.
+CREATE TABLE IF NOT EXISTS journal_orders (
+    order_id BIGINT PRIMARY KEY,
+    symbol VARCHAR(20) NOT NULL,
+    client_order_id VARCHAR(64) NOT NULL,
+    order_list_id BIGINT NOT NULL DEFAULT -1,
+    side VARCHAR(4),
+    order_type VARCHAR(32),
+    time_in_force VARCHAR(8),
+    status VARCHAR(32) NOT NULL,
+    price DOUBLE PRECISION,
+    stop_price DOUBLE PRECISION,
+    orig_qty DOUBLE PRECISION,
+    executed_qty DOUBLE PRECISION,
+    cummulative_quote_qty DOUBLE PRECISION,
+    transact_time BIGINT NOT NULL,
+    update_time BIGINT NOT NULL,
+    strategy VARCHAR(64),
+    notes TEXT
+);
+CREATE INDEX IF NOT EXISTS journal_orders_strategy_idx ON journal_orders (strategy, transact_time);
+
+CREATE TABLE IF NOT EXISTS journal_order_lists (
+    order_list_id BIGINT PRIMARY KEY,
+    symbol VARCHAR(20) NOT NULL,
+    contingency_type VARCHAR(16) NOT NULL,
+    list_status_type VARCHAR(32) NOT NULL,
+    list_order_status VARCHAR(32) NOT NULL,
+    list_client_order_id VARCHAR(64) NOT NULL,
+    transaction_time BIGINT NOT NULL,
+    strategy VARCHAR(64),
+    notes TEXT
+);
+
+CREATE TABLE IF NOT EXISTS journal_order_list_members (
+    order_list_id BIGINT NOT NULL,
+    order_id BIGINT NOT NULL,
+    symbol VARCHAR(20) NOT NULL,
+    client_order_id VARCHAR(64) NOT NULL,
+    PRIMARY KEY (order_list_id, order_id)
+);
+
+CREATE TABLE IF NOT EXISTS journal_fills (
+    symbol VARCHAR(20) NOT NULL,
+    trade_id BIGINT NOT NULL,
+    order_id BIGINT NOT NULL,
+    price DOUBLE PRECISION NOT NULL,
+    qty DOUBLE PRECISION NOT NULL,
+    commission DOUBLE PRECISION NOT NULL,
+    commission_asset VARCHAR(20) NOT NULL,
+    time BIGINT NOT NULL,
+    is_maker BOOLEAN,
+    PRIMARY KEY (symbol, trade_id)
+);
+CREATE INDEX IF NOT EXISTS journal_fills_order_idx ON journal_fills (order_id);
+
+CREATE TABLE IF NOT EXISTS journal_order_events (
+    id BIGSERIAL PRIMARY KEY,
+    order_id BIGINT,
+    symbol VARCHAR(20) NOT NULL,
+    event_type VARCHAR(32) NOT NULL,
+    reason TEXT,
+    request TEXT,
+    event_time BIGINT NOT NULL,
+    strategy VARCHAR(64)
+);
+CREATE UNIQUE INDEX IF NOT EXISTS journal_order_events_order_idx
+    ON journal_order_events (order_id, event_type) WHERE order_id IS NOT NULL;
@@ -0,0 +1,17 @@
+-- Account trades from /v3/myTrades, kept for reconciliation.
+CREATE TABLE IF NOT EXISTS account_trades (
+    symbol VARCHAR(20) NOT NULL,
+    trade_id BIGINT NOT NULL,
+    order_id BIGINT NOT NULL,
+    price DOUBLE PRECISION NOT NULL,
+    qty DOUBLE PRECISION NOT NULL,
+    quote_qty DOUBLE PRECISION NOT NULL,
+    commission DOUBLE PRECISION NOT NULL,
+    commission_asset VARCHAR(20) NOT NULL,
+    time BIGINT NOT NULL,
+    is_buyer BOOLEAN NOT NULL,
+    is_maker BOOLEAN NOT NULL,
+    is_best_match BOOLEAN NOT NULL,
+    PRIMARY KEY (symbol, trade_id)
+);
+CREATE INDEX IF NOT EXISTS account_trades_time_idx ON account_trades (symbol, time);
@@ -0,0 +1,51 @@
+-- Trade prints and order book samples from the market streams, partitioned by day.
+-- Daily partitions are created on demand by the writers and dropped by retention.
+CREATE TABLE IF NOT EXISTS market_trades (
+    symbol VARCHAR(20) NOT NULL,
+    trade_id BIGINT NOT NULL,
+    price DOUBLE PRECISION NOT NULL,
+    quantity DOUBLE PRECISION NOT NULL,
+    buyer_order_id BIGINT NOT NULL,
+    seller_order_id BIGINT NOT NULL,
+    trade_time BIGINT NOT NULL,
+    is_buyer_maker BOOLEAN NOT NULL,
+    PRIMARY KEY (symbol, trade_id, trade_time)
+) PARTITION BY RANGE (trade_time);
+CREATE INDEX IF NOT EXISTS market_trades_time_idx ON market_trades (symbol, trade_time);
+
+CREATE TABLE IF NOT EXISTS market_agg_trades (
+    symbol VARCHAR(20) NOT NULL,
+    agg_trade_id BIGINT NOT NULL,
+    price DOUBLE PRECISION NOT NULL,
+    quantity DOUBLE PRECISION NOT NULL,
+    first_trade_id BIGINT NOT NULL,
+    last_trade_id BIGINT NOT NULL,
+    trade_time BIGINT NOT NULL,
+    is_buyer_maker BOOLEAN NOT NULL,
+    PRIMARY KEY (symbol, agg_trade_id, trade_time)
+) PARTITION BY RANGE (trade_time);
+CREATE INDEX IF NOT EXISTS market_agg_trades_time_idx ON market_agg_trades (symbol, trade_time);
+
+-- Top levels of the book, best first, as parallel price and quantity arrays
+CREATE TABLE IF NOT EXISTS book_snapshots (
+    symbol VARCHAR(20) NOT NULL,
+    snapshot_time BIGINT NOT NULL,
+    last_update_id BIGINT NOT NULL,
+    bid_prices DOUBLE PRECISION[] NOT NULL,
+    bid_quantities DOUBLE PRECISION[] NOT NULL,
+    ask_prices DOUBLE PRECISION[] NOT NULL,
+    ask_quantities DOUBLE PRECISION[] NOT NULL,
+    PRIMARY KEY (symbol, snapshot_time)
+) PARTITION BY RANGE (snapshot_time);
+
+-- bookTicker events carry no timestamp, so `time` is when they were received
+CREATE TABLE IF NOT EXISTS book_tickers (
+    symbol VARCHAR(20) NOT NULL,
+    update_id BIGINT NOT NULL,
+    time BIGINT NOT NULL,
+    bid_price DOUBLE PRECISION NOT NULL,
+    bid_quantity DOUBLE PRECISION NOT NULL,
+    ask_price DOUBLE PRECISION NOT NULL,
+    ask_quantity DOUBLE PRECISION NOT NULL,
+    PRIMARY KEY (symbol, update_id, time)
+) PARTITION BY RANGE (time);
@@ -0,0 +1,7 @@
+-- Grid bot state written by GridStore, one row per bot.
+CREATE TABLE IF NOT EXISTS grid_bots (
+    name VARCHAR(64) PRIMARY KEY,
+    symbol VARCHAR(20) NOT NULL,
+    state TEXT NOT NULL, -- GridState as JSON
+    updated_at BIGINT NOT NULL
+);
@@ -0,0 +1,16 @@
+-- Scheduled runs of DCA plans written by DcaStore, one row per run whether it bought or not.
+CREATE TABLE IF NOT EXISTS dca_runs (
+    plan VARCHAR(64) NOT NULL,
+    symbol VARCHAR(20) NOT NULL,
+    scheduled_time BIGINT NOT NULL,
+    executed_at BIGINT NOT NULL,
+    outcome VARCHAR(16) NOT NULL,
+    order_id BIGINT,
+    price DOUBLE PRECISION,
+    quantity DOUBLE PRECISION NOT NULL, -- Net of commission paid in the base asset
+    quote_spent DOUBLE PRECISION NOT NULL, -- Including commission paid in the quote asset
+    total_quantity DOUBLE PRECISION NOT NULL, -- Cost basis of the plan after the run
+    total_cost DOUBLE PRECISION NOT NULL,
+    reason TEXT,
+    PRIMARY KEY (plan, scheduled_time)
+);
@@ -0,0 +1,13 @@
+-- Binance order and order list ids are only unique per symbol.
+ALTER TABLE journal_orders DROP CONSTRAINT journal_orders_pkey, ADD PRIMARY KEY (symbol, order_id);
+ALTER TABLE journal_order_lists DROP CONSTRAINT journal_order_lists_pkey, ADD PRIMARY KEY (symbol, order_list_id);
+ALTER TABLE journal_order_list_members
+    DROP CONSTRAINT journal_order_list_members_pkey,
+    ADD PRIMARY KEY (symbol, order_list_id, order_id);
+
+DROP INDEX IF EXISTS journal_fills_order_idx;
+CREATE INDEX journal_fills_order_idx ON journal_fills (symbol, order_id);
+
+DROP INDEX IF EXISTS journal_order_events_order_idx;
+CREATE UNIQUE INDEX journal_order_events_order_idx
+    ON journal_order_events (symbol, order_id, event_type) WHERE order_id IS NOT NULL;
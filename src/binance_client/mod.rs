@@ -1,5 +1,9 @@
 pub mod position_size;
+pub mod fee_model;
+pub mod ledger;
 pub mod spot_orders;
+pub mod order_api;
+pub mod ws_api_client;
 pub mod binance_client;
 
 pub mod logger_conf;
@@ -8,12 +12,31 @@ pub mod order_types;
 pub mod account;
 pub mod streams;
 pub mod database_client;
+pub mod market_data_store;
+pub mod archive_importer;
+pub mod migrations;
+pub mod kline_interval;
 pub mod database_config;
 pub mod load_env;
-mod binance_error;
-mod ticker_price;
+pub mod binance_error;
+pub mod ticker_price;
 pub(crate) mod deserialization;
 pub mod exchange_info;
 pub mod margin_client;
 pub mod order_response;
+pub mod market_data;
+pub mod sbe;
+pub mod strategy;
+pub mod strategy_runtime;
+pub mod paper_trader;
+#[cfg(test)]
+pub(crate) mod test_support;
+pub mod risk_manager;
+pub mod bracket_position;
+pub mod grid_bot;
+pub mod cron_schedule;
+pub mod dca;
+pub mod backtest;
+pub mod indicators;
+pub mod execution;
 mod cancel_order_response;
@@ -0,0 +1,64 @@
+use std::io::Error as IOError;
+use async_trait::async_trait;
+use crate::binance_client::account::open_order::OpenOrder;
+use crate::binance_client::order_response::{CancelReplaceResponse, OrderListResponse, OrderResponse};
+use crate::binance_client::order_types::cancel_order_response::CancelOrderResponse;
+use crate::binance_client::order_types::cancel_replace_order::CancelReplaceOrder;
+use crate::binance_client::order_types::limit_order::LimitOrder;
+use crate::binance_client::order_types::market_order::MarketOrder;
+use crate::binance_client::order_types::oco_order::OcoOrder;
+use crate::binance_client::order_types::stop_limit_order::StopLimitOrder;
+use crate::binance_client::spot_orders::SpotClient;
+
+/// Typed spot order entry, independent of the transport used to reach Binance.
+///
+/// Implemented by [`SpotClient`] (REST) and
+/// [`WsApiClient`](crate::binance_client::ws_api_client::WsApiClient) (WebSocket API), so code
+/// written against `&dyn OrderApi` can switch between them without changes.
+#[async_trait]
+pub trait OrderApi: Send + Sync {
+    async fn create_limit_order(&self, order: LimitOrder) -> Result<OrderResponse, IOError>;
+
+    async fn create_stop_limit_order(&self, order: StopLimitOrder) -> Result<OrderResponse, IOError>;
+
+    async fn create_oco_order(&self, order: OcoOrder) -> Result<OrderListResponse, IOError>;
+
+    async fn create_market_order(&self, order: MarketOrder) -> Result<OrderResponse, IOError>;
+
+    async fn cancel_order(&self, symbol: &str, order_id: i64) -> Result<CancelOrderResponse, IOError>;
+
+    async fn cancel_replace_order(&self, order: CancelReplaceOrder) -> Result<CancelReplaceResponse, IOError>;
+
+    async fn fetch_open_orders(&self, symbol: &str) -> Result<Vec<OpenOrder>, IOError>;
+}
+
+#[async_trait]
+impl OrderApi for SpotClient<'_> {
+    async fn create_limit_order(&self, order: LimitOrder) -> Result<OrderResponse, IOError> {
+        SpotClient::create_limit_order(self, order).await
+    }
+
+    async fn create_stop_limit_order(&self, order: StopLimitOrder) -> Result<OrderResponse, IOError> {
+        SpotClient::create_stop_limit_order(self, order).await
+    }
+
+    async fn create_oco_order(&self, order: OcoOrder) -> Result<OrderListResponse, IOError> {
+        SpotClient::create_oco_order(self, order).await
+    }
+
+    async fn create_market_order(&self, order: MarketOrder) -> Result<OrderResponse, IOError> {
+        SpotClient::create_market_order(self, order).await
+    }
+
+    async fn cancel_order(&self, symbol: &str, order_id: i64) -> Result<CancelOrderResponse, IOError> {
+        SpotClient::cancel_order(self, symbol, order_id).await
+    }
+
+    async fn cancel_replace_order(&self, order: CancelReplaceOrder) -> Result<CancelReplaceResponse, IOError> {
+        SpotClient::cancel_replace_order(self, order).await
+    }
+
+    async fn fetch_open_orders(&self, symbol: &str) -> Result<Vec<OpenOrder>, IOError> {
+        self.binance_client().fetch_open_orders(symbol).await
+    }
+}
@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
-use serde_with::{serde_as, DisplayFromStr, Map};
+use serde_with::{serde_as, DisplayFromStr};
+use crate::binance_client::order_types::cancel_order_response::CancelOrderResponse;
 
 #[serde_as]
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
@@ -80,3 +81,124 @@ pub struct Fill {
     pub trade_id: i64,
 }
 
+
+/// Response to placing an order list such as an OCO.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderListResponse {
+    pub order_list_id: i64,
+    pub contingency_type: String,
+    pub list_status_type: String,
+    pub list_order_status: String,
+    pub list_client_order_id: String,
+    pub transaction_time: u64,
+    pub symbol: String,
+    pub orders: Vec<OrderListEntry>,
+    // Empty when newOrderRespType is ACK
+    #[serde(default)]
+    pub order_reports: Vec<OrderResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderListEntry {
+    pub symbol: String,
+    pub order_id: i64,
+    pub client_order_id: String,
+}
+
+/// Response to a successful cancel-replace request.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelReplaceResponse {
+    pub cancel_result: String,
+    pub new_order_result: String,
+    pub cancel_response: CancelOrderResponse,
+    pub new_order_response: OrderResponse,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_oco_order_list() {
+        let json = r#"{
+            "orderListId": 0,
+            "contingencyType": "OCO",
+            "listStatusType": "EXEC_STARTED",
+            "listOrderStatus": "EXECUTING",
+            "listClientOrderId": "JYVpp3F0f5CAG15DhtrqLp",
+            "transactionTime": 1563417480525,
+            "symbol": "LTCBTC",
+            "orders": [
+                {"symbol": "LTCBTC", "orderId": 2, "clientOrderId": "Kk7sqHb9J6mJWTMDVW7Vos"},
+                {"symbol": "LTCBTC", "orderId": 3, "clientOrderId": "xTXKaGYd4bluPVp78IVRvl"}
+            ],
+            "orderReports": [
+                {
+                    "symbol": "LTCBTC",
+                    "orderId": 2,
+                    "orderListId": 0,
+                    "clientOrderId": "Kk7sqHb9J6mJWTMDVW7Vos",
+                    "transactTime": 1563417480525,
+                    "price": "0.000000",
+                    "origQty": "0.624363",
+                    "executedQty": "0.000000",
+                    "cummulativeQuoteQty": "0.000000",
+                    "status": "NEW",
+                    "timeInForce": "GTC",
+                    "type": "STOP_LOSS",
+                    "side": "BUY",
+                    "stopPrice": "0.960664"
+                }
+            ]
+        }"#;
+        let response: OrderListResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(response.orders.len(), 2);
+        assert_eq!(response.order_reports[0].stop_price, Some(0.960664));
+    }
+
+    #[test]
+    fn test_deserialize_cancel_replace_response() {
+        let json = r#"{
+            "cancelResult": "SUCCESS",
+            "newOrderResult": "SUCCESS",
+            "cancelResponse": {
+                "symbol": "BTCUSDT",
+                "origClientOrderId": "DnLo3vTAQcjha43lAZhZ0y",
+                "orderId": 9,
+                "orderListId": -1,
+                "clientOrderId": "osxN3JXAtJvKvCqGeMWMVR",
+                "price": "0.01000000",
+                "origQty": "0.000100",
+                "executedQty": "0.00000000",
+                "cummulativeQuoteQty": "0.00000000",
+                "status": "CANCELED",
+                "timeInForce": "GTC",
+                "type": "LIMIT",
+                "side": "SELL"
+            },
+            "newOrderResponse": {
+                "symbol": "BTCUSDT",
+                "orderId": 10,
+                "orderListId": -1,
+                "clientOrderId": "wOceeeOzNORyLiQfw7jd8S",
+                "transactTime": 1652928801803,
+                "price": "0.02000000",
+                "origQty": "0.040000",
+                "executedQty": "0.00000000",
+                "cummulativeQuoteQty": "0.00000000",
+                "status": "NEW",
+                "timeInForce": "GTC",
+                "type": "LIMIT",
+                "side": "BUY",
+                "fills": []
+            }
+        }"#;
+        let response: CancelReplaceResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(response.cancel_response.order_id, 9);
+        assert_eq!(response.new_order_response.order_id, 10);
+    }
+}
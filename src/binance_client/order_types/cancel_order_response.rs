@@ -1,12 +1,12 @@
 use serde::Deserialize;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct CancelOrderResponse {
     // Define fields according to Binance API response for a canceled order
-    symbol: String,
-    orig_client_order_id: Option<String>,
-    order_id: i64,
-    order_list_id: i64, // Unless dealing with OCO, this will be -1
-    client_order_id: String,
+    pub symbol: String,
+    pub orig_client_order_id: Option<String>,
+    pub order_id: i64,
+    pub order_list_id: i64, // Unless dealing with OCO, this will be -1
+    pub client_order_id: String,
 }
\ No newline at end of file
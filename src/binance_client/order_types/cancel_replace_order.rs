@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+use crate::binance_client::binance_client::BinanceClient;
+use crate::binance_client::order_types::order_type::OrderType;
+use crate::binance_client::order_types::side::Side;
+use crate::binance_client::order_types::time_in_force::TimeInForce;
+
+/// What Binance should do with the new order if cancelling the existing one fails.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CancelReplaceMode {
+    /// Only place the new order if the cancel succeeded.
+    StopOnFailure,
+    /// Place the new order regardless of whether the cancel succeeded.
+    AllowFailure,
+}
+
+/// Cancels an existing order and places a new order on the same symbol in a single request.
+///
+/// Sent to `POST /api/v3/order/cancelReplace` over REST or `order.cancelReplace` over the
+/// WebSocket API.
+///
+/// # Example
+///
+/// ```
+/// use binance_api::binance_client::order_types::cancel_replace_order::CancelReplaceOrder;
+/// use binance_api::binance_client::order_types::side::Side;
+/// // Move a resting buy order to a new price
+/// let order = CancelReplaceOrder::new_limit("BTCUSDT", Side::Buy, 12345, 0.01, 41000.0);
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelReplaceOrder {
+    pub symbol: String,
+    pub side: Side,
+    pub r#type: OrderType,
+    pub cancel_replace_mode: CancelReplaceMode,
+    pub cancel_order_id: i64, // Id of the order being replaced
+    pub time_in_force: Option<TimeInForce>,
+    pub quantity: Option<f64>,
+    pub price: Option<f64>,
+    pub stop_price: Option<f64>,
+    pub timestamp: u64,
+}
+
+impl CancelReplaceOrder {
+    /// Replaces `cancel_order_id` with a GTC limit order.
+    pub fn new_limit(symbol: &str, side: Side, cancel_order_id: i64, quantity: f64, price: f64) -> Self {
+        CancelReplaceOrder {
+            symbol: symbol.to_string(),
+            side,
+            r#type: OrderType::Limit,
+            cancel_replace_mode: CancelReplaceMode::StopOnFailure,
+            cancel_order_id,
+            time_in_force: Some(TimeInForce::GTC),
+            quantity: Some(quantity),
+            price: Some(price),
+            stop_price: None,
+            timestamp: BinanceClient::generate_timestamp().unwrap(),
+        }
+    }
+
+    /// Replaces `cancel_order_id` with a GTC stop-loss-limit order.
+    pub fn new_stop_limit(symbol: &str, side: Side, cancel_order_id: i64, quantity: f64, stop_price: f64, price: f64) -> Self {
+        CancelReplaceOrder {
+            r#type: OrderType::StopLossLimit,
+            stop_price: Some(stop_price),
+            ..CancelReplaceOrder::new_limit(symbol, side, cancel_order_id, quantity, price)
+        }
+    }
+}
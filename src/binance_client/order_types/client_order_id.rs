@@ -0,0 +1,8 @@
+/// Longest `newClientOrderId` Binance accepts.
+pub const MAX_CLIENT_ORDER_ID_LEN: usize = 36;
+
+/// Whether `text` only has characters Binance allows in a client order id, which are
+/// ASCII letters, digits and `.:/_-`.
+pub fn is_client_order_id_text(text: &str) -> bool {
+    text.chars().all(|c| c.is_ascii_alphanumeric() || ".:/_-".contains(c))
+}
@@ -6,15 +6,17 @@ use crate::binance_client::order_types::time_in_force::TimeInForce;
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct LimitOrder {
-    symbol: String,
-    side: Side,
+    pub(crate) symbol: String,
+    pub(crate) side: Side,
     
-    r#type: OrderType,
+    pub(crate) r#type: OrderType,
     
-    time_in_force: String,
-    quantity: f64,
-    price: f64,
-    timestamp: u64,
+    pub(crate) time_in_force: String,
+    pub(crate) quantity: f64,
+    pub(crate) price: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) new_client_order_id: Option<String>,
+    pub(crate) timestamp: u64,
 }
 
 impl LimitOrder {
@@ -26,7 +28,14 @@ impl LimitOrder {
             time_in_force: TimeInForce::GTC.to_string(),
             quantity,
             price,
+            new_client_order_id: None,
             timestamp,
         }
     }
+
+    /// Sets the `newClientOrderId`, which Binance generates when it is not given.
+    pub fn with_client_order_id(mut self, client_order_id: &str) -> Self {
+        self.new_client_order_id = Some(client_order_id.to_string());
+        self
+    }
 }
@@ -5,16 +5,18 @@ use crate::binance_client::binance_client::BinanceClient;
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct MarketOrder {
-    symbol: String,
-    side: Side,
+    pub(crate) symbol: String,
+    pub(crate) side: Side,
     
-    r#type: String,
-    quantity: Option<f64>,
+    pub(crate) r#type: String,
+    pub(crate) quantity: Option<f64>,
     // Optional, used for sell orders
     
-    quote_order_qty: Option<f64>,
+    pub(crate) quote_order_qty: Option<f64>,
     // Optional, used for buy orders
-    timestamp: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) new_client_order_id: Option<String>,
+    pub(crate) timestamp: u64,
 }
 
 impl MarketOrder {
@@ -25,6 +27,7 @@ impl MarketOrder {
             r#type: "MARKET".to_string(),
             quantity: Some(quantity),
             quote_order_qty: None,
+            new_client_order_id: None,
             timestamp: BinanceClient::generate_timestamp().unwrap(),
         }
     }
@@ -37,7 +40,14 @@ impl MarketOrder {
             r#type: "MARKET".to_string(),
             quantity: None,
             quote_order_qty: Some(quote_order_qty),
+            new_client_order_id: None,
             timestamp: BinanceClient::generate_timestamp().unwrap(),
         }
     }
+
+    /// Sets the `newClientOrderId`, which Binance generates when it is not given.
+    pub fn with_client_order_id(mut self, client_order_id: &str) -> Self {
+        self.new_client_order_id = Some(client_order_id.to_string());
+        self
+    }
 }
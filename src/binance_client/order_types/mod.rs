@@ -9,3 +9,5 @@ pub mod response_type;
 pub mod open_order;
 pub mod order_info;
 pub mod cancel_order_response;
+pub mod cancel_replace_order;
+pub mod client_order_id;
@@ -5,7 +5,7 @@ use serde::{Deserialize, Serialize};
 ///
 /// Each variant corresponds to a specific order type supported by Binance,
 /// defining how the order will be executed by the matching engine.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum OrderType {
     /// A limit order is an order to buy or sell at a specified price or better.
@@ -70,14 +70,14 @@ use crate::binance_client::order_types::time_in_force::TimeInForce;
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct StopLimitOrder {
-    symbol: String,
-    side: Side,
-    r#type: OrderType,
-    quantity: f64,
-    price: f64,
-    stop_price: f64,
-    timestamp: u64,
-    time_in_force: TimeInForce,
+    pub(crate) symbol: String,
+    pub(crate) side: Side,
+    pub(crate) r#type: OrderType,
+    pub(crate) quantity: f64,
+    pub(crate) price: f64,
+    pub(crate) stop_price: f64,
+    pub(crate) timestamp: u64,
+    pub(crate) time_in_force: TimeInForce,
 }
 
 impl StopLimitOrder {
@@ -6,7 +6,7 @@ use crate::binance_client::order_types::order_type::OrderType;
 ///
 /// These options provide traders with additional control over the timing of their trades
 /// and can be critical for strategy implementation, especially in fast-moving markets.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
 pub enum TimeInForce {
     /// Good Till Cancel (GTC) orders remain active until they are executed or manually canceled by the trader.
     /// GTC orders do not expire unless filled or canceled, providing a way to place long-term orders.
@@ -0,0 +1,1058 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::{Error as IOError, ErrorKind};
+use std::sync::Mutex;
+use async_trait::async_trait;
+use futures::StreamExt;
+use log::{trace, warn};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use crate::binance_client::account::asset_balance::AssetBalance;
+use crate::binance_client::account::open_order::OpenOrder;
+use crate::binance_client::account::order_status::OrderStatus;
+use crate::binance_client::backtest::broker::SimMarket;
+use crate::binance_client::binance_client::BinanceClient;
+use crate::binance_client::fee_model::LiquidityRole;
+use crate::binance_client::market_data_store::{BookSnapshot, TradePrint};
+use crate::binance_client::order_api::OrderApi;
+use crate::binance_client::order_response::{CancelReplaceResponse, Fill, OrderListEntry, OrderListResponse, OrderResponse};
+use crate::binance_client::order_types::cancel_order_response::CancelOrderResponse;
+use crate::binance_client::order_types::cancel_replace_order::CancelReplaceOrder;
+use crate::binance_client::order_types::limit_order::LimitOrder;
+use crate::binance_client::order_types::market_order::MarketOrder;
+use crate::binance_client::order_types::oco_order::OcoOrder;
+use crate::binance_client::order_types::order_type::OrderType;
+use crate::binance_client::order_types::side::Side;
+use crate::binance_client::order_types::stop_limit_order::StopLimitOrder;
+use crate::binance_client::order_types::time_in_force::TimeInForce;
+use crate::binance_client::strategy_runtime::MarketEvent;
+use crate::binance_client::streams::binance_stream::{BinanceStreamTypes, DepthLevels};
+use crate::binance_client::streams::binance_websocket::BinanceWebSocket;
+use crate::binance_client::streams::user_data::{ExecutionReport, ExecutionType, ListStatus, ListStatusOrder, UserDataEvent};
+
+const INSUFFICIENT_BALANCE: &str = "Account has insufficient balance for requested action.";
+const UNKNOWN_ORDER: &str = "Unknown order sent.";
+
+/// Simulated spot account that fills orders against live market data.
+///
+/// Implements [`OrderApi`] like [`SpotClient`](crate::binance_client::spot_orders::SpotClient),
+/// but keeps balances and open orders locally. Fed with the trade and partial depth
+/// streams of [`streams`](Self::streams), it fills them the way the exchange would have:
+///
+/// - Market orders, and limit orders that cross the book on arrival, take the visible
+///   levels as taker. Whatever the visible depth cannot fill of a market order expires.
+/// - Resting limit orders join the back of the queue at their price, as estimated from
+///   the book when they were placed. Trades at their price use up the queue before they
+///   fill the order as maker, so fills can be partial; a trade through the price, or a
+///   book that crossed it, fills the rest.
+/// - Stop-limit orders trigger on a trade at or through their stop price, then behave like
+///   a limit order that just arrived.
+/// - An OCO order expires its other leg as soon as one leg executes or its stop triggers,
+///   and canceling either leg cancels both.
+///
+/// Simulated orders never take liquidity away from the live book, so two paper orders
+/// can fill against the same level. Placement is validated like the exchange does, with
+/// the same error messages, commissions come from the market's fee model, and every change
+/// is published to the [`subscribe`](Self::subscribe)rs as the user data stream would report it.
+pub struct PaperTrader {
+    markets: HashMap<String, SimMarket>,
+    state: Mutex<PaperState>,
+    subscribers: Mutex<Vec<UnboundedSender<UserDataEvent>>>,
+}
+
+impl PaperTrader {
+    pub fn new(markets: Vec<SimMarket>) -> Self {
+        PaperTrader {
+            markets: markets.into_iter().map(|market| (market.symbol.clone(), market)).collect(),
+            state: Mutex::new(PaperState { next_id: 1, ..Default::default() }),
+            subscribers: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn markets(&self) -> impl Iterator<Item = &SimMarket> {
+        self.markets.values()
+    }
+
+    pub fn deposit(&self, asset: &str, amount: f64) {
+        let mut state = self.state.lock().unwrap();
+        state.balances.entry(asset.to_string()).or_default().free += amount;
+    }
+
+    pub fn balances(&self) -> Vec<AssetBalance> {
+        let state = self.state.lock().unwrap();
+        let mut balances: Vec<AssetBalance> = state.balances
+            .iter()
+            .map(|(asset, balance)| AssetBalance::new(asset, balance.free, balance.locked))
+            .collect();
+        balances.sort_by(|a, b| a.asset.cmp(&b.asset));
+        balances
+    }
+
+    pub fn free_balance(&self, asset: &str) -> f64 {
+        self.state.lock().unwrap().balances.get(asset).map_or(0.0, |balance| balance.free)
+    }
+
+    /// Receives the execution reports, list statuses and `outboundAccountPosition` events
+    /// of everything that happens from now on.
+    pub fn subscribe(&self) -> UnboundedReceiver<UserDataEvent> {
+        let (sender, receiver) = unbounded_channel();
+        self.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+
+    /// The trade and partial depth streams of every market, to be read from the mainnet.
+    pub fn streams(&self) -> Vec<BinanceStreamTypes> {
+        let mut symbols: Vec<&String> = self.markets.keys().collect();
+        symbols.sort();
+        symbols
+            .into_iter()
+            .flat_map(|symbol| [
+                BinanceStreamTypes::Trade(symbol.to_lowercase()),
+                BinanceStreamTypes::PartialDepth100ms(symbol.to_lowercase(), DepthLevels::Twenty),
+            ])
+            .collect()
+    }
+
+    pub fn apply(&self, event: &MarketEvent) {
+        match event {
+            MarketEvent::Trade(trade) => self.on_trade(trade),
+            MarketEvent::Depth(book) => self.on_book(book),
+            MarketEvent::Kline(_) | MarketEvent::Book(_) => {}
+        }
+    }
+
+    pub fn on_trade(&self, trade: &TradePrint) {
+        if !self.markets.contains_key(&trade.symbol) {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        state.on_trade(&self.markets, trade);
+        self.publish(&mut state, trade.trade_time as u64);
+    }
+
+    pub fn on_book(&self, book: &BookSnapshot) {
+        if !self.markets.contains_key(&book.symbol) {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        state.on_book(&self.markets, book);
+        self.publish(&mut state, book.snapshot_time as u64);
+    }
+
+    /// Feeds the simulation from the [`streams`](Self::streams) until the connection ends.
+    ///
+    /// `binance_client` has to point at the mainnet; market streams need no API key.
+    pub async fn stream_market_data(&self, binance_client: &BinanceClient) -> Result<(), IOError> {
+        let mut websocket = BinanceWebSocket::new(binance_client).connect(&self.streams()).await?;
+        while let Some(message) = websocket.next().await {
+            let message = message.map_err(|e| IOError::new(ErrorKind::Other, format!("Market data stream error: {}", e)))?;
+            let Some(text) = message.as_text() else { continue };
+            match MarketEvent::parse(text, BinanceClient::generate_timestamp()?) {
+                Ok(Some(event)) => self.apply(&event),
+                Ok(None) => {}
+                Err(e) => warn!("{}\n {}", e, text),
+            }
+        }
+        Ok(())
+    }
+
+    // Sends the events an operation caused once it is complete
+    fn publish(&self, state: &mut PaperState, time: u64) {
+        state.closed.clear();
+        state.fills.clear();
+        state.flush_balances(time);
+        let events = std::mem::take(&mut state.events);
+        if events.is_empty() {
+            return;
+        }
+        let mut subscribers = self.subscribers.lock().unwrap();
+        for event in events {
+            subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+        }
+    }
+
+    fn place(&self, order: NewOrder) -> Result<OrderResponse, IOError> {
+        let time = BinanceClient::generate_timestamp()?;
+        let mut state = self.state.lock().unwrap();
+        let result = state.place(&self.markets, &order, time).map(|order_id| state.response(order_id, time));
+        self.publish(&mut state, time);
+        result.map_err(|reason| IOError::new(ErrorKind::Other, reason))
+    }
+}
+
+#[async_trait]
+impl OrderApi for PaperTrader {
+    async fn create_limit_order(&self, order: LimitOrder) -> Result<OrderResponse, IOError> {
+        self.place(NewOrder {
+            symbol: order.symbol,
+            side: order.side,
+            order_type: OrderType::Limit,
+            quantity: order.quantity,
+            quote_order_qty: None,
+            price: order.price,
+            stop_price: 0.0,
+        })
+    }
+
+    async fn create_stop_limit_order(&self, order: StopLimitOrder) -> Result<OrderResponse, IOError> {
+        self.place(NewOrder {
+            symbol: order.symbol,
+            side: order.side,
+            order_type: OrderType::StopLossLimit,
+            quantity: order.quantity,
+            quote_order_qty: None,
+            price: order.price,
+            stop_price: order.stop_price,
+        })
+    }
+
+    async fn create_oco_order(&self, order: OcoOrder) -> Result<OrderListResponse, IOError> {
+        let time = BinanceClient::generate_timestamp()?;
+        let mut state = self.state.lock().unwrap();
+        let result = state.place_oco(&self.markets, &order, time).map(|list| {
+            let order_reports = list.1.order_ids.iter().map(|order_id| state.response(*order_id, time)).collect();
+            (list, order_reports)
+        });
+        self.publish(&mut state, time);
+        let ((list_id, list), order_reports) = result.map_err(|reason| IOError::new(ErrorKind::Other, reason))?;
+
+        Ok(OrderListResponse {
+            order_list_id: list_id,
+            contingency_type: "OCO".to_string(),
+            list_status_type: "EXEC_STARTED".to_string(),
+            list_order_status: "EXECUTING".to_string(),
+            list_client_order_id: list.client_order_id,
+            transaction_time: time,
+            symbol: order.symbol.clone(),
+            orders: list.order_ids
+                .iter()
+                .map(|order_id| OrderListEntry {
+                    symbol: order.symbol.clone(),
+                    order_id: *order_id,
+                    client_order_id: client_order_id(*order_id),
+                })
+                .collect(),
+            order_reports,
+        })
+    }
+
+    async fn create_market_order(&self, order: MarketOrder) -> Result<OrderResponse, IOError> {
+        self.place(NewOrder {
+            symbol: order.symbol,
+            side: order.side,
+            order_type: OrderType::Market,
+            quantity: order.quantity.unwrap_or(0.0),
+            quote_order_qty: order.quote_order_qty,
+            price: 0.0,
+            stop_price: 0.0,
+        })
+    }
+
+    async fn cancel_order(&self, symbol: &str, order_id: i64) -> Result<CancelOrderResponse, IOError> {
+        let time = BinanceClient::generate_timestamp()?;
+        let mut state = self.state.lock().unwrap();
+        let result = state.cancel(symbol, order_id, time);
+        self.publish(&mut state, time);
+        result.map_err(|reason| IOError::new(ErrorKind::Other, reason))
+    }
+
+    async fn cancel_replace_order(&self, order: CancelReplaceOrder) -> Result<CancelReplaceResponse, IOError> {
+        let cancel_response = self.cancel_order(&order.symbol, order.cancel_order_id).await?;
+        let new_order_response = self.place(NewOrder {
+            symbol: order.symbol,
+            side: order.side,
+            order_type: order.r#type,
+            quantity: order.quantity.unwrap_or(0.0),
+            quote_order_qty: None,
+            price: order.price.unwrap_or(0.0),
+            stop_price: order.stop_price.unwrap_or(0.0),
+        })?;
+        Ok(CancelReplaceResponse {
+            cancel_result: "SUCCESS".to_string(),
+            new_order_result: "SUCCESS".to_string(),
+            cancel_response,
+            new_order_response,
+        })
+    }
+
+    async fn fetch_open_orders(&self, symbol: &str) -> Result<Vec<OpenOrder>, IOError> {
+        let state = self.state.lock().unwrap();
+        Ok(state.orders
+            .iter()
+            .filter(|(_, order)| order.symbol == symbol)
+            .map(|(order_id, order)| OpenOrder {
+                symbol: order.symbol.clone(),
+                order_id: *order_id,
+                client_order_id: client_order_id(*order_id),
+                price: order.price,
+                orig_qty: order.quantity,
+                executed_qty: order.executed,
+                status: wire_name(&order.status),
+                time_in_force: TimeInForce::GTC,
+                r#type: wire_name(&order.order_type),
+                side: wire_name(&order.side),
+                stop_price: order.stop_price,
+                iceberg_qty: 0.0,
+                time: order.created,
+            })
+            .collect())
+    }
+}
+
+// An order as it arrives through the order API
+#[derive(Debug, Clone)]
+struct NewOrder {
+    symbol: String,
+    side: Side,
+    order_type: OrderType,
+    quantity: f64,
+    quote_order_qty: Option<f64>, // Market orders sized in the quote asset
+    price: f64,
+    stop_price: f64,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct PaperBalance {
+    free: f64,
+    locked: f64,
+}
+
+#[derive(Debug, Clone)]
+struct PaperOrder {
+    symbol: String,
+    side: Side,
+    order_type: OrderType,
+    quantity: f64,
+    price: f64,
+    stop_price: f64,
+    order_list_id: i64, // -1 unless part of an OCO
+    status: OrderStatus,
+    executed: f64,
+    cumulative_quote: f64,
+    created: u64,
+    // Whether the order works as a limit order, false for stop orders not yet triggered
+    triggered: bool,
+    queue_ahead: f64, // Estimated quantity ahead of the order at its price
+    lock_id: i64, // The order's id, or its list's id for the legs of an OCO
+    lock_asset: String, // The quote asset for buys, the base asset for sells
+    lock_price: f64, // Quote locked per unit for buys
+}
+
+impl PaperOrder {
+    fn remaining(&self) -> f64 {
+        let remaining = self.quantity - self.executed;
+        if remaining <= self.quantity * 1e-9 { 0.0 } else { remaining }
+    }
+
+    fn buy(&self) -> bool {
+        self.side == Side::Buy
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PaperList {
+    client_order_id: String,
+    order_ids: Vec<i64>,
+}
+
+#[derive(Debug, Default)]
+struct PaperState {
+    balances: HashMap<String, PaperBalance>,
+    books: HashMap<String, BookSnapshot>,
+    last_prices: HashMap<String, f64>,
+    orders: BTreeMap<i64, PaperOrder>, // Open orders
+    closed: HashMap<i64, PaperOrder>, // Orders the operation in progress closed
+    lists: HashMap<i64, PaperList>, // Open OCO orders
+    locks: HashMap<i64, f64>, // What open orders still hold, by lock id
+    next_id: i64, // Order and list ids share the sequence
+    next_trade_id: i64,
+    fills: Vec<(i64, Fill)>, // Of the operation in progress
+    events: Vec<UserDataEvent>,
+    touched_assets: BTreeSet<String>,
+}
+
+impl PaperState {
+    fn next_id(&mut self) -> i64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    fn free(&self, asset: &str) -> f64 {
+        self.balances.get(asset).map_or(0.0, |balance| balance.free)
+    }
+
+    fn change_free(&mut self, asset: &str, amount: f64) {
+        self.balances.entry(asset.to_string()).or_default().free += amount;
+        self.touched_assets.insert(asset.to_string());
+    }
+
+    fn lock(&mut self, lock_id: i64, asset: &str, amount: f64) {
+        let balance = self.balances.entry(asset.to_string()).or_default();
+        balance.free -= amount;
+        balance.locked += amount;
+        *self.locks.entry(lock_id).or_default() += amount;
+        self.touched_assets.insert(asset.to_string());
+    }
+
+    fn unlock(&mut self, lock_id: i64, asset: &str, amount: f64) {
+        let Some(held) = self.locks.get_mut(&lock_id) else { return };
+        let amount = amount.min(*held);
+        *held -= amount;
+        let balance = self.balances.entry(asset.to_string()).or_default();
+        balance.locked -= amount;
+        balance.free += amount;
+        self.touched_assets.insert(asset.to_string());
+    }
+
+    // Validates and places an order, returning its id or why it was rejected
+    fn place(&mut self, markets: &HashMap<String, SimMarket>, new: &NewOrder, time: u64) -> Result<i64, String> {
+        let market = markets.get(&new.symbol).ok_or("Invalid symbol.")?;
+        let filters = &market.filters;
+        let buy = new.side == Side::Buy;
+        let mut quantity = new.quantity;
+
+        let lock_price = match new.order_type {
+            OrderType::Market => {
+                let book = self.books.get(&new.symbol).ok_or(format!("No market data for {} yet.", new.symbol))?;
+                let levels = sweep(book, new.side, new.quantity, new.quote_order_qty, filters.step_size, None);
+                let cost: f64 = levels.iter().map(|(price, quantity)| price * quantity).sum();
+                let swept: f64 = levels.iter().map(|(_, quantity)| quantity).sum();
+                // A quote sized order becomes an order for what that buys now
+                if new.quote_order_qty.is_some() {
+                    quantity = swept;
+                }
+                let average_price = if swept > 0.0 { cost / swept } else { book.ask_prices.first().copied().unwrap_or_default() };
+                filters.check(average_price, quantity, true).map_err(|e| e.to_string())?;
+                let (asset, needed) = if buy { (market.quote_asset(), cost) } else { (market.base_asset(), swept) };
+                if self.free(asset) < needed {
+                    return Err(INSUFFICIENT_BALANCE.to_string());
+                }
+                0.0
+            }
+            OrderType::Limit | OrderType::LimitMaker => {
+                filters.check(new.price, new.quantity, false).map_err(|e| e.to_string())?;
+                new.price
+            }
+            OrderType::StopLossLimit | OrderType::TakeProfitLimit => {
+                filters.check(new.price, new.quantity, false).map_err(|e| e.to_string())?;
+                filters.check(new.stop_price, new.quantity, false).map_err(|e| e.to_string())?;
+                if self.would_trigger(&new.symbol, buy, new.stop_price) {
+                    return Err("Stop price would trigger immediately.".to_string());
+                }
+                new.price
+            }
+            _ => return Err("Unsupported order type.".to_string()),
+        };
+        if new.order_type == OrderType::LimitMaker && self.crosses_book(&new.symbol, buy, new.price) {
+            return Err("Order would immediately match and take.".to_string());
+        }
+
+        let order_id = self.next_id();
+        let lock = if buy { lock_price * quantity } else if new.order_type == OrderType::Market { 0.0 } else { quantity };
+        let lock_asset = if buy { market.quote_asset() } else { market.base_asset() };
+        if lock > self.free(lock_asset) {
+            return Err(INSUFFICIENT_BALANCE.to_string());
+        }
+        self.lock(order_id, lock_asset, lock);
+
+        let triggered = !matches!(new.order_type, OrderType::StopLossLimit | OrderType::TakeProfitLimit);
+        self.orders.insert(order_id, PaperOrder {
+            symbol: new.symbol.clone(),
+            side: new.side,
+            order_type: new.order_type,
+            quantity,
+            price: new.price,
+            stop_price: new.stop_price,
+            order_list_id: -1,
+            status: OrderStatus::New,
+            executed: 0.0,
+            cumulative_quote: 0.0,
+            created: time,
+            triggered,
+            queue_ahead: 0.0,
+            lock_id: order_id,
+            lock_asset: lock_asset.to_string(),
+            lock_price,
+        });
+        self.report(order_id, ExecutionType::New, time, None);
+        if triggered {
+            self.arrive(markets, order_id, time);
+        }
+        Ok(order_id)
+    }
+
+    fn place_oco(&mut self, markets: &HashMap<String, SimMarket>, oco: &OcoOrder, time: u64) -> Result<(i64, PaperList), String> {
+        let market = markets.get(&oco.symbol).ok_or("Invalid symbol.")?;
+        let buy = oco.side == Side::Buy;
+        for price in [oco.price, oco.stop_price, oco.stop_limit_price] {
+            market.filters.check(price, oco.quantity, false).map_err(|e| e.to_string())?;
+        }
+        let ordered = if buy { oco.price < oco.stop_price } else { oco.price > oco.stop_price };
+        if !ordered || self.would_trigger(&oco.symbol, buy, oco.stop_price) || self.crosses_book(&oco.symbol, buy, oco.price) {
+            return Err("The relationship of the prices for the orders is not correct.".to_string());
+        }
+
+        // Both legs share one lock, enough for whichever executes
+        let lock_price = oco.price.max(oco.stop_limit_price);
+        let lock = if buy { lock_price * oco.quantity } else { oco.quantity };
+        let lock_asset = if buy { market.quote_asset() } else { market.base_asset() };
+        if lock > self.free(lock_asset) {
+            return Err(INSUFFICIENT_BALANCE.to_string());
+        }
+
+        let list_id = self.next_id();
+        let (stop_leg, limit_leg) = (self.next_id(), self.next_id());
+        self.lock(list_id, lock_asset, lock);
+        let leg = |order_type, price, stop_price, triggered| PaperOrder {
+            symbol: oco.symbol.clone(),
+            side: oco.side,
+            order_type,
+            quantity: oco.quantity,
+            price,
+            stop_price,
+            order_list_id: list_id,
+            status: OrderStatus::New,
+            executed: 0.0,
+            cumulative_quote: 0.0,
+            created: time,
+            triggered,
+            queue_ahead: 0.0,
+            lock_id: list_id,
+            lock_asset: lock_asset.to_string(),
+            lock_price,
+        };
+        self.orders.insert(stop_leg, leg(OrderType::StopLossLimit, oco.stop_limit_price, oco.stop_price, false));
+        self.orders.insert(limit_leg, leg(OrderType::LimitMaker, oco.price, 0.0, true));
+        let list_client_order_id = oco.list_client_order_id.clone().unwrap_or_else(|| format!("paper-list-{}", list_id));
+        let list = PaperList { client_order_id: list_client_order_id, order_ids: vec![stop_leg, limit_leg] };
+        self.lists.insert(list_id, list.clone());
+
+        self.list_status(list_id, &oco.symbol, "EXEC_STARTED", "EXECUTING", time);
+        self.report(stop_leg, ExecutionType::New, time, None);
+        self.report(limit_leg, ExecutionType::New, time, None);
+        self.arrive(markets, limit_leg, time);
+        Ok((list_id, list))
+    }
+
+    fn cancel(&mut self, symbol: &str, order_id: i64, time: u64) -> Result<CancelOrderResponse, String> {
+        let order = self.orders.get(&order_id).filter(|order| order.symbol == symbol).ok_or(UNKNOWN_ORDER)?;
+        let order_list_id = order.order_list_id;
+        let order_ids = match self.lists.get(&order_list_id) {
+            Some(list) => list.order_ids.clone(),
+            None => vec![order_id],
+        };
+        for leg in order_ids {
+            self.finish(leg, OrderStatus::Canceled, ExecutionType::Canceled, time);
+        }
+        Ok(CancelOrderResponse {
+            symbol: symbol.to_string(),
+            orig_client_order_id: Some(client_order_id(order_id)),
+            order_id,
+            order_list_id,
+            client_order_id: client_order_id(order_id),
+        })
+    }
+
+    fn on_trade(&mut self, markets: &HashMap<String, SimMarket>, trade: &TradePrint) {
+        let time = trade.trade_time as u64;
+        self.last_prices.insert(trade.symbol.clone(), trade.price);
+
+        let triggered: Vec<i64> = self.orders
+            .iter()
+            .filter(|(_, order)| order.symbol == trade.symbol && !order.triggered)
+            .filter(|(_, order)| if order.buy() { trade.price >= order.stop_price } else { trade.price <= order.stop_price })
+            .map(|(order_id, _)| *order_id)
+            .collect();
+        for order_id in &triggered {
+            let Some(order) = self.orders.get_mut(order_id) else { continue };
+            order.triggered = true;
+            trace!("Paper order {} triggered at {}", order_id, trade.price);
+            self.expire_other_leg(*order_id, time);
+            self.arrive(markets, *order_id, time);
+        }
+
+        // Sells hitting the bid fill resting buys and vice versa, best price first
+        let resting_side = if trade.is_buyer_maker { Side::Buy } else { Side::Sell };
+        let mut resting: Vec<(i64, f64)> = self.orders
+            .iter()
+            .filter(|(order_id, order)| {
+                order.symbol == trade.symbol && order.side == resting_side && order.triggered && !triggered.contains(order_id)
+            })
+            .filter(|(_, order)| if order.buy() { trade.price <= order.price } else { trade.price >= order.price })
+            .map(|(order_id, order)| (*order_id, order.price))
+            .collect();
+        if resting_side == Side::Buy {
+            resting.sort_by(|a, b| b.1.total_cmp(&a.1));
+        } else {
+            resting.sort_by(|a, b| a.1.total_cmp(&b.1));
+        }
+
+        let mut volume = trade.quantity;
+        for (order_id, price) in resting {
+            let Some(order) = self.orders.get_mut(&order_id) else { continue };
+            let quantity = if price != trade.price {
+                // The whole level traded away
+                order.remaining()
+            } else {
+                let behind_queue = (volume - order.queue_ahead).max(0.0);
+                order.queue_ahead = (order.queue_ahead - volume).max(0.0);
+                let quantity = behind_queue.min(order.remaining());
+                volume -= quantity;
+                quantity
+            };
+            if quantity > 0.0 {
+                self.fill(markets, order_id, quantity, price, LiquidityRole::Maker, time);
+            }
+        }
+    }
+
+    fn on_book(&mut self, markets: &HashMap<String, SimMarket>, book: &BookSnapshot) {
+        let time = book.snapshot_time as u64;
+        self.books.insert(book.symbol.clone(), book.clone());
+
+        let resting: Vec<i64> = self.orders
+            .iter()
+            .filter(|(_, order)| order.symbol == book.symbol && order.triggered)
+            .map(|(order_id, _)| *order_id)
+            .collect();
+        for order_id in resting {
+            let Some(order) = self.orders.get_mut(&order_id) else { continue };
+            let (buy, price) = (order.buy(), order.price);
+            // Opposite orders at or through the price would have executed against this one
+            let (prices, quantities) = if buy { (&book.ask_prices, &book.ask_quantities) } else { (&book.bid_prices, &book.bid_quantities) };
+            let crossed: f64 = prices
+                .iter()
+                .zip(quantities)
+                .take_while(|(level, _)| if buy { **level <= price } else { **level >= price })
+                .map(|(_, quantity)| quantity)
+                .sum();
+            if crossed > 0.0 {
+                let quantity = crossed.min(order.remaining());
+                order.queue_ahead = 0.0;
+                self.fill(markets, order_id, quantity, price, LiquidityRole::Maker, time);
+                continue;
+            }
+
+            // Orders ahead that left the level shorten the queue
+            let (prices, quantities) = if buy { (&book.bid_prices, &book.bid_quantities) } else { (&book.ask_prices, &book.ask_quantities) };
+            let visible = prices.last().is_some_and(|last| if buy { price >= *last } else { price <= *last });
+            if visible {
+                let level = prices.iter().position(|level| *level == price).map_or(0.0, |index| quantities[index]);
+                order.queue_ahead = order.queue_ahead.min(level);
+            }
+        }
+    }
+
+    // Takes what the book offers of an order that just arrived or triggered and queues the rest
+    fn arrive(&mut self, markets: &HashMap<String, SimMarket>, order_id: i64, time: u64) {
+        let Some(order) = self.orders.get(&order_id) else { return };
+        let market = order.order_type == OrderType::Market;
+        let (buy, limit, remaining) = (order.buy(), (!market).then_some(order.price), order.remaining());
+        let plan = match self.books.get(&order.symbol) {
+            Some(book) => {
+                let (prices, quantities) = if buy { (&book.bid_prices, &book.bid_quantities) } else { (&book.ask_prices, &book.ask_quantities) };
+                let queue = prices.iter().position(|level| Some(*level) == limit).map_or(0.0, |index| quantities[index]);
+                (sweep(book, order.side, remaining, None, 0.0, limit), queue)
+            }
+            None => (Vec::new(), 0.0),
+        };
+
+        let (levels, queue) = plan;
+        for (price, quantity) in levels {
+            self.fill(markets, order_id, quantity, price, LiquidityRole::Taker, time);
+        }
+        if market {
+            // What the visible depth could not fill
+            self.finish(order_id, OrderStatus::Expired, ExecutionType::Expired, time);
+        } else if let Some(order) = self.orders.get_mut(&order_id) {
+            order.queue_ahead = queue;
+        }
+    }
+
+    fn fill(&mut self, markets: &HashMap<String, SimMarket>, order_id: i64, quantity: f64, price: f64, role: LiquidityRole, time: u64) {
+        let Some(order) = self.orders.get_mut(&order_id) else { return };
+        let market = &markets[&order.symbol];
+        let (base, quote) = (market.base_asset(), market.quote_asset());
+        let notional = quantity * price;
+
+        order.executed += quantity;
+        order.cumulative_quote += notional;
+        order.status = if order.remaining() == 0.0 { OrderStatus::Filled } else { OrderStatus::PartiallyFilled };
+        let (side, lock_id, lock_price, status) = (order.side, order.lock_id, order.lock_price, order.status);
+
+        match side {
+            Side::Buy => {
+                self.unlock(lock_id, quote, quantity * lock_price);
+                self.change_free(quote, -notional);
+                self.change_free(base, quantity);
+            }
+            Side::Sell => {
+                self.unlock(lock_id, base, quantity);
+                self.change_free(base, -quantity);
+                self.change_free(quote, notional);
+            }
+        }
+        let fee = market.fee_model.expected_fee(side, role, quantity, price);
+        self.change_free(&fee.asset, -fee.amount);
+
+        let trade_id = self.next_trade_id;
+        self.next_trade_id += 1;
+        self.fills.push((order_id, Fill {
+            price,
+            qty: quantity,
+            commission: fee.amount,
+            commission_asset: fee.asset.clone(),
+            trade_id,
+        }));
+        self.report(order_id, ExecutionType::Trade, time, Some(TradeReport {
+            quantity,
+            price,
+            commission: fee.amount,
+            commission_asset: fee.asset,
+            is_maker: role == LiquidityRole::Maker,
+            trade_id,
+        }));
+
+        self.expire_other_leg(order_id, time);
+        if status == OrderStatus::Filled {
+            self.close(order_id, time);
+        }
+    }
+
+    // The other leg of an OCO goes once one leg executes or triggers
+    fn expire_other_leg(&mut self, order_id: i64, time: u64) {
+        let Some(order) = self.orders.get(&order_id) else { return };
+        let Some(list) = self.lists.get(&order.order_list_id) else { return };
+        let others: Vec<i64> = list.order_ids.iter().copied().filter(|leg| *leg != order_id).collect();
+        for other in others {
+            self.finish(other, OrderStatus::Expired, ExecutionType::Expired, time);
+        }
+    }
+
+    // Ends an open order that did not fill completely
+    fn finish(&mut self, order_id: i64, status: OrderStatus, execution_type: ExecutionType, time: u64) {
+        let Some(order) = self.orders.get_mut(&order_id) else { return };
+        order.status = status;
+        self.report(order_id, execution_type, time, None);
+        self.close(order_id, time);
+    }
+
+    // Forgets a final order, freeing what its lock still holds once no leg needs it
+    fn close(&mut self, order_id: i64, time: u64) {
+        let Some(order) = self.orders.remove(&order_id) else { return };
+        self.closed.insert(order_id, order.clone());
+        if self.orders.values().any(|other| other.lock_id == order.lock_id) {
+            return;
+        }
+        if let Some(held) = self.locks.remove(&order.lock_id) {
+            let balance = self.balances.entry(order.lock_asset.clone()).or_default();
+            balance.locked -= held;
+            balance.free += held;
+            self.touched_assets.insert(order.lock_asset);
+        }
+        if self.lists.contains_key(&order.order_list_id) {
+            self.list_status(order.order_list_id, &order.symbol, "ALL_DONE", "ALL_DONE", time);
+            self.lists.remove(&order.order_list_id);
+        }
+    }
+
+    fn would_trigger(&self, symbol: &str, buy: bool, stop_price: f64) -> bool {
+        self.last_prices.get(symbol).is_some_and(|last| if buy { *last >= stop_price } else { *last <= stop_price })
+    }
+
+    fn crosses_book(&self, symbol: &str, buy: bool, price: f64) -> bool {
+        self.books.get(symbol).is_some_and(|book| {
+            if buy {
+                book.ask_prices.first().is_some_and(|ask| *ask <= price)
+            } else {
+                book.bid_prices.first().is_some_and(|bid| *bid >= price)
+            }
+        })
+    }
+
+    fn report(&mut self, order_id: i64, execution_type: ExecutionType, time: u64, trade: Option<TradeReport>) {
+        let Some(order) = self.orders.get(&order_id) else { return };
+        let trade = trade.unwrap_or_default();
+        self.events.push(UserDataEvent::ExecutionReport(ExecutionReport {
+            event_type: "executionReport".to_string(),
+            event_time: time,
+            symbol: order.symbol.clone(),
+            client_order_id: client_order_id(order_id),
+            side: order.side,
+            order_type: order.order_type,
+            time_in_force: "GTC".to_string(),
+            quantity: order.quantity,
+            price: order.price,
+            stop_price: order.stop_price,
+            order_list_id: order.order_list_id,
+            orig_client_order_id: String::new(),
+            execution_type,
+            order_status: order.status,
+            reject_reason: "NONE".to_string(),
+            order_id,
+            last_executed_qty: trade.quantity,
+            cumulative_filled_qty: order.executed,
+            last_executed_price: trade.price,
+            commission: trade.commission,
+            commission_asset: (execution_type == ExecutionType::Trade).then_some(trade.commission_asset),
+            transaction_time: time,
+            trade_id: if execution_type == ExecutionType::Trade { trade.trade_id } else { -1 },
+            is_on_book: order.triggered
+                && order.order_type != OrderType::Market
+                && matches!(order.status, OrderStatus::New | OrderStatus::PartiallyFilled),
+            is_maker: trade.is_maker,
+            order_creation_time: order.created,
+            cumulative_quote_qty: order.cumulative_quote,
+            last_quote_qty: trade.quantity * trade.price,
+        }));
+    }
+
+    fn list_status(&mut self, list_id: i64, symbol: &str, list_status_type: &str, list_order_status: &str, time: u64) {
+        let Some(list) = self.lists.get(&list_id) else { return };
+        self.events.push(UserDataEvent::ListStatus(ListStatus {
+            event_type: "listStatus".to_string(),
+            event_time: time,
+            symbol: symbol.to_string(),
+            order_list_id: list_id,
+            contingency_type: "OCO".to_string(),
+            list_status_type: list_status_type.to_string(),
+            list_order_status: list_order_status.to_string(),
+            list_reject_reason: "NONE".to_string(),
+            list_client_order_id: list.client_order_id.clone(),
+            transaction_time: time,
+            orders: list.order_ids
+                .iter()
+                .map(|order_id| ListStatusOrder {
+                    symbol: symbol.to_string(),
+                    order_id: *order_id,
+                    client_order_id: client_order_id(*order_id),
+                })
+                .collect(),
+        }));
+    }
+
+    // Reports the balances an operation changed, like `outboundAccountPosition`
+    fn flush_balances(&mut self, time: u64) {
+        if self.touched_assets.is_empty() {
+            return;
+        }
+        let balances: Vec<Value> = std::mem::take(&mut self.touched_assets)
+            .into_iter()
+            .map(|asset| {
+                let balance = self.balances.get(&asset).copied().unwrap_or_default();
+                json!({"a": asset, "f": balance.free.to_string(), "l": balance.locked.to_string()})
+            })
+            .collect();
+        self.events.push(UserDataEvent::Other(json!({
+            "e": "outboundAccountPosition",
+            "E": time,
+            "u": time,
+            "B": balances,
+        })));
+    }
+
+    // What the order API returns for an order, with the fills of the operation that placed it
+    fn response(&mut self, order_id: i64, time: u64) -> OrderResponse {
+        let (fills, others): (Vec<_>, Vec<_>) = std::mem::take(&mut self.fills)
+            .into_iter()
+            .partition(|(fill_order_id, _)| *fill_order_id == order_id);
+        self.fills = others;
+        let fills: Vec<Fill> = fills.into_iter().map(|(_, fill)| fill).collect();
+
+        // Placed by the same operation, so either still open or just closed
+        let order = self.orders.get(&order_id).or_else(|| self.closed.get(&order_id)).cloned().expect("order placed in this operation");
+        OrderResponse {
+            symbol: order.symbol,
+            order_id,
+            order_list_id: order.order_list_id,
+            client_order_id: client_order_id(order_id),
+            transact_time: time,
+            price: Some(order.price),
+            orig_qty: Some(order.quantity),
+            executed_qty: Some(order.executed),
+            cummulative_quote_qty: Some(order.cumulative_quote),
+            status: Some(wire_name(&order.status)),
+            time_in_force: Some("GTC".to_string()),
+            order_type: Some(wire_name(&order.order_type)),
+            side: Some(wire_name(&order.side)),
+            working_time: Some(time),
+            self_trade_prevention_mode: Some("NONE".to_string()),
+            fills: Some(fills),
+            stop_price: (order.stop_price > 0.0).then_some(order.stop_price),
+            iceberg_qty: None,
+            prevented_match_id: None,
+            prevented_quantity: None,
+            strategy_id: None,
+            strategy_type: None,
+            trailing_delta: None,
+            trailing_time: None,
+            used_sor: None,
+            working_floor: None,
+        }
+    }
+}
+
+// The execution a TRADE report describes
+#[derive(Debug, Clone, Default)]
+struct TradeReport {
+    quantity: f64,
+    price: f64,
+    commission: f64,
+    commission_asset: String,
+    is_maker: bool,
+    trade_id: i64,
+}
+
+fn client_order_id(order_id: i64) -> String {
+    format!("paper-{}", order_id)
+}
+
+// How Binance spells an enum on the wire
+fn wire_name(value: &impl Serialize) -> String {
+    serde_json::to_value(value).ok().and_then(|value| value.as_str().map(str::to_string)).unwrap_or_default()
+}
+
+// Levels and quantities an order arriving now takes from the visible book, best first.
+// Quote sized orders spend at most `quote_order_qty`, in multiples of `step_size`.
+fn sweep(book: &BookSnapshot, side: Side, quantity: f64, quote_order_qty: Option<f64>, step_size: f64, limit: Option<f64>) -> Vec<(f64, f64)> {
+    let (prices, quantities) = match side {
+        Side::Buy => (&book.ask_prices, &book.ask_quantities),
+        Side::Sell => (&book.bid_prices, &book.bid_quantities),
+    };
+    let (mut spent, mut filled) = (0.0, 0.0);
+    let mut levels = Vec::new();
+    for (price, available) in prices.iter().zip(quantities) {
+        if limit.is_some_and(|limit| if side == Side::Buy { *price > limit } else { *price < limit }) {
+            break;
+        }
+        let mut take = match quote_order_qty {
+            Some(quote_order_qty) => ((quote_order_qty - spent) / price).min(*available),
+            None => (quantity - filled).min(*available),
+        };
+        if quote_order_qty.is_some() && step_size > 0.0 {
+            take = (take / step_size + 1e-9).floor() * step_size;
+        }
+        if take <= 0.0 {
+            break;
+        }
+        spent += take * price;
+        filled += take;
+        levels.push((*price, take));
+    }
+    levels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binance_client::fee_model::FeeModel;
+
+    fn paper_trader() -> PaperTrader {
+        PaperTrader::new(vec![SimMarket::new("ETHUSDT", FeeModel::flat(0.001, 0.001, "ETH", "USDT"))])
+    }
+
+    fn book(bids: &[(f64, f64)], asks: &[(f64, f64)]) -> BookSnapshot {
+        BookSnapshot {
+            symbol: "ETHUSDT".to_string(),
+            snapshot_time: 1,
+            last_update_id: 1,
+            bid_prices: bids.iter().map(|level| level.0).collect(),
+            bid_quantities: bids.iter().map(|level| level.1).collect(),
+            ask_prices: asks.iter().map(|level| level.0).collect(),
+            ask_quantities: asks.iter().map(|level| level.1).collect(),
+        }
+    }
+
+    fn trade(price: f64, quantity: f64, is_buyer_maker: bool) -> TradePrint {
+        TradePrint {
+            symbol: "ETHUSDT".to_string(),
+            trade_id: 1,
+            price,
+            quantity,
+            buyer_order_id: -1,
+            seller_order_id: -1,
+            trade_time: 2,
+            is_buyer_maker,
+        }
+    }
+
+    fn locked(paper_trader: &PaperTrader, asset: &str) -> f64 {
+        paper_trader.balances().iter().find(|balance| balance.asset == asset).map_or(0.0, |balance| balance.locked)
+    }
+
+    #[tokio::test]
+    async fn test_limit_order_waits_for_its_queue() {
+        let paper_trader = paper_trader();
+        paper_trader.deposit("USDT", 10000.0);
+        paper_trader.on_book(&book(&[(1999.0, 2.0)], &[(2001.0, 1.0)]));
+
+        let too_big = LimitOrder::new("ETHUSDT", Side::Buy, 10.0, 1999.0, 0);
+        assert_eq!(paper_trader.create_limit_order(too_big).await.unwrap_err().to_string(), INSUFFICIENT_BALANCE);
+
+        let response = paper_trader.create_limit_order(LimitOrder::new("ETHUSDT", Side::Buy, 1.0, 1999.0, 0)).await.unwrap();
+        assert_eq!(response.status.as_deref(), Some("NEW"));
+        assert_eq!((paper_trader.free_balance("USDT"), locked(&paper_trader, "USDT")), (8001.0, 1999.0));
+
+        // 2.0 is ahead at 1999, so the order gets half of the second trade
+        paper_trader.on_trade(&trade(1999.0, 1.5, true));
+        assert_eq!(paper_trader.free_balance("ETH"), 0.0);
+        paper_trader.on_trade(&trade(1999.0, 1.0, true));
+        assert!((paper_trader.free_balance("ETH") - 0.5 * 0.999).abs() < 1e-9);
+        let open = paper_trader.fetch_open_orders("ETHUSDT").await.unwrap();
+        assert_eq!(open.len(), 1);
+
+        // A trade through the price fills the rest
+        paper_trader.on_trade(&trade(1998.0, 0.1, true));
+        assert!((paper_trader.free_balance("ETH") - 0.999).abs() < 1e-9);
+        assert!((paper_trader.free_balance("USDT") - 8001.0).abs() < 1e-9);
+        assert_eq!(locked(&paper_trader, "USDT"), 0.0);
+        assert!(paper_trader.fetch_open_orders("ETHUSDT").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_market_order_takes_visible_depth() {
+        let paper_trader = paper_trader();
+        paper_trader.deposit("ETH", 5.0);
+        let order = MarketOrder::new_with_base_asset("ETHUSDT", Side::Sell, 3.0);
+        assert_eq!(paper_trader.create_market_order(order.clone()).await.unwrap_err().to_string(), "No market data for ETHUSDT yet.");
+
+        paper_trader.on_book(&book(&[(2000.0, 1.0), (1999.0, 1.0)], &[(2001.0, 1.0)]));
+        let response = paper_trader.create_market_order(order).await.unwrap();
+        assert_eq!((response.status.as_deref(), response.executed_qty), (Some("EXPIRED"), Some(2.0)));
+        let prices: Vec<f64> = response.fills.unwrap().iter().map(|fill| fill.price).collect();
+        assert_eq!(prices, vec![2000.0, 1999.0]);
+        assert_eq!(paper_trader.free_balance("ETH"), 3.0);
+        assert!((paper_trader.free_balance("USDT") - 3999.0 * 0.999).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_oco_leg_expires_when_the_other_fills() {
+        let paper_trader = paper_trader();
+        paper_trader.deposit("ETH", 1.0);
+        paper_trader.on_trade(&trade(2000.0, 1.0, false));
+        let mut events = paper_trader.subscribe();
+
+        let oco = OcoOrder::new("ETHUSDT".to_string(), Side::Sell, 1.0, 2100.0, 1900.0, 1890.0, 0);
+        let response = paper_trader.create_oco_order(oco).await.unwrap();
+        assert_eq!(response.orders.len(), 2);
+        assert_eq!((paper_trader.free_balance("ETH"), locked(&paper_trader, "ETH")), (0.0, 1.0));
+
+        paper_trader.on_trade(&trade(2100.5, 2.0, false));
+        assert!((paper_trader.free_balance("USDT") - 2100.0 * 0.999).abs() < 1e-9);
+        assert_eq!(locked(&paper_trader, "ETH"), 0.0);
+
+        let mut received = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            received.push(match event {
+                UserDataEvent::ExecutionReport(report) => format!("{} {:?}", report.order_id, report.execution_type),
+                UserDataEvent::ListStatus(status) => status.list_status_type,
+                UserDataEvent::Other(value) => value["e"].as_str().unwrap().to_string(),
+            });
+        }
+        assert_eq!(received, vec![
+            "EXEC_STARTED", "2 New", "3 New", "outboundAccountPosition",
+            "3 Trade", "2 Expired", "ALL_DONE", "outboundAccountPosition",
+        ]);
+    }
+}
@@ -98,6 +98,8 @@
 //!
 //! It is crucial to remember that market conditions, volatility, and other factors can influence the effectiveness of these calculations, and adjustments may be necessary to adapt to changing market dynamics.
 
+use crate::binance_client::exchange_info::SymbolInfo;
+use crate::binance_client::kline_interval::KlineInterval;
 
 /// Rounds a floating-point number to a specified number of decimal places.
 ///
@@ -308,10 +310,251 @@ pub fn calculate_position_size_in_base_asset(
 }
 
 
+/// Calculates the position size in units of the base asset so that a stop-out loses
+/// `risk_percentage` of the account *including* the fees paid on entry and exit.
+///
+/// # Arguments
+///
+/// * `account_size` - The total capital available in the trading account.
+/// * `risk_percentage` - The percentage of the account size you are willing to risk on this trade.
+/// * `entry_price` - The price at which you plan to enter the trade.
+/// * `stop_loss_price` - The price at which you plan to exit the trade if it goes against you.
+/// * `entry_fee_rate` - Commission rate of the entry order, e.g. `0.001` for 0.1%.
+/// * `exit_fee_rate` - Commission rate of the stop loss order.
+///
+/// # Examples
+///
+/// ```
+/// use binance_api::binance_client::position_size::calculate_position_size_with_fees;
+/// let position_size = calculate_position_size_with_fees(1000.0, 1.0, 100.0, 98.0, 0.0, 0.0);
+/// assert_eq!(position_size, 5.0);
+/// let with_fees = calculate_position_size_with_fees(1000.0, 1.0, 100.0, 98.0, 0.001, 0.001);
+/// assert!(with_fees < position_size);
+/// ```
+pub fn calculate_position_size_with_fees(
+    account_size: f64,
+    risk_percentage: f64,
+    entry_price: f64,
+    stop_loss_price: f64,
+    entry_fee_rate: f64,
+    exit_fee_rate: f64,
+) -> f64 {
+    let risk_amount = account_size * (risk_percentage / 100.0);
+    let risk_per_unit = (entry_price - stop_loss_price).abs()
+        + entry_price * entry_fee_rate
+        + stop_loss_price * exit_fee_rate;
+
+    risk_amount / risk_per_unit
+}
+
+
+/// A trade sized by [`plan_position`], already valid for the symbol's filters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PositionPlan {
+    /// Quantity in the base asset, rounded down to the lot step size.
+    pub quantity: f64,
+    pub entry_price: f64,
+    /// Entry price times quantity, in the quote asset.
+    pub notional: f64,
+    /// Rounded to the tick size, for sizing modes that place a stop.
+    pub stop_loss_price: Option<f64>,
+    pub take_profit_price: Option<f64>,
+    /// What hitting the stop loses in the quote asset, before fees.
+    pub risk_amount: Option<f64>,
+}
+
+/// Win and loss statistics of past trades, the input of Kelly sizing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TradeStats {
+    pub win_rate: f64, // Between 0 and 1
+    pub average_win: f64,
+    pub average_loss: f64, // As a positive amount
+}
+
+impl TradeStats {
+    /// Statistics of the profit or loss of each trade. Trades that broke even count as
+    /// neither; `None` unless there is at least one win and one loss.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use binance_api::binance_client::position_size::TradeStats;
+    /// let stats = TradeStats::from_pnls(&[30.0, -10.0, 10.0, -10.0]).unwrap();
+    /// assert_eq!((stats.win_rate, stats.average_win, stats.average_loss), (0.5, 20.0, 10.0));
+    /// assert_eq!(stats.kelly_fraction(), 0.25);
+    /// ```
+    pub fn from_pnls(pnls: &[f64]) -> Option<Self> {
+        let wins: Vec<f64> = pnls.iter().copied().filter(|pnl| *pnl > 0.0).collect();
+        let losses: Vec<f64> = pnls.iter().copied().filter(|pnl| *pnl < 0.0).collect();
+        if wins.is_empty() || losses.is_empty() {
+            return None;
+        }
+        Some(TradeStats {
+            win_rate: wins.len() as f64 / (wins.len() + losses.len()) as f64,
+            average_win: wins.iter().sum::<f64>() / wins.len() as f64,
+            average_loss: -losses.iter().sum::<f64>() / losses.len() as f64,
+        })
+    }
+
+    /// The growth-optimal fraction of the account to invest per trade.
+    ///
+    /// ```latex
+    /// Kelly Fraction = Win Rate - (1 - Win Rate) / (Average Win / Average Loss)
+    /// ```
+    /// Zero or negative when the trades have no edge.
+    pub fn kelly_fraction(&self) -> f64 {
+        self.win_rate - (1.0 - self.win_rate) * self.average_loss / self.average_win
+    }
+}
+
+/// How [`plan_position`] sizes a trade.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SizingMode {
+    /// Risks `risk_percentage` of the account with the stop `atr_multiple` average true
+    /// ranges from the entry, and the take profit `take_profit_ratio` times that distance
+    /// on the other side, if given.
+    AtrStop { risk_percentage: f64, atr: f64, atr_multiple: f64, take_profit_ratio: Option<f64> },
+    /// Holds a position whose annualized volatility is `target_volatility`, e.g. `0.2` for
+    /// 20%, given the asset's annualized `volatility` from [`annualized_volatility`].
+    TargetVolatility { target_volatility: f64, volatility: f64 },
+    /// Invests `fraction` of the Kelly fraction of `stats`, e.g. `0.5` for half Kelly.
+    FractionalKelly { stats: TradeStats, fraction: f64 },
+    /// Invests `percentage` of the account, but at most `max_notional` of the quote asset.
+    FixedFractional { percentage: f64, max_notional: f64 },
+}
+
+/// Sizes a trade entered at `entry_price` and snaps it to the filters of `symbol`.
+///
+/// Positions are never worth more than the account, nor more than the symbol's maximum
+/// quantity or notional. The quantity is rounded down to the `LOT_SIZE` step, and stop
+/// and take profit prices to the tick size, with the stop distance measured after
+/// rounding. Sizes below the minimum quantity or notional are an error rather than
+/// rounded up, since that would take more risk than asked for.
+///
+/// # Examples
+///
+/// ```
+/// use binance_api::binance_client::exchange_info::SymbolInfo;
+/// use binance_api::binance_client::position_size::{plan_position, SizingMode};
+/// let symbol: SymbolInfo = serde_json::from_str(r#"{
+///     "symbol": "ETHUSDT", "status": "TRADING", "baseAsset": "ETH", "quoteAsset": "USDT", "permissions": ["SPOT"],
+///     "filters": [{"filterType": "LOT_SIZE", "minQty": "0.0001", "maxQty": "9000", "stepSize": "0.0001"}]
+/// }"#).unwrap();
+/// // Risk 1% of 10000 with the stop 2 ATRs of 25 below 2000
+/// let mode = SizingMode::AtrStop { risk_percentage: 1.0, atr: 25.0, atr_multiple: 2.0, take_profit_ratio: Some(3.0) };
+/// let plan = plan_position(10000.0, 2000.0, true, &mode, &symbol).unwrap();
+/// assert_eq!((plan.quantity, plan.stop_loss_price, plan.take_profit_price), (2.0, Some(1950.0), Some(2150.0)));
+/// ```
+pub fn plan_position(
+    account_size: f64,
+    entry_price: f64,
+    is_long: bool,
+    mode: &SizingMode,
+    symbol: &SymbolInfo,
+) -> Result<PositionPlan, &'static str> {
+    if account_size <= 0.0 || entry_price <= 0.0 {
+        return Err("Invalid input values for calculating position size.");
+    }
+    let filters = symbol.trading_filters();
+    let direction = if is_long { 1.0 } else { -1.0 };
+
+    let (quantity, stop_loss_price, take_profit_price) = match *mode {
+        SizingMode::AtrStop { risk_percentage, atr, atr_multiple, take_profit_ratio } => {
+            if risk_percentage <= 0.0 || risk_percentage > 100.0 || atr <= 0.0 || atr_multiple <= 0.0
+                || take_profit_ratio.is_some_and(|ratio| ratio <= 0.0) {
+                return Err("Invalid input values for calculating position size.");
+            }
+            let stop_loss_price = filters.round_price(entry_price - direction * atr * atr_multiple);
+            let stop_distance = (entry_price - stop_loss_price).abs();
+            if stop_loss_price <= 0.0 || stop_distance == 0.0 {
+                return Err("Stop loss is too far from or too close to the entry price.");
+            }
+            let take_profit_price = take_profit_ratio
+                .map(|ratio| filters.round_price(entry_price + direction * stop_distance * ratio));
+            let risk_amount = account_size * (risk_percentage / 100.0);
+            (risk_amount / stop_distance, Some(stop_loss_price), take_profit_price)
+        }
+        SizingMode::TargetVolatility { target_volatility, volatility } => {
+            if target_volatility <= 0.0 || volatility <= 0.0 {
+                return Err("Invalid input values for calculating position size.");
+            }
+            (account_size * target_volatility / volatility / entry_price, None, None)
+        }
+        SizingMode::FractionalKelly { stats, fraction } => {
+            if fraction <= 0.0 || fraction > 1.0 || stats.average_win <= 0.0 || stats.average_loss <= 0.0 {
+                return Err("Invalid input values for calculating position size.");
+            }
+            let kelly_fraction = stats.kelly_fraction();
+            if kelly_fraction <= 0.0 {
+                return Err("The trade statistics show no edge to size a position for.");
+            }
+            (account_size * kelly_fraction * fraction / entry_price, None, None)
+        }
+        SizingMode::FixedFractional { percentage, max_notional } => {
+            if percentage <= 0.0 || percentage > 100.0 || max_notional <= 0.0 {
+                return Err("Invalid input values for calculating position size.");
+            }
+            ((account_size * (percentage / 100.0)).min(max_notional) / entry_price, None, None)
+        }
+    };
+
+    let mut quantity = quantity.min(account_size / entry_price);
+    if filters.max_qty > 0.0 {
+        quantity = quantity.min(filters.max_qty);
+    }
+    if filters.max_notional > 0.0 {
+        quantity = quantity.min(filters.max_notional / entry_price);
+    }
+    let quantity = filters.floor_quantity(quantity, false);
+    if quantity <= 0.0 || quantity < filters.min_qty {
+        return Err("Position size is below the symbol's minimum quantity.");
+    }
+    let notional = quantity * entry_price;
+    if notional < filters.min_notional {
+        return Err("Position size is below the symbol's minimum notional.");
+    }
+
+    Ok(PositionPlan {
+        quantity,
+        entry_price,
+        notional,
+        stop_loss_price,
+        take_profit_price,
+        risk_amount: stop_loss_price.map(|stop_loss_price| quantity * (entry_price - stop_loss_price).abs()),
+    })
+}
+
+/// Annualized volatility of the log returns between `closes`, one kline of `interval` apart.
+///
+/// Crypto trades around the clock, so a year is 365 days of klines. `None` with fewer
+/// than three closes or a close that is not positive.
+///
+/// # Examples
+///
+/// ```
+/// use binance_api::binance_client::kline_interval::KlineInterval;
+/// use binance_api::binance_client::position_size::annualized_volatility;
+/// // Steady growth does not vary at all
+/// let closes = [100.0, 110.0, 121.0, 133.1];
+/// assert!(annualized_volatility(&closes, KlineInterval::OneDay).unwrap() < 1e-9);
+/// ```
+pub fn annualized_volatility(closes: &[f64], interval: KlineInterval) -> Option<f64> {
+    if closes.len() < 3 || closes.iter().any(|close| *close <= 0.0) {
+        return None;
+    }
+    let returns: Vec<f64> = closes.windows(2).map(|pair| (pair[1] / pair[0]).ln()).collect();
+    let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+    let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (returns.len() - 1) as f64;
+
+    const YEAR_MS: f64 = 365.0 * 24.0 * 60.0 * 60.0 * 1000.0;
+    let periods_per_year = interval.duration_ms().map_or(12.0, |duration| YEAR_MS / duration as f64);
+    Some((variance * periods_per_year).sqrt())
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::binance_client::test_support::eth_usdt;
 
     #[test]
     fn test_long_position_with_take_profit() {
@@ -390,4 +633,51 @@ mod tests {
         assert!((take_profit_price - 90.0).abs() < 1e-2, "Short position take profit calculation failed.");
         assert!((calculated_stop_loss_price - 102.0).abs() < 1e-2, "Stop loss price mismatch.");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_atr_stop_plan() {
+        let mode = SizingMode::AtrStop { risk_percentage: 1.0, atr: 33.333, atr_multiple: 1.5, take_profit_ratio: Some(2.0) };
+        let plan = plan_position(10000.0, 2000.0, false, &mode, &eth_usdt()).unwrap();
+        // The stop of a short is above the entry, 49.9995 rounded to the tick
+        assert_eq!(plan.stop_loss_price, Some(2050.0));
+        assert_eq!(plan.take_profit_price, Some(1900.0));
+        assert_eq!(plan.quantity, 2.0);
+        assert_eq!((plan.notional, plan.risk_amount), (4000.0, Some(100.0)));
+
+        // A tight stop would need more than the account can buy
+        let tight = SizingMode::AtrStop { risk_percentage: 1.0, atr: 0.5, atr_multiple: 1.0, take_profit_ratio: None };
+        assert_eq!(plan_position(10000.0, 2000.0, true, &tight, &eth_usdt()).unwrap().quantity, 5.0);
+    }
+
+    #[test]
+    fn test_volatility_kelly_and_fixed_fractional_plans() {
+        let target = SizingMode::TargetVolatility { target_volatility: 0.2, volatility: 0.8 };
+        let plan = plan_position(10000.0, 3000.0, true, &target, &eth_usdt()).unwrap();
+        // 2500 USDT of exposure, rounded down to the step size
+        assert_eq!((plan.quantity, plan.stop_loss_price, plan.risk_amount), (0.8333, None, None));
+
+        let stats = TradeStats::from_pnls(&[30.0, -10.0, 10.0, -10.0]).unwrap();
+        let kelly = SizingMode::FractionalKelly { stats, fraction: 0.5 };
+        assert_eq!(plan_position(10000.0, 2500.0, true, &kelly, &eth_usdt()).unwrap().notional, 1250.0);
+        let losing = TradeStats { win_rate: 0.3, average_win: 10.0, average_loss: 10.0 };
+        let no_edge = SizingMode::FractionalKelly { stats: losing, fraction: 0.5 };
+        assert!(plan_position(10000.0, 2500.0, true, &no_edge, &eth_usdt()).is_err());
+
+        let fixed = SizingMode::FixedFractional { percentage: 10.0, max_notional: 500.0 };
+        assert_eq!(plan_position(10000.0, 2500.0, true, &fixed, &eth_usdt()).unwrap().quantity, 0.2);
+        assert_eq!(
+            plan_position(40.0, 2500.0, true, &fixed, &eth_usdt()),
+            Err("Position size is below the symbol's minimum notional.")
+        );
+    }
+
+    #[test]
+    fn test_annualized_volatility() {
+        // Four daily log returns alternating between +1% and -1%
+        let closes: Vec<f64> = (0..5).map(|day| if day % 2 == 0 { 100.0 } else { 100.0 * 0.01f64.exp() }).collect();
+        let volatility = annualized_volatility(&closes, KlineInterval::OneDay).unwrap();
+        let expected = (0.01f64.powi(2) * 4.0 / 3.0 * 365.0).sqrt();
+        assert!((volatility - expected).abs() < 1e-12);
+        assert_eq!(annualized_volatility(&[100.0, 101.0], KlineInterval::OneDay), None);
+    }
+}
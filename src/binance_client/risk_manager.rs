@@ -0,0 +1,880 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{Error as IOError, ErrorKind};
+use std::sync::Mutex;
+use async_trait::async_trait;
+use log::{info, warn};
+use crate::binance_client::account::open_order::OpenOrder;
+use crate::binance_client::account::order_status::OrderStatus;
+use crate::binance_client::binance_client::BinanceClient;
+use crate::binance_client::market_data_store::TradePrint;
+use crate::binance_client::order_api::OrderApi;
+use crate::binance_client::order_response::{CancelReplaceResponse, OrderListResponse, OrderResponse};
+use crate::binance_client::order_types::cancel_order_response::CancelOrderResponse;
+use crate::binance_client::order_types::cancel_replace_order::CancelReplaceOrder;
+use crate::binance_client::order_types::limit_order::LimitOrder;
+use crate::binance_client::order_types::market_order::MarketOrder;
+use crate::binance_client::order_types::oco_order::OcoOrder;
+use crate::binance_client::order_types::side::Side;
+use crate::binance_client::order_types::stop_limit_order::StopLimitOrder;
+use crate::binance_client::streams::user_data::{ExecutionType, UserDataEvent};
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// Limits a [`RiskManager`] enforces, each disabled while `None`.
+///
+/// Notional amounts are in the quote asset, which is assumed to be the same for every
+/// symbol traded through one risk manager.
+#[derive(Debug, Clone, Default)]
+pub struct RiskLimits {
+    pub max_order_notional: Option<f64>,
+    pub max_position_notional: Option<f64>, // Per symbol, unless overridden below
+    pub symbol_max_position_notional: HashMap<String, f64>,
+    pub max_gross_exposure: Option<f64>, // Summed over all symbols
+    pub max_open_orders: Option<usize>,
+    pub max_daily_loss: Option<f64>, // Since 00:00 UTC, as a positive amount
+    pub price_collar_percentage: Option<f64>, // Order prices from the last trade
+    pub fat_finger_percentage: Option<f64>, // Order prices from the 24h average
+}
+
+impl RiskLimits {
+    pub fn new() -> Self {
+        RiskLimits::default()
+    }
+
+    pub fn with_max_order_notional(mut self, notional: f64) -> Self {
+        self.max_order_notional = Some(notional);
+        self
+    }
+
+    pub fn with_max_position_notional(mut self, notional: f64) -> Self {
+        self.max_position_notional = Some(notional);
+        self
+    }
+
+    pub fn with_symbol_max_position_notional(mut self, symbol: &str, notional: f64) -> Self {
+        self.symbol_max_position_notional.insert(symbol.to_string(), notional);
+        self
+    }
+
+    pub fn with_max_gross_exposure(mut self, notional: f64) -> Self {
+        self.max_gross_exposure = Some(notional);
+        self
+    }
+
+    pub fn with_max_open_orders(mut self, count: usize) -> Self {
+        self.max_open_orders = Some(count);
+        self
+    }
+
+    pub fn with_max_daily_loss(mut self, loss: f64) -> Self {
+        self.max_daily_loss = Some(loss);
+        self
+    }
+
+    pub fn with_price_collar(mut self, percentage: f64) -> Self {
+        self.price_collar_percentage = Some(percentage);
+        self
+    }
+
+    pub fn with_fat_finger_check(mut self, percentage: f64) -> Self {
+        self.fat_finger_percentage = Some(percentage);
+        self
+    }
+
+    fn position_limit(&self, symbol: &str) -> Option<f64> {
+        self.symbol_max_position_notional.get(symbol).copied().or(self.max_position_notional)
+    }
+
+    fn needs_last_price(&self) -> bool {
+        self.max_order_notional.is_some()
+            || self.max_position_notional.is_some()
+            || !self.symbol_max_position_notional.is_empty()
+            || self.max_gross_exposure.is_some()
+            || self.price_collar_percentage.is_some()
+    }
+}
+
+/// Why the [`RiskManager`] refused an order.
+///
+/// Returned inside the `IOError` of the order call, with kind `PermissionDenied`;
+/// [`RiskRejection::from_error`] gets it back.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RiskRejection {
+    KillSwitch,
+    // A limit needs the last trade or 24h average price of a symbol that has none yet
+    NoReferencePrice { symbol: String },
+    OrderNotional { notional: f64, limit: f64 },
+    Position { symbol: String, notional: f64, limit: f64 },
+    GrossExposure { notional: f64, limit: f64 },
+    OpenOrders { count: usize, limit: usize },
+    DailyLoss { loss: f64, limit: f64 },
+    PriceCollar { price: f64, last_price: f64, limit_percentage: f64 },
+    FatFinger { price: f64, average_price: f64, limit_percentage: f64 },
+}
+
+impl RiskRejection {
+    /// The rejection an order call failed with, if the risk manager refused it.
+    pub fn from_error(error: &IOError) -> Option<&RiskRejection> {
+        error.get_ref().and_then(|inner| inner.downcast_ref::<RiskRejection>())
+    }
+}
+
+impl fmt::Display for RiskRejection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RiskRejection::KillSwitch => write!(f, "Kill switch is active"),
+            RiskRejection::NoReferencePrice { symbol } => write!(f, "No reference price for {} yet", symbol),
+            RiskRejection::OrderNotional { notional, limit } => {
+                write!(f, "Order notional {} exceeds the limit of {}", notional, limit)
+            }
+            RiskRejection::Position { symbol, notional, limit } => {
+                write!(f, "Position of {} in {} would exceed the limit of {}", notional, symbol, limit)
+            }
+            RiskRejection::GrossExposure { notional, limit } => {
+                write!(f, "Gross exposure of {} would exceed the limit of {}", notional, limit)
+            }
+            RiskRejection::OpenOrders { count, limit } => {
+                write!(f, "{} open orders would exceed the limit of {}", count, limit)
+            }
+            RiskRejection::DailyLoss { loss, limit } => write!(f, "Daily loss of {} reached the limit of {}", loss, limit),
+            RiskRejection::PriceCollar { price, last_price, limit_percentage } => {
+                write!(f, "Price {} is more than {}% from the last trade at {}", price, limit_percentage, last_price)
+            }
+            RiskRejection::FatFinger { price, average_price, limit_percentage } => {
+                write!(f, "Price {} is more than {}% from the 24h average of {}", price, limit_percentage, average_price)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RiskRejection {}
+
+impl From<RiskRejection> for IOError {
+    fn from(rejection: RiskRejection) -> Self {
+        IOError::new(ErrorKind::PermissionDenied, rejection)
+    }
+}
+
+/// Pre-trade risk checks in front of another [`OrderApi`].
+///
+/// Every new order is checked against the [`RiskLimits`] before it is passed on, and
+/// refused with a [`RiskRejection`] otherwise. Cancels always go through. Positions are
+/// checked as if the open orders on the side of the new one filled too. Orders that
+/// only reduce a position are exempt from the position, exposure and daily loss limits,
+/// so a breached limit never traps a position.
+///
+/// The checks work on what the risk manager has been told:
+///
+/// - Positions and the daily profit and loss follow the fills of order responses and of
+///   execution reports passed to [`apply`](Self::apply), counting each trade once.
+///   Positions held before can be set with [`set_position`](Self::set_position).
+/// - Open orders follow the order responses, cancels and execution reports. An order
+///   that passed the checks counts as open while it is on its way to the exchange, so
+///   concurrent orders can't pass a limit together.
+/// - Prices come from [`on_trade`](Self::on_trade) or [`set_last_price`](Self::set_last_price),
+///   and 24h averages from [`refresh_average_prices`](Self::refresh_average_prices). Limits
+///   that need a price refuse orders on symbols without one.
+///
+/// The daily loss is the change in the value of the positions since 00:00 UTC, at the
+/// last prices, plus the cash flow of the day's fills. Commissions count when paid in the
+/// symbol's base or quote asset.
+///
+/// The [`kill_switch`](Self::kill_switch) cancels every open order and refuses all new
+/// ones until [`resume`](Self::resume).
+pub struct RiskManager<'a> {
+    order_api: &'a dyn OrderApi,
+    limits: RiskLimits,
+    state: Mutex<RiskState>,
+}
+
+impl<'a> RiskManager<'a> {
+    pub fn new(order_api: &'a dyn OrderApi, limits: RiskLimits) -> Self {
+        RiskManager { order_api, limits, state: Mutex::new(RiskState::default()) }
+    }
+
+    pub fn limits(&self) -> &RiskLimits {
+        &self.limits
+    }
+
+    pub fn set_last_price(&self, symbol: &str, price: f64) {
+        self.state.lock().unwrap().last_prices.insert(symbol.to_string(), price);
+    }
+
+    pub fn on_trade(&self, trade: &TradePrint) {
+        self.set_last_price(&trade.symbol, trade.price);
+    }
+
+    pub fn set_average_price(&self, symbol: &str, price: f64) {
+        self.state.lock().unwrap().average_prices.insert(symbol.to_string(), price);
+    }
+
+    /// Fetches the 24h weighted average price of `symbols`, which also sets their last price.
+    pub async fn refresh_average_prices(&self, binance_client: &BinanceClient, symbols: &[&str]) -> Result<(), IOError> {
+        for symbol in symbols {
+            let ticker = binance_client.fetch_24hr_ticker(symbol).await?;
+            let mut state = self.state.lock().unwrap();
+            state.average_prices.insert(ticker.symbol.clone(), ticker.weighted_avg_price);
+            state.last_prices.insert(ticker.symbol, ticker.last_price);
+        }
+        Ok(())
+    }
+
+    /// Sets the position in the base asset of `symbol`, negative for a short. Not a trade,
+    /// so the daily profit and loss stays the same.
+    pub fn set_position(&self, symbol: &str, quantity: f64) {
+        let mut state = self.state.lock().unwrap();
+        let previous = state.positions.insert(symbol.to_string(), quantity).unwrap_or_default();
+        let price = state.last_prices.get(symbol).copied().unwrap_or_default();
+        state.day_start_value += (quantity - previous) * price;
+    }
+
+    pub fn position(&self, symbol: &str) -> f64 {
+        self.state.lock().unwrap().positions.get(symbol).copied().unwrap_or_default()
+    }
+
+    pub fn open_order_count(&self) -> usize {
+        self.state.lock().unwrap().open_orders.len()
+    }
+
+    /// Profit or loss since 00:00 UTC, negative for a loss.
+    pub fn daily_pnl(&self) -> Result<f64, IOError> {
+        let now = BinanceClient::generate_timestamp()?;
+        let mut state = self.state.lock().unwrap();
+        state.roll_day(now);
+        Ok(state.daily_pnl())
+    }
+
+    /// Keeps positions and open orders up to date from the user data stream.
+    pub fn apply(&self, event: &UserDataEvent) {
+        let UserDataEvent::ExecutionReport(report) = event else { return };
+        let mut state = self.state.lock().unwrap();
+        if report.execution_type == ExecutionType::Trade {
+            let commission_asset = report.commission_asset.as_deref().unwrap_or_default();
+            state.record_fill(&report.symbol, report.side, &Fill {
+                trade_id: report.trade_id,
+                quantity: report.last_executed_qty,
+                price: report.last_executed_price,
+                commission: report.commission,
+                commission_asset,
+            }, report.transaction_time);
+        }
+        match report.order_status {
+            OrderStatus::New | OrderStatus::PartiallyFilled | OrderStatus::PendingCancel => {
+                state.open_orders.insert((report.symbol.clone(), report.order_id), WorkingOrder {
+                    symbol: report.symbol.clone(),
+                    order_list_id: report.order_list_id,
+                    side: Some(report.side),
+                    remaining: report.quantity - report.cumulative_filled_qty,
+                });
+            }
+            _ => {
+                state.open_orders.remove(&(report.symbol.clone(), report.order_id));
+            }
+        }
+    }
+
+    pub fn is_killed(&self) -> bool {
+        self.state.lock().unwrap().killed
+    }
+
+    /// Refuses all new orders and cancels every open order of the symbols the risk manager
+    /// knows about, returning how many were canceled.
+    ///
+    /// Failed cancels, like that of the second leg of an OCO the first cancel already
+    /// took, are only an error if the order is still open afterwards.
+    pub async fn kill_switch(&self) -> Result<usize, IOError> {
+        let symbols: HashSet<String> = {
+            let mut state = self.state.lock().unwrap();
+            state.killed = true;
+            state.open_orders.values().map(|order| order.symbol.clone())
+                .chain(state.positions.keys().cloned())
+                .chain(state.last_prices.keys().cloned())
+                .collect()
+        };
+        warn!("Kill switch activated, canceling the open orders of {} symbols", symbols.len());
+
+        let mut canceled = 0;
+        for symbol in &symbols {
+            for order in self.fetch_open_orders(symbol).await? {
+                match self.cancel_order(symbol, order.order_id).await {
+                    Ok(_) => canceled += 1,
+                    Err(e) => warn!("Failed to cancel order {} on {}: {}", order.order_id, symbol, e),
+                }
+            }
+            let remaining = self.fetch_open_orders(symbol).await?;
+            if !remaining.is_empty() {
+                return Err(IOError::new(ErrorKind::Other, format!("{} orders on {} are still open", remaining.len(), symbol)));
+            }
+        }
+        Ok(canceled)
+    }
+
+    /// Accepts new orders again after the kill switch.
+    pub fn resume(&self) {
+        info!("Kill switch released");
+        self.state.lock().unwrap().killed = false;
+    }
+
+    // Checks a new order, `replacing` an open order that goes away with it, and reserves
+    // its place under the limits until the returned reservation is dropped
+    fn check(&self, order: &Proposal, replacing: Option<i64>) -> Result<Reservation<'_>, IOError> {
+        let now = BinanceClient::generate_timestamp()?;
+        let mut state = self.state.lock().unwrap();
+        state.roll_day(now);
+        let quantity = state.check(&self.limits, order, replacing)?;
+        state.next_reservation += 1;
+        let id = state.next_reservation;
+        state.pending_orders.insert(id, PendingOrder {
+            symbol: order.symbol.clone(),
+            side: order.side,
+            quantity,
+            resting_orders: order.resting_orders,
+        });
+        Ok(Reservation { state: &self.state, id })
+    }
+
+    fn record_response(&self, response: &OrderResponse) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(side) = response.side.as_deref().and_then(parse_side) {
+            for fill in response.fills.iter().flatten() {
+                state.record_fill(&response.symbol, side, &Fill {
+                    trade_id: fill.trade_id,
+                    quantity: fill.qty,
+                    price: fill.price,
+                    commission: fill.commission,
+                    commission_asset: &fill.commission_asset,
+                }, response.transact_time);
+            }
+        }
+        // ACK responses carry no status, the order is open until a report says otherwise
+        if matches!(response.status.as_deref(), None | Some("NEW") | Some("PARTIALLY_FILLED")) {
+            state.open_orders.insert((response.symbol.clone(), response.order_id), WorkingOrder {
+                symbol: response.symbol.clone(),
+                order_list_id: response.order_list_id,
+                side: response.side.as_deref().and_then(parse_side),
+                remaining: response.orig_qty.unwrap_or_default() - response.executed_qty.unwrap_or_default(),
+            });
+        }
+    }
+}
+
+#[async_trait]
+impl OrderApi for RiskManager<'_> {
+    async fn create_limit_order(&self, order: LimitOrder) -> Result<OrderResponse, IOError> {
+        let _reservation = self.check(&Proposal {
+            symbol: order.symbol.clone(),
+            side: order.side,
+            quantity: Some(order.quantity),
+            quote_quantity: None,
+            prices: vec![order.price],
+            resting_orders: 1,
+        }, None)?;
+        let response = self.order_api.create_limit_order(order).await?;
+        self.record_response(&response);
+        Ok(response)
+    }
+
+    async fn create_stop_limit_order(&self, order: StopLimitOrder) -> Result<OrderResponse, IOError> {
+        let _reservation = self.check(&Proposal {
+            symbol: order.symbol.clone(),
+            side: order.side,
+            quantity: Some(order.quantity),
+            quote_quantity: None,
+            prices: vec![order.price, order.stop_price],
+            resting_orders: 1,
+        }, None)?;
+        let response = self.order_api.create_stop_limit_order(order).await?;
+        self.record_response(&response);
+        Ok(response)
+    }
+
+    async fn create_oco_order(&self, order: OcoOrder) -> Result<OrderListResponse, IOError> {
+        let _reservation = self.check(&Proposal {
+            symbol: order.symbol.clone(),
+            side: order.side,
+            quantity: Some(order.quantity),
+            quote_quantity: None,
+            prices: vec![order.price, order.stop_price, order.stop_limit_price],
+            resting_orders: 2,
+        }, None)?;
+        let (side, quantity) = (order.side, order.quantity);
+        let response = self.order_api.create_oco_order(order).await?;
+        for report in &response.order_reports {
+            self.record_response(report);
+        }
+        let mut state = self.state.lock().unwrap();
+        for entry in &response.orders {
+            state.open_orders.entry((entry.symbol.clone(), entry.order_id)).or_insert_with(|| WorkingOrder {
+                symbol: entry.symbol.clone(),
+                order_list_id: response.order_list_id,
+                side: Some(side),
+                remaining: quantity,
+            });
+        }
+        Ok(response)
+    }
+
+    async fn create_market_order(&self, order: MarketOrder) -> Result<OrderResponse, IOError> {
+        let _reservation = self.check(&Proposal {
+            symbol: order.symbol.clone(),
+            side: order.side,
+            quantity: order.quantity,
+            quote_quantity: order.quote_order_qty,
+            prices: Vec::new(),
+            resting_orders: 0,
+        }, None)?;
+        let response = self.order_api.create_market_order(order).await?;
+        self.record_response(&response);
+        Ok(response)
+    }
+
+    async fn cancel_order(&self, symbol: &str, order_id: i64) -> Result<CancelOrderResponse, IOError> {
+        let response = self.order_api.cancel_order(symbol, order_id).await?;
+        let mut state = self.state.lock().unwrap();
+        state.open_orders.remove(&(symbol.to_string(), order_id));
+        // Canceling one leg of an OCO cancels the list
+        if response.order_list_id != -1 {
+            state.open_orders.retain(|(order_symbol, _), order| {
+                *order_symbol != response.symbol || order.order_list_id != response.order_list_id
+            });
+        }
+        Ok(response)
+    }
+
+    async fn cancel_replace_order(&self, order: CancelReplaceOrder) -> Result<CancelReplaceResponse, IOError> {
+        let prices: Vec<f64> = order.price.into_iter().chain(order.stop_price).collect();
+        let _reservation = self.check(&Proposal {
+            symbol: order.symbol.clone(),
+            side: order.side,
+            quantity: order.quantity,
+            quote_quantity: None,
+            prices,
+            resting_orders: 1,
+        }, Some(order.cancel_order_id))?;
+        let canceled = (order.symbol.clone(), order.cancel_order_id);
+        let response = self.order_api.cancel_replace_order(order).await?;
+        self.state.lock().unwrap().open_orders.remove(&canceled);
+        self.record_response(&response.new_order_response);
+        Ok(response)
+    }
+
+    async fn fetch_open_orders(&self, symbol: &str) -> Result<Vec<OpenOrder>, IOError> {
+        let open_orders = self.order_api.fetch_open_orders(symbol).await?;
+        let mut state = self.state.lock().unwrap();
+        let order_lists: HashMap<i64, i64> = state.open_orders
+            .iter()
+            .filter(|((order_symbol, _), _)| order_symbol == symbol)
+            .map(|((_, order_id), order)| (*order_id, order.order_list_id))
+            .collect();
+        state.open_orders.retain(|(order_symbol, _), _| order_symbol != symbol);
+        for order in &open_orders {
+            state.open_orders.insert((order.symbol.clone(), order.order_id), WorkingOrder {
+                symbol: order.symbol.clone(),
+                order_list_id: order_lists.get(&order.order_id).copied().unwrap_or(-1),
+                side: parse_side(&order.side),
+                remaining: order.orig_qty - order.executed_qty,
+            });
+        }
+        Ok(open_orders)
+    }
+}
+
+// A new order as the risk checks see it
+#[derive(Debug, Clone)]
+struct Proposal {
+    symbol: String,
+    side: Side,
+    quantity: Option<f64>,
+    quote_quantity: Option<f64>, // Market orders sized in the quote asset
+    prices: Vec<f64>, // Limit and stop prices
+    resting_orders: usize, // Orders it adds to the book
+}
+
+// An open order of ours
+#[derive(Debug)]
+struct WorkingOrder {
+    symbol: String,
+    order_list_id: i64,
+    side: Option<Side>,
+    remaining: f64, // Quantity still to execute
+}
+
+// An order that passed the checks and has not been answered yet
+#[derive(Debug)]
+struct PendingOrder {
+    symbol: String,
+    side: Side,
+    quantity: f64,
+    resting_orders: usize,
+}
+
+// Releases the place of a pending order under the limits once it has been answered,
+// after its response is recorded, or failed
+struct Reservation<'a> {
+    state: &'a Mutex<RiskState>,
+    id: u64,
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.pending_orders.remove(&self.id);
+        }
+    }
+}
+
+// A trade of one of our orders
+struct Fill<'a> {
+    trade_id: i64,
+    quantity: f64,
+    price: f64,
+    commission: f64,
+    commission_asset: &'a str,
+}
+
+#[derive(Debug, Default)]
+struct RiskState {
+    killed: bool,
+    positions: HashMap<String, f64>,
+    last_prices: HashMap<String, f64>,
+    average_prices: HashMap<String, f64>,
+    open_orders: HashMap<(String, i64), WorkingOrder>, // By symbol and order id
+    pending_orders: HashMap<u64, PendingOrder>, // By reservation
+    next_reservation: u64,
+    seen_trades: HashSet<(String, i64)>, // Trades recorded today
+    previous_trades: HashSet<(String, i64)>,
+    day: u64,
+    day_start_value: f64,
+    cash_flow: f64, // Of the day's fills
+}
+
+impl RiskState {
+    // Returns the quantity of the order
+    fn check(&self, limits: &RiskLimits, order: &Proposal, replacing: Option<i64>) -> Result<f64, RiskRejection> {
+        if self.killed {
+            return Err(RiskRejection::KillSwitch);
+        }
+        let symbol = &order.symbol;
+        let no_reference_price = || RiskRejection::NoReferencePrice { symbol: symbol.clone() };
+        let last_price = self.last_prices.get(symbol).copied();
+
+        // Market orders are valued at the last trade
+        let price = match order.prices.first() {
+            Some(price) => *price,
+            None => match last_price {
+                Some(last_price) => last_price,
+                None if limits.needs_last_price() || limits.fat_finger_percentage.is_some() => return Err(no_reference_price()),
+                None => 0.0,
+            },
+        };
+        let (quantity, notional) = match (order.quantity, order.quote_quantity) {
+            (Some(quantity), _) => (quantity, quantity * price),
+            (None, Some(quote_quantity)) if price > 0.0 => (quote_quantity / price, quote_quantity),
+            _ => (0.0, order.quote_quantity.unwrap_or_default()),
+        };
+
+        if let Some(limit) = limits.max_order_notional {
+            if notional > limit {
+                return Err(RiskRejection::OrderNotional { notional, limit });
+            }
+        }
+        if let Some(limit_percentage) = limits.price_collar_percentage {
+            let last_price = last_price.ok_or_else(no_reference_price)?;
+            for price in &order.prices {
+                if (price - last_price).abs() / last_price * 100.0 > limit_percentage {
+                    return Err(RiskRejection::PriceCollar { price: *price, last_price, limit_percentage });
+                }
+            }
+        }
+        if let Some(limit_percentage) = limits.fat_finger_percentage {
+            let average_price = self.average_prices.get(symbol).copied().ok_or_else(no_reference_price)?;
+            let prices = if order.prices.is_empty() { vec![price] } else { order.prices.clone() };
+            for price in prices {
+                if (price - average_price).abs() / average_price * 100.0 > limit_percentage {
+                    return Err(RiskRejection::FatFinger { price, average_price, limit_percentage });
+                }
+            }
+        }
+        if let Some(limit) = limits.max_open_orders {
+            let replaced = replacing
+                .filter(|order_id| self.open_orders.contains_key(&(symbol.clone(), *order_id)))
+                .map_or(0, |_| 1);
+            let pending: usize = self.pending_orders.values().map(|pending| pending.resting_orders).sum();
+            let count = self.open_orders.len() + pending - replaced + order.resting_orders;
+            if order.resting_orders > 0 && count > limit {
+                return Err(RiskRejection::OpenOrders { count, limit });
+            }
+        }
+
+        // The position if every open order on the same side fills as well
+        let position = self.positions.get(symbol).copied().unwrap_or_default()
+            + self.working_quantity(symbol, order.side, replacing);
+        let new_position = match order.side {
+            Side::Buy => position + quantity,
+            Side::Sell => position - quantity,
+        };
+        if new_position.abs() <= position.abs() && new_position * position >= 0.0 {
+            return Ok(quantity);
+        }
+        if let Some(limit) = limits.position_limit(symbol) {
+            let notional = new_position.abs() * price;
+            if notional > limit {
+                return Err(RiskRejection::Position { symbol: symbol.clone(), notional, limit });
+            }
+        }
+        if let Some(limit) = limits.max_gross_exposure {
+            let others: f64 = self.positions
+                .iter()
+                .filter(|(other, _)| *other != symbol)
+                .map(|(other, position)| position.abs() * self.last_prices.get(other).copied().unwrap_or_default())
+                .sum();
+            let notional = others + new_position.abs() * price;
+            if notional > limit {
+                return Err(RiskRejection::GrossExposure { notional, limit });
+            }
+        }
+        if let Some(limit) = limits.max_daily_loss {
+            let loss = -self.daily_pnl();
+            if loss >= limit {
+                return Err(RiskRejection::DailyLoss { loss, limit });
+            }
+        }
+        Ok(quantity)
+    }
+
+    // Signed quantity still to execute of the open and pending orders of `symbol` on `side`,
+    // counting the legs of an order list once since only one of them can fill
+    fn working_quantity(&self, symbol: &str, side: Side, replacing: Option<i64>) -> f64 {
+        let mut lists: HashMap<i64, f64> = HashMap::new();
+        let mut quantity: f64 = self.pending_orders
+            .values()
+            .filter(|pending| pending.symbol == symbol && pending.side == side)
+            .map(|pending| pending.quantity)
+            .sum();
+        for ((_, order_id), order) in &self.open_orders {
+            if order.symbol != symbol || order.side != Some(side) || Some(*order_id) == replacing {
+                continue;
+            }
+            if order.order_list_id == -1 {
+                quantity += order.remaining;
+            } else {
+                let list = lists.entry(order.order_list_id).or_default();
+                *list = list.max(order.remaining);
+            }
+        }
+        quantity += lists.values().sum::<f64>();
+        match side {
+            Side::Buy => quantity,
+            Side::Sell => -quantity,
+        }
+    }
+
+    fn record_fill(&mut self, symbol: &str, side: Side, fill: &Fill, time: u64) {
+        self.roll_day(time);
+        let trade = (symbol.to_string(), fill.trade_id);
+        if self.previous_trades.contains(&trade) || !self.seen_trades.insert(trade) {
+            return;
+        }
+        let position = self.positions.entry(symbol.to_string()).or_default();
+        match side {
+            Side::Buy => {
+                *position += fill.quantity;
+                self.cash_flow -= fill.quantity * fill.price;
+            }
+            Side::Sell => {
+                *position -= fill.quantity;
+                self.cash_flow += fill.quantity * fill.price;
+            }
+        }
+        if !fill.commission_asset.is_empty() && symbol.starts_with(fill.commission_asset) {
+            *position -= fill.commission;
+        } else if !fill.commission_asset.is_empty() && symbol.ends_with(fill.commission_asset) {
+            self.cash_flow -= fill.commission;
+        }
+        self.last_prices.entry(symbol.to_string()).or_insert(fill.price);
+    }
+
+    // Starts a new day at 00:00 UTC from the positions' value at that point
+    fn roll_day(&mut self, time: u64) {
+        let day = time / DAY_MS;
+        if day > self.day {
+            self.day = day;
+            self.day_start_value = self.marked_value();
+            self.cash_flow = 0.0;
+            self.previous_trades = std::mem::take(&mut self.seen_trades);
+        }
+    }
+
+    fn marked_value(&self) -> f64 {
+        self.positions
+            .iter()
+            .map(|(symbol, position)| position * self.last_prices.get(symbol).copied().unwrap_or_default())
+            .sum()
+    }
+
+    fn daily_pnl(&self) -> f64 {
+        self.marked_value() + self.cash_flow - self.day_start_value
+    }
+}
+
+fn parse_side(side: &str) -> Option<Side> {
+    match side {
+        "BUY" => Some(Side::Buy),
+        "SELL" => Some(Side::Sell),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binance_client::test_support::paper_trader;
+
+    fn rejection(result: Result<OrderResponse, IOError>) -> RiskRejection {
+        RiskRejection::from_error(&result.unwrap_err()).cloned().expect("rejected by the risk manager")
+    }
+
+    #[tokio::test]
+    async fn test_order_limits() {
+        let paper_trader = paper_trader(1999.0, 2001.0);
+        let limits = RiskLimits::new()
+            .with_max_order_notional(5000.0)
+            .with_symbol_max_position_notional("ETHUSDT", 5000.0)
+            .with_max_open_orders(2)
+            .with_price_collar(5.0)
+            .with_fat_finger_check(10.0);
+        let risk_manager = RiskManager::new(&paper_trader, limits);
+
+        let buy = |quantity, price| LimitOrder::new("ETHUSDT", Side::Buy, quantity, price, 0);
+        let error = risk_manager.create_limit_order(buy(1.0, 1990.0)).await.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::PermissionDenied);
+        assert_eq!(RiskRejection::from_error(&error), Some(&RiskRejection::NoReferencePrice { symbol: "ETHUSDT".to_string() }));
+
+        risk_manager.set_last_price("ETHUSDT", 2000.0);
+        risk_manager.set_average_price("ETHUSDT", 2200.0);
+        assert!(matches!(rejection(risk_manager.create_limit_order(buy(3.0, 1990.0)).await), RiskRejection::OrderNotional { .. }));
+        assert!(matches!(rejection(risk_manager.create_limit_order(buy(1.0, 1850.0)).await), RiskRejection::PriceCollar { .. }));
+        // Within 5% of the last trade, but more than 10% below the 24h average
+        assert_eq!(
+            rejection(risk_manager.create_limit_order(buy(1.0, 1950.0)).await),
+            RiskRejection::FatFinger { price: 1950.0, average_price: 2200.0, limit_percentage: 10.0 },
+        );
+
+        risk_manager.set_average_price("ETHUSDT", 2000.0);
+        risk_manager.create_limit_order(buy(1.0, 1990.0)).await.unwrap();
+        risk_manager.create_limit_order(buy(1.0, 1980.0)).await.unwrap();
+        assert_eq!(
+            rejection(risk_manager.create_limit_order(buy(1.0, 1970.0)).await),
+            RiskRejection::OpenOrders { count: 3, limit: 2 },
+        );
+
+        // Bought 2 ETH at 2001, a third would exceed the position limit
+        for order in risk_manager.fetch_open_orders("ETHUSDT").await.unwrap() {
+            risk_manager.cancel_order("ETHUSDT", order.order_id).await.unwrap();
+        }
+        risk_manager.create_market_order(MarketOrder::new_with_base_asset("ETHUSDT", Side::Buy, 2.0)).await.unwrap();
+        assert_eq!(risk_manager.position("ETHUSDT"), 2.0);
+        assert!(matches!(rejection(risk_manager.create_limit_order(buy(1.0, 1990.0)).await), RiskRejection::Position { .. }));
+        let sell = MarketOrder::new_with_base_asset("ETHUSDT", Side::Sell, 1.0);
+        assert!(risk_manager.create_market_order(sell).await.is_ok());
+        assert_eq!(risk_manager.position("ETHUSDT"), 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_open_orders_count_towards_the_position() {
+        let paper_trader = paper_trader(1999.0, 2001.0);
+        paper_trader.deposit("ETH", 1.0);
+        let limits = RiskLimits::new().with_max_position_notional(5000.0);
+        let risk_manager = RiskManager::new(&paper_trader, limits);
+        risk_manager.set_last_price("ETHUSDT", 2000.0);
+
+        // Two resting bids of 1 ETH would make a third too many once they all fill
+        let buy = |price| LimitOrder::new("ETHUSDT", Side::Buy, 1.0, price, 0);
+        risk_manager.create_limit_order(buy(1990.0)).await.unwrap();
+        risk_manager.create_limit_order(buy(1980.0)).await.unwrap();
+        assert!(matches!(rejection(risk_manager.create_limit_order(buy(1970.0)).await), RiskRejection::Position { .. }));
+
+        // Replacing a bid frees its quantity
+        let open = risk_manager.fetch_open_orders("ETHUSDT").await.unwrap();
+        let replace = CancelReplaceOrder::new_limit("ETHUSDT", Side::Buy, open[0].order_id, 1.0, 1970.0);
+        risk_manager.cancel_replace_order(replace).await.unwrap();
+
+        // A sell of the 1 ETH held reduces the position, a second one on top of it opens a short
+        risk_manager.set_position("ETHUSDT", 1.0);
+        let sell = |quantity| LimitOrder::new("ETHUSDT", Side::Sell, quantity, 2100.0, 0);
+        risk_manager.create_limit_order(sell(1.0)).await.unwrap();
+        assert_eq!(
+            rejection(risk_manager.create_limit_order(sell(3.0)).await),
+            RiskRejection::Position { symbol: "ETHUSDT".to_string(), notional: 6300.0, limit: 5000.0 },
+        );
+    }
+
+    #[tokio::test]
+    async fn test_orders_on_their_way_count_towards_the_limits() {
+        let paper_trader = paper_trader(1999.0, 2001.0);
+        let limits = RiskLimits::new().with_max_position_notional(5000.0);
+        let risk_manager = RiskManager::new(&paper_trader, limits);
+        risk_manager.set_last_price("ETHUSDT", 2000.0);
+
+        // A second bid checked while the first is still being sent sees it
+        let bid = |quantity| Proposal {
+            symbol: "ETHUSDT".to_string(),
+            side: Side::Buy,
+            quantity: Some(quantity),
+            quote_quantity: None,
+            prices: vec![1990.0],
+            resting_orders: 1,
+        };
+        let reservation = risk_manager.check(&bid(2.0), None).unwrap();
+        let error = risk_manager.check(&bid(1.0), None).err().unwrap();
+        assert!(matches!(RiskRejection::from_error(&error), Some(RiskRejection::Position { .. })));
+        drop(reservation);
+        assert!(risk_manager.check(&bid(1.0), None).is_ok());
+
+        // An order the exchange refuses gives its place back
+        let risk_manager = RiskManager::new(&paper_trader, RiskLimits::new().with_max_open_orders(2));
+        let buy = |quantity| LimitOrder::new("ETHUSDT", Side::Buy, quantity, 1990.0, 0);
+        let error = risk_manager.create_limit_order(buy(60.0)).await.unwrap_err();
+        assert!(RiskRejection::from_error(&error).is_none());
+        risk_manager.create_limit_order(buy(1.0)).await.unwrap();
+        risk_manager.create_limit_order(buy(1.0)).await.unwrap();
+        assert_eq!(
+            rejection(risk_manager.create_limit_order(buy(0.1)).await),
+            RiskRejection::OpenOrders { count: 3, limit: 2 },
+        );
+    }
+
+    #[tokio::test]
+    async fn test_daily_loss_and_kill_switch() {
+        let paper_trader = paper_trader(1999.0, 2001.0);
+        let risk_manager = RiskManager::new(&paper_trader, RiskLimits::new().with_max_daily_loss(100.0));
+        let mut events = paper_trader.subscribe();
+
+        risk_manager.create_market_order(MarketOrder::new_with_base_asset("ETHUSDT", Side::Buy, 1.0)).await.unwrap();
+        // The execution report of the same trade is not counted again
+        while let Ok(event) = events.try_recv() {
+            risk_manager.apply(&event);
+        }
+        assert_eq!(risk_manager.position("ETHUSDT"), 1.0);
+        risk_manager.set_last_price("ETHUSDT", 1900.0);
+        assert_eq!(risk_manager.daily_pnl().unwrap(), -101.0);
+
+        let buy = LimitOrder::new("ETHUSDT", Side::Buy, 0.1, 1800.0, 0);
+        assert_eq!(
+            rejection(risk_manager.create_limit_order(buy).await),
+            RiskRejection::DailyLoss { loss: 101.0, limit: 100.0 },
+        );
+        let sell = LimitOrder::new("ETHUSDT", Side::Sell, 1.0, 2100.0, 0);
+        risk_manager.create_limit_order(sell).await.unwrap();
+        assert_eq!(risk_manager.open_order_count(), 1);
+
+        assert_eq!(risk_manager.kill_switch().await.unwrap(), 1);
+        assert!(paper_trader.fetch_open_orders("ETHUSDT").await.unwrap().is_empty());
+        assert_eq!(risk_manager.open_order_count(), 0);
+        let sell = MarketOrder::new_with_base_asset("ETHUSDT", Side::Sell, 1.0);
+        assert_eq!(rejection(risk_manager.create_market_order(sell.clone()).await), RiskRejection::KillSwitch);
+        risk_manager.resume();
+        assert!(risk_manager.create_market_order(sell).await.is_ok());
+    }
+}
@@ -0,0 +1,97 @@
+//! JSON spellings of the schema's enums, so decoded responses match what the JSON API returns.
+
+pub fn rate_limit_type(value: u8) -> &'static str {
+    match value {
+        0 => "RAW_REQUESTS",
+        1 => "CONNECTIONS",
+        2 => "REQUEST_WEIGHT",
+        3 => "ORDERS",
+        _ => "UNKNOWN",
+    }
+}
+
+pub fn rate_limit_interval(value: u8) -> &'static str {
+    match value {
+        0 => "SECOND",
+        1 => "MINUTE",
+        2 => "HOUR",
+        3 => "DAY",
+        _ => "UNKNOWN",
+    }
+}
+
+pub fn symbol_status(value: u8) -> &'static str {
+    match value {
+        0 => "PRE_TRADING",
+        1 => "TRADING",
+        2 => "POST_TRADING",
+        3 => "END_OF_DAY",
+        4 => "HALT",
+        5 => "AUCTION_MATCH",
+        7 => "BREAK",
+        _ => "UNKNOWN",
+    }
+}
+
+pub fn order_status(value: u8) -> &'static str {
+    match value {
+        0 => "NEW",
+        1 => "PARTIALLY_FILLED",
+        2 => "FILLED",
+        3 => "CANCELED",
+        4 => "PENDING_CANCEL",
+        5 => "REJECTED",
+        6 => "EXPIRED",
+        9 => "EXPIRED_IN_MATCH",
+        _ => "UNKNOWN",
+    }
+}
+
+pub fn time_in_force(value: u8) -> &'static str {
+    match value {
+        0 => "GTC",
+        1 => "IOC",
+        2 => "FOK",
+        _ => "UNKNOWN",
+    }
+}
+
+pub fn order_type(value: u8) -> &'static str {
+    match value {
+        0 => "MARKET",
+        1 => "LIMIT",
+        2 => "STOP_LOSS",
+        3 => "STOP_LOSS_LIMIT",
+        4 => "TAKE_PROFIT",
+        5 => "TAKE_PROFIT_LIMIT",
+        6 => "LIMIT_MAKER",
+        _ => "UNKNOWN",
+    }
+}
+
+pub fn order_side(value: u8) -> &'static str {
+    match value {
+        0 => "BUY",
+        1 => "SELL",
+        _ => "UNKNOWN",
+    }
+}
+
+pub fn self_trade_prevention_mode(value: u8) -> &'static str {
+    match value {
+        1 => "NONE",
+        2 => "EXPIRE_TAKER",
+        3 => "EXPIRE_MAKER",
+        4 => "EXPIRE_BOTH",
+        _ => "UNKNOWN",
+    }
+}
+
+pub fn floor(value: u8) -> &'static str {
+    match value {
+        1 => "EXCHANGE",
+        2 => "BROKER",
+        3 => "SOR",
+        _ => "UNKNOWN",
+    }
+}
@@ -0,0 +1,221 @@
+use std::io::{Error as IOError, ErrorKind};
+use crate::binance_client::exchange_info::{ExchangeInfo, Filter, RateLimit, SymbolInfo};
+use crate::binance_client::sbe::enums;
+use crate::binance_client::sbe::reader::{decimal, SbeReader};
+use crate::binance_client::sbe::{MessageHeader, SbeDecode};
+
+const EXCHANGE_INFO_RESPONSE_TEMPLATE_ID: u16 = 103;
+
+impl SbeDecode for ExchangeInfo {
+    const TEMPLATE_IDS: &'static [u16] = &[EXCHANGE_INFO_RESPONSE_TEMPLATE_ID];
+
+    fn decode_message(header: &MessageHeader, reader: &mut SbeReader) -> Result<Self, IOError> {
+        reader.skip_to_block_end(reader.position(), header.block_length)?;
+
+        let (block_length, count) = reader.group_header()?;
+        let mut rate_limits = Vec::with_capacity(count);
+        for _ in 0..count {
+            let block_start = reader.position();
+            rate_limits.push(RateLimit {
+                rate_limit_type: enums::rate_limit_type(reader.u8()?).to_string(),
+                interval: enums::rate_limit_interval(reader.u8()?).to_string(),
+                interval_num: reader.u8()? as i64,
+                limit: reader.i64()?,
+            });
+            reader.skip_to_block_end(block_start, block_length)?;
+        }
+
+        // Exchange-wide filters are not part of `ExchangeInfo`
+        let (block_length, count) = reader.group_header()?;
+        for _ in 0..count {
+            reader.skip_to_block_end(reader.position(), block_length)?;
+            reader.message_data8()?;
+        }
+
+        let (block_length, count) = reader.group_header()?;
+        let mut symbols = Vec::with_capacity(count);
+        for _ in 0..count {
+            symbols.push(read_symbol(reader, block_length)?);
+        }
+
+        // The trailing `sors` group is not part of `ExchangeInfo`
+        Ok(ExchangeInfo {
+            // SBE timestamps are always UTC and the server time is omitted
+            timezone: "UTC".to_string(),
+            server_time: 0,
+            rate_limits,
+            symbols,
+        })
+    }
+}
+
+fn read_symbol(reader: &mut SbeReader, block_length: u16) -> Result<SymbolInfo, IOError> {
+    let block_start = reader.position();
+    let status = enums::symbol_status(reader.u8()?).to_string();
+    reader.skip_to_block_end(block_start, block_length)?;
+
+    let (filter_block_length, count) = reader.group_header()?;
+    let mut filters = Vec::with_capacity(count);
+    for _ in 0..count {
+        reader.skip_to_block_end(reader.position(), filter_block_length)?;
+        if let Some(filter) = decode_filter(reader.message_data8()?)? {
+            filters.push(filter);
+        }
+    }
+
+    let (permission_block_length, count) = reader.group_header()?;
+    let mut permissions = Vec::with_capacity(count);
+    for _ in 0..count {
+        reader.skip_to_block_end(reader.position(), permission_block_length)?;
+        permissions.push(reader.var_string8()?);
+    }
+
+    Ok(SymbolInfo {
+        symbol: reader.var_string8()?,
+        base_asset: reader.var_string8()?,
+        quote_asset: reader.var_string8()?,
+        status,
+        filters,
+        permissions,
+    })
+}
+
+// Filters are nested SBE messages; returns None for filter types `Filter` does not model.
+fn decode_filter(buffer: &[u8]) -> Result<Option<Filter>, IOError> {
+    let mut reader = SbeReader::new(buffer);
+    let header = MessageHeader::read(&mut reader)?;
+    let filter = match header.template_id {
+        1 => {
+            let exponent = reader.i8()?;
+            Filter::PriceFilter {
+                min_price: decimal(reader.i64()?, exponent),
+                max_price: decimal(reader.i64()?, exponent),
+                tick_size: decimal(reader.i64()?, exponent),
+            }
+        }
+        2 => {
+            let exponent = reader.i8()?;
+            Filter::PercentPrice {
+                multiplier_up: decimal(reader.i64()?, exponent),
+                multiplier_down: decimal(reader.i64()?, exponent),
+                avg_price_mins: reader.i32()? as i64,
+            }
+        }
+        3 => {
+            let exponent = reader.i8()?;
+            Filter::PercentPriceBySide {
+                bid_multiplier_up: decimal(reader.i64()?, exponent),
+                bid_multiplier_down: decimal(reader.i64()?, exponent),
+                ask_multiplier_up: decimal(reader.i64()?, exponent),
+                ask_multiplier_down: decimal(reader.i64()?, exponent),
+                avg_price_mins: reader.i32()? as i64,
+            }
+        }
+        4 => {
+            let exponent = reader.i8()?;
+            Filter::LotSize {
+                min_qty: decimal(reader.i64()?, exponent),
+                max_qty: decimal(reader.i64()?, exponent),
+                step_size: decimal(reader.i64()?, exponent),
+            }
+        }
+        5 => {
+            let exponent = reader.i8()?;
+            Filter::MinNotional {
+                min_notional: decimal(reader.i64()?, exponent),
+                apply_to_market: reader.bool()?,
+                avg_price_mins: Some(reader.i32()? as i64),
+            }
+        }
+        6 => {
+            let exponent = reader.i8()?;
+            Filter::Notional {
+                min_notional: decimal(reader.i64()?, exponent),
+                apply_min_to_market: reader.bool()?,
+                max_notional: Some(decimal(reader.i64()?, exponent)),
+                apply_max_to_market: Some(reader.bool()?),
+                avg_price_mins: Some(reader.i32()? as i64),
+            }
+        }
+        7 => Filter::IcebergParts { limit: reader.i64()? },
+        8 => {
+            let exponent = reader.i8()?;
+            Filter::MarketLotSize {
+                min_qty: decimal(reader.i64()?, exponent),
+                max_qty: decimal(reader.i64()?, exponent),
+                step_size: decimal(reader.i64()?, exponent),
+            }
+        }
+        9 => Filter::MaxNumOrders { max_num_orders: reader.i64()? },
+        10 => Filter::MaxNumAlgoOrders { max_num_algo_orders: reader.i64()? },
+        11 => Filter::MaxNumIcebergOrders { max_num_iceberg_orders: reader.i64()? },
+        12 => {
+            let exponent = reader.i8()?;
+            Filter::MaxPosition { max_position: decimal(reader.i64()?, exponent) }
+        }
+        13 => Filter::TrailingDelta {
+            min_trailing_above_delta: reader.i64()?,
+            max_trailing_above_delta: reader.i64()?,
+            min_trailing_below_delta: reader.i64()?,
+            max_trailing_below_delta: reader.i64()?,
+        },
+        14 => return Ok(None),
+        template_id => {
+            return Err(IOError::new(ErrorKind::InvalidData, format!("Unknown SBE filter template id {}", template_id)));
+        }
+    };
+    Ok(Some(filter))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binance_client::sbe::decode;
+    use crate::binance_client::sbe::reader::SbeWriter;
+
+    #[test]
+    fn test_decode_exchange_info() {
+        let price_filter = SbeWriter::default().header(25, 1).i8(-2).i64(1).i64(100_000_000).i64(1).build();
+        let lot_size = SbeWriter::default().header(25, 4).i8(-5).i64(1).i64(900_000_000_000).i64(1).build();
+        let t_plus_sell = SbeWriter::default().header(8, 14).i64(i64::MIN).build();
+        let exchange_filter = SbeWriter::default().header(8, 15).i64(1000).build();
+
+        let buffer = SbeWriter::default()
+            .header(0, EXCHANGE_INFO_RESPONSE_TEMPLATE_ID)
+            .group(11, 1).u8(2).u8(1).u8(1).i64(6000)
+            .group(0, 1).data8(&exchange_filter)
+            .group(16, 1)
+            .u8(1).u8(8).u8(8).u8(8).u8(8).u16(0b111_1111).u8(1).u8(1).u8(1).u8(1).u8(1).u8(1).u8(0).u8(1).u8(0b1111)
+            .group(0, 3).data8(&price_filter).data8(&lot_size).data8(&t_plus_sell)
+            .group(0, 1).string8("SPOT")
+            .string8("BTCUSDT").string8("BTC").string8("USDT")
+            .group(0, 0)
+            .build();
+
+        let exchange_info: ExchangeInfo = decode(&buffer).unwrap();
+        assert_eq!(exchange_info.rate_limits[0].rate_limit_type, "REQUEST_WEIGHT");
+        assert_eq!(exchange_info.rate_limits[0].interval, "MINUTE");
+        assert_eq!(exchange_info.rate_limits[0].limit, 6000);
+
+        let symbol = &exchange_info.symbols[0];
+        assert_eq!(symbol.symbol, "BTCUSDT");
+        assert_eq!(symbol.base_asset, "BTC");
+        assert_eq!(symbol.quote_asset, "USDT");
+        assert_eq!(symbol.status, "TRADING");
+        assert_eq!(symbol.permissions, vec!["SPOT".to_string()]);
+        assert_eq!(symbol.filters.len(), 2);
+        match &symbol.filters[0] {
+            Filter::PriceFilter { min_price, max_price, tick_size } => {
+                assert_eq!(*min_price, 0.01);
+                assert_eq!(*max_price, 1_000_000.0);
+                assert_eq!(*tick_size, 0.01);
+            }
+            other => panic!("Unexpected filter {:?}", other),
+        }
+        match &symbol.filters[1] {
+            Filter::LotSize { step_size, .. } => assert_eq!(*step_size, 0.00001),
+            other => panic!("Unexpected filter {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use std::io::Error as IOError;
+use crate::binance_client::market_data::{DepthSnapshot, MarketTrade, PriceLevel};
+use crate::binance_client::sbe::reader::{decimal, decimal128, micros_to_millis, SbeReader};
+use crate::binance_client::sbe::{MessageHeader, SbeDecode};
+use crate::binance_client::streams::kline_data::RestKline;
+
+const DEPTH_RESPONSE_TEMPLATE_ID: u16 = 200;
+const TRADES_RESPONSE_TEMPLATE_ID: u16 = 201;
+const KLINES_RESPONSE_TEMPLATE_ID: u16 = 203;
+
+impl SbeDecode for DepthSnapshot {
+    const TEMPLATE_IDS: &'static [u16] = &[DEPTH_RESPONSE_TEMPLATE_ID];
+
+    fn decode_message(header: &MessageHeader, reader: &mut SbeReader) -> Result<Self, IOError> {
+        let block_start = reader.position();
+        let last_update_id = reader.i64()? as u64;
+        let price_exponent = reader.i8()?;
+        let qty_exponent = reader.i8()?;
+        reader.skip_to_block_end(block_start, header.block_length)?;
+
+        let bids = read_price_levels(reader, price_exponent, qty_exponent)?;
+        let asks = read_price_levels(reader, price_exponent, qty_exponent)?;
+        Ok(DepthSnapshot { last_update_id, bids, asks })
+    }
+}
+
+fn read_price_levels(reader: &mut SbeReader, price_exponent: i8, qty_exponent: i8) -> Result<Vec<PriceLevel>, IOError> {
+    let (block_length, count) = reader.group_header()?;
+    let mut levels = Vec::with_capacity(count);
+    for _ in 0..count {
+        let block_start = reader.position();
+        let price = decimal(reader.i64()?, price_exponent);
+        let quantity = decimal(reader.i64()?, qty_exponent);
+        reader.skip_to_block_end(block_start, block_length)?;
+        levels.push(PriceLevel { price, quantity });
+    }
+    Ok(levels)
+}
+
+impl SbeDecode for Vec<MarketTrade> {
+    const TEMPLATE_IDS: &'static [u16] = &[TRADES_RESPONSE_TEMPLATE_ID];
+
+    fn decode_message(header: &MessageHeader, reader: &mut SbeReader) -> Result<Self, IOError> {
+        let block_start = reader.position();
+        let price_exponent = reader.i8()?;
+        let qty_exponent = reader.i8()?;
+        reader.skip_to_block_end(block_start, header.block_length)?;
+
+        let (block_length, count) = reader.group_header()?;
+        let mut trades = Vec::with_capacity(count);
+        for _ in 0..count {
+            let block_start = reader.position();
+            trades.push(MarketTrade {
+                id: reader.i64()?,
+                price: decimal(reader.i64()?, price_exponent),
+                qty: decimal(reader.i64()?, qty_exponent),
+                quote_qty: decimal(reader.i64()?, price_exponent),
+                time: micros_to_millis(reader.i64()?),
+                is_buyer_maker: reader.bool()?,
+                is_best_match: reader.bool()?,
+            });
+            reader.skip_to_block_end(block_start, block_length)?;
+        }
+        Ok(trades)
+    }
+}
+
+impl SbeDecode for Vec<RestKline> {
+    const TEMPLATE_IDS: &'static [u16] = &[KLINES_RESPONSE_TEMPLATE_ID];
+
+    fn decode_message(header: &MessageHeader, reader: &mut SbeReader) -> Result<Self, IOError> {
+        let block_start = reader.position();
+        let price_exponent = reader.i8()?;
+        let qty_exponent = reader.i8()?;
+        reader.skip_to_block_end(block_start, header.block_length)?;
+
+        let (block_length, count) = reader.group_header()?;
+        let mut klines = Vec::with_capacity(count);
+        for _ in 0..count {
+            let block_start = reader.position();
+            klines.push(RestKline {
+                open_time: micros_to_millis(reader.i64()?),
+                open_price: decimal(reader.i64()?, price_exponent),
+                high_price: decimal(reader.i64()?, price_exponent),
+                low_price: decimal(reader.i64()?, price_exponent),
+                close_price: decimal(reader.i64()?, price_exponent),
+                base_asset_volume: decimal128(reader.i128()?, qty_exponent),
+                close_time: micros_to_millis(reader.i64()?),
+                quote_asset_volume: decimal128(reader.i128()?, price_exponent),
+                number_of_trades: reader.i64()? as u32,
+                taker_buy_base_asset_volume: decimal128(reader.i128()?, qty_exponent),
+                taker_buy_quote_asset_volume: decimal128(reader.i128()?, price_exponent),
+                ignore: "0".to_string(),
+            });
+            reader.skip_to_block_end(block_start, block_length)?;
+        }
+        Ok(klines)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binance_client::sbe::decode;
+    use crate::binance_client::sbe::reader::SbeWriter;
+
+    #[test]
+    fn test_decode_depth() {
+        let buffer = SbeWriter::default()
+            .header(10, DEPTH_RESPONSE_TEMPLATE_ID)
+            .i64(1027024).i8(-2).i8(-8)
+            .group(16, 1).i64(4_200_012).i64(150_000_000)
+            .group(16, 2).i64(4_200_013).i64(1).i64(4_200_100).i64(200_000_000)
+            .build();
+
+        let depth: DepthSnapshot = decode(&buffer).unwrap();
+        assert_eq!(depth.last_update_id, 1027024);
+        assert_eq!(depth.bids, vec![PriceLevel { price: 42000.12, quantity: 1.5 }]);
+        assert_eq!(depth.asks[0].quantity, 0.00000001);
+        assert_eq!(depth.asks[1].price, 42001.0);
+    }
+
+    #[test]
+    fn test_decode_trades_with_longer_block() {
+        // A newer schema may append fields to the entry block; they must be skipped
+        let buffer = SbeWriter::default()
+            .header(2, TRADES_RESPONSE_TEMPLATE_ID)
+            .i8(-2).i8(-3)
+            .group(44, 1)
+            .i64(28457).i64(400).i64(12_000).i64(48_000).i64(1_499_865_549_590_000).u8(1).u8(1).u16(0xffff)
+            .build();
+
+        let trades: Vec<MarketTrade> = decode(&buffer).unwrap();
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].price, 4.0);
+        assert_eq!(trades[0].qty, 12.0);
+        assert_eq!(trades[0].quote_qty, 480.0);
+        assert_eq!(trades[0].time, 1_499_865_549_590);
+        assert!(trades[0].is_buyer_maker);
+    }
+
+    #[test]
+    fn test_decode_klines() {
+        let buffer = SbeWriter::default()
+            .header(2, KLINES_RESPONSE_TEMPLATE_ID)
+            .i8(-2).i8(-4)
+            .group(120, 1)
+            .i64(1_499_040_000_000_000)
+            .i64(1_000).i64(1_200).i64(900).i64(1_100)
+            .i128(1_482_500)
+            .i64(1_499_644_799_999_000)
+            .i128(200_000)
+            .i64(308)
+            .i128(17_560_000)
+            .i128(2_500)
+            .build();
+
+        let klines: Vec<RestKline> = decode(&buffer).unwrap();
+        let kline = &klines[0];
+        assert_eq!(kline.open_time, 1_499_040_000_000);
+        assert_eq!(kline.close_time, 1_499_644_799_999);
+        assert_eq!(kline.high_price, 12.0);
+        assert_eq!(kline.base_asset_volume, 148.25);
+        assert_eq!(kline.quote_asset_volume, 2000.0);
+        assert_eq!(kline.number_of_trades, 308);
+        assert_eq!(kline.taker_buy_base_asset_volume, 1756.0);
+    }
+}
@@ -0,0 +1,133 @@
+//! Decoders for Binance's Simple Binary Encoding (SBE) responses.
+//!
+//! Hand-written against `resources/binance-spot-api-docs-master/sbe/schemas/spot_latest.xml`
+//! (schema id 1, version 0). Only the messages the client consumes are decoded: depth,
+//! trades, klines, exchange info, order placement and cancellation, errors, and the
+//! WebSocket API response wrapper.
+
+pub mod reader;
+pub mod enums;
+pub mod market_data;
+pub mod exchange_info;
+pub mod orders;
+pub mod websocket;
+
+use std::io::{Error as IOError, ErrorKind};
+use crate::binance_client::sbe::reader::SbeReader;
+
+pub const SBE_SCHEMA_ID: u16 = 1;
+pub const SBE_SCHEMA_VERSION: u16 = 0;
+
+pub const ERROR_RESPONSE_TEMPLATE_ID: u16 = 100;
+
+/// The `messageHeader` composite that starts every SBE message.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MessageHeader {
+    pub block_length: u16,
+    pub template_id: u16,
+    pub schema_id: u16,
+    pub version: u16,
+}
+
+impl MessageHeader {
+    pub const ENCODED_LENGTH: usize = 8;
+
+    pub fn read(reader: &mut SbeReader) -> Result<Self, IOError> {
+        Ok(MessageHeader {
+            block_length: reader.u16()?,
+            template_id: reader.u16()?,
+            schema_id: reader.u16()?,
+            version: reader.u16()?,
+        })
+    }
+}
+
+/// A response type that can be decoded from an SBE message.
+pub trait SbeDecode: Sized {
+    /// Template ids of the messages that decode into this type.
+    const TEMPLATE_IDS: &'static [u16];
+
+    /// Decodes the message body. `reader` is positioned just after the header.
+    fn decode_message(header: &MessageHeader, reader: &mut SbeReader) -> Result<Self, IOError>;
+}
+
+/// An `ErrorResponse` message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SbeError {
+    pub code: i16,
+    pub msg: String,
+}
+
+/// Decodes a complete SBE message (header included) into `T`.
+///
+/// An `ErrorResponse` is turned into an `IOError` carrying Binance's error code and message.
+pub fn decode<T: SbeDecode>(buffer: &[u8]) -> Result<T, IOError> {
+    let mut reader = SbeReader::new(buffer);
+    let header = MessageHeader::read(&mut reader)?;
+    if header.schema_id != SBE_SCHEMA_ID {
+        return Err(IOError::new(
+            ErrorKind::InvalidData,
+            format!("Unexpected SBE schema id {} (expected {})", header.schema_id, SBE_SCHEMA_ID),
+        ));
+    }
+    if header.template_id == ERROR_RESPONSE_TEMPLATE_ID {
+        let error = decode_error_body(&header, &mut reader)?;
+        return Err(IOError::new(ErrorKind::Other, format!("Binance error {}: {}", error.code, error.msg)));
+    }
+    if !T::TEMPLATE_IDS.contains(&header.template_id) {
+        return Err(IOError::new(
+            ErrorKind::InvalidData,
+            format!("Unexpected SBE template id {} (expected one of {:?})", header.template_id, T::TEMPLATE_IDS),
+        ));
+    }
+    T::decode_message(&header, &mut reader)
+}
+
+/// Decodes an `ErrorResponse` message, header included.
+pub fn decode_error(buffer: &[u8]) -> Result<SbeError, IOError> {
+    let mut reader = SbeReader::new(buffer);
+    let header = MessageHeader::read(&mut reader)?;
+    decode_error_body(&header, &mut reader)
+}
+
+fn decode_error_body(header: &MessageHeader, reader: &mut SbeReader) -> Result<SbeError, IOError> {
+    let block_start = reader.position();
+    let code = reader.i16()?;
+    // serverTime and retryAfter are not used
+    reader.skip_to_block_end(block_start, header.block_length)?;
+    let msg = reader.var_string16()?;
+    Ok(SbeError { code, msg })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binance_client::market_data::DepthSnapshot;
+    use crate::binance_client::sbe::reader::SbeWriter;
+
+    fn error_message() -> Vec<u8> {
+        SbeWriter::default()
+            .header(18, ERROR_RESPONSE_TEMPLATE_ID)
+            .i16(-1121)
+            .i64(reader::NULL_I64)
+            .i64(reader::NULL_I64)
+            .string16("Invalid symbol.")
+            .data32(&[])
+            .build()
+    }
+
+    #[test]
+    fn test_error_response_becomes_error() {
+        let err = decode::<DepthSnapshot>(&error_message()).unwrap_err();
+        assert_eq!(err.to_string(), "Binance error -1121: Invalid symbol.");
+        assert_eq!(decode_error(&error_message()).unwrap().code, -1121);
+    }
+
+    #[test]
+    fn test_unexpected_template_is_rejected() {
+        let buffer = SbeWriter::default().header(0, 101).build();
+        let err = decode::<DepthSnapshot>(&buffer).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
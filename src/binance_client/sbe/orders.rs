@@ -0,0 +1,277 @@
+use std::io::Error as IOError;
+use crate::binance_client::order_response::{Fill, OrderResponse};
+use crate::binance_client::order_types::cancel_order_response::CancelOrderResponse;
+use crate::binance_client::sbe::enums;
+use crate::binance_client::sbe::reader::{decimal, micros_to_millis, optional_decimal, optional_i64, SbeReader, NULL_ENUM};
+use crate::binance_client::sbe::{MessageHeader, SbeDecode};
+
+const NEW_ORDER_ACK_RESPONSE_TEMPLATE_ID: u16 = 300;
+const NEW_ORDER_RESULT_RESPONSE_TEMPLATE_ID: u16 = 301;
+const NEW_ORDER_FULL_RESPONSE_TEMPLATE_ID: u16 = 302;
+const CANCEL_ORDER_RESPONSE_TEMPLATE_ID: u16 = 305;
+
+// Orders that are not part of an order list report -1, as in the JSON responses
+const NO_ORDER_LIST: i64 = -1;
+
+impl SbeDecode for OrderResponse {
+    const TEMPLATE_IDS: &'static [u16] = &[
+        NEW_ORDER_ACK_RESPONSE_TEMPLATE_ID,
+        NEW_ORDER_RESULT_RESPONSE_TEMPLATE_ID,
+        NEW_ORDER_FULL_RESPONSE_TEMPLATE_ID,
+    ];
+
+    fn decode_message(header: &MessageHeader, reader: &mut SbeReader) -> Result<Self, IOError> {
+        if header.template_id == NEW_ORDER_ACK_RESPONSE_TEMPLATE_ID {
+            return decode_ack(header, reader);
+        }
+
+        let block_start = reader.position();
+        let price_exponent = reader.i8()?;
+        let qty_exponent = reader.i8()?;
+        let order_id = reader.i64()?;
+        let order_list_id = optional_i64(reader.i64()?).unwrap_or(NO_ORDER_LIST);
+        let transact_time = micros_to_millis(reader.i64()?);
+        let price = decimal(reader.i64()?, price_exponent);
+        let orig_qty = decimal(reader.i64()?, qty_exponent);
+        let executed_qty = decimal(reader.i64()?, qty_exponent);
+        let cummulative_quote_qty = decimal(reader.i64()?, price_exponent);
+        let status = enums::order_status(reader.u8()?);
+        let time_in_force = enums::time_in_force(reader.u8()?);
+        let order_type = enums::order_type(reader.u8()?);
+        let side = enums::order_side(reader.u8()?);
+        let stop_price = optional_decimal(reader.i64()?, price_exponent);
+        let trailing_delta = optional_i64(reader.i64()?).map(|delta| delta as f64);
+        let trailing_time = optional_i64(reader.i64()?).map(|micros| micros_to_millis(micros) as i64);
+        let working_time = optional_i64(reader.i64()?).map(micros_to_millis);
+        let iceberg_qty = optional_decimal(reader.i64()?, qty_exponent);
+        let strategy_id = optional_i64(reader.i64()?);
+        let strategy_type = reader.i32()?;
+        let _order_capacity = reader.u8()?;
+        let working_floor = reader.u8()?;
+        let self_trade_prevention_mode = enums::self_trade_prevention_mode(reader.u8()?);
+        let _trade_group_id = reader.i64()?;
+        let prevented_quantity = optional_decimal(reader.i64()?, qty_exponent);
+        let used_sor = reader.u8()?;
+        reader.skip_to_block_end(block_start, header.block_length)?;
+
+        let fills = if header.template_id == NEW_ORDER_FULL_RESPONSE_TEMPLATE_ID {
+            let fills = read_fills(reader, price_exponent, qty_exponent)?;
+            skip_prevented_matches(reader)?;
+            Some(fills)
+        } else {
+            None
+        };
+
+        Ok(OrderResponse {
+            symbol: reader.var_string8()?,
+            client_order_id: reader.var_string8()?,
+            order_id,
+            order_list_id,
+            transact_time,
+            price: Some(price),
+            orig_qty: Some(orig_qty),
+            executed_qty: Some(executed_qty),
+            cummulative_quote_qty: Some(cummulative_quote_qty),
+            status: Some(status.to_string()),
+            time_in_force: Some(time_in_force.to_string()),
+            order_type: Some(order_type.to_string()),
+            side: Some(side.to_string()),
+            working_time,
+            self_trade_prevention_mode: Some(self_trade_prevention_mode.to_string()),
+            fills,
+            stop_price,
+            iceberg_qty,
+            prevented_match_id: None,
+            prevented_quantity,
+            strategy_id,
+            strategy_type: (strategy_type != i32::MIN).then_some(strategy_type as i64),
+            trailing_delta,
+            trailing_time,
+            used_sor: (used_sor != NULL_ENUM).then_some(used_sor == 1),
+            working_floor: (working_floor != NULL_ENUM).then(|| enums::floor(working_floor).to_string()),
+        })
+    }
+}
+
+fn decode_ack(header: &MessageHeader, reader: &mut SbeReader) -> Result<OrderResponse, IOError> {
+    let block_start = reader.position();
+    let order_id = reader.i64()?;
+    let order_list_id = optional_i64(reader.i64()?).unwrap_or(NO_ORDER_LIST);
+    let transact_time = micros_to_millis(reader.i64()?);
+    reader.skip_to_block_end(block_start, header.block_length)?;
+
+    Ok(OrderResponse {
+        symbol: reader.var_string8()?,
+        client_order_id: reader.var_string8()?,
+        order_id,
+        order_list_id,
+        transact_time,
+        price: None,
+        orig_qty: None,
+        executed_qty: None,
+        cummulative_quote_qty: None,
+        status: None,
+        time_in_force: None,
+        order_type: None,
+        side: None,
+        working_time: None,
+        self_trade_prevention_mode: None,
+        fills: None,
+        stop_price: None,
+        iceberg_qty: None,
+        prevented_match_id: None,
+        prevented_quantity: None,
+        strategy_id: None,
+        strategy_type: None,
+        trailing_delta: None,
+        trailing_time: None,
+        used_sor: None,
+        working_floor: None,
+    })
+}
+
+fn read_fills(reader: &mut SbeReader, price_exponent: i8, qty_exponent: i8) -> Result<Vec<Fill>, IOError> {
+    let (block_length, count) = reader.group_header()?;
+    let mut fills = Vec::with_capacity(count);
+    for _ in 0..count {
+        let block_start = reader.position();
+        let commission_exponent = reader.i8()?;
+        let _match_type = reader.u8()?;
+        let price = decimal(reader.i64()?, price_exponent);
+        let qty = decimal(reader.i64()?, qty_exponent);
+        let commission = decimal(reader.i64()?, commission_exponent);
+        // SOR allocations have no trade id
+        let trade_id = optional_i64(reader.i64()?).unwrap_or(-1);
+        reader.skip_to_block_end(block_start, block_length)?;
+        fills.push(Fill {
+            price,
+            qty,
+            commission,
+            commission_asset: reader.var_string8()?,
+            trade_id,
+        });
+    }
+    Ok(fills)
+}
+
+fn skip_prevented_matches(reader: &mut SbeReader) -> Result<(), IOError> {
+    let (block_length, count) = reader.group_header()?;
+    for _ in 0..count {
+        reader.skip_to_block_end(reader.position(), block_length)?;
+        reader.var_string8()?;
+    }
+    Ok(())
+}
+
+impl SbeDecode for CancelOrderResponse {
+    const TEMPLATE_IDS: &'static [u16] = &[CANCEL_ORDER_RESPONSE_TEMPLATE_ID];
+
+    fn decode_message(header: &MessageHeader, reader: &mut SbeReader) -> Result<Self, IOError> {
+        let block_start = reader.position();
+        let _price_exponent = reader.i8()?;
+        let _qty_exponent = reader.i8()?;
+        let order_id = reader.i64()?;
+        let order_list_id = optional_i64(reader.i64()?).unwrap_or(NO_ORDER_LIST);
+        reader.skip_to_block_end(block_start, header.block_length)?;
+
+        Ok(CancelOrderResponse {
+            symbol: reader.var_string8()?,
+            orig_client_order_id: Some(reader.var_string8()?),
+            client_order_id: reader.var_string8()?,
+            order_id,
+            order_list_id,
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binance_client::sbe::decode;
+    use crate::binance_client::sbe::reader::{SbeWriter, NULL_I64};
+
+    const RESULT_BLOCK_LENGTH: u16 = 134;
+
+    fn write_result_block(writer: &mut SbeWriter) -> &mut SbeWriter {
+        writer
+            .i8(-2).i8(-5)
+            .i64(12569099453).i64(NULL_I64).i64(1_660_801_715_639_000)
+            .i64(2_300_000).i64(1_000).i64(1_000).i64(23_000)
+            .u8(2).u8(0).u8(1).u8(1)
+            .i64(NULL_I64).i64(NULL_I64).i64(NULL_I64).i64(1_660_801_715_639_000).i64(NULL_I64)
+            .i64(NULL_I64).i32(i32::MIN).u8(NULL_ENUM).u8(1).u8(1)
+            .i64(NULL_I64).i64(NULL_I64).u8(0)
+    }
+
+    #[test]
+    fn test_result_block_length_matches_schema() {
+        let mut writer = SbeWriter::default();
+        assert_eq!(write_result_block(&mut writer).build().len(), RESULT_BLOCK_LENGTH as usize);
+    }
+
+    #[test]
+    fn test_decode_new_order_full() {
+        let mut writer = SbeWriter::default();
+        writer.header(RESULT_BLOCK_LENGTH, NEW_ORDER_FULL_RESPONSE_TEMPLATE_ID);
+        write_result_block(&mut writer)
+            .group(42, 1)
+            .i8(-8).u8(1).i64(2_300_000).i64(1_000).i64(1_000).i64(1650).i64(NULL_I64)
+            .string8("BNB")
+            .group(0, 0)
+            .string8("BTCUSDT")
+            .string8("4d96324ff9d44481926157ec08158a40");
+
+        let order: OrderResponse = decode(&writer.build()).unwrap();
+        assert_eq!(order.symbol, "BTCUSDT");
+        assert_eq!(order.order_id, 12569099453);
+        assert_eq!(order.order_list_id, -1);
+        assert_eq!(order.transact_time, 1_660_801_715_639);
+        assert_eq!(order.price, Some(23000.0));
+        assert_eq!(order.orig_qty, Some(0.01));
+        assert_eq!(order.status.as_deref(), Some("FILLED"));
+        assert_eq!(order.order_type.as_deref(), Some("LIMIT"));
+        assert_eq!(order.side.as_deref(), Some("SELL"));
+        assert_eq!(order.stop_price, None);
+        assert_eq!(order.working_floor.as_deref(), Some("EXCHANGE"));
+        assert_eq!(order.used_sor, Some(false));
+
+        let fills = order.fills.unwrap();
+        assert_eq!(fills[0].price, 23000.0);
+        assert_eq!(fills[0].commission, 0.00001);
+        assert_eq!(fills[0].commission_asset, "BNB");
+        assert_eq!(fills[0].trade_id, 1650);
+    }
+
+    #[test]
+    fn test_decode_new_order_ack() {
+        let buffer = SbeWriter::default()
+            .header(24, NEW_ORDER_ACK_RESPONSE_TEMPLATE_ID)
+            .i64(7).i64(NULL_I64).i64(1_660_801_715_639_000)
+            .string8("BTCUSDT")
+            .string8("abc")
+            .build();
+
+        let order: OrderResponse = decode(&buffer).unwrap();
+        assert_eq!(order.order_id, 7);
+        assert_eq!(order.client_order_id, "abc");
+        assert_eq!(order.status, None);
+    }
+
+    #[test]
+    fn test_decode_cancel_order() {
+        let buffer = SbeWriter::default()
+            .header(22, CANCEL_ORDER_RESPONSE_TEMPLATE_ID)
+            .i8(-2).i8(-5).i64(9).i64(NULL_I64).u16(0).u16(0)
+            .string8("BTCUSDT")
+            .string8("orig")
+            .string8("new")
+            .build();
+
+        let response: CancelOrderResponse = decode(&buffer).unwrap();
+        assert_eq!(response.order_id, 9);
+        assert_eq!(response.order_list_id, -1);
+        assert_eq!(response.orig_client_order_id.as_deref(), Some("orig"));
+        assert_eq!(response.client_order_id, "new");
+    }
+}
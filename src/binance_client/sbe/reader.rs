@@ -0,0 +1,294 @@
+use std::io::{Error as IOError, ErrorKind};
+
+/// Cursor over a little-endian SBE buffer.
+///
+/// Fixed-size fields are read in schema order; callers use the block length from the
+/// message or group header to skip fields they do not need, which keeps decoding working
+/// when Binance appends new fields to a block.
+pub struct SbeReader<'a> {
+    buffer: &'a [u8],
+    position: usize,
+}
+
+impl<'a> SbeReader<'a> {
+    pub fn new(buffer: &'a [u8]) -> Self {
+        SbeReader { buffer, position: 0 }
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.buffer.len() - self.position
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Result<&'a [u8], IOError> {
+        if self.remaining() < len {
+            return Err(IOError::new(
+                ErrorKind::UnexpectedEof,
+                format!("SBE message truncated: needed {} bytes at offset {}, {} left", len, self.position, self.remaining()),
+            ));
+        }
+        let bytes = &self.buffer[self.position..self.position + len];
+        self.position += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], IOError> {
+        let mut array = [0u8; N];
+        array.copy_from_slice(self.bytes(N)?);
+        Ok(array)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, IOError> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    pub fn i8(&mut self) -> Result<i8, IOError> {
+        Ok(i8::from_le_bytes(self.array()?))
+    }
+
+    pub fn u16(&mut self) -> Result<u16, IOError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    pub fn i16(&mut self) -> Result<i16, IOError> {
+        Ok(i16::from_le_bytes(self.array()?))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, IOError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    pub fn i32(&mut self) -> Result<i32, IOError> {
+        Ok(i32::from_le_bytes(self.array()?))
+    }
+
+    pub fn i64(&mut self) -> Result<i64, IOError> {
+        Ok(i64::from_le_bytes(self.array()?))
+    }
+
+    pub fn i128(&mut self) -> Result<i128, IOError> {
+        Ok(i128::from_le_bytes(self.array()?))
+    }
+
+    pub fn bool(&mut self) -> Result<bool, IOError> {
+        Ok(self.u8()? == 1)
+    }
+
+    /// Moves to `block_start + block_length`, skipping fields this decoder does not read.
+    pub fn skip_to_block_end(&mut self, block_start: usize, block_length: u16) -> Result<(), IOError> {
+        let block_end = block_start + block_length as usize;
+        if block_end < self.position {
+            return Err(IOError::new(
+                ErrorKind::InvalidData,
+                format!("SBE block length {} is shorter than the fields read", block_length),
+            ));
+        }
+        self.bytes(block_end - self.position)?;
+        Ok(())
+    }
+
+    /// Reads a `groupSizeEncoding` header: block length and a 32-bit entry count.
+    pub fn group_header(&mut self) -> Result<(u16, usize), IOError> {
+        Ok((self.u16()?, self.u32()? as usize))
+    }
+
+    /// Reads a `groupSize16Encoding` header: block length and a 16-bit entry count.
+    pub fn group_header16(&mut self) -> Result<(u16, usize), IOError> {
+        Ok((self.u16()?, self.u16()? as usize))
+    }
+
+    /// Reads a `varString8`, an UTF-8 string prefixed with a one byte length.
+    pub fn var_string8(&mut self) -> Result<String, IOError> {
+        let len = self.u8()? as usize;
+        self.utf8(len)
+    }
+
+    /// Reads a `varString`, an UTF-8 string prefixed with a two byte length.
+    pub fn var_string16(&mut self) -> Result<String, IOError> {
+        let len = self.u16()? as usize;
+        self.utf8(len)
+    }
+
+    /// Reads a `messageData8` field holding a nested SBE message.
+    pub fn message_data8(&mut self) -> Result<&'a [u8], IOError> {
+        let len = self.u8()? as usize;
+        self.bytes(len)
+    }
+
+    /// Reads a `messageData` field holding a nested SBE message.
+    pub fn message_data32(&mut self) -> Result<&'a [u8], IOError> {
+        let len = self.u32()? as usize;
+        self.bytes(len)
+    }
+
+    fn utf8(&mut self, len: usize) -> Result<String, IOError> {
+        let bytes = self.bytes(len)?;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|err| IOError::new(ErrorKind::InvalidData, format!("SBE string is not valid UTF-8: {}", err)))
+    }
+}
+
+/// Null value of optional `int64` based fields such as optional mantissas and timestamps.
+pub const NULL_I64: i64 = i64::MIN;
+
+/// Null value of optional `uint8` enums.
+pub const NULL_ENUM: u8 = u8::MAX;
+
+/// Converts a decimal mantissa and exponent into an `f64`.
+pub fn decimal(mantissa: i64, exponent: i8) -> f64 {
+    scale(mantissa as f64, exponent)
+}
+
+/// Converts a 128-bit decimal mantissa and exponent into an `f64`.
+pub fn decimal128(mantissa: i128, exponent: i8) -> f64 {
+    scale(mantissa as f64, exponent)
+}
+
+pub fn optional_decimal(mantissa: i64, exponent: i8) -> Option<f64> {
+    (mantissa != NULL_I64).then(|| decimal(mantissa, exponent))
+}
+
+pub fn optional_i64(value: i64) -> Option<i64> {
+    (value != NULL_I64).then_some(value)
+}
+
+/// SBE timestamps are microseconds; the rest of the client works in milliseconds.
+pub fn micros_to_millis(micros: i64) -> u64 {
+    (micros / 1_000).max(0) as u64
+}
+
+fn scale(mantissa: f64, exponent: i8) -> f64 {
+    // Dividing by an exact power of ten keeps values like 0.01 exact, unlike multiplying by 1e-2
+    if exponent < 0 {
+        mantissa / 10f64.powi(-(exponent as i32))
+    } else {
+        mantissa * 10f64.powi(exponent as i32)
+    }
+}
+
+
+/// Builds SBE buffers for tests.
+#[cfg(test)]
+#[derive(Default)]
+pub(crate) struct SbeWriter {
+    pub buffer: Vec<u8>,
+}
+
+#[cfg(test)]
+impl SbeWriter {
+    pub fn header(&mut self, block_length: u16, template_id: u16) -> &mut Self {
+        self.u16(block_length).u16(template_id).u16(super::SBE_SCHEMA_ID).u16(super::SBE_SCHEMA_VERSION)
+    }
+
+    pub fn u8(&mut self, value: u8) -> &mut Self {
+        self.buffer.push(value);
+        self
+    }
+
+    pub fn i8(&mut self, value: i8) -> &mut Self {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn u16(&mut self, value: u16) -> &mut Self {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn i16(&mut self, value: i16) -> &mut Self {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn u32(&mut self, value: u32) -> &mut Self {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn i32(&mut self, value: i32) -> &mut Self {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn i64(&mut self, value: i64) -> &mut Self {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn i128(&mut self, value: i128) -> &mut Self {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn group(&mut self, block_length: u16, count: u32) -> &mut Self {
+        self.u16(block_length).u32(count)
+    }
+
+    pub fn group16(&mut self, block_length: u16, count: u16) -> &mut Self {
+        self.u16(block_length).u16(count)
+    }
+
+    pub fn string8(&mut self, value: &str) -> &mut Self {
+        self.u8(value.len() as u8);
+        self.buffer.extend_from_slice(value.as_bytes());
+        self
+    }
+
+    pub fn string16(&mut self, value: &str) -> &mut Self {
+        self.u16(value.len() as u16);
+        self.buffer.extend_from_slice(value.as_bytes());
+        self
+    }
+
+    pub fn data8(&mut self, value: &[u8]) -> &mut Self {
+        self.u8(value.len() as u8);
+        self.buffer.extend_from_slice(value);
+        self
+    }
+
+    pub fn data32(&mut self, value: &[u8]) -> &mut Self {
+        self.u32(value.len() as u32);
+        self.buffer.extend_from_slice(value);
+        self
+    }
+
+    pub fn build(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.buffer)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decimal_conversion() {
+        assert_eq!(decimal(1, -2), 0.01);
+        assert_eq!(decimal(4_200_012, -2), 42000.12);
+        assert_eq!(decimal(5, 3), 5000.0);
+        assert_eq!(decimal128(123_456, -4), 12.3456);
+        assert_eq!(optional_decimal(NULL_I64, -8), None);
+    }
+
+    #[test]
+    fn test_reader_reports_truncation() {
+        let buffer = [1u8, 0, 0];
+        let mut reader = SbeReader::new(&buffer);
+        assert_eq!(reader.u16().unwrap(), 1);
+        let err = reader.u32().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_skip_to_block_end() {
+        let buffer = SbeWriter::default().u8(7).i64(-1).u8(9).build();
+        let mut reader = SbeReader::new(&buffer);
+        assert_eq!(reader.u8().unwrap(), 7);
+        reader.skip_to_block_end(0, 9).unwrap();
+        assert_eq!(reader.u8().unwrap(), 9);
+    }
+}
@@ -0,0 +1,113 @@
+use std::io::{Error as IOError, ErrorKind};
+use crate::binance_client::binance_error::BinanceError;
+use crate::binance_client::sbe::reader::SbeReader;
+use crate::binance_client::sbe::{decode_error, enums, MessageHeader, ERROR_RESPONSE_TEMPLATE_ID};
+use crate::binance_client::ws_api_client::{WsApiResponse, WsRateLimit};
+
+const WEBSOCKET_RESPONSE_TEMPLATE_ID: u16 = 50;
+
+/// Decodes the `WebSocketResponse` wrapper sent over an SBE WebSocket API session.
+///
+/// The wrapped result is kept as raw bytes in `sbe_result` and decoded by the caller, who
+/// knows which response type to expect. A wrapped `ErrorResponse` is surfaced as `error`.
+pub fn decode_websocket_response(buffer: &[u8]) -> Result<WsApiResponse, IOError> {
+    let mut reader = SbeReader::new(buffer);
+    let header = MessageHeader::read(&mut reader)?;
+    if header.template_id != WEBSOCKET_RESPONSE_TEMPLATE_ID {
+        return Err(IOError::new(
+            ErrorKind::InvalidData,
+            format!("Expected a WebSocketResponse, got SBE template id {}", header.template_id),
+        ));
+    }
+
+    let block_start = reader.position();
+    let _schema_deprecated = reader.u8()?;
+    let status = reader.u16()?;
+    reader.skip_to_block_end(block_start, header.block_length)?;
+
+    let (block_length, count) = reader.group_header16()?;
+    let mut rate_limits = Vec::with_capacity(count);
+    for _ in 0..count {
+        let block_start = reader.position();
+        rate_limits.push(WsRateLimit {
+            rate_limit_type: enums::rate_limit_type(reader.u8()?).to_string(),
+            interval: enums::rate_limit_interval(reader.u8()?).to_string(),
+            interval_num: reader.u8()? as u32,
+            limit: reader.i64()? as u32,
+            count: reader.i64()? as u32,
+        });
+        reader.skip_to_block_end(block_start, block_length)?;
+    }
+
+    let id = reader.var_string8()?;
+    let result = reader.message_data32()?;
+
+    let mut result_reader = SbeReader::new(result);
+    let result_header = MessageHeader::read(&mut result_reader)?;
+    let (error, sbe_result) = if result_header.template_id == ERROR_RESPONSE_TEMPLATE_ID {
+        let error = decode_error(result)?;
+        (Some(BinanceError { code: error.code as i32, msg: error.msg }), None)
+    } else {
+        (None, Some(result.to_vec()))
+    };
+
+    Ok(WsApiResponse {
+        // Requests sent with a null id come back with an empty one
+        id: (!id.is_empty()).then_some(id),
+        status,
+        result: None,
+        error,
+        rate_limits,
+        sbe_result,
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binance_client::order_response::OrderResponse;
+    use crate::binance_client::sbe::reader::{SbeWriter, NULL_I64};
+
+    fn wrap(result: &[u8]) -> Vec<u8> {
+        SbeWriter::default()
+            .header(3, WEBSOCKET_RESPONSE_TEMPLATE_ID)
+            .u8(0).u16(200)
+            .group16(19, 1).u8(3).u8(0).u8(10).i64(50).i64(1)
+            .string8("56374a46-3061-486b-a311-99ee972eb648")
+            .data32(result)
+            .build()
+    }
+
+    #[test]
+    fn test_decode_wrapped_order() {
+        let ack = SbeWriter::default()
+            .header(24, 300)
+            .i64(7).i64(NULL_I64).i64(1_660_801_715_639_000)
+            .string8("BTCUSDT")
+            .string8("abc")
+            .build();
+
+        let response = decode_websocket_response(&wrap(&ack)).unwrap();
+        assert_eq!(response.id.as_deref(), Some("56374a46-3061-486b-a311-99ee972eb648"));
+        assert_eq!(response.status, 200);
+        assert_eq!(response.rate_limits[0].rate_limit_type, "ORDERS");
+        assert_eq!(response.rate_limits[0].limit, 50);
+        let order: OrderResponse = response.into_sbe_result().unwrap();
+        assert_eq!(order.order_id, 7);
+    }
+
+    #[test]
+    fn test_decode_wrapped_error() {
+        let error = SbeWriter::default()
+            .header(18, ERROR_RESPONSE_TEMPLATE_ID)
+            .i16(-2010).i64(NULL_I64).i64(NULL_I64)
+            .string16("Account has insufficient balance for requested action.")
+            .data32(&[])
+            .build();
+
+        let response = decode_websocket_response(&wrap(&error)).unwrap();
+        assert_eq!(response.error.as_ref().unwrap().code, -2010);
+        assert!(response.into_sbe_result::<OrderResponse>().unwrap_err().to_string().contains("-2010"));
+    }
+}
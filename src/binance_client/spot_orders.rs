@@ -1,12 +1,17 @@
 use std::collections::HashMap;
+use std::fmt::Debug;
 use reqwest::{Client, Response, StatusCode};
 use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
 use std::io::{Error as IOError, ErrorKind};
-use log::trace;
+use log::{trace, warn};
 use serde_json::{json, Value};
+use crate::binance_client::account::postgresql::{JournalTag, OrderJournal};
 use crate::binance_client::binance_client::BinanceClient;
-use crate::binance_client::order_response::OrderResponse;
+use crate::binance_client::binance_error::BinanceError;
+use crate::binance_client::order_response::{CancelReplaceResponse, OrderListResponse, OrderResponse};
 use crate::binance_client::order_types::cancel_order_response::CancelOrderResponse;
+use crate::binance_client::order_types::cancel_replace_order::CancelReplaceOrder;
 use crate::binance_client::order_types::limit_order::LimitOrder;
 use crate::binance_client::order_types::market_order::MarketOrder;
 use crate::binance_client::order_types::oco_order::OcoOrder;
@@ -14,11 +19,24 @@ use crate::binance_client::order_types::stop_limit_order::StopLimitOrder;
 
 pub struct SpotClient<'a> {
     binance_client: &'a BinanceClient,
+    journal: Option<(OrderJournal, JournalTag)>,
 }
 
 impl SpotClient<'_> {
     pub fn new(api: &BinanceClient) -> SpotClient {
-        SpotClient { binance_client: api }
+        SpotClient { binance_client: api, journal: None }
+    }
+
+    /// Writes every order placed or canceled through this client to `journal`, tagged with `tag`.
+    ///
+    /// A failed journal write is logged and does not fail the order request.
+    pub fn with_journal(mut self, journal: OrderJournal, tag: JournalTag) -> Self {
+        self.journal = Some((journal, tag));
+        self
+    }
+
+    pub fn binance_client(&self) -> &BinanceClient {
+        self.binance_client
     }
     
     pub async fn create_limit_order(&self, order: LimitOrder) -> Result<OrderResponse, IOError> {
@@ -28,8 +46,9 @@ impl SpotClient<'_> {
 
         let signature = self.binance_client.sign(&params);
         let full_params = format!("{}&signature={}", params, signature);
-        let response = self.send_request(url, full_params).await;
-        Self::parse_order_response(response?).await
+        let result = self.submit(url, full_params).await;
+        self.journal_order(&order, &result).await;
+        result.map_err(OrderError::into_io_error)
     }
 
 
@@ -43,12 +62,13 @@ impl SpotClient<'_> {
         let signature = self.binance_client.sign(&params);
         let full_params = format!("{}&signature={}", params, signature);
         trace!("full params: {:?}", full_params);
-        let response = self.send_request(url, full_params).await;
-        Self::parse_order_response(response?).await
+        let result = self.submit(url, full_params).await;
+        self.journal_order(&order, &result).await;
+        result.map_err(OrderError::into_io_error)
     }
 
 
-    pub async fn create_oco_order(&self, order: OcoOrder) -> Result<OrderResponse, IOError> {
+    pub async fn create_oco_order(&self, order: OcoOrder) -> Result<OrderListResponse, IOError> {
         let endpoint = "/v3/order/oco";
         let url = format!("{}{}", self.binance_client.api_url, endpoint);
         let params = serde_qs::to_string(&order).map_err(|err| IOError::new(ErrorKind::InvalidInput, format!("Failed to serialize order: {}", err)))?;
@@ -56,8 +76,9 @@ impl SpotClient<'_> {
         let signature = self.binance_client.sign(&params);
         let full_params = format!("{}&signature={}", params, signature);
 
-        let response = self.send_request(url, full_params).await;
-        Self::parse_order_response(response?).await
+        let result = self.submit(url, full_params).await;
+        self.journal_order_list(&order, &result).await;
+        result.map_err(OrderError::into_io_error)
     }
 
 
@@ -68,8 +89,9 @@ impl SpotClient<'_> {
 
         let signature = self.binance_client.sign(&params);
         let full_params = format!("{}&signature={}", params, signature);
-        let response = self.send_request(url, full_params).await;
-        Self::parse_order_response(response?).await
+        let result = self.submit(url, full_params).await;
+        self.journal_order(&order, &result).await;
+        result.map_err(OrderError::into_io_error)
     }
 
     // Function to cancel an order given its ID and symbol
@@ -101,6 +123,7 @@ impl SpotClient<'_> {
                 let body = response.text().await.map_err(|_| IOError::new(ErrorKind::Other, "Failed to read response body"))?;
                 trace!("Cancel order response: {:?}", body);
                 let cancel_order_response : CancelOrderResponse = serde_json::from_str(&body)?;
+                self.journal_cancel(&cancel_order_response).await;
                 Ok(cancel_order_response)
             }
             _ => {
@@ -109,19 +132,44 @@ impl SpotClient<'_> {
         }
     }
 
+    // Cancels an existing order and places a new one in a single request
+    pub async fn cancel_replace_order(&self, order: CancelReplaceOrder) -> Result<CancelReplaceResponse, IOError> {
+        let endpoint = "/v3/order/cancelReplace";
+        let url = format!("{}{}", self.binance_client.api_url, endpoint);
+        let params = serde_qs::to_string(&order).map_err(|err| IOError::new(ErrorKind::InvalidInput, format!("Failed to serialize order: {}", err)))?;
 
-    async fn parse_order_response(response: Response) -> Result<OrderResponse, IOError> {
+        let signature = self.binance_client.sign(&params);
+        let full_params = format!("{}&signature={}", params, signature);
+        let result = self.submit(url, full_params).await;
+        self.journal_cancel_replace(&order, &result).await;
+        result.map_err(OrderError::into_io_error)
+    }
+
+    // Sends an order request; failures say whether the order may have been placed
+    async fn submit<T: DeserializeOwned + Debug>(&self, url: String, params: String) -> Result<T, OrderError> {
+        let response = self.send_request(url, params).await.map_err(OrderError::Unknown)?;
+        Self::parse_order_response(response).await
+    }
+
+    async fn parse_order_response<T: DeserializeOwned + Debug>(response: Response) -> Result<T, OrderError> {
         match response.status() {
             StatusCode::OK => {
-                let body = response.text().await.map_err(|_| IOError::new(ErrorKind::Other, "Failed to read response body"))?;
+                let body = response.text().await
+                    .map_err(|_| OrderError::Unknown(IOError::new(ErrorKind::Other, "Failed to read response body")))?;
                 trace!("body: {:?}", body);
 
-                let order_response: OrderResponse = serde_json::from_str(body.as_str())?;
+                let order_response: T = serde_json::from_str(body.as_str()).map_err(|err| OrderError::Unknown(err.into()))?;
                 trace!("order response: {:?}", order_response);
                 Ok(order_response)
             }
-            _ => {
-                Err(Self::parse_error(response, "Failed to place order").await)
+            status => {
+                let body = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+                let error = Self::error_from_body(body.clone(), "Failed to place order");
+                // -1007 is a backend timeout, the order may have been placed anyway
+                match serde_json::from_str::<BinanceError>(&body) {
+                    Ok(binance_error) if status.is_client_error() && binance_error.code != -1007 => Err(OrderError::Rejected(error)),
+                    _ => Err(OrderError::Unknown(error)),
+                }
             }
         }
     }
@@ -137,8 +185,76 @@ impl SpotClient<'_> {
         Ok(resp)
     }
 
+    async fn journal_order<O: Serialize>(&self, request: &O, result: &Result<OrderResponse, OrderError>) {
+        if let Some((journal, tag)) = &self.journal {
+            let written = match result {
+                Ok(order) => journal.record_order(order, tag).await,
+                Err(err) => Self::journal_failure(journal, tag, request, err).await,
+            };
+            if let Err(err) = written {
+                warn!("Failed to journal order: {}", err);
+            }
+        }
+    }
+
+    async fn journal_order_list<O: Serialize>(&self, request: &O, result: &Result<OrderListResponse, OrderError>) {
+        if let Some((journal, tag)) = &self.journal {
+            let written = match result {
+                Ok(order_list) => journal.record_order_list(order_list, tag).await,
+                Err(err) => Self::journal_failure(journal, tag, request, err).await,
+            };
+            if let Err(err) = written {
+                warn!("Failed to journal order list: {}", err);
+            }
+        }
+    }
+
+    async fn journal_cancel(&self, cancel: &CancelOrderResponse) {
+        if let Some((journal, tag)) = &self.journal {
+            let written = match BinanceClient::generate_timestamp() {
+                Ok(now) => journal.record_cancel(cancel, now, tag).await,
+                Err(err) => Err(err),
+            };
+            if let Err(err) = written {
+                warn!("Failed to journal cancel: {}", err);
+            }
+        }
+    }
+
+    async fn journal_cancel_replace(&self, request: &CancelReplaceOrder, result: &Result<CancelReplaceResponse, OrderError>) {
+        let Some((journal, tag)) = &self.journal else { return };
+        let written = match result {
+            Ok(response) => Self::journal_replaced(journal, tag, response).await,
+            Err(err) => Self::journal_failure(journal, tag, request, err).await,
+        };
+        if let Err(err) = written {
+            warn!("Failed to journal cancel-replace: {}", err);
+        }
+    }
+
+    async fn journal_replaced(journal: &OrderJournal, tag: &JournalTag, response: &CancelReplaceResponse) -> Result<(), IOError> {
+        let now = BinanceClient::generate_timestamp()?;
+        journal.record_cancel(&response.cancel_response, now, tag).await?;
+        journal.record_order(&response.new_order_response, tag).await
+    }
+
+    // A rejected order never existed; any other failure is kept for reconciliation, as the order may be live
+    async fn journal_failure<O: Serialize>(journal: &OrderJournal, tag: &JournalTag, request: &O, err: &OrderError) -> Result<(), IOError> {
+        let request = serde_json::to_value(request).unwrap_or(Value::Null);
+        let symbol = request["symbol"].as_str().unwrap_or_default().to_string();
+        let now = BinanceClient::generate_timestamp()?;
+        match err {
+            OrderError::Rejected(err) => journal.record_reject(&symbol, &request, &err.to_string(), now, tag).await,
+            OrderError::Unknown(err) => journal.record_unknown(&symbol, &request, &err.to_string(), now, tag).await,
+        }
+    }
+
     async fn parse_error(response: Response, error_message: &str) -> IOError  {
         let error_body = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+        Self::error_from_body(error_body, error_message)
+    }
+
+    fn error_from_body(error_body: String, error_message: &str) -> IOError {
         let response_error_message = serde_json::from_str::<Value>(&error_body)
             .ok()
             .and_then(|v| v["msg"].as_str().map(ToString::to_string))
@@ -148,3 +264,17 @@ impl SpotClient<'_> {
     }
 
 }
+
+// Why an order request failed, which decides how the journal records it
+enum OrderError {
+    Rejected(IOError), // A 4xx with a Binance error code, no order was placed
+    Unknown(IOError), // No answer, a 5xx or an unreadable one, the order may have been placed
+}
+
+impl OrderError {
+    fn into_io_error(self) -> IOError {
+        match self {
+            OrderError::Rejected(err) | OrderError::Unknown(err) => err,
+        }
+    }
+}
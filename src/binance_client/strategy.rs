@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+use crate::binance_client::account::order_status::OrderStatus;
+use crate::binance_client::fee_model::{Fee, LiquidityRole};
+use crate::binance_client::market_data_store::{BookTick, TradePrint};
+use crate::binance_client::order_types::side::Side;
+use crate::binance_client::streams::kline_data::Kline;
+
+/// Identifies an order a strategy placed, across backtests and live runs.
+pub type OrderId = u64;
+
+/// An order a strategy wants placed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OrderIntent {
+    Market { symbol: String, side: Side, quantity: f64 },
+    Limit { symbol: String, side: Side, quantity: f64, price: f64 },
+    // Becomes a limit order at `price` once the last price reaches `stop_price`
+    StopLimit { symbol: String, side: Side, quantity: f64, stop_price: f64, price: f64 },
+    // A limit order at `price` and a stop-limit order, whichever fills first cancels the other
+    Oco { symbol: String, side: Side, quantity: f64, price: f64, stop_price: f64, stop_limit_price: f64 },
+}
+
+impl OrderIntent {
+    pub fn market(symbol: &str, side: Side, quantity: f64) -> Self {
+        OrderIntent::Market { symbol: symbol.to_string(), side, quantity }
+    }
+
+    pub fn limit(symbol: &str, side: Side, quantity: f64, price: f64) -> Self {
+        OrderIntent::Limit { symbol: symbol.to_string(), side, quantity, price }
+    }
+
+    pub fn stop_limit(symbol: &str, side: Side, quantity: f64, stop_price: f64, price: f64) -> Self {
+        OrderIntent::StopLimit { symbol: symbol.to_string(), side, quantity, stop_price, price }
+    }
+
+    pub fn oco(symbol: &str, side: Side, quantity: f64, price: f64, stop_price: f64, stop_limit_price: f64) -> Self {
+        OrderIntent::Oco { symbol: symbol.to_string(), side, quantity, price, stop_price, stop_limit_price }
+    }
+
+    pub fn symbol(&self) -> &str {
+        match self {
+            OrderIntent::Market { symbol, .. }
+            | OrderIntent::Limit { symbol, .. }
+            | OrderIntent::StopLimit { symbol, .. }
+            | OrderIntent::Oco { symbol, .. } => symbol,
+        }
+    }
+
+    pub fn side(&self) -> Side {
+        match self {
+            OrderIntent::Market { side, .. }
+            | OrderIntent::Limit { side, .. }
+            | OrderIntent::StopLimit { side, .. }
+            | OrderIntent::Oco { side, .. } => *side,
+        }
+    }
+
+    pub fn quantity(&self) -> f64 {
+        match self {
+            OrderIntent::Market { quantity, .. }
+            | OrderIntent::Limit { quantity, .. }
+            | OrderIntent::StopLimit { quantity, .. }
+            | OrderIntent::Oco { quantity, .. } => *quantity,
+        }
+    }
+}
+
+/// One execution of a strategy's order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrategyFill {
+    pub price: f64,
+    pub quantity: f64,
+    pub fee: Fee,
+    pub role: LiquidityRole,
+}
+
+/// A change in the state of a strategy's order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderUpdate {
+    pub order_id: OrderId,
+    pub symbol: String,
+    pub side: Side,
+    pub status: OrderStatus,
+    pub time: u64,
+    pub fill: Option<StrategyFill>, // The execution that caused this update, if any
+    pub filled_quantity: f64, // Cumulative
+    pub reason: Option<String>, // Why the order was rejected
+}
+
+/// Something the strategy asked for during a callback, executed once the callback returns.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StrategyCommand {
+    Submit(OrderId, OrderIntent),
+    Cancel(OrderId),
+}
+
+// Commands a strategy may queue in reaction to one event before the run is aborted
+pub(crate) const MAX_COMMANDS_PER_EVENT: usize = 10_000;
+
+/// What a strategy sees of its account and the market, and how it places orders.
+///
+/// Engines keep the view up to date between callbacks and execute the commands the
+/// strategy queued once a callback returns, so a strategy never waits on the exchange.
+#[derive(Debug, Clone, Default)]
+pub struct StrategyContext {
+    time: u64,
+    balances: HashMap<String, f64>, // Free balances by asset
+    prices: HashMap<String, f64>, // Last price by symbol
+    open_orders: HashMap<OrderId, OrderIntent>,
+    next_order_id: OrderId,
+    commands: Vec<StrategyCommand>,
+}
+
+impl StrategyContext {
+    pub fn new() -> Self {
+        StrategyContext { next_order_id: 1, ..Default::default() }
+    }
+
+    /// Milliseconds since the epoch, simulated in backtests.
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    /// Free balance of `asset`, not counting what open orders hold.
+    pub fn balance(&self, asset: &str) -> f64 {
+        self.balances.get(asset).copied().unwrap_or(0.0)
+    }
+
+    pub fn price(&self, symbol: &str) -> Option<f64> {
+        self.prices.get(symbol).copied()
+    }
+
+    /// Orders that are placed or queued and not yet filled, canceled or rejected.
+    pub fn open_orders(&self) -> impl Iterator<Item = (OrderId, &OrderIntent)> {
+        self.open_orders.iter().map(|(order_id, intent)| (*order_id, intent))
+    }
+
+    pub fn open_order(&self, order_id: OrderId) -> Option<&OrderIntent> {
+        self.open_orders.get(&order_id)
+    }
+
+    /// Queues an order and returns the id its updates will carry.
+    pub fn submit(&mut self, intent: OrderIntent) -> OrderId {
+        let order_id = self.next_order_id;
+        self.next_order_id += 1;
+        self.open_orders.insert(order_id, intent.clone());
+        self.commands.push(StrategyCommand::Submit(order_id, intent));
+        order_id
+    }
+
+    pub fn cancel(&mut self, order_id: OrderId) {
+        self.commands.push(StrategyCommand::Cancel(order_id));
+    }
+
+    /// Cancels every open order on `symbol`.
+    pub fn cancel_all(&mut self, symbol: &str) {
+        let mut order_ids: Vec<OrderId> = self.open_orders
+            .iter()
+            .filter(|(_, intent)| intent.symbol() == symbol)
+            .map(|(order_id, _)| *order_id)
+            .collect();
+        order_ids.sort_unstable();
+        for order_id in order_ids {
+            self.cancel(order_id);
+        }
+    }
+
+    // The rest is for engines
+
+    pub fn set_time(&mut self, time: u64) {
+        self.time = time;
+    }
+
+    pub fn set_balance(&mut self, asset: &str, free: f64) {
+        self.balances.insert(asset.to_string(), free);
+    }
+
+    pub fn set_price(&mut self, symbol: &str, price: f64) {
+        self.prices.insert(symbol.to_string(), price);
+    }
+
+    /// Forgets orders that reached a final state.
+    pub fn apply_update(&mut self, update: &OrderUpdate) {
+        if !matches!(update.status, OrderStatus::New | OrderStatus::PartiallyFilled | OrderStatus::PendingCancel) {
+            self.open_orders.remove(&update.order_id);
+        }
+    }
+
+    pub fn take_commands(&mut self) -> Vec<StrategyCommand> {
+        std::mem::take(&mut self.commands)
+    }
+}
+
+/// Trading logic driven by market data and the updates of its own orders.
+///
+/// Callbacks get a [`StrategyContext`] to read balances and prices and to queue orders.
+/// They are synchronous; the engine running the strategy places the queued orders. The
+/// backtester only has klines, so strategies meant to be backtested should not depend
+/// on trades or the book.
+pub trait Strategy {
+    fn on_start(&mut self, _ctx: &mut StrategyContext) {}
+
+    /// Called with every closed kline.
+    fn on_kline(&mut self, _ctx: &mut StrategyContext, _kline: &Kline) {}
+
+    /// Called with every trade, or aggregate trade, of the subscribed symbols.
+    fn on_trade(&mut self, _ctx: &mut StrategyContext, _trade: &TradePrint) {}
+
+    /// Called whenever the best bid or ask of a subscribed symbol changes.
+    fn on_book(&mut self, _ctx: &mut StrategyContext, _book: &BookTick) {}
+
+    fn on_order_update(&mut self, _ctx: &mut StrategyContext, _update: &OrderUpdate) {}
+
+    /// Called at the timer interval the engine was configured with.
+    fn on_timer(&mut self, _ctx: &mut StrategyContext) {}
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_queues_commands() {
+        let mut ctx = StrategyContext::new();
+        let buy = ctx.submit(OrderIntent::limit("ETHUSDT", Side::Buy, 1.0, 2000.0));
+        let sell = ctx.submit(OrderIntent::limit("BTCUSDT", Side::Sell, 0.1, 70000.0));
+        assert_ne!(buy, sell);
+        ctx.cancel_all("ETHUSDT");
+
+        assert_eq!(ctx.take_commands(), vec![
+            StrategyCommand::Submit(buy, OrderIntent::limit("ETHUSDT", Side::Buy, 1.0, 2000.0)),
+            StrategyCommand::Submit(sell, OrderIntent::limit("BTCUSDT", Side::Sell, 0.1, 70000.0)),
+            StrategyCommand::Cancel(buy),
+        ]);
+        assert!(ctx.take_commands().is_empty());
+
+        ctx.apply_update(&OrderUpdate {
+            order_id: buy,
+            symbol: "ETHUSDT".to_string(),
+            side: Side::Buy,
+            status: OrderStatus::Canceled,
+            time: 0,
+            fill: None,
+            filled_quantity: 0.0,
+            reason: None,
+        });
+        assert_eq!(ctx.open_orders().map(|(order_id, _)| order_id).collect::<Vec<_>>(), vec![sell]);
+    }
+}
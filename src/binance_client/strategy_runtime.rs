@@ -0,0 +1,743 @@
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::io::{Error as IOError, ErrorKind};
+use std::time::Duration;
+use futures::{SinkExt, StreamExt};
+use log::{info, trace, warn};
+use serde_json::Value;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::time::{interval_at, Instant, Interval, MissedTickBehavior};
+use tokio_websockets::{MaybeTlsStream, WebSocketStream};
+use crate::binance_client::account::account_info::AccountInfoClient;
+use crate::binance_client::account::order_status::OrderStatus;
+use crate::binance_client::binance_client::BinanceClient;
+use crate::binance_client::fee_model::{Fee, FeeModel, LiquidityRole};
+use crate::binance_client::market_data_store::{AggTradePrint, BookSnapshot, BookTick, TradePrint};
+use crate::binance_client::order_api::OrderApi;
+use crate::binance_client::order_types::limit_order::LimitOrder;
+use crate::binance_client::order_types::market_order::MarketOrder;
+use crate::binance_client::order_types::oco_order::OcoOrder;
+use crate::binance_client::order_types::order_type::OrderType;
+use crate::binance_client::order_types::side::Side;
+use crate::binance_client::order_types::stop_limit_order::StopLimitOrder;
+use crate::binance_client::order_types::time_in_force::TimeInForce;
+use crate::binance_client::paper_trader::PaperTrader;
+use crate::binance_client::strategy::{
+    OrderId, OrderIntent, OrderUpdate, Strategy, StrategyCommand, StrategyContext, StrategyFill, MAX_COMMANDS_PER_EVENT,
+};
+use crate::binance_client::streams::agg_trade_stream::AggTradeData;
+use crate::binance_client::streams::binance_stream::BinanceStreamTypes;
+use crate::binance_client::streams::binance_websocket::BinanceWebSocket;
+use crate::binance_client::streams::book_ticker_stream::BookTickerData;
+use crate::binance_client::streams::kline_data::{Kline, KlineData};
+use crate::binance_client::streams::partial_depth_stream::PartialDepthData;
+use crate::binance_client::streams::trade_stream::TradeData;
+use crate::binance_client::streams::user_data::{ExecutionReport, ExecutionType, UserDataEvent};
+
+type UserDataSocket = tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<TcpStream>>;
+
+// Well within the 60 minutes after which Binance closes a user data stream
+const LISTEN_KEY_KEEP_ALIVE: Duration = Duration::from_secs(30 * 60);
+
+/// A market data event a strategy is fed.
+#[derive(Debug, Clone)]
+pub enum MarketEvent {
+    Kline(Kline), // Only closed klines
+    Trade(TradePrint),
+    Book(BookTick),
+    Depth(BookSnapshot), // From the partial depth streams
+}
+
+impl MarketEvent {
+    /// Parses a combined stream message.
+    ///
+    /// Aggregate trades become one print per aggregate, without order ids. Returns `None`
+    /// for open klines and for streams strategies have no callback for, such as diff depth.
+    pub fn parse(text: &str, received_time: u64) -> Result<Option<Self>, IOError> {
+        let mut message: Value = serde_json::from_str(text).map_err(parse_error)?;
+        let Some(stream) = message["stream"].as_str() else { return Ok(None) };
+        let mut parts = stream.split('@');
+        let stream_symbol = parts.next().unwrap_or_default().to_string();
+        let kind = parts.next().unwrap_or_default().to_string();
+        let data = message["data"].take();
+
+        let event = if kind.starts_with("kline_") {
+            let kline: KlineData = serde_json::from_value(data).map_err(parse_error)?;
+            if !kline.k.is_kline_closed {
+                return Ok(None);
+            }
+            MarketEvent::Kline(kline.k)
+        } else if kind == "trade" {
+            let trade: TradeData = serde_json::from_value(data).map_err(parse_error)?;
+            MarketEvent::Trade(TradePrint::try_from(&trade).map_err(parse_error)?)
+        } else if kind == "aggTrade" {
+            let trade: AggTradeData = serde_json::from_value(data).map_err(parse_error)?;
+            let trade = AggTradePrint::try_from(&trade).map_err(parse_error)?;
+            MarketEvent::Trade(TradePrint {
+                symbol: trade.symbol,
+                trade_id: trade.agg_trade_id,
+                price: trade.price,
+                quantity: trade.quantity,
+                buyer_order_id: -1,
+                seller_order_id: -1,
+                trade_time: trade.trade_time,
+                is_buyer_maker: trade.is_buyer_maker,
+            })
+        } else if kind == "bookTicker" {
+            let book: BookTickerData = serde_json::from_value(data).map_err(parse_error)?;
+            MarketEvent::Book(BookTick::from_book_ticker(&book, received_time).map_err(parse_error)?)
+        } else if kind.len() > "depth".len() && kind.starts_with("depth") {
+            let depth: PartialDepthData = serde_json::from_value(data).map_err(parse_error)?;
+            let levels = depth.bids.len().max(depth.asks.len());
+            MarketEvent::Depth(BookSnapshot::from_partial_depth(&stream_symbol, received_time, &depth, levels).map_err(parse_error)?)
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(event))
+    }
+
+    pub fn symbol(&self) -> &str {
+        match self {
+            MarketEvent::Kline(kline) => &kline.symbol,
+            MarketEvent::Trade(trade) => &trade.symbol,
+            MarketEvent::Book(book) => &book.symbol,
+            MarketEvent::Depth(depth) => &depth.symbol,
+        }
+    }
+
+    /// Records the last price the event carries and passes it to the strategy.
+    ///
+    /// Strategies see depth snapshots as their best bid and ask.
+    pub fn dispatch<S: Strategy + ?Sized>(&self, strategy: &mut S, ctx: &mut StrategyContext) {
+        match self {
+            MarketEvent::Kline(kline) => {
+                ctx.set_price(&kline.symbol, kline.close_price);
+                strategy.on_kline(ctx, kline);
+            }
+            MarketEvent::Trade(trade) => {
+                ctx.set_price(&trade.symbol, trade.price);
+                strategy.on_trade(ctx, trade);
+            }
+            MarketEvent::Book(book) => strategy.on_book(ctx, book),
+            MarketEvent::Depth(depth) => {
+                if let (Some(bid_price), Some(ask_price)) = (depth.bid_prices.first(), depth.ask_prices.first()) {
+                    strategy.on_book(ctx, &BookTick {
+                        symbol: depth.symbol.clone(),
+                        update_id: depth.last_update_id,
+                        time: depth.snapshot_time,
+                        bid_price: *bid_price,
+                        bid_quantity: depth.bid_quantities[0],
+                        ask_price: *ask_price,
+                        ask_quantity: depth.ask_quantities[0],
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn parse_error(e: impl std::fmt::Display) -> IOError {
+    IOError::new(ErrorKind::InvalidData, format!("Failed to parse stream message: {}", e))
+}
+
+/// What a live strategy is fed and how it is stopped.
+#[derive(Debug)]
+pub struct StrategyRuntimeConfig {
+    pub streams: Vec<BinanceStreamTypes>, // Lower case symbols, as for `BinanceWebSocket`
+    pub markets: Vec<(String, String)>, // Base and quote asset of every symbol traded
+    pub timer_interval: Option<Duration>,
+    // Cancel the orders the strategy left open when shut down
+    pub cancel_on_shutdown: bool,
+}
+
+impl StrategyRuntimeConfig {
+    pub fn new(streams: Vec<BinanceStreamTypes>) -> Self {
+        StrategyRuntimeConfig {
+            streams,
+            markets: Vec::new(),
+            timer_interval: None,
+            cancel_on_shutdown: false,
+        }
+    }
+
+    pub fn with_market(mut self, base_asset: &str, quote_asset: &str) -> Self {
+        self.markets.push((base_asset.to_string(), quote_asset.to_string()));
+        self
+    }
+
+    pub fn with_timer(mut self, interval: Duration) -> Self {
+        self.timer_interval = Some(interval);
+        self
+    }
+
+    pub fn with_cancel_on_shutdown(mut self) -> Self {
+        self.cancel_on_shutdown = true;
+        self
+    }
+}
+
+/// Runs a [`Strategy`] against the live exchange, or against a [`PaperTrader`].
+///
+/// Market data comes from [`BinanceWebSocket`], order updates and balances from the user
+/// data stream, and queued orders are placed through an [`OrderApi`] such as
+/// [`SpotClient`](crate::binance_client::spot_orders::SpotClient). Events are handled one at
+/// a time: orders are placed before the next message is read, so the execution reports
+/// of an order are always matched to the strategy's order id.
+pub struct StrategyRuntime<'a> {
+    binance_client: &'a BinanceClient,
+    order_api: &'a dyn OrderApi,
+    paper_trader: Option<&'a PaperTrader>,
+    config: StrategyRuntimeConfig,
+}
+
+impl<'a> StrategyRuntime<'a> {
+    pub fn new(binance_client: &'a BinanceClient, order_api: &'a dyn OrderApi, config: StrategyRuntimeConfig) -> Self {
+        StrategyRuntime { binance_client, order_api, paper_trader: None, config }
+    }
+
+    /// Trades on `paper_trader` instead, fed with the market data of `binance_client`.
+    ///
+    /// The client should point at the mainnet, it needs no API key. Balances, commissions
+    /// and order updates come from the paper trader, which also gets the trade and depth
+    /// streams it fills orders against; `markets` of the config is not used.
+    pub fn paper(binance_client: &'a BinanceClient, paper_trader: &'a PaperTrader, config: StrategyRuntimeConfig) -> Self {
+        StrategyRuntime { binance_client, order_api: paper_trader, paper_trader: Some(paper_trader), config }
+    }
+
+    /// Runs `strategy` until `shutdown` completes or a stream fails.
+    ///
+    /// Either way the streams are closed and the listen key is released; only a shutdown
+    /// cancels open orders, and only with `cancel_on_shutdown`.
+    pub async fn run<S: Strategy + ?Sized>(&self, strategy: &mut S, shutdown: impl Future<Output = ()>) -> Result<(), IOError> {
+        let mut streams = self.config.streams.clone();
+        let mut fee_models = HashMap::new();
+        // Listen before fetching balances so no balance update is missed
+        let mut user_data = match self.paper_trader {
+            Some(paper_trader) => {
+                for stream in paper_trader.streams() {
+                    if !streams.iter().any(|existing| existing.to_stream_path() == stream.to_stream_path()) {
+                        streams.push(stream);
+                    }
+                }
+                for market in paper_trader.markets() {
+                    fee_models.insert(market.symbol.clone(), market.fee_model.clone());
+                }
+                UserData::Paper(paper_trader.subscribe())
+            }
+            None => {
+                for (base_asset, quote_asset) in &self.config.markets {
+                    let model = FeeModel::fetch(self.binance_client, base_asset, quote_asset).await?;
+                    fee_models.insert(format!("{}{}", base_asset, quote_asset), model);
+                }
+                let listen_key = self.binance_client.get_listen_key().await?;
+                let ws_url = format!("{}/{}", self.binance_client.websocket_url, listen_key);
+                let (socket, _) = tokio_tungstenite::connect_async(ws_url)
+                    .await
+                    .map_err(|e| IOError::new(ErrorKind::Other, format!("User data stream connection failed: {}", e)))?;
+                UserData::Live { socket: Box::new(socket), listen_key }
+            }
+        };
+        let mut market_data = BinanceWebSocket::new(self.binance_client).connect(&streams).await?;
+
+        let mut session = Session { order_api: self.order_api, fee_models, orders: LiveOrders::default() };
+        let mut ctx = StrategyContext::new();
+        let result = self.listen(&mut session, strategy, &mut ctx, &mut market_data, &mut user_data, shutdown).await;
+
+        if result.is_ok() && self.config.cancel_on_shutdown {
+            session.cancel_open_orders().await;
+        }
+        if let Err(e) = market_data.close().await {
+            trace!("Failed to close market data stream: {}", e);
+        }
+        if let UserData::Live { mut socket, listen_key } = user_data {
+            if let Err(e) = (*socket).close(None).await {
+                trace!("Failed to close user data stream: {}", e);
+            }
+            if let Err(e) = self.binance_client.close_listen_key(&listen_key).await {
+                warn!("{}", e);
+            }
+        }
+        info!("Strategy runtime stopped");
+        result
+    }
+
+    async fn listen<S: Strategy + ?Sized>(
+        &self,
+        session: &mut Session<'_>,
+        strategy: &mut S,
+        ctx: &mut StrategyContext,
+        market_data: &mut WebSocketStream<MaybeTlsStream<TcpStream>>,
+        user_data: &mut UserData,
+        shutdown: impl Future<Output = ()>,
+    ) -> Result<(), IOError> {
+        let balances = match self.paper_trader {
+            Some(paper_trader) => paper_trader.balances(),
+            None => AccountInfoClient::new_without_zero_balances(self.binance_client)
+                .await
+                .map_err(|e| IOError::new(ErrorKind::Other, format!("Failed to fetch balances: {}", e)))?
+                .balances,
+        };
+        for balance in &balances {
+            ctx.set_balance(&balance.asset, balance.free);
+        }
+
+        ctx.set_time(BinanceClient::generate_timestamp()?);
+        strategy.on_start(ctx);
+        session.execute(strategy, ctx, Vec::new()).await?;
+
+        let mut timer = self.config.timer_interval.map(|period| {
+            let mut timer = interval_at(Instant::now() + period, period);
+            timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            timer
+        });
+        let mut keep_alive = interval_at(Instant::now() + LISTEN_KEY_KEEP_ALIVE, LISTEN_KEY_KEEP_ALIVE);
+        tokio::pin!(shutdown);
+
+        loop {
+            tokio::select! {
+                _ = &mut shutdown => {
+                    info!("Strategy runtime shutting down");
+                    return Ok(());
+                }
+                message = market_data.next() => {
+                    let message = message
+                        .ok_or_else(|| IOError::new(ErrorKind::UnexpectedEof, "Market data stream closed"))?
+                        .map_err(|e| IOError::new(ErrorKind::Other, format!("Market data stream error: {}", e)))?;
+                    let Some(text) = message.as_text() else { continue };
+                    let now = BinanceClient::generate_timestamp()?;
+                    match MarketEvent::parse(text, now) {
+                        Ok(Some(event)) => {
+                            // Resting paper orders fill before the strategy reacts to the event
+                            if let Some(paper_trader) = self.paper_trader {
+                                paper_trader.apply(&event);
+                            }
+                            ctx.set_time(now);
+                            event.dispatch(strategy, ctx);
+                            session.execute(strategy, ctx, Vec::new()).await?;
+                        }
+                        Ok(None) => {}
+                        Err(e) => warn!("{}\n {}", e, text),
+                    }
+                }
+                event = user_data.next() => {
+                    let Some(event) = event? else { continue };
+                    ctx.set_time(BinanceClient::generate_timestamp()?);
+                    let updates = session.handle_user_data(ctx, event);
+                    session.execute(strategy, ctx, updates).await?;
+                }
+                _ = tick(&mut timer) => {
+                    ctx.set_time(BinanceClient::generate_timestamp()?);
+                    strategy.on_timer(ctx);
+                    session.execute(strategy, ctx, Vec::new()).await?;
+                }
+                _ = keep_alive.tick() => {
+                    if let UserData::Live { listen_key, .. } = user_data {
+                        self.binance_client.keep_alive_listen_key(listen_key).await?;
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Where order updates and balance changes come from
+enum UserData {
+    Live { socket: Box<UserDataSocket>, listen_key: String },
+    Paper(UnboundedReceiver<UserDataEvent>),
+}
+
+impl UserData {
+    // `None` for messages that are no event
+    async fn next(&mut self) -> Result<Option<UserDataEvent>, IOError> {
+        match self {
+            UserData::Live { socket, .. } => {
+                let message = socket
+                    .next()
+                    .await
+                    .ok_or_else(|| IOError::new(ErrorKind::UnexpectedEof, "User data stream closed"))?
+                    .map_err(|e| IOError::new(ErrorKind::Other, format!("User data stream error: {}", e)))?;
+                let Ok(text) = message.to_text() else { return Ok(None) };
+                match UserDataEvent::parse(text) {
+                    Ok(event) => Ok(Some(event)),
+                    Err(e) => {
+                        warn!("Failed to parse user data message: {}\n {}", e, text);
+                        Ok(None)
+                    }
+                }
+            }
+            UserData::Paper(events) => events
+                .recv()
+                .await
+                .map(Some)
+                .ok_or_else(|| IOError::new(ErrorKind::UnexpectedEof, "Paper trader stopped")),
+        }
+    }
+}
+
+// Never completes without a timer
+async fn tick(timer: &mut Option<Interval>) {
+    match timer {
+        Some(timer) => {
+            timer.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+// State of one run that the strategy callbacks do not borrow
+struct Session<'a> {
+    order_api: &'a dyn OrderApi,
+    fee_models: HashMap<String, FeeModel>, // By symbol
+    orders: LiveOrders,
+}
+
+impl Session<'_> {
+    // Places queued orders and delivers updates until the strategy stops reacting
+    async fn execute<S: Strategy + ?Sized>(&mut self, strategy: &mut S, ctx: &mut StrategyContext, updates: Vec<OrderUpdate>) -> Result<(), IOError> {
+        let mut pending = VecDeque::from(updates);
+        let mut commands = 0;
+        loop {
+            for command in ctx.take_commands() {
+                commands += 1;
+                match command {
+                    StrategyCommand::Submit(order_id, intent) => pending.extend(self.submit(order_id, intent, ctx.time()).await),
+                    StrategyCommand::Cancel(order_id) => self.cancel(order_id).await,
+                }
+            }
+            if commands > MAX_COMMANDS_PER_EVENT {
+                return Err(IOError::new(ErrorKind::Other, format!(
+                    "Strategy queued more than {} commands at {}", MAX_COMMANDS_PER_EVENT, ctx.time()
+                )));
+            }
+
+            let Some(update) = pending.pop_front() else { return Ok(()) };
+            ctx.apply_update(&update);
+            strategy.on_order_update(ctx, &update);
+        }
+    }
+
+    // Updates arrive through the user data stream; a failed request is a rejection
+    async fn submit(&mut self, order_id: OrderId, intent: OrderIntent, time: u64) -> Option<OrderUpdate> {
+        match place_order(self.order_api, &intent, time).await {
+            Ok(exchange_ids) => {
+                trace!("Placed order {} as {:?}: {:?}", order_id, exchange_ids, intent);
+                self.orders.insert(order_id, intent, exchange_ids);
+                None
+            }
+            Err(e) => {
+                warn!("Order {} rejected: {}", order_id, e);
+                Some(OrderUpdate {
+                    order_id,
+                    symbol: intent.symbol().to_string(),
+                    side: intent.side(),
+                    status: OrderStatus::Rejected,
+                    time,
+                    fill: None,
+                    filled_quantity: 0.0,
+                    reason: Some(e.to_string()),
+                })
+            }
+        }
+    }
+
+    // The cancellation is reported through the user data stream
+    async fn cancel(&mut self, order_id: OrderId) {
+        let Some((symbol, exchange_id)) = self.orders.exchange_order(order_id) else { return };
+        // Canceling one order of an OCO cancels the whole list
+        if let Err(e) = self.order_api.cancel_order(&symbol, exchange_id).await {
+            warn!("Failed to cancel order {}: {}", order_id, e);
+        }
+    }
+
+    async fn cancel_open_orders(&mut self) {
+        let mut order_ids = self.orders.open_order_ids();
+        order_ids.sort_unstable();
+        for order_id in order_ids {
+            self.cancel(order_id).await;
+        }
+    }
+
+    fn handle_user_data(&mut self, ctx: &mut StrategyContext, event: UserDataEvent) -> Vec<OrderUpdate> {
+        match event {
+            UserDataEvent::ExecutionReport(report) => {
+                let fee_model = self.fee_models.get(&report.symbol);
+                let Some(update) = self.orders.update(&report, fee_model) else { return Vec::new() };
+                if let (Some(fee_model), Some(_)) = (fee_model, &update.fill) {
+                    credit_fill(ctx, &report, fee_model);
+                }
+                vec![update]
+            }
+            UserDataEvent::ListStatus(_) => Vec::new(),
+            UserDataEvent::Other(value) => {
+                for (asset, free) in balance_updates(&value) {
+                    ctx.set_balance(&asset, free);
+                }
+                Vec::new()
+            }
+        }
+    }
+}
+
+async fn place_order(order_api: &dyn OrderApi, intent: &OrderIntent, time: u64) -> Result<Vec<i64>, IOError> {
+    match intent {
+        OrderIntent::Market { symbol, side, quantity } => {
+            let order = MarketOrder::new_with_base_asset(symbol, *side, *quantity);
+            Ok(vec![order_api.create_market_order(order).await?.order_id])
+        }
+        OrderIntent::Limit { symbol, side, quantity, price } => {
+            let order = LimitOrder::new(symbol, *side, *quantity, *price, time);
+            Ok(vec![order_api.create_limit_order(order).await?.order_id])
+        }
+        OrderIntent::StopLimit { symbol, side, quantity, stop_price, price } => {
+            let order = StopLimitOrder::new(symbol, *side, *quantity, *stop_price, *price, TimeInForce::GTC);
+            Ok(vec![order_api.create_stop_limit_order(order).await?.order_id])
+        }
+        OrderIntent::Oco { symbol, side, quantity, price, stop_price, stop_limit_price } => {
+            let mut order = OcoOrder::new(symbol.clone(), *side, *quantity, *price, *stop_price, *stop_limit_price, time);
+            order.stop_limit_time_in_force = Some(TimeInForce::GTC);
+            let response = order_api.create_oco_order(order).await?;
+            Ok(response.orders.iter().map(|order| order.order_id).collect())
+        }
+    }
+}
+
+// Binance sends the balance update after the execution report, so until it arrives the
+// proceeds of a fill are credited here. Resting orders locked what they spend when placed.
+fn credit_fill(ctx: &mut StrategyContext, report: &ExecutionReport, fee_model: &FeeModel) {
+    let (received, received_qty, spent, spent_qty) = match report.side {
+        Side::Buy => (&fee_model.base_asset, report.last_executed_qty, &fee_model.quote_asset, report.last_quote_qty),
+        Side::Sell => (&fee_model.quote_asset, report.last_quote_qty, &fee_model.base_asset, report.last_executed_qty),
+    };
+    ctx.set_balance(received, ctx.balance(received) + received_qty);
+    if report.order_type == OrderType::Market {
+        ctx.set_balance(spent, (ctx.balance(spent) - spent_qty).max(0.0));
+    }
+    if let Some(asset) = report.commission_asset.as_deref() {
+        ctx.set_balance(asset, (ctx.balance(asset) - report.commission).max(0.0));
+    }
+}
+
+/// Free balances of an `outboundAccountPosition` event, empty for other events.
+pub fn balance_updates(event: &Value) -> Vec<(String, f64)> {
+    if event["e"].as_str() != Some("outboundAccountPosition") {
+        return Vec::new();
+    }
+    event["B"]
+        .as_array()
+        .map(|balances| {
+            balances
+                .iter()
+                .filter_map(|balance| {
+                    let asset = balance["a"].as_str()?;
+                    let free = balance["f"].as_str()?.parse().ok()?;
+                    Some((asset.to_string(), free))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// A placed order as the strategy knows it
+#[derive(Debug)]
+struct LiveOrder {
+    symbol: String,
+    side: Side,
+    exchange_ids: Vec<i64>, // Two for an OCO
+    acknowledged: bool,
+    filled_quantity: f64,
+}
+
+// Translates execution reports into updates of the strategy's orders
+#[derive(Debug, Default)]
+struct LiveOrders {
+    orders: HashMap<OrderId, LiveOrder>,
+    by_exchange_id: HashMap<(String, i64), OrderId>, // Exchange ids are only unique per symbol
+}
+
+impl LiveOrders {
+    fn insert(&mut self, order_id: OrderId, intent: OrderIntent, exchange_ids: Vec<i64>) {
+        for exchange_id in &exchange_ids {
+            self.by_exchange_id.insert((intent.symbol().to_string(), *exchange_id), order_id);
+        }
+        self.orders.insert(order_id, LiveOrder {
+            symbol: intent.symbol().to_string(),
+            side: intent.side(),
+            exchange_ids,
+            acknowledged: false,
+            filled_quantity: 0.0,
+        });
+    }
+
+    fn exchange_order(&self, order_id: OrderId) -> Option<(String, i64)> {
+        let order = self.orders.get(&order_id)?;
+        Some((order.symbol.clone(), *order.exchange_ids.first()?))
+    }
+
+    fn open_order_ids(&self) -> Vec<OrderId> {
+        self.orders.keys().copied().collect()
+    }
+
+    // `None` for reports of other orders and for the duplicates the legs of an OCO cause
+    fn update(&mut self, report: &ExecutionReport, fee_model: Option<&FeeModel>) -> Option<OrderUpdate> {
+        let order_id = *self.by_exchange_id.get(&(report.symbol.clone(), report.order_id))?;
+        let order = self.orders.get_mut(&order_id)?;
+
+        let (status, fill) = match report.execution_type {
+            ExecutionType::New if order.acknowledged => return None,
+            ExecutionType::New => {
+                order.acknowledged = true;
+                (OrderStatus::New, None)
+            }
+            ExecutionType::Trade => {
+                order.acknowledged = true;
+                order.filled_quantity += report.last_executed_qty;
+                let fee = match report.commission_asset.as_deref() {
+                    Some(asset) => fee_model
+                        .and_then(|model| model.actual_fee(report.commission, asset, report.last_executed_price, None))
+                        .unwrap_or(Fee { asset: asset.to_string(), amount: report.commission, quote_value: 0.0 }),
+                    None => Fee { asset: String::new(), amount: 0.0, quote_value: 0.0 },
+                };
+                let fill = StrategyFill {
+                    price: report.last_executed_price,
+                    quantity: report.last_executed_qty,
+                    fee,
+                    role: LiquidityRole::from_is_maker(report.is_maker),
+                };
+                let status = if report.order_status == OrderStatus::Filled { OrderStatus::Filled } else { OrderStatus::PartiallyFilled };
+                (status, Some(fill))
+            }
+            ExecutionType::Canceled | ExecutionType::Rejected | ExecutionType::Expired | ExecutionType::TradePrevention => {
+                (report.order_status, None)
+            }
+            ExecutionType::Replaced => return None,
+        };
+
+        let update = OrderUpdate {
+            order_id,
+            symbol: order.symbol.clone(),
+            side: order.side,
+            status,
+            time: report.transaction_time,
+            fill,
+            filled_quantity: order.filled_quantity,
+            reason: (status == OrderStatus::Rejected).then(|| report.reject_reason.clone()),
+        };
+        // The other order of a filled or canceled OCO reports too late to matter
+        if !matches!(status, OrderStatus::New | OrderStatus::PartiallyFilled | OrderStatus::PendingCancel) {
+            if let Some(order) = self.orders.remove(&order_id) {
+                for exchange_id in order.exchange_ids {
+                    self.by_exchange_id.remove(&(order.symbol.clone(), exchange_id));
+                }
+            }
+        }
+        Some(update)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn execution_report(order_id: i64, execution_type: &str, status: &str, last_qty: &str) -> ExecutionReport {
+        let json_data = format!(r#"{{
+            "e": "executionReport", "E": 1499405658658, "s": "ETHUSDT", "c": "mUvoqJxFIILMdfAW5iGSOW",
+            "S": "SELL", "o": "LIMIT_MAKER", "f": "GTC", "q": "1.00000000", "p": "2100.00000000", "P": "0.00000000",
+            "F": "0.00000000", "g": 7, "C": "", "x": "{}", "X": "{}", "r": "NONE",
+            "i": {}, "l": "{}", "z": "{}", "L": "2100.00000000", "n": "0.21000000",
+            "N": "USDT", "T": 1499405658657, "t": 77, "I": 8641984, "w": false, "m": true, "M": false,
+            "O": 1499405658657, "Z": "0.00000000", "Y": "0.00000000", "Q": "0.00000000",
+            "W": 1499405658657, "V": "NONE"
+        }}"#, execution_type, status, order_id, last_qty, last_qty);
+        serde_json::from_str(&json_data).unwrap()
+    }
+
+    #[test]
+    fn test_market_event_parse() {
+        let trade = r#"{"stream":"ethusdt@aggTrade","data":{"e":"aggTrade","E":1672515782136,"s":"ETHUSDT",
+            "a":12345,"p":"2000.50","q":"0.25","f":100,"l":105,"T":1672515782136,"m":true,"M":true}}"#;
+        let Some(MarketEvent::Trade(trade)) = MarketEvent::parse(trade, 0).unwrap() else { panic!("Not a trade") };
+        assert_eq!((trade.trade_id, trade.price, trade.quantity, trade.buyer_order_id), (12345, 2000.5, 0.25, -1));
+
+        let book = r#"{"stream":"ethusdt@bookTicker","data":{"u":400900217,"s":"ETHUSDT",
+            "b":"1999.9","B":"31.2","a":"2000.1","A":"40.6"}}"#;
+        let event = MarketEvent::parse(book, 42).unwrap().unwrap();
+        assert_eq!(event.symbol(), "ETHUSDT");
+        let MarketEvent::Book(book) = event else { panic!("Not a book tick") };
+        assert_eq!((book.time, book.bid_price, book.ask_quantity), (42, 1999.9, 40.6));
+
+        let open_kline = r#"{"stream":"ethusdt@kline_1m","data":{"e":"kline","E":1672515782136,"s":"ETHUSDT",
+            "k":{"t":1672515780000,"T":1672515839999,"s":"ETHUSDT","i":"1m","f":100,"L":200,"o":"2000.0",
+            "c":"2001.0","h":"2002.0","l":"1999.0","v":"10.0","n":100,"x":false,"q":"20000.0","V":"5.0",
+            "Q":"10000.0","B":"0"}}}"#;
+        assert!(MarketEvent::parse(open_kline, 0).unwrap().is_none());
+        let closed_kline = open_kline.replace(r#""x":false"#, r#""x":true"#);
+        let Some(MarketEvent::Kline(kline)) = MarketEvent::parse(&closed_kline, 0).unwrap() else { panic!("Not a kline") };
+        assert_eq!(kline.close_price, 2001.0);
+
+        let depth = r#"{"stream":"ethusdt@depth5@100ms","data":{"lastUpdateId":160,
+            "bids":[["1999.9","2.0"],["1999.8","3.0"]],"asks":[["2000.1","1.5"]]}}"#;
+        let event = MarketEvent::parse(depth, 7).unwrap().unwrap();
+        assert_eq!(event.symbol(), "ETHUSDT");
+        let MarketEvent::Depth(depth) = event else { panic!("Not a depth snapshot") };
+        assert_eq!((depth.snapshot_time, depth.bid_quantities, depth.ask_prices), (7, vec![2.0, 3.0], vec![2000.1]));
+
+        let avg_price = r#"{"stream":"ethusdt@avgPrice","data":{"e":"avgPrice","E":1,"s":"ETHUSDT","i":"5m","w":"2000.0","T":1}}"#;
+        assert!(MarketEvent::parse(avg_price, 0).unwrap().is_none());
+        assert_eq!(MarketEvent::parse("not json", 0).unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_oco_execution_reports() {
+        let fee_model = FeeModel::flat(0.0001, 0.0001, "ETH", "USDT");
+        let mut orders = LiveOrders::default();
+        orders.insert(3, OrderIntent::oco("ETHUSDT", Side::Sell, 1.0, 2100.0, 1900.0, 1890.0), vec![11, 12]);
+
+        // Both legs are acknowledged, the strategy hears about its order once
+        let new = orders.update(&execution_report(11, "NEW", "NEW", "0"), Some(&fee_model)).unwrap();
+        assert_eq!((new.order_id, new.status), (3, OrderStatus::New));
+        assert!(orders.update(&execution_report(12, "NEW", "NEW", "0"), Some(&fee_model)).is_none());
+
+        // An order of another symbol with the same id is not the strategy's
+        let mut other_symbol = execution_report(12, "TRADE", "FILLED", "1.0");
+        other_symbol.symbol = "BTCUSDT".to_string();
+        assert!(orders.update(&other_symbol, Some(&fee_model)).is_none());
+
+        let partial = orders.update(&execution_report(12, "TRADE", "PARTIALLY_FILLED", "0.4"), Some(&fee_model)).unwrap();
+        assert_eq!(partial.status, OrderStatus::PartiallyFilled);
+        let fill = partial.fill.unwrap();
+        assert_eq!((fill.price, fill.quantity, fill.role), (2100.0, 0.4, LiquidityRole::Maker));
+        assert_eq!((fill.fee.asset.as_str(), fill.fee.quote_value), ("USDT", 0.21));
+
+        let filled = orders.update(&execution_report(12, "TRADE", "FILLED", "0.6"), Some(&fee_model)).unwrap();
+        assert_eq!(filled.status, OrderStatus::Filled);
+        assert!((filled.filled_quantity - 1.0).abs() < 1e-12);
+
+        // The other leg expires after the fill
+        assert!(orders.update(&execution_report(11, "EXPIRED", "EXPIRED", "0"), Some(&fee_model)).is_none());
+        assert!(orders.exchange_order(3).is_none());
+        assert!(orders.update(&execution_report(99, "NEW", "NEW", "0"), None).is_none());
+    }
+
+    #[test]
+    fn test_fill_is_credited_until_balance_update() {
+        let fee_model = FeeModel::flat(0.0001, 0.0001, "ETH", "USDT");
+        let mut ctx = StrategyContext::new();
+        ctx.set_balance("ETH", 1.0);
+        ctx.set_balance("USDT", 10.0);
+
+        let mut report = execution_report(12, "TRADE", "FILLED", "1.0");
+        report.last_quote_qty = 2100.0;
+        credit_fill(&mut ctx, &report, &fee_model);
+        // The sold ETH was locked by the resting order
+        assert_eq!((ctx.balance("ETH"), ctx.balance("USDT")), (1.0, 10.0 + 2100.0 - 0.21));
+
+        let position: Value = serde_json::from_str(r#"{
+            "e": "outboundAccountPosition", "E": 1564034571105, "u": 1564034571073,
+            "B": [{"a": "ETH", "f": "0.00000000", "l": "0.00000000"}, {"a": "USDT", "f": "2109.79000000", "l": "0.00000000"}]
+        }"#).unwrap();
+        assert_eq!(balance_updates(&position), vec![("ETH".to_string(), 0.0), ("USDT".to_string(), 2109.79)]);
+        assert!(balance_updates(&serde_json::json!({"e": "balanceUpdate"})).is_empty());
+    }
+}
@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AggTradeMessage {
+    pub stream: String,
+    pub data: AggTradeData,
+}
+
+/// Trade information aggregated for a single taker order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AggTradeData {
+    #[serde(rename = "e")]
+    pub event_type: String, // Event type
+    #[serde(rename = "E")]
+    pub event_time: u64, // Event time
+    #[serde(rename = "s")]
+    pub symbol: String, // Symbol
+    #[serde(rename = "a")]
+    pub aggregate_trade_id: u64, // Aggregate trade ID
+    #[serde(rename = "p")]
+    pub price: String, // Price
+    #[serde(rename = "q")]
+    pub quantity: String, // Quantity
+    #[serde(rename = "f")]
+    pub first_trade_id: u64, // First trade ID
+    #[serde(rename = "l")]
+    pub last_trade_id: u64, // Last trade ID
+    #[serde(rename = "T")]
+    pub trade_time: u64, // Trade time
+    #[serde(rename = "m")]
+    pub is_market_maker: bool, // Is the buyer the market maker?
+    #[serde(rename = "M")]
+    pub ignore: bool, // Placeholder (ignore)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn agg_trade_message_deserialization() {
+        let json_str = r#"{
+            "stream": "bnbbtc@aggTrade",
+            "data": {
+                "e": "aggTrade",
+                "E": 1672515782136,
+                "s": "BNBBTC",
+                "a": 12345,
+                "p": "0.001",
+                "q": "100",
+                "f": 100,
+                "l": 105,
+                "T": 1672515782136,
+                "m": true,
+                "M": true
+            }
+        }"#;
+
+        let message: AggTradeMessage = serde_json::from_str(json_str).expect("Failed to deserialize AggTradeMessage");
+        assert_eq!(message.stream, "bnbbtc@aggTrade");
+        assert_eq!(message.data.aggregate_trade_id, 12345);
+        assert_eq!(message.data.first_trade_id, 100);
+        assert_eq!(message.data.last_trade_id, 105);
+        assert_eq!(message.data.price, "0.001");
+        assert!(message.data.is_market_maker);
+    }
+}
@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+use crate::binance_client::streams::ticker_stream::TickerData;
+
+/// Message for `!ticker@arr`, only containing the symbols whose 24hr ticker changed.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AllMarketTickersMessage {
+    pub stream: String,
+    pub data: Vec<TickerData>,
+}
@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AvgPriceMessage {
+    pub stream: String,
+    pub data: AvgPriceData,
+}
+
+/// Changes in the average price over a fixed time interval.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AvgPriceData {
+    #[serde(rename = "e")]
+    pub event_type: String, // Event type
+    #[serde(rename = "E")]
+    pub event_time: u64, // Event time
+    #[serde(rename = "s")]
+    pub symbol: String, // Symbol
+    #[serde(rename = "i")]
+    pub interval: String, // Average price interval
+    #[serde(rename = "w")]
+    pub average_price: String, // Average price
+    #[serde(rename = "T")]
+    pub last_trade_time: u64, // Last trade time
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn avg_price_message_deserialization() {
+        let json_str = r#"{
+            "stream": "btcusdt@avgPrice",
+            "data": {
+                "e": "avgPrice",
+                "E": 1693907033000,
+                "s": "BTCUSDT",
+                "i": "5m",
+                "w": "25776.86000000",
+                "T": 1693907032213
+            }
+        }"#;
+
+        let message: AvgPriceMessage = serde_json::from_str(json_str).unwrap();
+        assert_eq!(message.data.symbol, "BTCUSDT");
+        assert_eq!(message.data.interval, "5m");
+        assert_eq!(message.data.average_price, "25776.86000000");
+    }
+}
@@ -1,24 +1,85 @@
-#[derive(Debug)]
+use std::fmt;
+use crate::binance_client::kline_interval::KlineInterval;
+
+/// Number of price levels pushed by the partial book depth streams.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepthLevels {
+    Five,
+    Ten,
+    Twenty,
+}
+
+impl fmt::Display for DepthLevels {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let levels = match self {
+            DepthLevels::Five => 5,
+            DepthLevels::Ten => 10,
+            DepthLevels::Twenty => 20,
+        };
+        write!(f, "{}", levels)
+    }
+}
+
+/// Window sizes supported by the rolling window statistics streams.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RollingWindow {
+    OneHour,
+    FourHours,
+    OneDay,
+}
+
+impl fmt::Display for RollingWindow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let window = match self {
+            RollingWindow::OneHour => "1h",
+            RollingWindow::FourHours => "4h",
+            RollingWindow::OneDay => "1d",
+        };
+        write!(f, "{}", window)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum BinanceStreamTypes {
     Depth(String),
+    Depth100ms(String),
+    PartialDepth(String, DepthLevels),
+    PartialDepth100ms(String, DepthLevels),
     Trade(String),
-    Kline(String, String), // Symbol and interval
+    AggTrade(String),
+    Kline(String, KlineInterval), // Symbol and interval
+    KlineUtc8(String, KlineInterval), // Kline with intervals opened in UTC+8
     Ticker(String),
+    RollingWindowTicker(String, RollingWindow),
     MiniTicker(String),
     BookTicker(String),
-    AllMarketMiniTickers
+    AvgPrice(String),
+    AllMarketMiniTickers,
+    AllMarketTickers,
+    AllMarketRollingWindowTickers(RollingWindow),
+    AllBookTickers,
 }
 
 impl BinanceStreamTypes {
     pub(crate) fn to_stream_path(&self) -> String {
         match self {
             BinanceStreamTypes::Depth(symbol) => format!("{}@depth", symbol),
+            BinanceStreamTypes::Depth100ms(symbol) => format!("{}@depth@100ms", symbol),
+            BinanceStreamTypes::PartialDepth(symbol, levels) => format!("{}@depth{}", symbol, levels),
+            BinanceStreamTypes::PartialDepth100ms(symbol, levels) => format!("{}@depth{}@100ms", symbol, levels),
             BinanceStreamTypes::Trade(symbol) => format!("{}@trade", symbol),
+            BinanceStreamTypes::AggTrade(symbol) => format!("{}@aggTrade", symbol),
             BinanceStreamTypes::Kline(symbol, interval) => format!("{}@kline_{}", symbol, interval),
+            BinanceStreamTypes::KlineUtc8(symbol, interval) => format!("{}@kline_{}@+08:00", symbol, interval),
             BinanceStreamTypes::Ticker(symbol) => format!("{}@ticker", symbol),
+            BinanceStreamTypes::RollingWindowTicker(symbol, window) => format!("{}@ticker_{}", symbol, window),
             BinanceStreamTypes::MiniTicker(symbol) => format!("{}@miniTicker", symbol),
             BinanceStreamTypes::BookTicker(symbol) => format!("{}@bookTicker", symbol),
+            BinanceStreamTypes::AvgPrice(symbol) => format!("{}@avgPrice", symbol),
             BinanceStreamTypes::AllMarketMiniTickers => "!miniTicker@arr".to_string(),
+            BinanceStreamTypes::AllMarketTickers => "!ticker@arr".to_string(),
+            BinanceStreamTypes::AllMarketRollingWindowTickers(window) => format!("!ticker_{}@arr", window),
+            BinanceStreamTypes::AllBookTickers => "!bookTicker".to_string(),
         }
     }
 }
@@ -31,9 +92,32 @@ mod tests {
     use crate::binance_client::binance_client::BinanceClient;
     use crate::binance_client::load_env::EnvVars;
     use crate::binance_client::logger_conf::init_logger;
-    use crate::binance_client::streams::binance_stream::BinanceStreamTypes;
+    use crate::binance_client::kline_interval::KlineInterval;
+    use crate::binance_client::streams::binance_stream::{BinanceStreamTypes, DepthLevels, RollingWindow};
     use crate::binance_client::streams::binance_websocket::BinanceWebSocket;
 
+    #[test]
+    fn stream_paths() {
+        let symbol = "bnbbtc".to_string();
+        let cases = vec![
+            (BinanceStreamTypes::Depth(symbol.clone()), "bnbbtc@depth"),
+            (BinanceStreamTypes::Depth100ms(symbol.clone()), "bnbbtc@depth@100ms"),
+            (BinanceStreamTypes::PartialDepth(symbol.clone(), DepthLevels::Five), "bnbbtc@depth5"),
+            (BinanceStreamTypes::PartialDepth100ms(symbol.clone(), DepthLevels::Twenty), "bnbbtc@depth20@100ms"),
+            (BinanceStreamTypes::AggTrade(symbol.clone()), "bnbbtc@aggTrade"),
+            (BinanceStreamTypes::Kline(symbol.clone(), KlineInterval::OneMonth), "bnbbtc@kline_1M"),
+            (BinanceStreamTypes::KlineUtc8(symbol.clone(), KlineInterval::OneDay), "bnbbtc@kline_1d@+08:00"),
+            (BinanceStreamTypes::RollingWindowTicker(symbol.clone(), RollingWindow::FourHours), "bnbbtc@ticker_4h"),
+            (BinanceStreamTypes::AvgPrice(symbol.clone()), "bnbbtc@avgPrice"),
+            (BinanceStreamTypes::AllMarketTickers, "!ticker@arr"),
+            (BinanceStreamTypes::AllMarketRollingWindowTickers(RollingWindow::OneDay), "!ticker_1d@arr"),
+            (BinanceStreamTypes::AllBookTickers, "!bookTicker"),
+        ];
+        for (stream, expected) in cases {
+            assert_eq!(stream.to_stream_path(), expected);
+        }
+    }
+
     #[tokio::test]
     async fn depth_stream_test() {
         // Initialize logger for detailed output, if needed.
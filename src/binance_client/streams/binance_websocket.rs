@@ -6,12 +6,28 @@ use async_std::stream::StreamExt;
 use futures_util::future::err;
 use futures_util::SinkExt;
 use serde_json::{Error, Value};
-use tokio_websockets::{ClientBuilder, Message};
+use tokio::net::TcpStream;
+use tokio_websockets::{ClientBuilder, MaybeTlsStream, Message, WebSocketStream};
 use regex::Regex;
 use crate::binance_client::binance_client::BinanceClient;
 use crate::binance_client::streams::binance_stream::BinanceStreamTypes;
+use std::fmt::Debug;
+use serde::de::DeserializeOwned;
+use crate::binance_client::streams::agg_trade_stream::AggTradeMessage;
+use crate::binance_client::streams::all_market_mini_ticker_streams::AllMarketMiniTickersMessage;
+use crate::binance_client::streams::all_market_ticker_streams::AllMarketTickersMessage;
+use crate::binance_client::streams::avg_price_stream::AvgPriceMessage;
+use crate::binance_client::streams::book_ticker_stream::BookTickerMessage;
 use crate::binance_client::streams::depth_stream::DepthMessage;
 use crate::binance_client::streams::kline_data::{Kline, KlineMessage};
+use crate::binance_client::streams::mini_ticker_stream::MiniTickerMessage;
+use crate::binance_client::streams::partial_depth_stream::PartialDepthMessage;
+use crate::binance_client::streams::rolling_window_ticker_stream::{AllMarketRollingWindowTickersMessage, RollingWindowTickerMessage};
+use crate::binance_client::streams::ticker_stream::TickerMessage;
+use crate::binance_client::streams::trade_stream::TradeMessage;
+
+// Matches combined stream names such as `btcusdt@kline_1m`, `bnbbtc@depth5@100ms` or `!ticker_1h@arr`
+const STREAM_NAME_PATTERN: &str = r"^(?:(?P<symbol>[a-z0-9]+)@|!)(?P<stream_type>[a-zA-Z]+?)(?P<levels>5|10|20)?(?:_(?P<detail>[a-zA-Z0-9]+))?(?:@(?P<suffix>100ms|arr|\+08:00))?$";
 
 #[derive(Debug)]
 pub struct BinanceWebSocket<'a> {
@@ -25,9 +41,12 @@ impl<'a> BinanceWebSocket<'a> {
         }
     }
 
-    // The method to connect to WebSocket and listen for messages
-    pub async fn connect_and_listen(&self, streams: Vec<BinanceStreamTypes>) -> Result<(), IOError> {
-        let combined_streams: Vec<String> = streams.into_iter().map(|s| s.to_stream_path()).collect();
+    /// Opens a combined stream connection subscribed to `streams`.
+    ///
+    /// Messages arrive wrapped as `{"stream": <name>, "data": <payload>}`. Pings from
+    /// Binance are answered by the websocket itself.
+    pub async fn connect(&self, streams: &[BinanceStreamTypes]) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, IOError> {
+        let combined_streams: Vec<String> = streams.iter().map(|s| s.to_stream_path()).collect();
         let stream_paths = combined_streams.join("/");
         let ws_url = format!("{}?streams={}", self.binance_client.stream_url, stream_paths);
 
@@ -40,12 +59,20 @@ impl<'a> BinanceWebSocket<'a> {
         };
 
         // Connect to the WebSocket server
-        let (mut client, _) = ClientBuilder::from_uri(uri)
+        let (client, _) = ClientBuilder::from_uri(uri)
             .connect()
             .await
             .map_err(|e| IOError::new(ErrorKind::Other, format!("Failed to connect: {}", e)))?;
 
         info!("WebSocket connected: {:?}" ,client);
+        Ok(client)
+    }
+
+    // The method to connect to WebSocket and listen for messages
+    pub async fn connect_and_listen(&self, streams: Vec<BinanceStreamTypes>) -> Result<(), IOError> {
+        let mut client = self.connect(&streams).await?;
+
+        let re = Regex::new(STREAM_NAME_PATTERN).unwrap();
 
 
         while let Some(message) = client.next().await {
@@ -66,16 +93,18 @@ impl<'a> BinanceWebSocket<'a> {
                         let message_as_string: Value = serde_json::from_str(msg.as_text().unwrap()).expect("Failed to parse message to JSON");
 
                         if let Some(stream_name) = message_as_string["stream"].as_str() {
-                            let re = Regex::new(r"^(?P<symbol>[a-z0-9]+)@(?P<stream_type>[a-z]+)(?:_(?P<detail>[a-z0-9]+))?$").unwrap();
-
                             if let Some(caps) = re.captures(stream_name) {
-                                let symbol = caps.name("symbol").map_or("", |m| m.as_str());
+                                let symbol = caps.name("symbol").map_or("", |m| m.as_str()); // Empty for all-market streams
                                 let stream_type = caps.name("stream_type").map_or("", |m| m.as_str());
+                                let levels = caps.name("levels").map_or("", |m| m.as_str()); // Only set for partial depth streams
                                 let detail = caps.name("detail").map_or("", |m| m.as_str()); // This can be empty for streams without additional details
 
-                                trace!("symbol: {:?}, stream type: {:?}, detail: {:?}", symbol, stream_type, detail);
+                                trace!("symbol: {:?}, stream type: {:?}, levels: {:?}, detail: {:?}", symbol, stream_type, levels, detail);
 
                                 match stream_type {
+                                    "depth" if !levels.is_empty() => {
+                                        log_stream_message::<PartialDepthMessage>(stream_type, symbol, &message_as_string);
+                                    }
                                     "depth" => {
                                         // Handle depth message
                                         if let Ok(depth_message) = serde_json::from_value::<DepthMessage>(message_as_string.clone()) {
@@ -97,6 +126,20 @@ impl<'a> BinanceWebSocket<'a> {
                                             }
                                         }
                                     }
+                                    "trade" => log_stream_message::<TradeMessage>(stream_type, symbol, &message_as_string),
+                                    "aggTrade" => log_stream_message::<AggTradeMessage>(stream_type, symbol, &message_as_string),
+                                    "ticker" => match (symbol.is_empty(), detail.is_empty()) {
+                                        (false, true) => log_stream_message::<TickerMessage>(stream_type, symbol, &message_as_string),
+                                        (false, false) => log_stream_message::<RollingWindowTickerMessage>(stream_type, symbol, &message_as_string),
+                                        (true, true) => log_stream_message::<AllMarketTickersMessage>(stream_type, symbol, &message_as_string),
+                                        (true, false) => log_stream_message::<AllMarketRollingWindowTickersMessage>(stream_type, symbol, &message_as_string),
+                                    },
+                                    "miniTicker" if symbol.is_empty() => {
+                                        log_stream_message::<AllMarketMiniTickersMessage>(stream_type, symbol, &message_as_string);
+                                    }
+                                    "miniTicker" => log_stream_message::<MiniTickerMessage>(stream_type, symbol, &message_as_string),
+                                    "bookTicker" => log_stream_message::<BookTickerMessage>(stream_type, symbol, &message_as_string),
+                                    "avgPrice" => log_stream_message::<AvgPriceMessage>(stream_type, symbol, &message_as_string),
                                     // Add more cases for other stream types as needed
                                     _ => error!("Unknown stream type: {}", stream_type),
                                 }
@@ -115,6 +158,14 @@ impl<'a> BinanceWebSocket<'a> {
     }
 }
 
+// Parses a combined stream message into its typed form and prints it
+fn log_stream_message<T: DeserializeOwned + Debug>(stream_type: &str, symbol: &str, message: &Value) {
+    match serde_json::from_value::<T>(message.clone()) {
+        Ok(parsed) => println!("Received {} message for {}: {:?}", stream_type, symbol, parsed),
+        Err(e) => error!("Failed to parse {} message: {:?}\n {:?}", stream_type, e, message),
+    }
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -122,10 +173,33 @@ mod tests {
     use log::LevelFilter::Trace;
     use log::trace;
     use crate::binance_client::binance_client::BinanceClient;
+    use crate::binance_client::kline_interval::KlineInterval;
     use crate::binance_client::load_env::EnvVars;
     use crate::binance_client::logger_conf::init_logger;
     use crate::binance_client::streams::binance_stream::BinanceStreamTypes;
-    use crate::binance_client::streams::binance_websocket::BinanceWebSocket;
+    use crate::binance_client::streams::binance_websocket::{BinanceWebSocket, STREAM_NAME_PATTERN};
+
+    #[test]
+    fn stream_name_pattern_captures() {
+        let re = regex::Regex::new(STREAM_NAME_PATTERN).unwrap();
+        let cases = [
+            ("btcusdt@kline_1m", "btcusdt", "kline", "", "1m"),
+            ("btcusdt@kline_1M@+08:00", "btcusdt", "kline", "", "1M"),
+            ("bnbbtc@depth5@100ms", "bnbbtc", "depth", "5", ""),
+            ("bnbbtc@depth@100ms", "bnbbtc", "depth", "", ""),
+            ("bnbbtc@aggTrade", "bnbbtc", "aggTrade", "", ""),
+            ("bnbbtc@ticker_4h", "bnbbtc", "ticker", "", "4h"),
+            ("!ticker_1d@arr", "", "ticker", "", "1d"),
+            ("!bookTicker", "", "bookTicker", "", ""),
+        ];
+        for (name, symbol, stream_type, levels, detail) in cases {
+            let caps = re.captures(name).unwrap_or_else(|| panic!("{} did not match", name));
+            assert_eq!(caps.name("symbol").map_or("", |m| m.as_str()), symbol, "{}", name);
+            assert_eq!(&caps["stream_type"], stream_type, "{}", name);
+            assert_eq!(caps.name("levels").map_or("", |m| m.as_str()), levels, "{}", name);
+            assert_eq!(caps.name("detail").map_or("", |m| m.as_str()), detail, "{}", name);
+        }
+    }
 
     #[tokio::test]
     async fn kline_websocket_stream_test() {
@@ -140,8 +214,8 @@ mod tests {
 
         // Define the streams you want to subscribe to
         let streams = vec![
-            BinanceStreamTypes::Kline("btcusdt".to_string(), "1m".to_string()),
-            BinanceStreamTypes::Kline("ethusdt".to_string(), "1m".to_string()),
+            BinanceStreamTypes::Kline("btcusdt".to_string(), KlineInterval::OneMinute),
+            BinanceStreamTypes::Kline("ethusdt".to_string(), KlineInterval::OneMinute),
             // BinanceStream::Depth(symbol.clone()),
             // BinanceStream::Trade(symbol.clone()),
         ];
@@ -31,18 +31,18 @@ pub struct DepthData {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Bid {
     #[serde(rename = "p")]
-    price: String, // Price of bid
+    pub price: String, // Price of bid
     #[serde(rename = "q")]
-    quantity: String, // Quantity of bid
+    pub quantity: String, // Quantity of bid
 }
 
 /// Represents a single ask in the order book.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Ask {
     #[serde(rename = "p")]
-    price: String, // Price of ask
+    pub price: String, // Price of ask
     #[serde(rename = "q")]
-    quantity: String, // Quantity of ask
+    pub quantity: String, // Quantity of ask
 }
 
 
@@ -1,12 +1,15 @@
 use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+use crate::binance_client::deserialization::deserialize_string_to_f64;
+use crate::binance_client::kline_interval::KlineInterval;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KlineMessage {
     pub stream: String,
     pub data: KlineData,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KlineData {
     #[serde(rename = "e")]
     pub event_type: String,
@@ -17,7 +20,8 @@ pub struct KlineData {
     pub k: Kline,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Kline {
     #[serde(rename = "t")]
     pub start_time: u64,
@@ -32,29 +36,94 @@ pub struct Kline {
     #[serde(rename = "L")]
     pub last_trade_id: u64,
     #[serde(rename = "o")]
+    #[serde_as(as = "DisplayFromStr")]
     pub open_price: f64,
     #[serde(rename = "c")]
+    #[serde_as(as = "DisplayFromStr")]
     pub close_price: f64,
     #[serde(rename = "h")]
+    #[serde_as(as = "DisplayFromStr")]
     pub high_price: f64,
     #[serde(rename = "l")]
+    #[serde_as(as = "DisplayFromStr")]
     pub low_price: f64,
     #[serde(rename = "v")]
+    #[serde_as(as = "DisplayFromStr")]
     pub base_asset_volume: f64,
     #[serde(rename = "n")]
     pub number_of_trades: u32,
     #[serde(rename = "x")]
     pub is_kline_closed: bool,
     #[serde(rename = "q")]
+    #[serde_as(as = "DisplayFromStr")]
     pub quote_asset_volume: f64,
     #[serde(rename = "V")]
+    #[serde_as(as = "DisplayFromStr")]
     pub taker_buy_base_asset_volume: f64,
     #[serde(rename = "Q")]
+    #[serde_as(as = "DisplayFromStr")]
     pub taker_buy_quote_asset_volume: f64,
     #[serde(rename = "B")]
     pub ignore: String,
 }
 
+/// A single kline as returned by the REST `/v3/klines` endpoint.
+///
+/// Binance sends each kline as a positional JSON array, so the field order below
+/// must match the documented response order.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RestKline {
+    pub open_time: u64,
+    #[serde(deserialize_with = "deserialize_string_to_f64")]
+    pub open_price: f64,
+    #[serde(deserialize_with = "deserialize_string_to_f64")]
+    pub high_price: f64,
+    #[serde(deserialize_with = "deserialize_string_to_f64")]
+    pub low_price: f64,
+    #[serde(deserialize_with = "deserialize_string_to_f64")]
+    pub close_price: f64,
+    #[serde(deserialize_with = "deserialize_string_to_f64")]
+    pub base_asset_volume: f64,
+    pub close_time: u64,
+    #[serde(deserialize_with = "deserialize_string_to_f64")]
+    pub quote_asset_volume: f64,
+    pub number_of_trades: u32,
+    #[serde(deserialize_with = "deserialize_string_to_f64")]
+    pub taker_buy_base_asset_volume: f64,
+    #[serde(deserialize_with = "deserialize_string_to_f64")]
+    pub taker_buy_quote_asset_volume: f64,
+    pub ignore: String,
+}
+
+impl RestKline {
+    /// Converts a REST kline into the stream `Kline` type.
+    ///
+    /// The REST response carries neither the symbol, the interval nor trade ids, so the
+    /// first two are taken from the request and the trade ids are left at zero. A kline
+    /// is considered closed once its close time is before `now_ms`.
+    pub fn into_kline(self, symbol: &str, interval: KlineInterval, now_ms: u64) -> Kline {
+        Kline {
+            start_time: self.open_time,
+            end_time: self.close_time,
+            symbol: symbol.to_string(),
+            interval: interval.to_string(),
+            first_trade_id: 0,
+            last_trade_id: 0,
+            open_price: self.open_price,
+            close_price: self.close_price,
+            high_price: self.high_price,
+            low_price: self.low_price,
+            base_asset_volume: self.base_asset_volume,
+            number_of_trades: self.number_of_trades,
+            is_kline_closed: self.close_time < now_ms,
+            quote_asset_volume: self.quote_asset_volume,
+            taker_buy_base_asset_volume: self.taker_buy_base_asset_volume,
+            taker_buy_quote_asset_volume: self.taker_buy_quote_asset_volume,
+            ignore: self.ignore,
+        }
+    }
+}
+
 
 
 #[cfg(test)]
@@ -108,5 +177,35 @@ mod tests {
 
 
     }
-}
 
+    #[test]
+    fn test_rest_kline_deserialization() {
+        let json_data = r#"[
+            [
+                1499040000000,
+                "0.01634790",
+                "0.80000000",
+                "0.01575800",
+                "0.01577100",
+                "148976.11427815",
+                1499644799999,
+                "2434.19055334",
+                308,
+                "1756.87402397",
+                "28.46694368",
+                "0"
+            ]
+        ]"#;
+        let parsed: Vec<RestKline> = serde_json::from_str(json_data).unwrap();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].open_time, 1499040000000);
+        assert_eq!(parsed[0].high_price, 0.8);
+        assert_eq!(parsed[0].number_of_trades, 308);
+
+        let kline = parsed[0].clone().into_kline("BNBBTC", KlineInterval::OneWeek, 1499644800000);
+        assert_eq!(kline.symbol, "BNBBTC");
+        assert_eq!(kline.interval, "1w");
+        assert_eq!(kline.close_price, 0.015771);
+        assert!(kline.is_kline_closed);
+    }
+}
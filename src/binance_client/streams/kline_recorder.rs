@@ -0,0 +1,375 @@
+use std::collections::HashMap;
+use std::io::{Error as IOError, ErrorKind};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use futures::{pin_mut, StreamExt};
+use log::{info, trace, warn};
+use tokio::net::TcpStream;
+use tokio_websockets::{MaybeTlsStream, WebSocketStream};
+use crate::binance_client::binance_client::BinanceClient;
+use crate::binance_client::database_client::{DatabaseClient, KlineGap, UpsertStats};
+use crate::binance_client::kline_interval::KlineInterval;
+use crate::binance_client::streams::binance_stream::BinanceStreamTypes;
+use crate::binance_client::streams::binance_websocket::BinanceWebSocket;
+use crate::binance_client::streams::kline_data::{Kline, KlineMessage};
+
+// Most klines `/v3/klines` returns per request
+const MAX_KLINES_PER_REQUEST: u16 = 1000;
+
+// A session up this long was healthy, the next reconnect starts from the initial delay
+const HEALTHY_SESSION: Duration = Duration::from_secs(60);
+
+/// What the recorder subscribes to and how it reconnects.
+#[derive(Debug, Clone)]
+pub struct KlineRecorderConfig {
+    pub streams: Vec<(String, KlineInterval)>, // Upper case symbol and interval
+    // Also store klines that are still open, overwriting them on every update
+    pub record_open_klines: bool,
+    // Backfill from here when a stream has no klines stored yet, or fewer than that
+    pub backfill_from: Option<u64>,
+    pub request_delay: Duration, // Pause between backfill requests
+    pub reconnect_delay: Duration,
+    pub max_reconnect_delay: Duration,
+}
+
+impl KlineRecorderConfig {
+    pub fn new(streams: &[(&str, KlineInterval)]) -> Self {
+        KlineRecorderConfig {
+            streams: streams.iter().map(|(symbol, interval)| (symbol.to_uppercase(), *interval)).collect(),
+            record_open_klines: false,
+            backfill_from: None,
+            request_delay: Duration::from_millis(250),
+            reconnect_delay: Duration::from_secs(1),
+            max_reconnect_delay: Duration::from_secs(60),
+        }
+    }
+
+    pub fn with_open_klines(mut self) -> Self {
+        self.record_open_klines = true;
+        self
+    }
+
+    pub fn with_backfill_from(mut self, backfill_from: u64) -> Self {
+        self.backfill_from = Some(backfill_from);
+        self
+    }
+}
+
+/// Outcome of backfilling one stream.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackfillReport {
+    pub symbol: String,
+    pub interval: KlineInterval,
+    pub gaps_found: usize,
+    pub klines_inserted: u64,
+    // Klines stored while open and fetched again once closed
+    pub klines_refreshed: u64,
+    // Gaps Binance has no klines for either, such as exchange downtime
+    pub unfilled: Vec<KlineGap>,
+}
+
+/// Records kline streams into `kline_data` and keeps the table free of gaps.
+///
+/// Every (re)connect first subscribes, then backfills from `/v3/klines` whatever is
+/// missing up to now while the stream is already being recorded, so nothing falls
+/// between the backfill and the first streamed kline. The first backfill of a stream
+/// checks all its stored klines; later ones only check what was added since. Stored
+/// klines that were still open are fetched again once they have closed.
+#[derive(Debug)]
+pub struct KlineRecorder<'a> {
+    binance_client: &'a BinanceClient,
+    db: DatabaseClient,
+    config: KlineRecorderConfig,
+    // Per stream, the open time up to which stored klines were checked against the exchange
+    verified_until: Mutex<HashMap<(String, KlineInterval), u64>>,
+}
+
+impl<'a> KlineRecorder<'a> {
+    pub fn new(binance_client: &'a BinanceClient, db: DatabaseClient, config: KlineRecorderConfig) -> Self {
+        KlineRecorder {
+            binance_client,
+            db,
+            config,
+            verified_until: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Records until the future is dropped, reconnecting with exponential backoff that
+    /// starts over once a session stayed up for a minute.
+    ///
+    /// Only returns on configuration errors; connection, REST and database failures are
+    /// logged and end the session, and the next session backfills what was missed.
+    pub async fn run(&self) -> Result<(), IOError> {
+        if self.config.streams.is_empty() {
+            return Err(IOError::new(ErrorKind::InvalidInput, "Kline recorder has no streams configured"));
+        }
+
+        let mut delay = self.config.reconnect_delay;
+        loop {
+            let started = Instant::now();
+            let result = self.record_session().await;
+            if started.elapsed() >= HEALTHY_SESSION {
+                delay = self.config.reconnect_delay;
+            }
+            match result {
+                Ok(()) => {
+                    info!("Kline stream closed, reconnecting");
+                    delay = self.config.reconnect_delay;
+                }
+                Err(e) => warn!("Kline recorder session failed, reconnecting in {:?}: {}", delay, e),
+            }
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(self.config.max_reconnect_delay);
+        }
+    }
+
+    /// Backfills every configured stream up to now.
+    pub async fn backfill(&self) -> Result<Vec<BackfillReport>, IOError> {
+        let mut reports = Vec::with_capacity(self.config.streams.len());
+        for (symbol, interval) in &self.config.streams {
+            reports.push(self.backfill_stream(symbol, *interval).await?);
+        }
+        Ok(reports)
+    }
+
+    /// Finds the klines of one stream missing up to now and fetches them from REST.
+    pub async fn backfill_stream(&self, symbol: &str, interval: KlineInterval) -> Result<BackfillReport, IOError> {
+        let key = (symbol.to_string(), interval);
+        let since = self.verified_until.lock().unwrap().get(&key).copied();
+        // The range before the first stored kline only needs checking once
+        let backfill_from = if since.is_none() { self.config.backfill_from } else { None };
+
+        let now = BinanceClient::generate_timestamp()?;
+        let missing = self.missing_klines(symbol, interval, since.unwrap_or(0), backfill_from, now).await?;
+
+        let mut klines_inserted = 0;
+        for gap in &missing {
+            trace!("Backfilling {} {} klines from {} to {}", symbol, interval, gap.start_time, gap.end_time);
+            klines_inserted += self.fill_gap(symbol, interval, gap).await?.inserted;
+        }
+
+        // Open klines stored before a disconnect never get their closing update from the stream
+        let mut klines_refreshed = 0;
+        for open_time in self.db.stale_open_klines(symbol, interval, now).await? {
+            let kline = KlineGap { start_time: open_time, end_time: interval.next_open_time(open_time) };
+            klines_refreshed += self.fill_gap(symbol, interval, &kline).await?.updated;
+        }
+
+        let unfilled = if missing.is_empty() {
+            Vec::new()
+        } else {
+            self.missing_klines(symbol, interval, since.unwrap_or(0), backfill_from, now).await?
+        };
+        for gap in &unfilled {
+            warn!("Binance has no {} {} klines from {} to {}", symbol, interval, gap.start_time, gap.end_time);
+        }
+
+        if let Some((_, last)) = self.db.kline_time_range(symbol, interval).await? {
+            self.verified_until.lock().unwrap().insert(key, last);
+        }
+
+        let report = BackfillReport {
+            symbol: symbol.to_string(),
+            interval,
+            gaps_found: missing.len(),
+            klines_inserted,
+            klines_refreshed,
+            unfilled,
+        };
+        info!(
+            "Backfilled {} {}: {} gaps, {} klines inserted, {} refreshed",
+            symbol, interval, report.gaps_found, report.klines_inserted, report.klines_refreshed,
+        );
+        Ok(report)
+    }
+
+    // One websocket connection: records the streams while backfilling, until the connection ends
+    async fn record_session(&self) -> Result<(), IOError> {
+        let streams: Vec<BinanceStreamTypes> = self.config.streams
+            .iter()
+            .map(|(symbol, interval)| BinanceStreamTypes::Kline(symbol.to_lowercase(), *interval))
+            .collect();
+        let mut websocket = BinanceWebSocket::new(self.binance_client).connect(&streams).await?;
+
+        let listen = self.listen(&mut websocket);
+        pin_mut!(listen);
+        tokio::select! {
+            // The connection ended before the backfill finished; the next session retries it
+            result = &mut listen => return result,
+            reports = self.backfill() => {
+                reports?;
+            }
+        }
+        listen.await
+    }
+
+    async fn listen(&self, websocket: &mut WebSocketStream<MaybeTlsStream<TcpStream>>) -> Result<(), IOError> {
+        while let Some(message) = websocket.next().await {
+            let message = message.map_err(|e| IOError::new(ErrorKind::Other, format!("Kline stream error: {}", e)))?;
+            let Some(text) = message.as_text() else { continue };
+
+            match serde_json::from_str::<KlineMessage>(text) {
+                Ok(kline_message) if self.should_record(&kline_message.data.k) => {
+                    // A failed write ends the session, so the next backfill repairs it
+                    self.db.insert_kline_data(&kline_message).await?;
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Failed to parse kline message: {}\n {}", e, text),
+            }
+        }
+        Ok(())
+    }
+
+    fn should_record(&self, kline: &Kline) -> bool {
+        kline.is_kline_closed || self.config.record_open_klines
+    }
+
+    async fn missing_klines(
+        &self,
+        symbol: &str,
+        interval: KlineInterval,
+        since: u64,
+        backfill_from: Option<u64>,
+        now: u64,
+    ) -> Result<Vec<KlineGap>, IOError> {
+        let stored = self.db.kline_time_range(symbol, interval).await?;
+        let gaps = self.db.kline_gaps(symbol, interval, since, now).await?;
+        Ok(missing_klines(interval, stored, gaps, backfill_from, now))
+    }
+
+    // Fetches the klines of a gap page by page, returning how many were new or updated
+    async fn fill_gap(&self, symbol: &str, interval: KlineInterval, gap: &KlineGap) -> Result<UpsertStats, IOError> {
+        let mut written = UpsertStats::default();
+        let mut start_time = gap.start_time;
+        while start_time < gap.end_time {
+            let rest_klines = self.binance_client
+                .fetch_klines(symbol, interval, Some(start_time), Some(gap.end_time - 1), Some(MAX_KLINES_PER_REQUEST))
+                .await?;
+            let Some(last) = rest_klines.last() else { break };
+            start_time = interval.next_open_time(last.open_time);
+            let full_page = rest_klines.len() == MAX_KLINES_PER_REQUEST as usize;
+
+            let now = BinanceClient::generate_timestamp()?;
+            let klines: Vec<Kline> = rest_klines
+                .into_iter()
+                .map(|kline| kline.into_kline(symbol, interval, now))
+                .filter(|kline| self.should_record(kline))
+                .collect();
+            let stats = self.db.bulk_upsert_klines(&klines).await?;
+            written.inserted += stats.inserted;
+            written.updated += stats.updated;
+
+            if !full_page {
+                break;
+            }
+            tokio::time::sleep(self.config.request_delay).await;
+        }
+        Ok(written)
+    }
+}
+
+/// Every run of klines that should be stored by `now` but is not.
+///
+/// `stored` is the open time of the first and last stored kline and `gaps` the holes
+/// between them. Adds the klines closed since the last stored one and, with
+/// `backfill_from`, those opened from then until the first stored one.
+pub fn missing_klines(
+    interval: KlineInterval,
+    stored: Option<(u64, u64)>,
+    gaps: Vec<KlineGap>,
+    backfill_from: Option<u64>,
+    now: u64,
+) -> Vec<KlineGap> {
+    let Some((first, last)) = stored else {
+        return match backfill_from {
+            Some(from) if from < now => vec![KlineGap { start_time: from, end_time: now }],
+            _ => Vec::new(),
+        };
+    };
+
+    let mut missing = Vec::with_capacity(gaps.len() + 2);
+    if let Some(from) = backfill_from {
+        // Some kline opens between `from` and the first stored one
+        if interval.next_open_time(from) <= first {
+            missing.push(KlineGap { start_time: from, end_time: first });
+        }
+    }
+    missing.extend(gaps);
+
+    // The kline after the last stored one has closed
+    let next = interval.next_open_time(last);
+    if interval.next_open_time(next) <= now {
+        missing.push(KlineGap { start_time: next, end_time: now });
+    }
+    missing
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binance_client::load_env::EnvVars;
+
+    const MINUTE: u64 = 60_000;
+
+    #[test]
+    fn test_missing_klines_of_empty_stream() {
+        let interval = KlineInterval::OneMinute;
+        assert!(missing_klines(interval, None, Vec::new(), None, 10 * MINUTE).is_empty());
+        assert_eq!(
+            missing_klines(interval, None, Vec::new(), Some(2 * MINUTE), 10 * MINUTE),
+            vec![KlineGap { start_time: 2 * MINUTE, end_time: 10 * MINUTE }],
+        );
+    }
+
+    #[test]
+    fn test_missing_klines_around_stored_range() {
+        let interval = KlineInterval::OneMinute;
+        let hole = KlineGap { start_time: 5 * MINUTE, end_time: 7 * MINUTE };
+
+        // The kline after the last stored one is still open, so nothing is missing at the end
+        let missing = missing_klines(interval, Some((2 * MINUTE, 9 * MINUTE)), vec![hole], None, 10 * MINUTE + 30_000);
+        assert_eq!(missing, vec![hole]);
+
+        let missing = missing_klines(interval, Some((2 * MINUTE, 9 * MINUTE)), vec![hole], Some(0), 12 * MINUTE + 30_000);
+        assert_eq!(missing, vec![
+            KlineGap { start_time: 0, end_time: 2 * MINUTE },
+            hole,
+            KlineGap { start_time: 10 * MINUTE, end_time: 12 * MINUTE + 30_000 },
+        ]);
+
+        // No kline opens between an unaligned start and the first stored kline
+        assert!(missing_klines(interval, Some((2 * MINUTE, 9 * MINUTE)), Vec::new(), Some(MINUTE + 1), 10 * MINUTE).is_empty());
+    }
+
+    #[test]
+    fn test_config_normalizes_symbols() {
+        let config = KlineRecorderConfig::new(&[("btcusdt", KlineInterval::OneMinute)]).with_backfill_from(0);
+        assert_eq!(config.streams, vec![("BTCUSDT".to_string(), KlineInterval::OneMinute)]);
+        assert!(!config.record_open_klines);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs the Binance API and Postgres"]
+    async fn test_backfill_leaves_no_gaps() {
+        let vars = EnvVars::new();
+        let binance_client = BinanceClient::new(vars.api_key.to_string(), vars.api_secret.to_string(), false).await;
+        let db = DatabaseClient::connect_or_create_if_not_exist(&vars.database_config()).await.expect("Failed to setup database");
+
+        let now = BinanceClient::generate_timestamp().unwrap();
+        let config = KlineRecorderConfig::new(&[("BTCUSDT", KlineInterval::OneMinute)]).with_backfill_from(now - 90 * MINUTE);
+        let recorder = KlineRecorder::new(&binance_client, db.clone(), config);
+
+        let report = recorder.backfill_stream("BTCUSDT", KlineInterval::OneMinute).await.expect("Backfill failed");
+        assert!(report.klines_inserted >= 88, "{:?}", report);
+        assert!(db.kline_gaps("BTCUSDT", KlineInterval::OneMinute, 0, now).await.unwrap().is_empty());
+
+        // Nothing left to do the second time
+        let report = recorder.backfill_stream("BTCUSDT", KlineInterval::OneMinute).await.expect("Backfill failed");
+        assert_eq!(report.klines_inserted, 0);
+
+        db.close().await;
+        drop(recorder);
+        DatabaseClient::drop_database_if_exists(&vars.database_config()).await.expect("Failed to drop database");
+    }
+}
@@ -1,9 +1,16 @@
 pub mod binance_stream;
 pub mod kline_data;
-mod binance_websocket;
-mod depth_stream;
-mod trade_stream;
-mod ticker_stream;
-mod mini_ticker_stream;
-mod book_ticker_stream;
-mod all_market_mini_ticker_streams;
\ No newline at end of file
+pub mod kline_recorder;
+pub mod binance_websocket;
+pub mod depth_stream;
+pub mod partial_depth_stream;
+pub mod trade_stream;
+pub mod agg_trade_stream;
+pub mod ticker_stream;
+pub mod rolling_window_ticker_stream;
+pub mod mini_ticker_stream;
+pub mod book_ticker_stream;
+pub mod avg_price_stream;
+pub mod all_market_mini_ticker_streams;
+pub mod all_market_ticker_streams;
+pub mod user_data;
@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+use crate::binance_client::streams::depth_stream::{Ask, Bid};
+
+/// Message for the partial book depth streams (`<symbol>@depth<levels>` and
+/// `<symbol>@depth<levels>@100ms`).
+///
+/// Unlike the diff depth stream, each message is a snapshot of the top levels of the book.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PartialDepthMessage {
+    pub stream: String,
+    pub data: PartialDepthData,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartialDepthData {
+    pub last_update_id: u64, // Last update ID
+    pub bids: Vec<Bid>, // Top bids, best first
+    pub asks: Vec<Ask>, // Top asks, best first
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_depth_message_deserialization() {
+        let json_str = r#"{
+            "stream": "bnbbtc@depth5@100ms",
+            "data": {
+                "lastUpdateId": 160,
+                "bids": [["0.0024", "10"], ["0.0023", "5"]],
+                "asks": [["0.0026", "100"]]
+            }
+        }"#;
+
+        let message: PartialDepthMessage = serde_json::from_str(json_str).expect("Failed to deserialize PartialDepthMessage");
+        assert_eq!(message.stream, "bnbbtc@depth5@100ms");
+        assert_eq!(message.data.last_update_id, 160);
+        assert_eq!(message.data.bids.len(), 2);
+        assert_eq!(message.data.bids[0].price, "0.0024");
+        assert_eq!(message.data.asks[0].quantity, "100");
+    }
+}
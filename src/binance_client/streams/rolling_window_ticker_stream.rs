@@ -0,0 +1,97 @@
+use serde::{Deserialize, Serialize};
+
+/// Message for `<symbol>@ticker_<window>` rolling window statistics.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RollingWindowTickerMessage {
+    pub stream: String,
+    pub data: RollingWindowTickerData,
+}
+
+/// Message for `!ticker_<window>@arr`, only containing the symbols that changed.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AllMarketRollingWindowTickersMessage {
+    pub stream: String,
+    pub data: Vec<RollingWindowTickerData>,
+}
+
+/// Rolling window statistics. The open time always starts on a minute, so the effective
+/// window can be up to 59999ms wider than the requested window size.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RollingWindowTickerData {
+    #[serde(rename = "e")]
+    pub event_type: String,  // Event type, e.g. "1hTicker"
+    #[serde(rename = "E")]
+    pub event_time: u64,  // Event time
+    #[serde(rename = "s")]
+    pub symbol: String,  // Symbol
+    #[serde(rename = "p")]
+    pub price_change: String,  // Price change
+    #[serde(rename = "P")]
+    pub price_change_percent: String,  // Price change percent
+    #[serde(rename = "o")]
+    pub open_price: String,  // Open price
+    #[serde(rename = "h")]
+    pub high_price: String,  // High price
+    #[serde(rename = "l")]
+    pub low_price: String,  // Low price
+    #[serde(rename = "c")]
+    pub last_price: String,  // Last price
+    #[serde(rename = "w")]
+    pub weighted_avg_price: String,  // Weighted average price
+    #[serde(rename = "v")]
+    pub total_traded_base_asset_volume: String,  // Total traded base asset volume
+    #[serde(rename = "q")]
+    pub total_traded_quote_asset_volume: String,  // Total traded quote asset volume
+    #[serde(rename = "O")]
+    pub statistics_open_time: u64,  // Statistics open time
+    #[serde(rename = "C")]
+    pub statistics_close_time: u64,  // Statistics close time
+    #[serde(rename = "F")]
+    pub first_trade_id: i64,  // First trade ID
+    #[serde(rename = "L")]
+    pub last_trade_id: i64,  // Last trade ID
+    #[serde(rename = "n")]
+    pub total_number_of_trades: u64,  // Total number of trades
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICKER: &str = r#"{
+        "e": "1hTicker",
+        "E": 1672515782136,
+        "s": "BNBBTC",
+        "p": "0.0015",
+        "P": "250.00",
+        "o": "0.0010",
+        "h": "0.0025",
+        "l": "0.0010",
+        "c": "0.0025",
+        "w": "0.0018",
+        "v": "10000",
+        "q": "18",
+        "O": 0,
+        "C": 1675216573749,
+        "F": 0,
+        "L": 18150,
+        "n": 18151
+    }"#;
+
+    #[test]
+    fn rolling_window_ticker_deserialization() {
+        let json_str = format!(r#"{{"stream": "bnbbtc@ticker_1h", "data": {}}}"#, TICKER);
+        let message: RollingWindowTickerMessage = serde_json::from_str(&json_str).unwrap();
+        assert_eq!(message.data.event_type, "1hTicker");
+        assert_eq!(message.data.total_number_of_trades, 18151);
+    }
+
+    #[test]
+    fn all_market_rolling_window_tickers_deserialization() {
+        let json_str = format!(r#"{{"stream": "!ticker_1h@arr", "data": [{}, {}]}}"#, TICKER, TICKER);
+        let message: AllMarketRollingWindowTickersMessage = serde_json::from_str(&json_str).unwrap();
+        assert_eq!(message.stream, "!ticker_1h@arr");
+        assert_eq!(message.data.len(), 2);
+    }
+}
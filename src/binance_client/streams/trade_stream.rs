@@ -1,34 +1,34 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TradeMessage {
-    stream: String,
-    data: TradeData,
+    pub stream: String,
+    pub data: TradeData,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TradeData {
     #[serde(rename = "e")]
-    event_type: String, // Event type
+    pub event_type: String, // Event type
     #[serde(rename = "E")]
-    event_time: u64, // Event time
+    pub event_time: u64, // Event time
     #[serde(rename = "s")]
-    symbol: String, // Symbol
+    pub symbol: String, // Symbol
     #[serde(rename = "t")]
-    trade_id: u64, // Trade ID
+    pub trade_id: u64, // Trade ID
     #[serde(rename = "p")]
-    price: String, // Price
+    pub price: String, // Price
     #[serde(rename = "q")]
-    quantity: String, // Quantity
+    pub quantity: String, // Quantity
     #[serde(rename = "b")]
-    buyer_order_id: u64, // Buyer's order ID
+    pub buyer_order_id: u64, // Buyer's order ID
     #[serde(rename = "a")]
-    seller_order_id: u64, // Seller's order ID
+    pub seller_order_id: u64, // Seller's order ID
     #[serde(rename = "T")]
-    trade_time: u64, // Trade time
+    pub trade_time: u64, // Trade time
     #[serde(rename = "m")]
-    is_market_maker: bool, // Is the buyer the market maker?
+    pub is_market_maker: bool, // Is the buyer the market maker?
     #[serde(rename = "M")]
-    ignore: bool, // Placeholder (ignore)
+    pub ignore: bool, // Placeholder (ignore)
 }
 
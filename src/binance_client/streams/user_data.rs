@@ -0,0 +1,194 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_with::{serde_as, DisplayFromStr};
+use crate::binance_client::account::order_status::OrderStatus;
+use crate::binance_client::order_types::order_type::OrderType;
+use crate::binance_client::order_types::side::Side;
+
+/// What happened to the order in an `executionReport`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ExecutionType {
+    New,
+    Canceled,
+    Replaced,
+    Rejected,
+    Trade,
+    Expired,
+    TradePrevention,
+}
+
+/// An order update from the user data stream.
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionReport {
+    #[serde(rename = "e")]
+    pub event_type: String, // Event type
+    #[serde(rename = "E")]
+    pub event_time: u64, // Event time
+    #[serde(rename = "s")]
+    pub symbol: String, // Symbol
+    #[serde(rename = "c")]
+    pub client_order_id: String, // Client order ID
+    #[serde(rename = "S")]
+    pub side: Side, // Side
+    #[serde(rename = "o")]
+    pub order_type: OrderType, // Order type
+    #[serde(rename = "f")]
+    pub time_in_force: String, // Time in force
+    #[serde(rename = "q")]
+    #[serde_as(as = "DisplayFromStr")]
+    pub quantity: f64, // Order quantity
+    #[serde(rename = "p")]
+    #[serde_as(as = "DisplayFromStr")]
+    pub price: f64, // Order price
+    #[serde(rename = "P")]
+    #[serde_as(as = "DisplayFromStr")]
+    pub stop_price: f64, // Stop price
+    #[serde(rename = "g")]
+    pub order_list_id: i64, // OrderListId, -1 unless part of a list
+    #[serde(rename = "C")]
+    pub orig_client_order_id: String, // Client order ID of the order being canceled
+    #[serde(rename = "x")]
+    pub execution_type: ExecutionType, // Current execution type
+    #[serde(rename = "X")]
+    pub order_status: OrderStatus, // Current order status
+    #[serde(rename = "r")]
+    pub reject_reason: String, // Order reject reason
+    #[serde(rename = "i")]
+    pub order_id: i64, // Order ID
+    #[serde(rename = "l")]
+    #[serde_as(as = "DisplayFromStr")]
+    pub last_executed_qty: f64, // Last executed quantity
+    #[serde(rename = "z")]
+    #[serde_as(as = "DisplayFromStr")]
+    pub cumulative_filled_qty: f64, // Cumulative filled quantity
+    #[serde(rename = "L")]
+    #[serde_as(as = "DisplayFromStr")]
+    pub last_executed_price: f64, // Last executed price
+    #[serde(rename = "n")]
+    #[serde_as(as = "DisplayFromStr")]
+    pub commission: f64, // Commission amount
+    #[serde(rename = "N")]
+    pub commission_asset: Option<String>, // Commission asset, null until a trade happens
+    #[serde(rename = "T")]
+    pub transaction_time: u64, // Transaction time
+    #[serde(rename = "t")]
+    pub trade_id: i64, // Trade ID, -1 unless this is a trade
+    #[serde(rename = "w")]
+    pub is_on_book: bool, // Is the order on the book?
+    #[serde(rename = "m")]
+    pub is_maker: bool, // Is this trade the maker side?
+    #[serde(rename = "O")]
+    pub order_creation_time: u64, // Order creation time
+    #[serde(rename = "Z")]
+    #[serde_as(as = "DisplayFromStr")]
+    pub cumulative_quote_qty: f64, // Cumulative quote asset transacted quantity
+    #[serde(rename = "Y")]
+    #[serde_as(as = "DisplayFromStr")]
+    pub last_quote_qty: f64, // Last quote asset transacted quantity
+}
+
+
+/// Status of an order list such as an OCO, sent alongside the execution reports of its orders.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListStatus {
+    #[serde(rename = "e")]
+    pub event_type: String, // Event type
+    #[serde(rename = "E")]
+    pub event_time: u64, // Event time
+    #[serde(rename = "s")]
+    pub symbol: String, // Symbol
+    #[serde(rename = "g")]
+    pub order_list_id: i64, // OrderListId
+    #[serde(rename = "c")]
+    pub contingency_type: String, // Contingency type
+    #[serde(rename = "l")]
+    pub list_status_type: String, // List status type
+    #[serde(rename = "L")]
+    pub list_order_status: String, // List order status
+    #[serde(rename = "r")]
+    pub list_reject_reason: String, // List reject reason
+    #[serde(rename = "C")]
+    pub list_client_order_id: String, // List client order ID
+    #[serde(rename = "T")]
+    pub transaction_time: u64, // Transaction time
+    #[serde(rename = "O")]
+    pub orders: Vec<ListStatusOrder>, // Orders of the list
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListStatusOrder {
+    #[serde(rename = "s")]
+    pub symbol: String, // Symbol
+    #[serde(rename = "i")]
+    pub order_id: i64, // Order ID
+    #[serde(rename = "c")]
+    pub client_order_id: String, // Client order ID
+}
+
+/// An event of the user data stream.
+#[derive(Debug, Clone)]
+pub enum UserDataEvent {
+    ExecutionReport(ExecutionReport),
+    ListStatus(ListStatus),
+    // Balance and account updates are passed through untyped
+    Other(Value),
+}
+
+impl UserDataEvent {
+    /// Parses a user data stream message, dispatching on its `e` field.
+    pub fn parse(text: &str) -> Result<Self, serde_json::Error> {
+        let value: Value = serde_json::from_str(text)?;
+        match value["e"].as_str() {
+            Some("executionReport") => serde_json::from_value(value).map(UserDataEvent::ExecutionReport),
+            Some("listStatus") => serde_json::from_value(value).map(UserDataEvent::ListStatus),
+            _ => Ok(UserDataEvent::Other(value)),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_execution_report_deserialization() {
+        let json_data = r#"{
+            "e": "executionReport", "E": 1499405658658, "s": "ETHBTC", "c": "mUvoqJxFIILMdfAW5iGSOW",
+            "S": "BUY", "o": "LIMIT", "f": "GTC", "q": "1.00000000", "p": "0.10264410", "P": "0.00000000",
+            "F": "0.00000000", "g": -1, "C": "", "x": "TRADE", "X": "PARTIALLY_FILLED", "r": "NONE",
+            "i": 4293153, "l": "0.40000000", "z": "0.40000000", "L": "0.10264410", "n": "0.00040000",
+            "N": "ETH", "T": 1499405658657, "t": 77, "I": 8641984, "w": true, "m": true, "M": false,
+            "O": 1499405658657, "Z": "0.04105764", "Y": "0.04105764", "Q": "0.00000000",
+            "W": 1499405658657, "V": "NONE"
+        }"#;
+        let report: ExecutionReport = serde_json::from_str(json_data).unwrap();
+        assert_eq!(report.execution_type, ExecutionType::Trade);
+        assert_eq!(report.order_status, OrderStatus::PartiallyFilled);
+        assert_eq!(report.side, Side::Buy);
+        assert_eq!(report.last_executed_qty, 0.4);
+        assert_eq!(report.commission_asset.as_deref(), Some("ETH"));
+        assert_eq!(report.trade_id, 77);
+    }
+
+    #[test]
+    fn test_user_data_event_dispatch() {
+        let list_status = r#"{
+            "e": "listStatus", "E": 1564035303637, "s": "ETHBTC", "g": 2, "c": "OCO", "l": "EXEC_STARTED",
+            "L": "EXECUTING", "r": "NONE", "C": "F4QN4G8DlFATFlIUQ0cjdD", "T": 1564035303625,
+            "O": [{"s": "ETHBTC", "i": 17, "c": "AJYsMjErWJesZvqlJCTUgL"}, {"s": "ETHBTC", "i": 18, "c": "bfYPSQdLoqAJeNrOr9adzq"}]
+        }"#;
+        match UserDataEvent::parse(list_status).unwrap() {
+            UserDataEvent::ListStatus(status) => {
+                assert_eq!(status.order_list_id, 2);
+                assert_eq!(status.orders[1].order_id, 18);
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        let balance = r#"{"e": "balanceUpdate", "E": 1573200697110, "a": "BTC", "d": "100.00000000", "T": 1573200697068}"#;
+        assert!(matches!(UserDataEvent::parse(balance).unwrap(), UserDataEvent::Other(_)));
+    }
+}
@@ -0,0 +1,71 @@
+//! Fixtures shared by the unit tests of the trading modules.
+
+use std::io::Error as IOError;
+use async_trait::async_trait;
+use tokio::sync::mpsc::UnboundedReceiver;
+use crate::binance_client::backtest::broker::SimMarket;
+use crate::binance_client::exchange_info::SymbolInfo;
+use crate::binance_client::fee_model::FeeModel;
+use crate::binance_client::market_data_store::{BookSnapshot, TradePrint};
+use crate::binance_client::paper_trader::PaperTrader;
+use crate::binance_client::streams::user_data::{ExecutionReport, UserDataEvent};
+
+// ETHUSDT with the price, lot size and notional filters of the live exchange
+pub(crate) fn eth_usdt() -> SymbolInfo {
+    serde_json::from_str(r#"{
+        "symbol": "ETHUSDT", "status": "TRADING", "baseAsset": "ETH", "quoteAsset": "USDT", "permissions": ["SPOT"],
+        "filters": [
+            {"filterType": "PRICE_FILTER", "minPrice": "0.01000000", "maxPrice": "1000000.00000000", "tickSize": "0.01000000"},
+            {"filterType": "LOT_SIZE", "minQty": "0.00010000", "maxQty": "9000.00000000", "stepSize": "0.00010000"},
+            {"filterType": "NOTIONAL", "minNotional": "5.00000000", "applyMinToMarket": true, "maxNotional": "9000000.00000000", "applyMaxToMarket": false, "avgPriceMins": 5}
+        ]
+    }"#).unwrap()
+}
+
+/// An ETHUSDT paper trader without fees holding 100000 USDT, quoting `bid` and `ask`.
+pub(crate) fn paper_trader(bid: f64, ask: f64) -> PaperTrader {
+    let paper_trader = PaperTrader::new(vec![SimMarket::new("ETHUSDT", FeeModel::flat(0.0, 0.0, "ETH", "USDT"))]);
+    paper_trader.deposit("USDT", 100000.0);
+    set_book(&paper_trader, bid, ask);
+    paper_trader
+}
+
+pub(crate) fn set_book(paper_trader: &PaperTrader, bid: f64, ask: f64) {
+    paper_trader.on_book(&BookSnapshot {
+        symbol: "ETHUSDT".to_string(),
+        snapshot_time: 1,
+        last_update_id: 1,
+        bid_prices: vec![bid],
+        bid_quantities: vec![100.0],
+        ask_prices: vec![ask],
+        ask_quantities: vec![100.0],
+    });
+}
+
+pub(crate) fn trade(paper_trader: &PaperTrader, price: f64, quantity: f64, is_buyer_maker: bool) {
+    paper_trader.on_trade(&TradePrint {
+        symbol: "ETHUSDT".to_string(),
+        trade_id: 1,
+        price,
+        quantity,
+        buyer_order_id: -1,
+        seller_order_id: -1,
+        trade_time: 2,
+        is_buyer_maker,
+    });
+}
+
+// What follows the user data stream of an order api
+#[async_trait]
+pub(crate) trait ReportHandler {
+    async fn handle(&mut self, report: &ExecutionReport) -> Result<(), IOError>;
+}
+
+/// Passes the execution reports published so far to `handler`.
+pub(crate) async fn forward(handler: &mut (impl ReportHandler + Send), events: &mut UnboundedReceiver<UserDataEvent>) {
+    while let Ok(event) = events.try_recv() {
+        if let UserDataEvent::ExecutionReport(report) = event {
+            handler.handle(&report).await.unwrap();
+        }
+    }
+}
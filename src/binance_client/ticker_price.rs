@@ -1,9 +1,28 @@
 use serde::Deserialize;
 use crate::binance_client::deserialization::deserialize_string_to_f64;
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct TickerPrice {
     pub symbol: String,
     #[serde(deserialize_with = "deserialize_string_to_f64")]
     pub price: f64,
-}
\ No newline at end of file
+}
+/// Rolling 24 hour statistics of a symbol from `/v3/ticker/24hr`, of which only the
+/// prices and volumes are kept.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TickerStatistics {
+    pub symbol: String,
+    #[serde(deserialize_with = "deserialize_string_to_f64")]
+    pub weighted_avg_price: f64,
+    #[serde(deserialize_with = "deserialize_string_to_f64")]
+    pub last_price: f64,
+    #[serde(deserialize_with = "deserialize_string_to_f64")]
+    pub high_price: f64,
+    #[serde(deserialize_with = "deserialize_string_to_f64")]
+    pub low_price: f64,
+    #[serde(deserialize_with = "deserialize_string_to_f64")]
+    pub volume: f64,
+    #[serde(deserialize_with = "deserialize_string_to_f64")]
+    pub quote_volume: f64,
+}
@@ -0,0 +1,561 @@
+use std::collections::HashMap;
+use std::io::{Error as IOError, ErrorKind};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use async_trait::async_trait;
+use async_tungstenite::tungstenite::http::Uri;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ed25519_dalek::pkcs8::DecodePrivateKey;
+use ed25519_dalek::{Signer, SigningKey};
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
+use log::{error, trace, warn};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio_websockets::{ClientBuilder, Message};
+use uuid::Uuid;
+use crate::binance_client::account::account_info::AccountInfoClient;
+use crate::binance_client::account::open_order::OpenOrder;
+use crate::binance_client::binance_client::BinanceClient;
+use crate::binance_client::binance_error::BinanceError;
+use crate::binance_client::order_api::OrderApi;
+use crate::binance_client::order_response::{CancelReplaceResponse, OrderListResponse, OrderResponse};
+use crate::binance_client::order_types::cancel_order_response::CancelOrderResponse;
+use crate::binance_client::order_types::cancel_replace_order::CancelReplaceOrder;
+use crate::binance_client::order_types::limit_order::LimitOrder;
+use crate::binance_client::order_types::market_order::MarketOrder;
+use crate::binance_client::order_types::oco_order::OcoOrder;
+use crate::binance_client::order_types::stop_limit_order::StopLimitOrder;
+use crate::binance_client::sbe;
+use crate::binance_client::sbe::websocket::decode_websocket_response;
+use crate::binance_client::sbe::{SbeDecode, SBE_SCHEMA_ID, SBE_SCHEMA_VERSION};
+
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+type PendingRequests = Arc<Mutex<HashMap<String, oneshot::Sender<WsApiResponse>>>>;
+
+/// A rate limit reported back by the WebSocket API alongside every response.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WsRateLimit {
+    #[serde(alias = "rate_limit_type")]
+    pub rate_limit_type: String,
+    pub interval: String,
+    #[serde(alias = "interval_num")]
+    pub interval_num: u32,
+    pub limit: u32,
+    // Current usage against `limit`
+    #[serde(default)]
+    pub count: u32,
+}
+
+/// A single response frame from the WebSocket API.
+#[derive(Debug, Deserialize)]
+pub struct WsApiResponse {
+    // Echoes the id of the request; null when the server could not parse the request
+    pub id: Option<String>,
+    pub status: u16,
+    #[serde(default)]
+    pub result: Option<Value>,
+    #[serde(default)]
+    pub error: Option<BinanceError>,
+    #[serde(default, rename = "rateLimits", alias = "rate_limits")]
+    pub rate_limits: Vec<WsRateLimit>,
+    // Undecoded result message when the session uses SBE
+    #[serde(skip)]
+    pub sbe_result: Option<Vec<u8>>,
+}
+
+impl WsApiResponse {
+    /// Deserializes `result` into `T`, or turns an error response into an `IOError`.
+    pub fn into_result<T: DeserializeOwned>(self) -> Result<T, IOError> {
+        self.check_error()?;
+        if self.sbe_result.is_some() {
+            return Err(IOError::new(ErrorKind::InvalidData, "Received an SBE result where JSON was expected"));
+        }
+        let result = self.result.ok_or_else(|| {
+            IOError::new(ErrorKind::Other, format!("WebSocket API response with status {} has no result", self.status))
+        })?;
+        serde_json::from_value(result)
+            .map_err(|err| IOError::new(ErrorKind::Other, format!("Failed to deserialize WebSocket API result: {}", err)))
+    }
+
+    /// Decodes the SBE `result` into `T`, or turns an error response into an `IOError`.
+    pub fn into_sbe_result<T: SbeDecode>(self) -> Result<T, IOError> {
+        self.check_error()?;
+        let result = self.sbe_result.ok_or_else(|| {
+            IOError::new(ErrorKind::InvalidData, format!("WebSocket API response with status {} has no SBE result", self.status))
+        })?;
+        sbe::decode(&result)
+    }
+
+    /// Decodes the result with whichever encoding the session uses.
+    pub fn into_typed<T: DeserializeOwned + SbeDecode>(self) -> Result<T, IOError> {
+        if self.sbe_result.is_some() {
+            self.into_sbe_result()
+        } else {
+            self.into_result()
+        }
+    }
+
+    fn check_error(&self) -> Result<(), IOError> {
+        match &self.error {
+            Some(error) => Err(IOError::new(
+                ErrorKind::Other,
+                format!("WebSocket API request failed with status {}: {} - {}", self.status, error.code, error.msg),
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Result of `session.logon`, `session.status` and `session.logout`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WsSessionStatus {
+    pub api_key: Option<String>,
+    pub authorized_since: Option<u64>,
+    pub connected_since: u64,
+    pub return_rate_limits: bool,
+    #[serde(alias = "server_time")]
+    pub server_time: u64,
+}
+
+/// Client for the Binance WebSocket API (`ws-api.binance.com`).
+///
+/// Keeps one connection open and multiplexes requests over it, matching responses to
+/// callers by request id. Requests are HMAC signed with the API secret of the
+/// underlying [`BinanceClient`] unless the session has been authenticated with
+/// [`WsApiClient::session_logon`], in which case the server signs on our behalf.
+///
+/// Implements [`OrderApi`], so it can be used anywhere a [`SpotClient`](crate::binance_client::spot_orders::SpotClient) is.
+pub struct WsApiClient<'a> {
+    binance_client: &'a BinanceClient,
+    outgoing: mpsc::UnboundedSender<Message>,
+    pending: PendingRequests,
+    rate_limits: Arc<Mutex<Vec<WsRateLimit>>>,
+    session_logged_on: Mutex<bool>,
+    request_timeout: Duration,
+    connection_handle: JoinHandle<()>,
+}
+
+impl<'a> WsApiClient<'a> {
+    /// Opens a connection to `binance_client.ws_api_url`.
+    pub async fn connect(binance_client: &'a BinanceClient) -> Result<WsApiClient<'a>, IOError> {
+        Self::connect_to(binance_client, &binance_client.ws_api_url).await
+    }
+
+    /// Opens a connection whose responses are SBE encoded instead of JSON.
+    ///
+    /// Order placement and cancellation decode their SBE results; methods without an SBE
+    /// decoder return an error on such a session.
+    pub async fn connect_sbe(binance_client: &'a BinanceClient) -> Result<WsApiClient<'a>, IOError> {
+        let url = format!(
+            "{}?responseFormat=sbe&sbeSchemaId={}&sbeSchemaVersion={}",
+            binance_client.ws_api_url, SBE_SCHEMA_ID, SBE_SCHEMA_VERSION
+        );
+        Self::connect_to(binance_client, &url).await
+    }
+
+    async fn connect_to(binance_client: &'a BinanceClient, url: &str) -> Result<WsApiClient<'a>, IOError> {
+        let uri = url.parse::<Uri>()
+            .map_err(|err| IOError::new(ErrorKind::InvalidInput, format!("Invalid WebSocket API url: {}", err)))?;
+
+        let (ws_stream, _) = ClientBuilder::from_uri(uri)
+            .connect()
+            .await
+            .map_err(|err| IOError::new(ErrorKind::Other, format!("Failed to connect to WebSocket API: {}", err)))?;
+        trace!("Connected to WebSocket API at {}", url);
+
+        let (outgoing, outgoing_rx) = mpsc::unbounded_channel();
+        let pending: PendingRequests = Arc::new(Mutex::new(HashMap::new()));
+        let rate_limits = Arc::new(Mutex::new(Vec::new()));
+
+        let connection_handle = tokio::spawn(run_connection(
+            ws_stream,
+            outgoing_rx,
+            pending.clone(),
+            rate_limits.clone(),
+        ));
+
+        Ok(WsApiClient {
+            binance_client,
+            outgoing,
+            pending,
+            rate_limits,
+            session_logged_on: Mutex::new(false),
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            connection_handle,
+        })
+    }
+
+    pub fn set_request_timeout(&mut self, request_timeout: Duration) {
+        self.request_timeout = request_timeout;
+    }
+
+    /// Rate limit usage reported by the most recent response.
+    pub fn rate_limits(&self) -> Vec<WsRateLimit> {
+        self.rate_limits.lock().unwrap().clone()
+    }
+
+    /// Authenticates the connection with an Ed25519 API key.
+    ///
+    /// After a successful logon, signed requests no longer carry `apiKey` and `signature`.
+    pub async fn session_logon(&self, api_key: &str, private_key_pem: &str) -> Result<WsSessionStatus, IOError> {
+        let signing_key = SigningKey::from_pkcs8_pem(private_key_pem)
+            .map_err(|err| IOError::new(ErrorKind::InvalidInput, format!("Invalid Ed25519 private key: {}", err)))?;
+
+        let mut params = Map::new();
+        params.insert("apiKey".to_string(), Value::String(api_key.to_string()));
+        params.insert("timestamp".to_string(), json!(BinanceClient::generate_timestamp()?));
+        params.insert("signature".to_string(), Value::String(sign_ed25519(&signing_key, &signature_payload(&params))));
+
+        let status: WsSessionStatus = self.send_request("session.logon", Some(params)).await?.into_result()?;
+        *self.session_logged_on.lock().unwrap() = true;
+        Ok(status)
+    }
+
+    pub async fn session_status(&self) -> Result<WsSessionStatus, IOError> {
+        self.send_request("session.status", None).await?.into_result()
+    }
+
+    pub async fn session_logout(&self) -> Result<WsSessionStatus, IOError> {
+        let status = self.send_request("session.logout", None).await?.into_result()?;
+        *self.session_logged_on.lock().unwrap() = false;
+        Ok(status)
+    }
+
+    pub async fn account_status(&self) -> Result<AccountInfoClient, IOError> {
+        self.send_signed_request("account.status", Map::new()).await?.into_result()
+    }
+
+    /// Sends a signed request, adding `timestamp`, `apiKey` and `signature` as needed.
+    pub async fn send_signed_request(&self, method: &str, mut params: Map<String, Value>) -> Result<WsApiResponse, IOError> {
+        if !params.contains_key("timestamp") {
+            params.insert("timestamp".to_string(), json!(BinanceClient::generate_timestamp()?));
+        }
+        let logged_on = *self.session_logged_on.lock().unwrap();
+        if !logged_on {
+            params.insert("apiKey".to_string(), Value::String(self.binance_client.get_api_key().to_string()));
+            let signature = self.binance_client.sign(&signature_payload(&params));
+            params.insert("signature".to_string(), Value::String(signature));
+        }
+        self.send_request(method, Some(params)).await
+    }
+
+    /// Sends a request and waits for the response with the matching id.
+    pub async fn send_request(&self, method: &str, params: Option<Map<String, Value>>) -> Result<WsApiResponse, IOError> {
+        let id = Uuid::new_v4().to_string();
+        let mut request = json!({"id": id, "method": method});
+        if let Some(params) = params {
+            request["params"] = Value::Object(params);
+        }
+
+        let (response_tx, response_rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id.clone(), response_tx);
+        trace!("WebSocket API request: {}", request);
+
+        if self.outgoing.send(Message::text(request.to_string())).is_err() {
+            self.pending.lock().unwrap().remove(&id);
+            return Err(IOError::new(ErrorKind::NotConnected, "WebSocket API connection is closed"));
+        }
+
+        match tokio::time::timeout(self.request_timeout, response_rx).await {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(_)) => Err(IOError::new(ErrorKind::ConnectionAborted, format!("WebSocket API connection closed before {} responded", method))),
+            Err(_) => {
+                self.pending.lock().unwrap().remove(&id);
+                Err(IOError::new(ErrorKind::TimedOut, format!("WebSocket API request {} timed out", method)))
+            }
+        }
+    }
+
+    async fn place<T: Serialize, R: DeserializeOwned>(&self, method: &str, order: &T) -> Result<R, IOError> {
+        let params = order_to_params(order)?;
+        self.send_signed_request(method, params).await?.into_result()
+    }
+
+    // Like `place`, for responses that can also arrive SBE encoded
+    async fn place_typed<T: Serialize, R: DeserializeOwned + SbeDecode>(&self, method: &str, order: &T) -> Result<R, IOError> {
+        let params = order_to_params(order)?;
+        self.send_signed_request(method, params).await?.into_typed()
+    }
+}
+
+impl Drop for WsApiClient<'_> {
+    fn drop(&mut self) {
+        self.connection_handle.abort();
+    }
+}
+
+#[async_trait]
+impl OrderApi for WsApiClient<'_> {
+    async fn create_limit_order(&self, order: LimitOrder) -> Result<OrderResponse, IOError> {
+        self.place_typed("order.place", &order).await
+    }
+
+    async fn create_stop_limit_order(&self, order: StopLimitOrder) -> Result<OrderResponse, IOError> {
+        self.place_typed("order.place", &order).await
+    }
+
+    async fn create_oco_order(&self, order: OcoOrder) -> Result<OrderListResponse, IOError> {
+        self.place("orderList.place", &order).await
+    }
+
+    async fn create_market_order(&self, order: MarketOrder) -> Result<OrderResponse, IOError> {
+        self.place_typed("order.place", &order).await
+    }
+
+    async fn cancel_order(&self, symbol: &str, order_id: i64) -> Result<CancelOrderResponse, IOError> {
+        let mut params = Map::new();
+        params.insert("symbol".to_string(), Value::String(symbol.to_string()));
+        params.insert("orderId".to_string(), json!(order_id));
+        self.send_signed_request("order.cancel", params).await?.into_typed()
+    }
+
+    async fn cancel_replace_order(&self, order: CancelReplaceOrder) -> Result<CancelReplaceResponse, IOError> {
+        self.place("order.cancelReplace", &order).await
+    }
+
+    async fn fetch_open_orders(&self, symbol: &str) -> Result<Vec<OpenOrder>, IOError> {
+        let mut params = Map::new();
+        params.insert("symbol".to_string(), Value::String(symbol.to_string()));
+        self.send_signed_request("openOrders.status", params).await?.into_result()
+    }
+}
+
+// Owns the socket: writes queued requests and routes responses to whoever is waiting on them.
+async fn run_connection<S>(
+    mut ws_stream: S,
+    mut outgoing: mpsc::UnboundedReceiver<Message>,
+    pending: PendingRequests,
+    rate_limits: Arc<Mutex<Vec<WsRateLimit>>>,
+) where
+    S: Stream<Item = Result<Message, tokio_websockets::Error>> + Sink<Message, Error = tokio_websockets::Error> + Unpin,
+{
+    loop {
+        tokio::select! {
+            request = outgoing.recv() => match request {
+                Some(message) => {
+                    if let Err(err) = ws_stream.send(message).await {
+                        error!("Failed to send WebSocket API request: {}", err);
+                        break;
+                    }
+                }
+                // The client was dropped
+                None => break,
+            },
+            incoming = ws_stream.next() => match incoming {
+                Some(Ok(message)) => {
+                    if message.is_ping() {
+                        if let Err(err) = ws_stream.send(Message::pong(message.into_payload())).await {
+                            error!("Failed to answer WebSocket API ping: {}", err);
+                            break;
+                        }
+                    } else if let Some(text) = message.as_text() {
+                        dispatch_response(text, &pending, &rate_limits);
+                    } else if message.is_binary() {
+                        dispatch_sbe_response(message.as_payload(), &pending, &rate_limits);
+                    } else if message.is_close() {
+                        warn!("WebSocket API connection closed by server: {:?}", message.as_close());
+                        break;
+                    }
+                }
+                Some(Err(err)) => {
+                    error!("WebSocket API connection error: {}", err);
+                    break;
+                }
+                None => break,
+            },
+        }
+    }
+    // Dropping the senders wakes every waiting request with a closed-connection error
+    pending.lock().unwrap().clear();
+}
+
+fn dispatch_response(text: &str, pending: &PendingRequests, rate_limits: &Arc<Mutex<Vec<WsRateLimit>>>) {
+    trace!("WebSocket API response: {}", text);
+    match serde_json::from_str(text) {
+        Ok(response) => route_response(response, pending, rate_limits),
+        Err(err) => error!("Failed to parse WebSocket API response: {}. Message: {}", err, text),
+    }
+}
+
+fn dispatch_sbe_response(payload: &[u8], pending: &PendingRequests, rate_limits: &Arc<Mutex<Vec<WsRateLimit>>>) {
+    match decode_websocket_response(payload) {
+        Ok(response) => route_response(response, pending, rate_limits),
+        Err(err) => error!("Failed to decode SBE WebSocket API response: {}", err),
+    }
+}
+
+fn route_response(response: WsApiResponse, pending: &PendingRequests, rate_limits: &Arc<Mutex<Vec<WsRateLimit>>>) {
+    if !response.rate_limits.is_empty() {
+        *rate_limits.lock().unwrap() = response.rate_limits.clone();
+    }
+
+    let Some(id) = response.id.clone() else {
+        error!("WebSocket API response without request id: {:?}", response.error);
+        return;
+    };
+    match pending.lock().unwrap().remove(&id) {
+        Some(sender) => {
+            let _ = sender.send(response);
+        }
+        None => warn!("WebSocket API response for unknown request id {}", id),
+    }
+}
+
+/// Converts an order into WebSocket API params.
+///
+/// Unset optional fields are dropped and floats are sent as strings, matching what the
+/// REST endpoints receive from `serde_qs`.
+pub(crate) fn order_to_params<T: Serialize>(order: &T) -> Result<Map<String, Value>, IOError> {
+    let value = serde_json::to_value(order)
+        .map_err(|err| IOError::new(ErrorKind::InvalidInput, format!("Failed to serialize order: {}", err)))?;
+    let Value::Object(fields) = value else {
+        return Err(IOError::new(ErrorKind::InvalidInput, "Order did not serialize to an object"));
+    };
+
+    Ok(fields
+        .into_iter()
+        .filter(|(_, value)| !value.is_null())
+        .map(|(key, value)| match value {
+            Value::Number(number) if number.is_f64() => (key, Value::String(number.to_string())),
+            other => (key, other),
+        })
+        .collect())
+}
+
+/// Builds the string that is signed: params sorted by name and joined as `key=value&...`.
+pub(crate) fn signature_payload(params: &Map<String, Value>) -> String {
+    let mut pairs: Vec<(&String, String)> = params
+        .iter()
+        .map(|(key, value)| match value {
+            Value::String(s) => (key, s.clone()),
+            other => (key, other.to_string()),
+        })
+        .collect();
+    pairs.sort_by(|a, b| a.0.cmp(b.0));
+    pairs
+        .into_iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn sign_ed25519(signing_key: &SigningKey, payload: &str) -> String {
+    BASE64.encode(signing_key.sign(payload.as_bytes()).to_bytes())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::pkcs8::EncodePrivateKey;
+    use ed25519_dalek::pkcs8::spki::der::pem::LineEnding;
+    use ed25519_dalek::{Signature, Verifier};
+    use crate::binance_client::order_types::side::Side;
+    use super::*;
+
+    #[tokio::test]
+    async fn test_hmac_signature_matches_documentation_example() {
+        let binance_client = BinanceClient::new(
+            "vmPUZE6mv9SD5VNHk4HlWFsOr6aKE2zvsw0MuIgwCIPy6utIco14y7Ju91duEh8A".to_string(),
+            "NhqPtmdSJYdKjVHjA7PZj4Mge3R5YNiP1e3UZjInClVN65XAbvqqM6A7H5fATj0j".to_string(),
+            false,
+        ).await;
+
+        let params = json!({
+            "symbol": "BTCUSDT",
+            "side": "SELL",
+            "type": "LIMIT",
+            "timeInForce": "GTC",
+            "quantity": "0.01000000",
+            "price": "52000.00",
+            "newOrderRespType": "ACK",
+            "recvWindow": 100,
+            "timestamp": 1645423376532u64,
+            "apiKey": "vmPUZE6mv9SD5VNHk4HlWFsOr6aKE2zvsw0MuIgwCIPy6utIco14y7Ju91duEh8A"
+        });
+        let payload = signature_payload(params.as_object().unwrap());
+        assert_eq!(
+            payload,
+            "apiKey=vmPUZE6mv9SD5VNHk4HlWFsOr6aKE2zvsw0MuIgwCIPy6utIco14y7Ju91duEh8A&newOrderRespType=ACK&price=52000.00&quantity=0.01000000&recvWindow=100&side=SELL&symbol=BTCUSDT&timeInForce=GTC&timestamp=1645423376532&type=LIMIT"
+        );
+        assert_eq!(
+            binance_client.sign(&payload),
+            "cc15477742bd704c29492d96c7ead9414dfd8e0ec4a00f947bb5bb454ddbd08a"
+        );
+    }
+
+    #[test]
+    fn test_order_to_params_drops_unset_fields() {
+        let order = MarketOrder::new_with_quote_asset("BTCUSDT", Side::Buy, 25.5);
+        let params = order_to_params(&order).unwrap();
+        assert_eq!(params["quoteOrderQty"], json!("25.5"));
+        assert_eq!(params["type"], json!("MARKET"));
+        assert!(!params.contains_key("quantity"));
+        assert!(params["timestamp"].is_u64());
+    }
+
+    #[test]
+    fn test_parse_success_response_with_rate_limits() {
+        let text = r#"{
+            "id": "56374a46-3061-486b-a311-99ee972eb648",
+            "status": 200,
+            "result": {
+                "symbol": "BTCUSDT",
+                "orderId": 12569099453,
+                "orderListId": -1,
+                "clientOrderId": "4d96324ff9d44481926157ec08158a40",
+                "transactTime": 1660801715639
+            },
+            "rateLimits": [
+                {"rateLimitType": "ORDERS", "interval": "SECOND", "intervalNum": 10, "limit": 50, "count": 1},
+                {"rateLimitType": "REQUEST_WEIGHT", "interval": "MINUTE", "intervalNum": 1, "limit": 6000, "count": 1}
+            ]
+        }"#;
+        let pending: PendingRequests = Arc::new(Mutex::new(HashMap::new()));
+        let rate_limits = Arc::new(Mutex::new(Vec::new()));
+        let (tx, mut rx) = oneshot::channel();
+        pending.lock().unwrap().insert("56374a46-3061-486b-a311-99ee972eb648".to_string(), tx);
+
+        dispatch_response(text, &pending, &rate_limits);
+
+        assert!(pending.lock().unwrap().is_empty());
+        assert_eq!(rate_limits.lock().unwrap().len(), 2);
+        assert_eq!(rate_limits.lock().unwrap()[1].limit, 6000);
+        let order: OrderResponse = rx.try_recv().unwrap().into_result().unwrap();
+        assert_eq!(order.order_id, 12569099453);
+    }
+
+    #[test]
+    fn test_parse_error_response() {
+        let text = r#"{
+            "id": "e2a85d9f-07a5-4f94-8d5f-789dc3deb097",
+            "status": 400,
+            "error": {"code": -2010, "msg": "Account has insufficient balance for requested action."},
+            "rate_limits": [
+                {"rate_limit_type": "ORDERS", "interval": "SECOND", "interval_num": 10, "limit": 50, "count": 13}
+            ]
+        }"#;
+        let response: WsApiResponse = serde_json::from_str(text).unwrap();
+        assert_eq!(response.rate_limits[0].count, 13);
+        let err = response.into_result::<OrderResponse>().unwrap_err();
+        assert!(err.to_string().contains("-2010"));
+    }
+
+    #[test]
+    fn test_ed25519_signature_verifies() {
+        let signing_key = SigningKey::from_bytes(&[7u8; 32]);
+        let pem = signing_key.to_pkcs8_pem(LineEnding::LF).unwrap();
+        let loaded = SigningKey::from_pkcs8_pem(&pem).unwrap();
+
+        let payload = "apiKey=abc&timestamp=1649729878532";
+        let signature = BASE64.decode(sign_ed25519(&loaded, payload)).unwrap();
+        let signature = Signature::from_slice(&signature).unwrap();
+        assert!(signing_key.verifying_key().verify(payload.as_bytes(), &signature).is_ok());
+    }
+}